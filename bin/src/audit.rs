@@ -0,0 +1,117 @@
+use crate::config::{Config, Sink};
+use crate::error::Error;
+use crate::notify;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::Utc;
+use kct_helper::io::Error as IOError;
+use serde_json::{json, Value};
+
+/// Whether compilations are noted, only while an operation is audited so
+/// long-running commands don't pile them up
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// What the audited operation compiled so far
+static NOTED: Mutex<Vec<Value>> = Mutex::new(vec![]);
+
+/// Starts noting compilations when the config has somewhere to audit them
+pub fn start(config: &Config) {
+	ENABLED.store(config.audit.is_some(), Ordering::Relaxed);
+}
+
+/// Notes the package, release, target, and inputs digest a compilation was
+/// made of, as its metadata tells
+pub fn note(metadata: &Value) {
+	if !ENABLED.load(Ordering::Relaxed) {
+		return;
+	}
+
+	let package = &metadata["package"];
+	let noted = json!({
+		"package": {
+			"name": package["name"],
+			"version": package["version"],
+			"digest": package["digest"],
+		},
+		"release": metadata["release"]["name"],
+		"target": metadata["release"]["target"],
+		"inputsDigest": metadata["inputsDigest"],
+	});
+
+	if let Ok(mut compilations) = NOTED.lock() {
+		compilations.push(noted);
+	}
+}
+
+/// Appends the record of the operation to the sink, along who ran it, when,
+/// and what it compiled. Operations that failed keep their own error, the one
+/// of auditing them is only warned about.
+pub fn record(config: &Config, operation: &str, result: Result<()>) -> Result<()> {
+	let sink = match &config.audit {
+		Some(sink) => sink,
+		None => return result,
+	};
+
+	let compilations = NOTED
+		.lock()
+		.map(|mut noted| std::mem::take(&mut *noted))
+		.unwrap_or_default();
+	let user = env::var("USER")
+		.or_else(|_| env::var("USERNAME"))
+		.unwrap_or_else(|_| String::from("unknown"));
+
+	let mut entry = json!({
+		"time": Utc::now().to_rfc3339(),
+		"user": user,
+		"operation": operation,
+		"outcome": if result.is_ok() { "success" } else { "failure" },
+		"compilations": compilations,
+	});
+	if let Err(err) = &result {
+		entry["error"] = json!(format!("{err:#}"));
+	}
+
+	let audited = match sink {
+		Sink::File(path) => append(path, &entry),
+		Sink::Webhook(url) => notify::post(url, &entry)
+			.map_err(|reason| Error::InvalidInput(format!("unable to audit at {url}, {reason}"))),
+	};
+
+	match (result, audited) {
+		(Ok(()), audited) => Ok(audited?),
+		(Err(err), Err(audit)) => {
+			eprintln!("Warning: {audit}");
+
+			Err(err)
+		}
+		(Err(err), Ok(())) => Err(err),
+	}
+}
+
+/// Appends the entry as a line of JSON, readable by the owner alone
+fn append(path: &std::path::Path, entry: &Value) -> Result<(), Error> {
+	if let Some(parent) = path
+		.parent()
+		.filter(|parent| !parent.as_os_str().is_empty())
+	{
+		fs::create_dir_all(parent).map_err(|_err| IOError::UnableToWrite)?;
+	}
+
+	let mut options = OpenOptions::new();
+	options.append(true).create(true);
+	#[cfg(unix)]
+	options.mode(0o600);
+
+	options
+		.open(path)
+		.and_then(|mut file| file.write_all(format!("{entry}\n").as_bytes()))
+		.map_err(|_err| IOError::UnableToWrite.into())
+}
@@ -0,0 +1,111 @@
+use crate::error::Error;
+use crate::operation::release;
+use crate::App;
+
+use std::env;
+use std::ffi::OsStr;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::{Command, CommandFactory, Parser};
+use clap_complete::engine::{CompletionCandidate, PathCompleter, ValueCompleter};
+use clap_complete::env::Shells;
+use clap_complete::{CompleteEnv, Shell};
+use kct_kube::stamp::Keys;
+use tokio::runtime::Handle;
+use tokio::{task, time};
+
+/// Variable the registered scripts call kct back with to complete
+const VAR: &str = "COMPLETE";
+
+/// Time the cluster has to tell its releases, as completing can't wait long
+const CLUSTER_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "shell to generate the completions for")]
+	shell: Shell,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let name = env!("CARGO_BIN_NAME");
+	let shells = Shells::builtins();
+	let shell = shells
+		.completer(&args.shell.to_string())
+		.ok_or_else(|| Error::InvalidInput(format!("{} has no completions", args.shell)))?;
+
+	shell.write_registration(VAR, name, name, name, &mut io::stdout())?;
+
+	Ok(())
+}
+
+/// Completes the command line the registered scripts call kct back with,
+/// exiting once done, or does nothing when kct isn't called to complete
+pub fn complete() {
+	CompleteEnv::with_factory(command).var(VAR).complete();
+}
+
+/// Command named after the binary, as scripts name their functions after it
+fn command() -> Command {
+	let name = env!("CARGO_BIN_NAME");
+
+	App::command().name(name).bin_name(name)
+}
+
+/// Kubeconfig contexts, which targets are
+pub fn targets(current: &OsStr) -> Vec<CompletionCandidate> {
+	matching(current, kct_kube::contexts())
+}
+
+/// Releases installed from here, along the ones with objects in the cluster
+/// of the current context when it answers in time
+pub fn releases(current: &OsStr) -> Vec<CompletionCandidate> {
+	let mut names = release::stored();
+
+	let keys = Keys::default();
+	let found = Handle::try_current().ok().and_then(|handle| {
+		let listing = kct_kube::releases(None, &keys);
+
+		task::block_in_place(|| handle.block_on(time::timeout(CLUSTER_TIMEOUT, listing)))
+			.ok()?
+			.ok()
+	});
+	names.extend(found.into_iter().flatten());
+	names.sort();
+	names.dedup();
+
+	matching(current, names)
+}
+
+/// Example of the package on the command line, along the files
+pub fn inputs(current: &OsStr) -> Vec<CompletionCandidate> {
+	let example = package().map(|root| root.join("example.json"));
+	let example = example
+		.filter(|example| example.is_file())
+		.map(|example| example.to_string_lossy().into_owned());
+
+	let mut candidates = matching(current, example.into_iter().collect());
+	candidates.extend(PathCompleter::file().stdio().complete(current));
+
+	candidates
+}
+
+/// Directory of the package being completed, the first argument holding one
+fn package() -> Option<PathBuf> {
+	env::args_os()
+		.skip_while(|arg| arg != "--")
+		.map(PathBuf::from)
+		.find(|arg| arg.join("kcp.json").is_file())
+}
+
+fn matching(current: &OsStr, names: Vec<String>) -> Vec<CompletionCandidate> {
+	let current = current.to_string_lossy();
+
+	names
+		.into_iter()
+		.filter(|name| name.starts_with(current.as_ref()))
+		.map(CompletionCandidate::new)
+		.collect()
+}
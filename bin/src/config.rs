@@ -0,0 +1,229 @@
+use crate::error::Error;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use kct_helper::ignore::{Error as IgnoreError, Rule};
+use kct_helper::io;
+use serde_json::Value;
+
+/// File kct reads its settings from instead of the default one
+const CONFIG_VAR: &str = "KCT_CONFIG";
+
+/// Settings of kct itself, as opposed to the ones of packages
+#[derive(Debug, Default)]
+pub struct Config {
+	pub notifications: Vec<Notification>,
+	/// Where compile and apply operations are recorded, when anywhere
+	pub audit: Option<Sink>,
+	/// Namespaces rendered objects are confined to, when any
+	pub namespaces: Option<Vec<String>>,
+	/// Fields diffs leave out, besides the ones servers populate
+	pub ignore: Vec<Rule>,
+	/// Contexts each release goes into when no target is given
+	pub releases: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Sink {
+	/// File each record is appended to as a line of JSON
+	File(PathBuf),
+	/// URL receiving each record as JSON
+	Webhook(String),
+}
+
+/// Where to tell about release operations, and about which outcomes
+#[derive(Debug, Clone)]
+pub struct Notification {
+	pub target: Target,
+	pub on: Vec<Outcome>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Target {
+	/// URL receiving the event as JSON
+	Webhook(String),
+	/// Incoming webhook of Slack receiving a message about the event
+	Slack(String),
+	/// Program and its arguments, receiving the event as JSON through STDIN
+	Exec(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+	Success,
+	Failure,
+}
+
+impl Outcome {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Outcome::Success => "success",
+			Outcome::Failure => "failure",
+		}
+	}
+}
+
+impl Config {
+	/// Reads the file `KCT_CONFIG` points to, or `kct/config.yaml` within the
+	/// XDG config directory when it's there
+	pub fn load() -> Result<Self, Error> {
+		let path = match env::var_os(CONFIG_VAR) {
+			Some(path) => PathBuf::from(path),
+			None => match default() {
+				Some(path) if path.is_file() => path,
+				_ => return Ok(Config::default()),
+			},
+		};
+
+		let contents = io::from_file(&path)?;
+		let invalid = |reason: String| Error::InvalidInput(format!("{}: {reason}", path.display()));
+
+		let value: Value =
+			serde_yaml::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+
+		Config::try_from(&value).map_err(invalid)
+	}
+}
+
+impl TryFrom<&Value> for Config {
+	type Error = String;
+
+	fn try_from(value: &Value) -> Result<Self, Self::Error> {
+		let notifications = match &value["notifications"] {
+			Value::Null => vec![],
+			Value::Array(notifications) => notifications
+				.iter()
+				.enumerate()
+				.map(|(i, notification)| {
+					Notification::try_from(notification)
+						.map_err(|reason| format!("notifications[{i}] {reason}"))
+				})
+				.collect::<Result<_, _>>()?,
+			_ => return Err("notifications should be a list".to_string()),
+		};
+
+		let audit = match &value["audit"] {
+			Value::Null => None,
+			audit => match (&audit["file"], &audit["webhook"]) {
+				(Value::String(file), Value::Null) => Some(Sink::File(PathBuf::from(file))),
+				(Value::Null, Value::String(url)) => Some(Sink::Webhook(url.clone())),
+				_ => return Err("audit should have either a file or a webhook".to_string()),
+			},
+		};
+
+		let namespaces = match &value["namespaces"] {
+			Value::Null => None,
+			Value::Array(namespaces) => Some(
+				namespaces
+					.iter()
+					.map(|namespace| namespace.as_str().map(String::from))
+					.collect::<Option<Vec<String>>>()
+					.ok_or("namespaces should be a list of strings")?,
+			),
+			_ => return Err("namespaces should be a list of strings".to_string()),
+		};
+
+		let ignore = match &value["ignore"] {
+			Value::Null => vec![],
+			Value::Array(rules) => rules
+				.iter()
+				.map(|rule| match rule {
+					Value::String(rule) => rule.parse().map_err(|err: IgnoreError| err.to_string()),
+					_ => Err("ignore should be a list of strings".to_string()),
+				})
+				.collect::<Result<_, _>>()?,
+			_ => return Err("ignore should be a list of strings".to_string()),
+		};
+
+		let releases = match &value["releases"] {
+			Value::Null => BTreeMap::new(),
+			Value::Object(releases) => releases
+				.iter()
+				.map(|(name, release)| {
+					let targets = match &release["targets"] {
+						Value::Array(targets) => targets
+							.iter()
+							.map(|target| target.as_str().map(String::from))
+							.collect::<Option<Vec<String>>>(),
+						_ => None,
+					};
+					let targets = targets.ok_or_else(|| {
+						format!("releases.{name} should have targets as a list of strings")
+					})?;
+
+					Ok((name.clone(), targets))
+				})
+				.collect::<Result<_, String>>()?,
+			_ => return Err("releases should be a map of names to releases".to_string()),
+		};
+
+		Ok(Config {
+			notifications,
+			audit,
+			namespaces,
+			ignore,
+			releases,
+		})
+	}
+}
+
+impl TryFrom<&Value> for Notification {
+	type Error = String;
+
+	fn try_from(value: &Value) -> Result<Self, Self::Error> {
+		let url = |key: &str| match &value[key] {
+			Value::String(url) => Ok(url.clone()),
+			_ => Err(format!("should have {key} as a URL")),
+		};
+
+		let target = match (&value["webhook"], &value["slack"], &value["exec"]) {
+			(Value::Null, Value::Null, Value::Null) => {
+				return Err("should have one of webhook, slack, or exec".to_string())
+			}
+			(_, Value::Null, Value::Null) => Target::Webhook(url("webhook")?),
+			(Value::Null, _, Value::Null) => Target::Slack(url("slack")?),
+			(Value::Null, Value::Null, Value::Array(command)) if !command.is_empty() => {
+				let command = command
+					.iter()
+					.map(|word| word.as_str().map(String::from))
+					.collect::<Option<Vec<String>>>()
+					.ok_or("should have exec as a list of strings")?;
+
+				Target::Exec(command)
+			}
+			(Value::Null, Value::Null, _) => {
+				return Err("should have exec as a program along its arguments".to_string())
+			}
+			_ => return Err("should have only one of webhook, slack, or exec".to_string()),
+		};
+
+		let on = match &value["on"] {
+			Value::Null => vec![Outcome::Success, Outcome::Failure],
+			Value::Array(outcomes) => outcomes
+				.iter()
+				.map(|outcome| match outcome.as_str() {
+					Some("success") => Ok(Outcome::Success),
+					Some("failure") => Ok(Outcome::Failure),
+					_ => Err(format!(
+						"has {outcome} in on, which isn't success or failure"
+					)),
+				})
+				.collect::<Result<_, _>>()?,
+			_ => return Err("should have on as a list of success or failure".to_string()),
+		};
+
+		Ok(Notification { target, on })
+	}
+}
+
+/// Follows the XDG base directories, falling back to `~/.config`
+fn default() -> Option<PathBuf> {
+	let base = match env::var_os("XDG_CONFIG_HOME") {
+		Some(config) if !config.is_empty() => PathBuf::from(config),
+		_ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+	};
+
+	Some(base.join("kct").join("config.yaml"))
+}
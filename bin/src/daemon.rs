@@ -0,0 +1,360 @@
+use crate::error::{Error, Exit};
+use crate::operation::compile;
+use crate::progress;
+use crate::render;
+
+use std::env;
+#[cfg(unix)]
+use std::fs;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::Semaphore;
+
+/// Codes of the errors JSON-RPC defines, the rest telling the exit code the
+/// CLI would have failed with instead
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const FAILED: i64 = -32000;
+
+/// Flags of render requests may give, as the others would read files of the
+/// host, reach the cluster, the network, or STDIN, write files, or change the
+/// whole daemon. Inputs are only taken inline, as the `input` of requests.
+const FLAGS: [&str; 16] = [
+	"--set",
+	"-s",
+	"--set-string",
+	"--release",
+	"--target",
+	"-t",
+	"--timestamp",
+	"--seed",
+	"--offline",
+	"--allow-namespace",
+	"--cluster-scoped",
+	"--only",
+	"--except",
+	"--label-prefix",
+	"--no-labels",
+	"--frozen",
+];
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "Unix socket to take requests from, kct.sock within the runtime directory by default",
+		long,
+		conflicts_with = "listen",
+		value_hint = ValueHint::FilePath
+	)]
+	socket: Option<PathBuf>,
+	#[arg(
+		help = "TCP address to take requests from instead of a socket, only a loopback one unless told otherwise",
+		long
+	)]
+	listen: Option<SocketAddr>,
+	#[arg(
+		help = "take requests from any address, which anyone reaching it can compile packages of this host with",
+		long,
+		requires = "listen"
+	)]
+	allow_remote: bool,
+	#[arg(
+		help = "compilations running at once, the number of CPUs by default",
+		long,
+		value_parser = clap::value_parser!(u32).range(1..)
+	)]
+	jobs: Option<u32>,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	let jobs = args.jobs.map(|jobs| jobs as usize).unwrap_or_else(|| {
+		thread::available_parallelism()
+			.map(usize::from)
+			.unwrap_or(1)
+	});
+	let limit = Arc::new(Semaphore::new(jobs));
+
+	if let Some(address) = args.listen {
+		if !address.ip().is_loopback() && !args.allow_remote {
+			let reason = format!(
+				"{address} isn't a loopback address, give --allow-remote to take requests from anyone reaching it"
+			);
+
+			return Err(Error::InvalidInput(reason).into());
+		}
+
+		let listener = TcpListener::bind(address).await?;
+		progress::message(&format!("Taking requests at {address}"));
+
+		loop {
+			let (stream, _peer) = listener.accept().await?;
+			tokio::spawn(serve(stream, limit.clone()));
+		}
+	}
+
+	let path = args.socket.unwrap_or_else(socket);
+	unix(path, limit).await
+}
+
+#[cfg(unix)]
+async fn unix(path: PathBuf, limit: Arc<Semaphore>) -> Result<()> {
+	taken(&path)?;
+
+	// Bound aside and moved in once only its owner can connect to it
+	let mut bound = path.clone().into_os_string();
+	bound.push(format!(".{}", std::process::id()));
+	let bound = PathBuf::from(bound);
+
+	let listener = UnixListener::bind(&bound)?;
+	fs::set_permissions(&bound, fs::Permissions::from_mode(0o600))?;
+	// Something may have taken the path while binding
+	if let Err(err) = taken(&path) {
+		fs::remove_file(&bound)?;
+
+		return Err(err);
+	}
+	fs::rename(&bound, &path)?;
+	progress::message(&format!("Taking requests at {}", path.display()));
+
+	loop {
+		let (stream, _peer) = listener.accept().await?;
+		tokio::spawn(serve(stream, limit.clone()));
+	}
+}
+
+/// Refuses to take the path from a daemon still taking requests there, or
+/// from anything that isn't a socket, removing the ones left behind by
+/// daemons that didn't stop cleanly
+#[cfg(unix)]
+fn taken(path: &Path) -> Result<()> {
+	let meta = match fs::symlink_metadata(path) {
+		Ok(meta) => meta,
+		Err(_) => return Ok(()),
+	};
+
+	if !meta.file_type().is_socket() {
+		let reason = format!("{} exists and isn't a socket", path.display());
+
+		return Err(Error::InvalidInput(reason).into());
+	}
+
+	if UnixStream::connect(path).is_ok() {
+		let reason = format!("another daemon takes requests at {}", path.display());
+
+		return Err(Error::InvalidInput(reason).into());
+	}
+
+	fs::remove_file(path)?;
+
+	Ok(())
+}
+
+#[cfg(not(unix))]
+async fn unix(_path: PathBuf, _limit: Arc<Semaphore>) -> Result<()> {
+	Err(Error::InvalidInput(
+		"sockets are only taken on Unix, give an address with --listen instead".to_string(),
+	)
+	.into())
+}
+
+/// Within the runtime directory of XDG, falling back to the temporary one
+fn socket() -> PathBuf {
+	env::var_os("XDG_RUNTIME_DIR")
+		.map(PathBuf::from)
+		.unwrap_or_else(env::temp_dir)
+		.join("kct.sock")
+}
+
+/// Answers the requests of the connection, one JSON-RPC message per line,
+/// in the order they come
+async fn serve<S>(stream: S, limit: Arc<Semaphore>)
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let (reader, mut writer) = tokio::io::split(stream);
+	let mut lines = BufReader::new(reader).lines();
+
+	while let Ok(Some(line)) = lines.next_line().await {
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let response = answer(&line, &limit).await;
+		let written = writer.write_all(format!("{response}\n").as_bytes()).await;
+
+		if written.is_err() {
+			break;
+		}
+	}
+}
+
+async fn answer(line: &str, limit: &Semaphore) -> Value {
+	let request: Value = match serde_json::from_str(line) {
+		Ok(request) => request,
+		Err(err) => return failure(Value::Null, PARSE_ERROR, err.to_string(), None),
+	};
+
+	let id = request.get("id").cloned().unwrap_or(Value::Null);
+	let method = match request.get("method").and_then(Value::as_str) {
+		Some(method) => method.to_string(),
+		None => return failure(id, INVALID_REQUEST, "method is missing".into(), None),
+	};
+	let params = request.get("params").cloned().unwrap_or(json!({}));
+
+	let args = match arguments(&params) {
+		Ok(args) => args,
+		Err(reason) => return failure(id, INVALID_PARAMS, reason, None),
+	};
+	let input = params.get("input").cloned().unwrap_or(json!({}));
+
+	let work: Box<dyn FnOnce() -> Result<Value> + Send> = match method.as_str() {
+		"compile" => Box::new(move || {
+			let compilations = compile::preview(&args, input)?;
+			let (documents, _files) = render::documents(compilations)?;
+			let documents: Vec<Value> = documents
+				.into_iter()
+				.map(
+					|(path, contents)| json!({ "path": path.display().to_string(), "contents": contents }),
+				)
+				.collect();
+
+			Ok(json!({ "documents": documents }))
+		}),
+		"validate" => Box::new(move || match compile::preview(&args, input) {
+			Ok(_) => Ok(json!({ "valid": true })),
+			Err(err) if matches!(Exit::from(&err), Exit::Validation | Exit::Render) => {
+				Ok(json!({ "valid": false, "error": format!("{err:?}") }))
+			}
+			Err(err) => Err(err),
+		}),
+		"inspect" => Box::new(move || compile::inspect(&args)),
+		_ => {
+			let reason = format!("{method} isn't one of compile, validate, or inspect");
+			return failure(id, METHOD_NOT_FOUND, reason, None);
+		}
+	};
+
+	// Only closed along the daemon, so it's always there
+	let _permit = limit.acquire().await.expect("limit is never closed");
+	let result = tokio::task::spawn_blocking(work)
+		.await
+		.map_err(anyhow::Error::from)
+		.and_then(|result| result);
+
+	match result {
+		Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+		Err(err) => {
+			let exit = Exit::from(&err) as u8;
+
+			failure(
+				id,
+				FAILED,
+				format!("{err:?}"),
+				Some(json!({ "exit": exit })),
+			)
+		}
+	}
+}
+
+/// Parameters of the compilation as the flags of the CLI, the package along
+/// the `args` given to render
+fn arguments(params: &Value) -> Result<compile::Params, String> {
+	let package = params
+		.get("package")
+		.and_then(Value::as_str)
+		.ok_or("package is missing")?;
+	let args = match params.get("args") {
+		None => vec![],
+		Some(Value::Array(args)) => args
+			.iter()
+			.map(|arg| arg.as_str().map(String::from))
+			.collect::<Option<Vec<String>>>()
+			.ok_or("args should be a list of strings")?,
+		Some(_) => return Err("args should be a list of strings".into()),
+	};
+
+	if package == "-" {
+		return Err("package can't be read from STDIN, give its directory instead".into());
+	}
+
+	for arg in &args {
+		allowed(arg)?;
+	}
+
+	let argv = ["kct".to_string(), package.to_string()]
+		.into_iter()
+		.chain(args);
+
+	// Only the reason, as the usage of the CLI means nothing to callers
+	compile::Params::try_parse_from(argv).map_err(|err| {
+		let reason = err.to_string();
+		let reason = reason.lines().next().unwrap_or_default();
+
+		reason.trim_start_matches("error: ").to_string()
+	})
+}
+
+/// Refuses flags outside of [FLAGS], and reading anything from STDIN
+fn allowed(arg: &str) -> Result<(), String> {
+	match arg {
+		"-" => return Err("STDIN of the daemon can't be read".to_string()),
+		"--" => return Err("-- isn't taken, as the package is given apart".to_string()),
+		_ => (),
+	}
+
+	let (flag, value) = match (arg.strip_prefix("--"), arg.strip_prefix('-')) {
+		(Some(long), _) => match long.split_once('=') {
+			Some((flag, value)) => (format!("--{flag}"), Some(value)),
+			None => (arg.to_string(), None),
+		},
+		(None, Some(short)) => {
+			let mut chars = short.chars();
+			let flag = chars
+				.next()
+				.map(|flag| format!("-{flag}"))
+				.unwrap_or_default();
+			let value = chars.as_str();
+			let value = value.strip_prefix('=').unwrap_or(value);
+
+			(flag, Some(value).filter(|value| !value.is_empty()))
+		}
+		// Values of the flags before
+		(None, None) => return Ok(()),
+	};
+
+	if !FLAGS.contains(&flag.as_str()) {
+		return Err(format!("{flag} can't be given to the daemon"));
+	}
+
+	match value {
+		Some("-") => Err(format!("{flag} can't read from STDIN of the daemon")),
+		_ => Ok(()),
+	}
+}
+
+fn failure(id: Value, code: i64, message: String, data: Option<Value>) -> Value {
+	let mut error = json!({ "code": code, "message": message });
+	if let Some(data) = data {
+		error["data"] = data;
+	}
+
+	json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
@@ -0,0 +1,127 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::operation::{compile, diff};
+use crate::progress;
+use crate::render;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+use clap::{Args as Flags, Parser, ValueHint};
+use kct_cli::Input;
+use kct_helper::ignore::{Ignore, Rule};
+use serde_json::Value;
+
+/// Fields comparisons leave out, besides the ones servers populate
+#[derive(Flags)]
+pub struct Noise {
+	#[arg(
+		help = "field to leave out of the comparison, as a JSON pointer where * matches anything, optionally after Kind:",
+		long = "ignore",
+		value_name = "RULE"
+	)]
+	ignores: Vec<Rule>,
+}
+
+impl Noise {
+	/// Rules to leave out the fields servers populate, the ones in the
+	/// config file, and the given ones
+	pub fn ignore(self) -> Result<Ignore> {
+		let config = Config::load()?;
+
+		Ok(Ignore::default().with(config.ignore).with(self.ignores))
+	}
+}
+
+#[derive(Parser)]
+pub struct Packages {
+	#[arg(
+		help = "directory or archive with the package to compare with, or - for one from STDIN",
+		value_hint = ValueHint::AnyPath
+	)]
+	before: PathBuf,
+	#[command(flatten)]
+	noise: Noise,
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+#[derive(Parser)]
+pub struct Inputs {
+	#[command(flatten)]
+	compile: compile::Params,
+	#[arg(
+		help = "input to compare with, merged over the other inputs",
+		value_hint = ValueHint::FilePath
+	)]
+	before: Input,
+	#[arg(
+		help = "input to compare, merged over the other inputs",
+		value_hint = ValueHint::FilePath
+	)]
+	after: Input,
+	#[command(flatten)]
+	noise: Noise,
+}
+
+/// Renders the packages with the same inputs and time, printing how the
+/// objects of the latter differ from the ones of the former
+pub fn packages(args: Packages) -> Result<()> {
+	if args.before == Path::new("-") && args.compile.package() == Path::new("-") {
+		let reason = "only one of the packages can come from STDIN";
+
+		return Err(Error::InvalidInput(reason.to_string()).into());
+	}
+
+	let ignore = rendered(args.noise, &args.compile)?;
+	let timestamp = Utc::now();
+	let before = args.compile.clone().comparing(args.before, timestamp);
+	let after = args.compile.at(timestamp);
+
+	let before = render::resources(compile::compilations(before)?)?;
+	let after = render::resources(compile::compilations(after)?)?;
+
+	print(diff::diff(&before, &after, &ignore));
+
+	Ok(())
+}
+
+/// Renders the package with each input at the same time, printing how the
+/// objects rendered with the latter differ from the ones with the former
+pub fn inputs(args: Inputs) -> Result<()> {
+	if args.compile.package() == Path::new("-") {
+		let reason = "the package is read once per input, so it can't come from STDIN";
+
+		return Err(Error::InvalidInput(reason.to_string()).into());
+	}
+
+	let ignore = rendered(args.noise, &args.compile)?;
+	let compile = args.compile.at(Utc::now());
+
+	let before = render::resources(compile::preview(&compile, Value::from(args.before))?)?;
+	let after = render::resources(compile::preview(&compile, Value::from(args.after))?)?;
+
+	print(diff::diff(&before, &after, &ignore));
+
+	Ok(())
+}
+
+/// Also leaves out the hash annotation, which only changes along the rest of
+/// the object
+fn rendered(noise: Noise, compile: &compile::Params) -> Result<Ignore> {
+	let hash = compile.keys().hash.replace('~', "~0").replace('/', "~1");
+	let hash: Rule = format!("/metadata/annotations/{hash}").parse()?;
+
+	Ok(noise.ignore()?.with([hash]))
+}
+
+fn print(differences: Vec<String>) {
+	if differences.is_empty() {
+		progress::message("Renders don't differ");
+	}
+
+	for difference in differences {
+		println!("{difference}");
+	}
+}
@@ -0,0 +1,112 @@
+use crate::operation;
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum, ValueHint};
+use pulldown_cmark::{html, Options, Parser as Markdown};
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+	#[default]
+	Markdown,
+	Html,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum CatalogFormat {
+	#[default]
+	Json,
+	Yaml,
+}
+
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Args {
+	#[command(subcommand)]
+	command: Option<Command>,
+	#[arg(
+		help = "directory or archive with the package to document, or - for one from STDIN",
+		required = true,
+		value_hint = ValueHint::DirPath
+	)]
+	package: Option<PathBuf>,
+	#[arg(
+		help = "format of the documentation",
+		long,
+		value_enum,
+		default_value_t
+	)]
+	format: Format,
+	#[arg(help = "file to save the documentation", long, short, value_hint = ValueHint::FilePath)]
+	output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	#[command(
+		name = "inputs",
+		about = "Catalogs every input of your package, and whether its templates read it"
+	)]
+	Inputs(Inputs),
+}
+
+#[derive(Parser)]
+struct Inputs {
+	#[arg(
+		help = "directory or archive with the package to catalog, or - for one from STDIN",
+		value_hint = ValueHint::DirPath
+	)]
+	package: PathBuf,
+	#[arg(help = "format of the catalog", long, value_enum, default_value_t)]
+	format: CatalogFormat,
+	#[arg(help = "file to save the catalog", long, short, value_hint = ValueHint::FilePath)]
+	output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let path = match (args.command, args.package) {
+		(Some(Command::Inputs(inputs)), _) => return catalog(inputs),
+		(None, Some(path)) => path,
+		(None, None) => unreachable!("clap requires the package without a subcommand"),
+	};
+
+	let (package, _unpacked) = operation::load(&path)?;
+	let markdown = package.docs();
+
+	let contents = match args.format {
+		Format::Markdown => markdown,
+		Format::Html => {
+			let parser = Markdown::new_ext(&markdown, Options::ENABLE_TABLES);
+			let mut contents = String::new();
+			html::push_html(&mut contents, parser);
+
+			contents
+		}
+	};
+
+	match args.output {
+		None => print!("{contents}"),
+		Some(path) => fs::write(path, contents)?,
+	}
+
+	Ok(())
+}
+
+fn catalog(args: Inputs) -> Result<()> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+	let catalog = package.inputs()?;
+
+	let contents = match args.format {
+		CatalogFormat::Json => format!("{}\n", serde_json::to_string_pretty(&catalog)?),
+		CatalogFormat::Yaml => serde_yaml::to_string(&catalog)?,
+	};
+
+	match args.output {
+		None => print!("{contents}"),
+		Some(path) => fs::write(path, contents)?,
+	}
+
+	Ok(())
+}
@@ -1,3 +1,6 @@
+use std::process::ExitCode;
+
+use kct_compiler::Error as CompilerError;
 use kct_helper::io::Error as IOError;
 use kct_kube::Error as KubeError;
 use kct_package::Error as PackageError;
@@ -7,6 +10,26 @@ use thiserror::Error;
 pub enum Error {
 	#[error("Invalid input: {0}")]
 	InvalidInput(String),
+	#[error("Invalid release \"{0}\": {1}")]
+	InvalidRelease(String, String),
+	#[error("Package renders files instead of objects, write them with render")]
+	RendersFiles,
+	#[error("Render isn't reproducible, differences from the record: {0}")]
+	NotReproducible(usize),
+	#[error("Render isn't deterministic, differences between compilations: {0}")]
+	Nondeterministic(usize),
+	#[error("Jsonnet doesn't pass the lint rules, findings: {0}")]
+	Unlinted(usize),
+	#[error("Unable to sign with {0}, it should be an Ed25519 or ECDSA P-256 key in PKCS#8 PEM")]
+	InvalidKey(String),
+	#[error("Rendered objects violate policies: {0}")]
+	Denied(String),
+	#[error("Unable to plan the objects, {0}")]
+	InvalidPlan(String),
+	#[error("Unable to override {0}")]
+	InvalidOverride(String),
+	#[error("Render read files it doesn't declare: {0}")]
+	Undeclared(String),
 	#[error(transparent)]
 	IO(#[from] IOError),
 	#[error(transparent)]
@@ -14,3 +37,84 @@ pub enum Error {
 	#[error(transparent)]
 	InvalidManifest(#[from] KubeError),
 }
+
+/// Kinds of failure told apart by the exit code, so scripts can react to each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+	/// Anything not falling into the other kinds
+	Failure = 1,
+	/// Invalid package, input, or arguments
+	Validation = 3,
+	/// Templates that failed to evaluate or rendered invalid objects
+	Render = 4,
+	/// Files that couldn't be read or written
+	IO = 5,
+	/// Cluster that couldn't be reached or refused the request
+	Network = 6,
+}
+
+impl From<&anyhow::Error> for Exit {
+	fn from(err: &anyhow::Error) -> Self {
+		if kct_kube::is_unreachable(err) {
+			return Exit::Network;
+		}
+
+		for cause in err.chain() {
+			if let Some(err) = cause.downcast_ref::<Error>() {
+				match err {
+					Error::InvalidInput(_)
+					| Error::InvalidRelease(..)
+					| Error::RendersFiles
+					| Error::NotReproducible(_)
+					| Error::Nondeterministic(_)
+					| Error::Unlinted(_)
+					| Error::InvalidKey(_)
+					| Error::Undeclared(_) => return Exit::Validation,
+					Error::IO(_) => return Exit::IO,
+					Error::InvalidManifest(_)
+					| Error::Denied(_)
+					| Error::InvalidPlan(_)
+					| Error::InvalidOverride(_) => return Exit::Render,
+					Error::InvalidPackage(err) => return err.into(),
+				}
+			}
+
+			if let Some(err) = cause.downcast_ref::<PackageError>() {
+				return err.into();
+			}
+
+			if cause.is::<KubeError>() {
+				return Exit::Render;
+			}
+
+			if cause.is::<IOError>() || cause.is::<std::io::Error>() {
+				return Exit::IO;
+			}
+		}
+
+		Exit::Failure
+	}
+}
+
+impl From<&PackageError> for Exit {
+	fn from(err: &PackageError) -> Self {
+		match err {
+			PackageError::InvalidArtifact(..) => Exit::Render,
+			PackageError::Fetch(..) | PackageError::Push(..) => Exit::Network,
+			PackageError::MissingCue(_) => Exit::Failure,
+			PackageError::Compilation(
+				CompilerError::NoValidator
+				| CompilerError::NoInput
+				| CompilerError::InvalidInput(_),
+			) => Exit::Validation,
+			PackageError::Compilation(_) => Exit::Render,
+			_ => Exit::Validation,
+		}
+	}
+}
+
+impl From<Exit> for ExitCode {
+	fn from(exit: Exit) -> Self {
+		ExitCode::from(exit as u8)
+	}
+}
@@ -0,0 +1,116 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::progress;
+use crate::render;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueHint};
+use kct_helper::io;
+use serde_json::json;
+
+#[derive(Parser)]
+pub struct Args {
+	#[command(subcommand)]
+	format: Format,
+}
+
+#[derive(Subcommand)]
+pub enum Format {
+	#[command(
+		name = "helm",
+		about = "Writes your objects as a Helm chart with static templates"
+	)]
+	Helm(Helm),
+}
+
+#[derive(Parser)]
+pub struct Helm {
+	#[command(flatten)]
+	compile: compile::Params,
+	#[arg(help = "directory to write the chart into", long, short, value_hint = ValueHint::DirPath)]
+	output: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	match args.format {
+		Format::Helm(args) => helm(args),
+	}
+}
+
+/// Writes the chart of the compilation, with a template per object holding it
+/// as rendered, the input it was compiled with as values, and the schema of
+/// the package to validate them
+fn helm(args: Helm) -> Result<()> {
+	let (compilations, input, schema) = compile::export(args.compile)?;
+
+	if compilations.len() > 1 {
+		let reason = String::from("a chart holds the objects of a single target");
+
+		return Err(Error::InvalidInput(reason).into());
+	}
+
+	let package = compilations
+		.first()
+		.map(|compilation| compilation.metadata["package"].clone())
+		.unwrap_or_default();
+	let (documents, files) = render::documents(compilations)?;
+
+	if files {
+		return Err(Error::RendersFiles.into());
+	}
+
+	let chart = json!({
+		"apiVersion": "v2",
+		"name": package["name"],
+		"version": package["version"],
+		"description": format!("Objects rendered from {}", package["name"].as_str().unwrap_or_default()),
+		"type": "application",
+	});
+	let values = input.unwrap_or_else(|| json!({}));
+
+	io::write_contents(
+		&args.output.join("Chart.yaml"),
+		&serde_yaml::to_string(&chart)?,
+	)?;
+	io::write_contents(
+		&args.output.join("values.yaml"),
+		&serde_yaml::to_string(&values)?,
+	)?;
+	if let Some(schema) = schema {
+		let contents = serde_json::to_string_pretty(&schema)?;
+		io::write_contents(
+			&args.output.join("values.schema.json"),
+			&format!("{contents}\n"),
+		)?;
+	}
+
+	for (path, contents) in documents {
+		io::write_contents(&template(&args.output, &path), &escape(&contents))?;
+	}
+
+	progress::message(&format!("Chart written at \"{}\"", args.output.display()));
+
+	Ok(())
+}
+
+/// Template of the object, the one rendered at the root being `main.yaml`
+fn template(chart: &Path, path: &Path) -> PathBuf {
+	let relative = path.strip_prefix("/").unwrap_or(path);
+	let relative = match relative.as_os_str().is_empty() {
+		true => PathBuf::from("main"),
+		false => relative.to_path_buf(),
+	};
+
+	let mut template = chart.join("templates").join(relative).into_os_string();
+	template.push(".yaml");
+
+	PathBuf::from(template)
+}
+
+/// Keeps Helm from evaluating what looks like actions, printing the
+/// delimiters as they are
+fn escape(contents: &str) -> String {
+	contents.replace("{{", "{{ \"{{\" }}")
+}
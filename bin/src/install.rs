@@ -1,17 +1,176 @@
-use crate::operation::compile;
+use crate::config::{Config, Outcome};
+use crate::error::Error;
+use crate::notify;
+use crate::operation::{compile, release};
+use crate::progress;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Result};
+use clap::{Args as Flags, Parser, ValueEnum};
+use kct_kube::merge::{self, Conflicts, Policy};
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DryRun {
+	Server,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Strategy {
+	#[default]
+	ServerSide,
+	ThreeWay,
+}
+
+/// How the objects go into the cluster, as install and upgrade take them
+#[derive(Flags)]
+pub struct Apply {
+	#[arg(
+		help = "install over objects that belong to other releases, or to none",
+		long
+	)]
+	take_ownership: bool,
+	#[arg(
+		help = "merge objects with the live ones server-side, or with the last applied ones too",
+		long,
+		value_enum,
+		default_value_t
+	)]
+	strategy: Strategy,
+	#[arg(
+		help = "settle fields a manager changed since the last apply, as manager=overwrite|keep|fail with * for any",
+		long = "conflict",
+		value_parser = conflict
+	)]
+	conflicts: Vec<(String, Policy)>,
+}
+
+impl Apply {
+	fn strategy(&self) -> Result<merge::Strategy, Error> {
+		match self.strategy {
+			Strategy::ThreeWay => Ok(merge::Strategy::ThreeWay(
+				self.conflicts.iter().cloned().collect::<Conflicts>(),
+			)),
+			Strategy::ServerSide if self.conflicts.is_empty() => Ok(merge::Strategy::ServerSide),
+			Strategy::ServerSide => Err(Error::InvalidInput(
+				"--conflict only settles three-way merges, pass --strategy three-way".to_string(),
+			)),
+		}
+	}
+}
 
 #[derive(Parser)]
 pub struct Args {
+	#[arg(
+		help = "validate objects against the cluster without persisting them",
+		long,
+		value_enum,
+		require_equals = true
+	)]
+	dry_run: Option<DryRun>,
+	#[command(flatten)]
+	apply: Apply,
 	#[command(flatten)]
 	compile: compile::Params,
 }
 
 pub async fn run(args: Args) -> Result<()> {
-	let kube = compile::run(args.compile)?;
-	kube.install().await?;
+	match args.dry_run {
+		None => install(args.compile, &args.apply, "install").await?,
+		Some(DryRun::Server) => {
+			let kubes = compile::run(args.compile)?;
+			let mut failures = 0;
+
+			for kube in kubes {
+				let target = kube.context().map(|ctx| format!("{ctx}: "));
+				let target = target.unwrap_or_default();
+				let checks = kube.validate().await?;
+				failures += checks.iter().filter(|check| !check.passed()).count();
+
+				for check in checks {
+					let path = check.path.display();
+
+					match (check.error, check.skipped) {
+						(Some(err), _) => eprintln!("{target}{path} invalid: {err}"),
+						(None, Some(reason)) => {
+							progress::message(&format!("{target}{path} skipped, {reason}"))
+						}
+						(None, None) => progress::message(&format!("{target}{path} valid")),
+					}
+				}
+			}
+
+			if failures > 0 {
+				bail!("{failures} object(s) were rejected by the cluster");
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Installs the objects into every target, storing the inputs of the release
+/// once they're all in so upgrades can reuse them, and the objects so status
+/// can compare them, then notifies about how
+/// the operation, install or upgrade, went
+pub async fn install(params: compile::Params, apply: &Apply, operation: &str) -> Result<()> {
+	let config = Config::load()?;
+	let mut event = json!({
+		"operation": operation,
+		"release": params.release(),
+		"package": null,
+		"targets": [],
+	});
+
+	let installed = deliver(params, apply, &mut event).await;
+
+	event["outcome"] = json!(match &installed {
+		Ok(()) => Outcome::Success.as_str(),
+		Err(_) => Outcome::Failure.as_str(),
+	});
+	if let Err(err) = &installed {
+		event["error"] = json!(format!("{err:#}"));
+	}
+	notify::send(&config.notifications, &event);
+
+	installed
+}
+
+/// Installs the objects, describing what went into the event as it's known
+async fn deliver(params: compile::Params, apply: &Apply, event: &mut Value) -> Result<()> {
+	let strategy = apply.strategy()?;
+	let compilations = compile::compilations(params.installing())?;
+	let stored = compilations.first().and_then(|compilation| {
+		event["package"] = compilation.metadata["package"].clone();
+		event["release"] = compilation.metadata["release"]["name"].clone();
+
+		let name = compilation.metadata["release"]["name"].as_str()?;
+
+		Some((name.to_string(), compilation.input.clone()))
+	});
+	event["targets"] = compilations
+		.iter()
+		.map(|compilation| json!(compilation.target))
+		.collect();
+
+	let kubes = compile::kubes(compilations)?;
+	let snapshot = release::snapshot(&kubes)?;
+
+	for kube in kubes {
+		kube.install(apply.take_ownership, &strategy).await?;
+	}
+
+	if let Some((name, input)) = stored {
+		release::store(&name, &input.unwrap_or_else(|| json!({})))?;
+		release::record(&name, &snapshot)?;
+	}
 
 	Ok(())
 }
+
+fn conflict(value: &str) -> Result<(String, Policy), String> {
+	let (manager, policy) = value
+		.split_once('=')
+		.ok_or_else(|| format!("{value} should be as manager=policy"))?;
+
+	Ok((manager.to_string(), policy.parse()?))
+}
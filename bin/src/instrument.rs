@@ -1,15 +1,58 @@
+use clap::ValueEnum;
+use kct_compiler::IMPORTS_TARGET;
 use tracing::error;
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::filter::{LevelFilter, Targets};
 use tracing_subscriber::fmt;
-use tracing_subscriber::{layer::SubscriberExt, Registry};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{layer::SubscriberExt, Layer, Registry};
 
 pub struct Guard {
 	_appender: WorkerGuard,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Level {
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl From<u8> for Level {
+	fn from(verbosity: u8) -> Self {
+		match verbosity {
+			0 => Level::Error,
+			1 => Level::Warn,
+			2 => Level::Info,
+			3 => Level::Debug,
+			_ => Level::Trace,
+		}
+	}
+}
+
+impl From<Level> for LevelFilter {
+	fn from(level: Level) -> Self {
+		match level {
+			Level::Error => LevelFilter::ERROR,
+			Level::Warn => LevelFilter::WARN,
+			Level::Info => LevelFilter::INFO,
+			Level::Debug => LevelFilter::DEBUG,
+			Level::Trace => LevelFilter::TRACE,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+	#[default]
+	Text,
+	Json,
+}
+
 #[must_use]
-pub fn init(level: u8) -> Guard {
+pub fn init(level: Level, format: Format, imports: bool) -> Guard {
 	let (writer, guard) = tracing_appender::non_blocking(std::io::stderr());
 
 	let logs = fmt::layer()
@@ -17,19 +60,21 @@ pub fn init(level: u8) -> Guard {
 		.with_target(true)
 		.with_level(true)
 		.with_line_number(true)
-		.with_ansi(true)
 		.with_thread_ids(true)
+		.with_span_events(FmtSpan::CLOSE)
 		.with_writer(writer);
 
-	let max_level = match level {
-		0 => LevelFilter::ERROR,
-		1 => LevelFilter::WARN,
-		2 => LevelFilter::INFO,
-		3 => LevelFilter::DEBUG,
-		_ => LevelFilter::TRACE,
+	let logs = match format {
+		Format::Text => logs.with_ansi(true).boxed(),
+		Format::Json => logs.json().with_current_span(true).boxed(),
 	};
 
-	let subscriber = Registry::default().with(max_level).with(logs);
+	let mut filter = Targets::new().with_default(LevelFilter::from(level));
+	if imports {
+		filter = filter.with_target(IMPORTS_TARGET, LevelFilter::DEBUG);
+	}
+
+	let subscriber = Registry::default().with(filter).with(logs);
 
 	tracing::subscriber::set_global_default(subscriber)
 		.expect("Unable to register tracing subscriber");
@@ -0,0 +1,61 @@
+use clap::{Arg, ArgAction, Command};
+use serde_json::{json, Value};
+
+/// Machine-readable description of the CLI, meant for tools wrapping `kct` that need to know which
+/// commands and options are available without scraping the help text.
+pub fn describe(command: &Command) -> Value {
+	let args: Vec<Value> = command
+		.get_arguments()
+		.filter(|arg| !arg.is_hide_set())
+		.map(describe_arg)
+		.collect();
+
+	let subcommands: Vec<Value> = command
+		.get_subcommands()
+		.filter(|cmd| !cmd.is_hide_set())
+		.map(describe)
+		.collect();
+
+	json!({
+		"name": command.get_name(),
+		"version": command.get_version(),
+		"about": command.get_about().map(|about| about.to_string()),
+		"aliases": command.get_visible_aliases().collect::<Vec<_>>(),
+		"args": args,
+		"subcommands": subcommands,
+	})
+}
+
+fn describe_arg(arg: &Arg) -> Value {
+	let takes_value = !matches!(
+		arg.get_action(),
+		ArgAction::SetTrue
+			| ArgAction::SetFalse
+			| ArgAction::Count
+			| ArgAction::Help
+			| ArgAction::Version
+	);
+
+	let possible: Vec<String> = if takes_value {
+		arg.get_possible_values()
+			.into_iter()
+			.map(|value| value.get_name().to_string())
+			.collect()
+	} else {
+		vec![]
+	};
+
+	json!({
+		"id": arg.get_id().as_str(),
+		"long": arg.get_long(),
+		"short": arg.get_short().map(String::from),
+		"help": arg.get_help().map(|help| help.to_string()),
+		"positional": arg.is_positional(),
+		"required": arg.is_required_set(),
+		"global": arg.is_global_set(),
+		"takes_value": takes_value,
+		"multiple": matches!(arg.get_action(), ArgAction::Append | ArgAction::Count),
+		"possible_values": possible,
+		"hint": format!("{:?}", arg.get_value_hint()),
+	})
+}
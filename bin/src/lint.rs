@@ -0,0 +1,131 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::progress;
+
+use anyhow::Result;
+use clap::Parser;
+use kct_compiler::lint::Finding;
+use kct_helper::policy::Action;
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "compile twice, at other times and with other random values, telling what changes",
+		long
+	)]
+	determinism: bool,
+	#[arg(
+		help = "lint the Jsonnet of templates and libs instead, without compiling",
+		long,
+		conflicts_with = "determinism"
+	)]
+	jsonnet: bool,
+	#[arg(
+		help = "lint the inputs instead, finding the ones the schema declares but templates never read, and the other way around",
+		long,
+		conflicts_with_all = ["determinism", "jsonnet"]
+	)]
+	inputs: bool,
+	#[arg(
+		help = "lint the files instead, finding the ones within files and lib nothing reads",
+		long,
+		conflicts_with_all = ["determinism", "jsonnet", "inputs"]
+	)]
+	files: bool,
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	if args.jsonnet {
+		return jsonnet(args.compile);
+	}
+
+	if args.inputs {
+		return inputs(args.compile);
+	}
+
+	if args.files {
+		return files(args.compile);
+	}
+
+	let (compilations, varying) = match args.determinism {
+		true => compile::determinism(args.compile)?,
+		false => (compile::lint(args.compile)?, vec![]),
+	};
+	let violations: Vec<_> = compilations
+		.iter()
+		.flat_map(|compilation| compilation.violations.iter())
+		.collect();
+
+	for violation in &violations {
+		println!(
+			"{} {}",
+			violation.action.as_str(),
+			compile::describe_violation(violation)
+		);
+	}
+
+	for path in &varying {
+		println!("varies {path}");
+	}
+
+	let denied = violations
+		.iter()
+		.filter(|violation| violation.action == Action::Deny)
+		.count();
+
+	match (violations.is_empty(), denied) {
+		(true, _) => progress::message("Objects satisfy every policy"),
+		(false, 0) => (),
+		(false, denied) => {
+			return Err(Error::Denied(format!("{denied} denials listed above")).into())
+		}
+	}
+
+	match (args.determinism, varying.len()) {
+		(false, _) => (),
+		(true, 0) => progress::message("Objects render the same every time"),
+		(true, varying) => return Err(Error::Nondeterministic(varying).into()),
+	}
+
+	Ok(())
+}
+
+fn jsonnet(params: compile::Params) -> Result<()> {
+	let findings = compile::jsonnet(params)?;
+
+	report(&findings, "Jsonnet passes every lint rule")
+}
+
+fn inputs(params: compile::Params) -> Result<()> {
+	let findings = compile::lint_inputs(params)?;
+
+	report(&findings, "Templates read every input the schema declares")
+}
+
+fn files(params: compile::Params) -> Result<()> {
+	let findings = compile::lint_files(params)?;
+
+	report(&findings, "Templates read every file of the package")
+}
+
+fn report(findings: &[Finding], clean: &str) -> Result<()> {
+	for finding in findings {
+		println!(
+			"{}:{}:{}: {} ({})",
+			finding.path.display(),
+			finding.line,
+			finding.column,
+			finding.message,
+			finding.rule.as_str()
+		);
+	}
+
+	match findings.len() {
+		0 => progress::message(clean),
+		findings => return Err(Error::Unlinted(findings).into()),
+	}
+
+	Ok(())
+}
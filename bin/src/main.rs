@@ -1,12 +1,40 @@
+mod audit;
+mod completions;
+mod config;
+mod daemon;
+mod diff;
+mod docs;
 mod error;
+mod export;
 mod install;
 mod instrument;
+mod introspect;
+mod lint;
+mod memory;
+mod notify;
 mod operation;
+mod outdated;
+mod package;
+mod progress;
+mod push;
 mod render;
+mod serve;
+mod status;
 mod uninstall;
+mod upgrade;
+mod vendor;
+mod verify;
+
+use crate::config::Config;
+use crate::error::Exit;
+
+use std::process::ExitCode;
 
 use anyhow::Result;
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+
+#[global_allocator]
+static ALLOCATOR: memory::Capped = memory::Capped;
 
 #[derive(Parser)]
 #[command(
@@ -22,8 +50,42 @@ use clap::{ArgAction, Parser, Subcommand};
 pub struct App {
 	#[arg(help = "increase logging levels", long, short, global = true, action = ArgAction::Count)]
 	verbose: u8,
+	#[arg(
+		help = "set the logging level, overrides verbose",
+		long,
+		global = true,
+		value_enum
+	)]
+	log_level: Option<instrument::Level>,
+	#[arg(
+		help = "set the format for the logs",
+		long,
+		global = true,
+		value_enum,
+		default_value_t
+	)]
+	log_format: instrument::Format,
+	#[arg(
+		help = "log every import, the resolver handling it, and why the others declined",
+		long,
+		global = true
+	)]
+	debug_imports: bool,
+	#[arg(
+		help = "only print the output, hiding progress and messages",
+		long,
+		short,
+		global = true
+	)]
+	quiet: bool,
+	#[arg(
+		help = "print the commands and options as JSON",
+		long,
+		exclusive = true
+	)]
+	help_json: bool,
 	#[command(subcommand)]
-	command: Command,
+	command: Option<Command>,
 }
 
 #[derive(Subcommand)]
@@ -40,25 +102,160 @@ pub enum Command {
 		about = "Puts your objects in the current cluster"
 	)]
 	Install(install::Args),
+	#[command(
+		name = "upgrade",
+		about = "Installs your objects again as a release, reusing its inputs when told to"
+	)]
+	Upgrade(upgrade::Args),
 	#[command(
 		name = "uninstall",
 		alias = "u",
 		about = "Removes your objects from the current cluster"
 	)]
 	Uninstall(uninstall::Args),
+	#[command(
+		name = "status",
+		alias = "s",
+		about = "Compares the objects a release was installed with to the ones in the cluster"
+	)]
+	Status(status::Args),
+	#[command(
+		name = "diff-packages",
+		about = "Compares the objects of two versions of your package, rendered with the same inputs"
+	)]
+	DiffPackages(diff::Packages),
+	#[command(
+		name = "diff-inputs",
+		about = "Compares the objects your package renders with two inputs"
+	)]
+	DiffInputs(diff::Inputs),
+	#[command(name = "lint", about = "Checks your objects against the policies")]
+	Lint(lint::Args),
+	#[command(
+		name = "push-manifests",
+		about = "Pushes your objects to an OCI registry as an artifact Flux can deploy"
+	)]
+	PushManifests(push::Args),
+	#[command(
+		name = "export",
+		about = "Converts your objects into the format of other tools"
+	)]
+	Export(export::Args),
+	#[command(
+		name = "vendor",
+		about = "Fetches the dependencies of your package into its vendor directory"
+	)]
+	Vendor(vendor::Args),
+	#[command(
+		name = "package",
+		about = "Archives your package, with its vendored dependencies, for distribution"
+	)]
+	Package(package::Args),
+	#[command(
+		name = "outdated",
+		about = "Lists newer releases of the dependencies of your package"
+	)]
+	Outdated(outdated::Args),
+	#[command(
+		name = "verify",
+		about = "Checks your vendored dependencies, or that a recorded render reproduces"
+	)]
+	Verify(verify::Args),
+	#[command(
+		name = "serve",
+		about = "Previews your objects in the browser as you change the inputs"
+	)]
+	Serve(serve::Args),
+	#[command(
+		name = "daemon",
+		about = "Compiles packages for build tools and editors over JSON-RPC from a running process"
+	)]
+	Daemon(daemon::Args),
+	#[command(name = "docs", about = "Generates documentation for your package")]
+	Docs(docs::Args),
+	#[command(name = "completions", about = "Generates completions for your shell")]
+	Completions(completions::Args),
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+	completions::complete();
+
 	let app = App::parse();
 
-	let _guard = instrument::init(app.verbose);
+	match run(app).await {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			eprintln!("Error: {err:?}");
+
+			Exit::from(&err).into()
+		}
+	}
+}
+
+async fn run(app: App) -> Result<()> {
+	if app.quiet {
+		progress::quiet();
+	}
+
+	if app.help_json {
+		let description = introspect::describe(&App::command());
+		println!("{}", serde_json::to_string_pretty(&description)?);
+
+		return Ok(());
+	}
+
+	let level = app.log_level.unwrap_or_else(|| app.verbose.into());
+	let _guard = instrument::init(level, app.log_format, app.debug_imports);
+
+	let command = match app.command {
+		Some(command) => command,
+		None => {
+			App::command().print_help()?;
+
+			return Ok(());
+		}
+	};
+
+	// Only the operations compiling or applying objects are audited
+	let audited = match &command {
+		Command::Render(_) => Some("render"),
+		Command::Install(_) => Some("install"),
+		Command::Upgrade(_) => Some("upgrade"),
+		Command::Uninstall(_) => Some("uninstall"),
+		Command::PushManifests(_) => Some("push-manifests"),
+		Command::Export(_) => Some("export"),
+		_ => None,
+	};
+	let config = match audited {
+		Some(_) => Config::load()?,
+		None => Config::default(),
+	};
+	audit::start(&config);
 
-	match app.command {
-		Command::Render(args) => render::run(args)?,
-		Command::Install(args) => install::run(args).await?,
-		Command::Uninstall(args) => uninstall::run(args).await?,
+	let result = match command {
+		Command::Render(args) => render::run(args),
+		Command::Install(args) => install::run(args).await,
+		Command::Upgrade(args) => upgrade::run(args).await,
+		Command::Uninstall(args) => uninstall::run(args).await,
+		Command::PushManifests(args) => push::run(args),
+		Command::Export(args) => export::run(args),
+		Command::Status(args) => status::run(args).await,
+		Command::DiffPackages(args) => diff::packages(args),
+		Command::DiffInputs(args) => diff::inputs(args),
+		Command::Lint(args) => lint::run(args),
+		Command::Vendor(args) => vendor::run(args),
+		Command::Package(args) => package::run(args),
+		Command::Outdated(args) => outdated::run(args),
+		Command::Verify(args) => verify::run(args),
+		Command::Serve(args) => serve::run(args).await,
+		Command::Daemon(args) => daemon::run(args).await,
+		Command::Docs(args) => docs::run(args),
+		Command::Completions(args) => completions::run(args),
 	};
 
-	Ok(())
+	match audited {
+		Some(operation) => audit::record(&config, operation, result),
+		None => result,
+	}
 }
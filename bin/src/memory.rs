@@ -0,0 +1,37 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+
+use kct_helper::memory::{hold, release};
+
+/// Allocator counting the memory held by the threads compiling against
+/// their budget, to fail them once going beyond `--max-memory` instead of
+/// being killed by the system out of memory
+pub struct Capped;
+
+unsafe impl GlobalAlloc for Capped {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		hold(layout.size());
+
+		System.alloc(layout)
+	}
+
+	unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+		hold(layout.size());
+
+		System.alloc_zeroed(layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		match new_size.checked_sub(layout.size()) {
+			Some(grown) => hold(grown),
+			None => release(layout.size() - new_size),
+		}
+
+		System.realloc(ptr, layout, new_size)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		release(layout.size());
+
+		System.dealloc(ptr, layout)
+	}
+}
@@ -0,0 +1,89 @@
+use crate::config::{Notification, Outcome, Target};
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Time each webhook has to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tells every notification interested in the outcome about the event,
+/// warning about the ones that couldn't be told instead of failing, as the
+/// operation already happened
+pub fn send(notifications: &[Notification], event: &Value) {
+	let outcome = match event["outcome"].as_str() {
+		Some("success") => Outcome::Success,
+		_ => Outcome::Failure,
+	};
+
+	for notification in notifications {
+		if !notification.on.contains(&outcome) {
+			continue;
+		}
+
+		let sent = match &notification.target {
+			Target::Webhook(url) => post(url, event),
+			Target::Slack(url) => post(url, &json!({ "text": describe(event) })),
+			Target::Exec(command) => exec(command, event),
+		};
+
+		if let Err(reason) = sent {
+			eprintln!(
+				"Warning: unable to notify about the {}, {reason}",
+				event["operation"].as_str().unwrap_or("operation")
+			);
+		}
+	}
+}
+
+/// One line about the event, as `install of prod (web 1.2.0) succeeded`
+pub fn describe(event: &Value) -> String {
+	let operation = event["operation"].as_str().unwrap_or("operation");
+	let release = event["release"].as_str().unwrap_or("no release");
+
+	let package = &event["package"];
+	let package = match (package["name"].as_str(), package["version"].as_str()) {
+		(Some(name), Some(version)) => format!(" ({name} {version})"),
+		_ => String::new(),
+	};
+
+	match &event["error"] {
+		Value::String(error) => format!("{operation} of {release}{package} failed: {error}"),
+		_ => format!("{operation} of {release}{package} succeeded"),
+	}
+}
+
+/// Posts the body as JSON, telling why it wasn't taken otherwise
+pub fn post(url: &str, body: &Value) -> Result<(), String> {
+	ureq::AgentBuilder::new()
+		.timeout(TIMEOUT)
+		.build()
+		.post(url)
+		.set("Content-Type", "application/json")
+		.send_string(&body.to_string())
+		.map(|_| ())
+		.map_err(|err| err.to_string())
+}
+
+fn exec(command: &[String], event: &Value) -> Result<(), String> {
+	let (program, args) = command.split_first().ok_or("there's no program")?;
+
+	let mut child = Command::new(program)
+		.args(args)
+		.stdin(Stdio::piped())
+		.spawn()
+		.map_err(|err| format!("{program} didn't start, {err}"))?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		// Programs may not read it, closing it early
+		let _ = stdin.write_all(event.to_string().as_bytes());
+	}
+
+	let status = child.wait().map_err(|err| err.to_string())?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(format!("{program} exited with {status}")),
+	}
+}
@@ -0,0 +1,160 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use clap::{Args, ValueHint};
+use kct_helper::io;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_json::{json, Value};
+
+const STATEMENT: &str = "https://in-toto.io/Statement/v1";
+const PROVENANCE: &str = "https://slsa.dev/provenance/v1";
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+const BUILDER: &str = env!("CARGO_PKG_REPOSITORY");
+
+#[derive(Args, Clone, Default)]
+pub struct Attestation {
+	#[arg(
+		help = "file to write an in-toto attestation with the SLSA provenance into",
+		long,
+		value_hint = ValueHint::FilePath
+	)]
+	attest: Option<PathBuf>,
+	#[arg(
+		help = "Ed25519 or ECDSA P-256 key in PKCS#8 PEM to sign the attestation with",
+		long,
+		requires = "attest",
+		value_hint = ValueHint::FilePath
+	)]
+	sign_key: Option<PathBuf>,
+}
+
+impl Attestation {
+	pub fn requested(&self) -> bool {
+		self.attest.is_some()
+	}
+
+	/// Writes the provenance of the subjects, names and SHA-256 digests in
+	/// hex, when asked to. The build is described by the metadata of the
+	/// compilation, or of the package alone, and signed attestations are
+	/// written as DSSE envelopes.
+	pub fn attest(
+		&self,
+		build: &str,
+		metadata: &Value,
+		subjects: &[(String, String)],
+	) -> Result<()> {
+		let path = match &self.attest {
+			Some(path) => path,
+			None => return Ok(()),
+		};
+
+		let statement = statement(build, metadata, subjects)?;
+		let attestation = match &self.sign_key {
+			Some(key) => envelope(&statement, key)?,
+			None => statement,
+		};
+
+		let contents = serde_json::to_string_pretty(&attestation)?;
+		io::write_contents(path, &format!("{contents}\n"))?;
+
+		Ok(())
+	}
+}
+
+fn statement(build: &str, metadata: &Value, subjects: &[(String, String)]) -> Result<Value> {
+	let subjects: Vec<Value> = subjects
+		.iter()
+		.map(|(name, digest)| json!({ "name": name, "digest": { "sha256": digest } }))
+		.collect();
+
+	let package = &metadata["package"];
+	let digest = package["digest"].as_str().unwrap_or_default();
+	let digest: String = STANDARD
+		.decode(digest)?
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect();
+
+	let mut dependencies = vec![json!({
+		"name": package["name"],
+		"digest": { "sha256": digest },
+	})];
+	if let Value::Array(locked) = &package["dependencies"] {
+		dependencies.extend(locked.iter().map(|locked| {
+			let remote = locked["remote"].as_str().unwrap_or_default();
+			let remote = remote.strip_prefix("git+").unwrap_or(remote);
+
+			json!({
+				"name": locked["name"],
+				"uri": format!("git+{remote}@{}", locked["rev"].as_str().unwrap_or_default()),
+				"digest": { "gitCommit": locked["rev"] },
+			})
+		}));
+	}
+
+	Ok(json!({
+		"_type": STATEMENT,
+		"subject": subjects,
+		"predicateType": PROVENANCE,
+		"predicate": {
+			"buildDefinition": {
+				"buildType": format!("{BUILDER}/{build}@v1"),
+				"externalParameters": {
+					"package": { "name": package["name"], "version": package["version"] },
+					"release": metadata["release"],
+					"inputsDigest": metadata["inputsDigest"],
+				},
+				"resolvedDependencies": dependencies,
+			},
+			"runDetails": {
+				"builder": {
+					"id": BUILDER,
+					"version": { "kct": env!("CARGO_PKG_VERSION") },
+				},
+				"metadata": { "finishedOn": Utc::now().to_rfc3339() },
+			},
+		},
+	}))
+}
+
+/// Wraps the statement into a DSSE envelope signed with the key
+fn envelope(statement: &Value, key: &Path) -> Result<Value> {
+	let invalid = || Error::InvalidKey(key.display().to_string());
+
+	let payload = serde_json::to_vec(statement)?;
+	let pem = pem::parse(io::from_file(key)?).map_err(|_err| invalid())?;
+
+	// Pre-authentication encoding, binding the type to what's signed
+	let mut message = format!(
+		"DSSEv1 {} {PAYLOAD_TYPE} {} ",
+		PAYLOAD_TYPE.len(),
+		payload.len()
+	)
+	.into_bytes();
+	message.extend(&payload);
+
+	let signature = match Ed25519KeyPair::from_pkcs8_maybe_unchecked(&pem.contents) {
+		Ok(pair) => pair.sign(&message).as_ref().to_vec(),
+		Err(_) => {
+			let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &pem.contents)
+				.map_err(|_err| invalid())?;
+
+			pair.sign(&SystemRandom::new(), &message)
+				.map_err(|_err| invalid())?
+				.as_ref()
+				.to_vec()
+		}
+	};
+
+	Ok(json!({
+		"payloadType": PAYLOAD_TYPE,
+		"payload": STANDARD.encode(payload),
+		"signatures": [{ "keyid": "", "sig": STANDARD.encode(signature) }],
+	}))
+}
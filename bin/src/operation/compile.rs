@@ -1,69 +1,980 @@
+use crate::audit;
+use crate::completions;
+use crate::config::Config;
 use crate::error::Error;
+use crate::operation::overrides::{self, Override};
+use crate::operation::{self, prompt, release as store};
+use crate::progress;
 
-use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::error::Error as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use clap::Parser;
-use kct_cli::{Input, Paths, Set};
-use kct_compiler::Release;
-use kct_helper::json::merge;
-use kct_kube::Kube;
-use kct_package::Package;
-use serde_json::{Map, Value};
+use chrono::{DateTime, Utc};
+use clap::{Parser, ValueEnum, ValueHint};
+use clap_complete::engine::ArgValueCompleter;
+use kct_cli::{Input, Paths, Set, SetFile, SetString, Values};
+use kct_compiler::lint::Finding;
+use kct_compiler::source::Sources;
+use kct_compiler::{Context, Http, Release};
+use kct_helper::io;
+use kct_helper::json::{self, merge};
+use kct_helper::memory::{self, Budget};
+use kct_helper::policy::{self, Action, Namespaces, Policy, Violation};
+use kct_helper::quantity::Quantity;
+use kct_kube::stamp::{self, Keys, Stamp};
+use kct_kube::{scope, Cluster, Kube};
+use kct_package::{self as package, Capability, Error as PackageError, Integrity, Output, Package};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use tera::{Context as TeraContext, Tera};
+
+/// What happens to cluster-scoped objects, as restricted pipelines refuse
+/// tenants shipping them
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Scoped {
+	#[default]
+	Allow,
+	Reject,
+	Strip,
+}
 
 #[derive(Parser, Clone)]
 pub struct Params {
-	#[arg(help = "directory with the package to compile")]
+	#[arg(
+		help = "directory or archive with the package to compile, or - for one from STDIN",
+		value_hint = ValueHint::DirPath
+	)]
 	package: PathBuf,
-	#[arg(help = "set multiple values for the package", long, short)]
+	#[arg(
+		help = "set multiple values for the package",
+		long,
+		short,
+		add = ArgValueCompleter::new(completions::inputs)
+	)]
 	input: Option<Vec<Input>>,
 	#[arg(help = "set specific parameters for the package", long, short)]
 	set: Option<Vec<Set>>,
-	#[arg(help = "scope your package within a release", long)]
+	#[arg(
+		help = "Helm-style values in YAML or JSON, merged after the inputs",
+		long,
+		short = 'f',
+		value_hint = ValueHint::FilePath
+	)]
+	values: Option<Vec<Values>>,
+	#[arg(
+		help = "set parameters to strings, Helm-style as path=value[,path=value]",
+		long
+	)]
+	set_string: Option<Vec<SetString>>,
+	#[arg(
+		help = "set parameters to the contents of files, Helm-style as path=file[,path=file]",
+		long
+	)]
+	set_file: Option<Vec<SetFile>>,
+	#[arg(help = "ask for the inputs the schema requires that are missing", long)]
+	interactive: bool,
+	#[arg(
+		help = "file to save the answers of --interactive into, as JSON to pass with --input",
+		long,
+		requires = "interactive",
+		value_hint = ValueHint::FilePath
+	)]
+	save_answers: Option<PathBuf>,
+	#[arg(
+		help = "scope your package within a release",
+		long,
+		add = ArgValueCompleter::new(completions::releases)
+	)]
 	release: Option<String>,
+	#[arg(
+		help = "kubeconfig contexts the release goes into",
+		long = "target",
+		short = 't',
+		requires = "release",
+		add = ArgValueCompleter::new(completions::targets)
+	)]
+	targets: Option<Vec<String>>,
+	#[arg(
+		help = "RFC 3339 time seen by templates, defaults to now",
+		long,
+		requires = "release"
+	)]
+	timestamp: Option<DateTime<Utc>>,
+	#[arg(
+		help = "secret to derive values that must persist across renders",
+		long,
+		env = "KCT_SEED",
+		hide_env_values = true,
+		requires = "release"
+	)]
+	seed: Option<String>,
+	#[arg(
+		help = "directory to search for libraries before the ones of the package, the right-most first",
+		long = "jpath",
+		short = 'J',
+		value_hint = ValueHint::DirPath
+	)]
+	libraries: Option<Vec<PathBuf>>,
+	#[arg(help = "let templates read objects from the cluster", long)]
+	lookup: bool,
+	#[arg(
+		help = "refuse templates anything reaching the network, using cached responses only",
+		long,
+		conflicts_with = "lookup"
+	)]
+	offline: bool,
+	#[arg(
+		help = "URL prefix templates are allowed to request",
+		long = "allow-url"
+	)]
+	allow_urls: Option<Vec<String>>,
+	#[arg(
+		help = "seconds to reuse responses requested by templates",
+		long,
+		default_value_t = 3600
+	)]
+	http_cache_ttl: u64,
+	#[arg(help = "don't cache responses requested by templates", long)]
+	no_http_cache: bool,
+	#[arg(
+		help = "YAML or JSON file with CEL policies rendered objects must satisfy",
+		long = "policy",
+		value_hint = ValueHint::FilePath
+	)]
+	policies: Option<Vec<PathBuf>>,
+	#[arg(
+		help = "YAML or JSON file with patches of rendered objects by their addresses, or a directory of them",
+		long = "patch",
+		value_hint = ValueHint::AnyPath
+	)]
+	patches: Option<Vec<PathBuf>>,
+	#[arg(
+		help = "namespace objects may go into, or prefix ending in *, refusing the others",
+		long = "allow-namespace"
+	)]
+	allow_namespaces: Option<Vec<String>>,
+	#[arg(
+		help = "allow, reject, or strip with a warning cluster-scoped objects",
+		long,
+		value_enum,
+		default_value_t
+	)]
+	cluster_scoped: Scoped,
 	#[arg(help = "comma separated paths to compile", long)]
 	only: Option<Paths>,
 	#[arg(help = "comma separated paths to not compile", long)]
 	except: Option<Paths>,
+	#[arg(help = "prefix for the tracking labels and annotations", long, default_value = stamp::DEFAULT_PREFIX)]
+	label_prefix: String,
+	#[arg(help = "don't inject tracking labels and annotations", long)]
+	no_labels: bool,
+	#[arg(
+		help = "require vendored dependencies to be locked and match the lockfile",
+		long,
+		conflicts_with = "update"
+	)]
+	frozen: bool,
+	#[arg(
+		help = "record vendored dependencies into the lockfile instead of refusing drifts",
+		long
+	)]
+	update: bool,
+	#[arg(
+		help = "memory evaluating each compile may hold, such as 2Gi, failing once it goes beyond",
+		long,
+		env = "KCT_MAX_MEMORY"
+	)]
+	max_memory: Option<Quantity>,
+	/// Inputs the release was last installed with, under every other
+	#[arg(skip)]
+	reused: Option<Value>,
+	/// Whether the objects go into the cluster
+	#[arg(skip)]
+	installing: bool,
+}
+
+impl Params {
+	/// Rebuilds the release in the record, at the time it happened, unless
+	/// told otherwise
+	pub fn reproducing(mut self, record: &Value) -> Self {
+		let release = &record["release"];
+
+		if self.release.is_none() {
+			self.release = release["name"].as_str().map(String::from);
+		}
+
+		if self.timestamp.is_none() {
+			self.timestamp = release["timestamp"]
+				.as_str()
+				.and_then(|timestamp| timestamp.parse().ok());
+		}
+
+		self
+	}
+
+	/// Upgrades the release, merging the inputs over the ones it was last
+	/// installed with when given
+	pub fn upgrading(mut self, name: String, reused: Option<Value>) -> Self {
+		self.release = Some(name);
+		self.reused = reused;
+
+		self
+	}
+
+	/// Goes into the cluster, so the release gets a seed stored when it's
+	/// given none
+	pub fn installing(mut self) -> Self {
+		self.installing = true;
+
+		self
+	}
+
+	/// Compiles the package at the path instead, at the time unless told
+	/// otherwise, so it can be compared with other compilations
+	pub fn comparing(self, package: PathBuf, timestamp: DateTime<Utc>) -> Self {
+		Self { package, ..self }.at(timestamp)
+	}
+
+	/// Compiles at the time unless told otherwise, so compilations being
+	/// compared see the same one
+	pub fn at(mut self, timestamp: DateTime<Utc>) -> Self {
+		self.timestamp = self.timestamp.or(Some(timestamp));
+
+		self
+	}
+
+	/// Keys of the tracking labels and annotations
+	pub fn keys(&self) -> Keys {
+		Keys::prefixed(&self.label_prefix)
+	}
+
+	/// Name of the release, before resolving it when it's a template
+	pub fn release(&self) -> Option<&str> {
+		self.release.as_deref()
+	}
+
+	/// Directory of the package, or `-` when it's piped into STDIN
+	pub fn package(&self) -> &Path {
+		&self.package
+	}
+
+	fn integrity(&self) -> Integrity {
+		match (self.frozen, self.update) {
+			(true, _) => Integrity::Frozen,
+			(_, true) => Integrity::Update,
+			_ => Integrity::Verify,
+		}
+	}
+}
+
+/// What the package renders into, according to its output
+pub enum Rendered {
+	Objects(Box<Kube>),
+	/// Paths and contents of files, which aren't meant for clusters
+	Files(Vec<(PathBuf, String)>),
+}
+
+/// Objects compiled for a target along where they came from
+pub struct Compilation {
+	pub rendered: Rendered,
+	pub target: Option<String>,
+	/// Package, release, compile time, and inputs digest as JSON
+	pub metadata: Value,
+	/// Input the package was compiled with, kept apart from the metadata as
+	/// it may hold secrets
+	pub input: Option<Value>,
+	/// Paths given to `--only`, to tell which didn't match any object
+	pub only: Vec<PathBuf>,
+	/// Objects failing the policies
+	pub violations: Vec<Violation>,
+	/// Templates the objects were compiled from
+	pub sources: Option<Sources>,
+}
+
+/// Compiles the package once per target, each becoming its own set of objects
+pub fn run(args: Params) -> Result<Vec<Kube>> {
+	kubes(compilations(args)?)
+}
+
+/// Objects of the compilations, refusing the ones rendering files
+pub fn kubes(compilations: Vec<Compilation>) -> Result<Vec<Kube>> {
+	compilations
+		.into_iter()
+		.map(|compilation| match compilation.rendered {
+			Rendered::Objects(kube) => Ok(*kube),
+			Rendered::Files(_) => Err(Error::RendersFiles.into()),
+		})
+		.collect()
+}
+
+pub fn compilations(args: Params) -> Result<Vec<Compilation>> {
+	let compilations = lint(args)?;
+	enforce(&compilations)?;
+
+	Ok(compilations)
+}
+
+/// Compiles the package once per target as [compilations], telling the
+/// policies objects fail without refusing them
+pub fn lint(args: Params) -> Result<Vec<Compilation>> {
+	let input = inputs(&args, None)?;
+
+	// Kept until every target is compiled, as templates read from the package
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+	let input = answered(&args, &package, input)?;
+
+	// Frozen once so every target sees the same time
+	let timestamp = args.timestamp.unwrap_or_else(Utc::now);
+
+	targets(&args, &package, input, timestamp)
+}
+
+/// Compiles the package as [compilations], along the input it was given and
+/// its schema, for exporting the objects into other formats
+pub fn export(args: Params) -> Result<(Vec<Compilation>, Option<Value>, Option<Value>)> {
+	let input = inputs(&args, None)?;
+
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+	let input = answered(&args, &package, input)?;
+
+	let timestamp = args.timestamp.unwrap_or_else(Utc::now);
+	let schema = package
+		.select(input.as_ref())
+		.ok()
+		.and_then(|(schema, _)| schema)
+		.map(|schema| schema.value().clone());
+
+	let compilations = targets(&args, &package, input.clone(), timestamp)?;
+	enforce(&compilations)?;
+
+	Ok((compilations, input, schema))
 }
 
-pub fn run(args: Params) -> Result<Kube> {
-	let input = {
-		let mut inputs = args
-			.input
-			.unwrap_or_default()
-			.into_iter()
-			.map(|input| input.into())
-			.collect::<Vec<Value>>();
+/// Compiles the package as [compilations] with the input merged over the
+/// given ones, as [matrix] does with its entries, for previewing changes
+pub fn preview(args: &Params, input: Value) -> Result<Vec<Compilation>> {
+	let input = inputs(args, Some(input))?;
+
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+
+	let timestamp = args.timestamp.unwrap_or_else(Utc::now);
+	let compilations = targets(args, &package, input, timestamp)?;
+	enforce(&compilations)?;
+
+	Ok(compilations)
+}
+
+/// Latest schema of the package along its example, describing its inputs
+pub fn schema(args: &Params) -> Result<(Option<Value>, Option<Value>)> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+	let schema = package.latest_schema().map(|schema| schema.value().clone());
+
+	Ok((schema, package.example.clone()))
+}
+
+/// Identifies the package as [describe] does, along its schema and example
+pub fn inspect(args: &Params) -> Result<Value> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+	let schema = package.latest_schema().map(|schema| schema.value().clone());
+
+	Ok(json!({
+		"package": describe(&package)?,
+		"schema": schema,
+		"example": package.example,
+	}))
+}
+
+/// Compiles the package as [lint], then once more with the time a day, an
+/// hour, a minute, and a second later, and other random values unless seeded,
+/// telling the fields of objects, or the files, that changed between both
+pub fn determinism(args: Params) -> Result<(Vec<Compilation>, Vec<String>)> {
+	let input = inputs(&args, None)?;
+
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+	let input = answered(&args, &package, input)?;
 
-		let sets = args
-			.set
-			.unwrap_or_default()
-			.into_iter()
-			.map(|set| set.into())
-			.collect::<Vec<Value>>();
+	let timestamp = args.timestamp.unwrap_or_else(Utc::now);
+	let later = timestamp + chrono::Duration::seconds(90061);
 
-		inputs.extend(sets);
+	let compilations = targets(&args, &package, input.clone(), timestamp)?;
+	let again = targets(&args, &package, input, later)?;
+
+	// Only changes along the rest of the object, so it would be repeated
+	let hash = Keys::prefixed(&args.label_prefix).hash.replace('/', "~1");
+	let hash = format!("/metadata/annotations/{hash}");
+
+	let mut varying = vec![];
+	for (compilation, again) in compilations.iter().zip(&again) {
+		let target = compilation.target.as_ref().map(|ctx| format!("{ctx}: "));
+		let target = target.unwrap_or_default();
+
+		let (first, second) = (outputs(compilation)?, outputs(again)?);
+		let paths: BTreeSet<&PathBuf> = first.keys().chain(second.keys()).collect();
+
+		for path in paths {
+			let shown = path.display();
+
+			let (first, second) = match (first.get(path), second.get(path)) {
+				(Some(first), Some(second)) => (first, second),
+				_ => {
+					varying.push(format!("{target}{shown}"));
+					continue;
+				}
+			};
+
+			let mut fields = json::diverging(first, second);
+			fields.extend(json::diverging(second, first));
+			let fields: BTreeSet<String> = fields.into_iter().filter(|at| at != &hash).collect();
+
+			varying.extend(fields.into_iter().map(|at| match at.is_empty() {
+				true => format!("{target}{shown}"),
+				false => format!("{target}{shown} {at}"),
+			}));
+		}
+	}
+
+	Ok((compilations, varying))
+}
+
+/// Objects, or files as strings, by their paths
+fn outputs(compilation: &Compilation) -> Result<BTreeMap<PathBuf, Value>> {
+	match &compilation.rendered {
+		Rendered::Objects(kube) => Ok(kube.render()?.into_iter().map(Into::into).collect()),
+		Rendered::Files(files) => Ok(files
+			.iter()
+			.map(|(path, contents)| (path.clone(), Value::String(contents.clone())))
+			.collect()),
+	}
+}
+
+/// Lints the Jsonnet of the package without compiling it
+pub fn jsonnet(args: Params) -> Result<Vec<Finding>> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+
+	Ok(package.lint()?)
+}
+
+/// Lints the inputs of the package against its templates without compiling it
+pub fn lint_inputs(args: Params) -> Result<Vec<Finding>> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+
+	Ok(package.lint_inputs()?)
+}
+
+/// Lints the files of the package against its templates without compiling it
+pub fn lint_files(args: Params) -> Result<Vec<Finding>> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+
+	Ok(package.lint_files()?)
+}
+
+/// Checks the vendored dependencies of the package against its lockfile
+pub fn verify(args: Params) -> Result<()> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+
+	Ok(())
+}
+
+/// Compiles the package for each input file, named after it, loading the
+/// package only once. Files are merged over `--input` and under `--set`.
+/// Each input is handed over as soon as it's compiled, so its compilations
+/// are dropped before compiling the next.
+pub fn matrix(
+	args: Params,
+	files: &[PathBuf],
+	mut each: impl FnMut(String, Vec<Compilation>) -> Result<()>,
+) -> Result<()> {
+	let (package, _unpacked) = operation::load(&args.package)?;
+	package.verify(args.integrity())?;
+
+	let timestamp = args.timestamp.unwrap_or_else(Utc::now);
+
+	let mut names: Vec<String> = vec![];
+	for file in files {
+		let name = file
+			.file_stem()
+			.map(|stem| stem.to_string_lossy().to_string())
+			.unwrap_or_default();
+
+		if names.contains(&name) {
+			let reason = format!("{} is the second input named \"{name}\"", file.display());
+
+			return Err(Error::InvalidInput(reason).into());
+		}
+
+		names.push(name);
+	}
+
+	for (file, name) in files.iter().zip(names) {
+		let entry = io::from_file(file)?;
+		let entry = serde_json::from_str(&entry)
+			.map_err(|err| Error::InvalidInput(format!("{}: {err}", file.display())))?;
+
+		let input = inputs(&args, Some(entry))?;
+		let compilations = targets(&args, &package, input, timestamp)?;
+		enforce(&compilations)?;
+
+		each(name, compilations)?;
+	}
 
-		merge_inputs(&inputs)?
+	Ok(())
+}
+
+/// Directories given with `-J`, in the order they're searched, as jsonnet
+/// does with the right-most winning
+fn libraries(args: &Params) -> Vec<PathBuf> {
+	let mut libraries = args.libraries.clone().unwrap_or_default();
+	libraries.reverse();
+
+	libraries
+}
+
+fn inputs(args: &Params, entry: Option<Value>) -> Result<Option<Value>, Error> {
+	let mut inputs = args
+		.input
+		.clone()
+		.unwrap_or_default()
+		.into_iter()
+		.map(|input| input.into())
+		.collect::<Vec<Value>>();
+	inputs.splice(0..0, args.reused.clone());
+
+	let values = args.values.clone().unwrap_or_default();
+	inputs.extend(values.into_iter().map(Value::from));
+
+	let sets = args
+		.set
+		.clone()
+		.unwrap_or_default()
+		.into_iter()
+		.map(|set| set.into())
+		.collect::<Vec<Value>>();
+
+	// Helm applies strings and files after the other sets
+	let strings = args.set_string.clone().unwrap_or_default();
+	let files = args.set_file.clone().unwrap_or_default();
+
+	inputs.extend(entry);
+	inputs.extend(sets);
+	inputs.extend(strings.into_iter().map(Value::from));
+	inputs.extend(files.into_iter().map(Value::from));
+
+	merge_inputs(&inputs)
+}
+
+/// Asks for the inputs the schema requires that are missing when compiling
+/// interactively, merged into the input and saved when told to
+fn answered(args: &Params, package: &Package, input: Option<Value>) -> Result<Option<Value>> {
+	if !args.interactive {
+		return Ok(input);
+	}
+
+	let schema = match package.select(input.as_ref()) {
+		Ok((Some(schema), _)) => schema,
+		// Refused along the compilation, which tells why
+		_ => return Ok(input),
 	};
 
-	let package = Package::try_from(args.package.as_path())?;
+	let answers = prompt::missing(schema.value(), input.as_ref().unwrap_or(&Value::Null))?;
+	if let Some(path) = &args.save_answers {
+		let contents = serde_json::to_string_pretty(&answers)?;
+		io::write_contents(path, &format!("{contents}\n"))?;
+	}
 
-	let release = args.release.map(|name| Release { name });
-	let rendered = package.compile(input, release)?;
+	if answers.as_object().is_some_and(Map::is_empty) {
+		return Ok(input);
+	}
 
-	let only: Vec<PathBuf> = args.only.map(|v| v.into()).unwrap_or_default();
-	let except: Vec<PathBuf> = args.except.map(|v| v.into()).unwrap_or_default();
+	let mut merged = input.unwrap_or_else(|| json!({}));
+	merge(&mut merged, &answers);
 
-	let kube = Kube::builder()
-		.only(only)
-		.except(except)
-		.value(rendered)
-		.build()?;
+	Ok(Some(merged))
+}
+
+/// Refuses compilations with objects denied by the policies, warning about
+/// the others
+fn enforce(compilations: &[Compilation]) -> Result<(), Error> {
+	let violations = compilations
+		.iter()
+		.flat_map(|compilation| compilation.violations.iter());
+	let (denied, warned): (Vec<&Violation>, Vec<&Violation>) =
+		violations.partition(|violation| violation.action == Action::Deny);
+
+	for violation in warned {
+		eprintln!("Warning: {}", describe_violation(violation));
+	}
 
-	Ok(kube)
+	if denied.is_empty() {
+		return Ok(());
+	}
+
+	let denied: Vec<String> = denied.into_iter().map(describe_violation).collect();
+
+	Err(Error::Denied(denied.join("; ")))
+}
+
+pub fn describe_violation(violation: &Violation) -> String {
+	format!(
+		"{}: {} ({})",
+		violation.path.display(),
+		violation.message,
+		violation.policy
+	)
+}
+
+fn targets(
+	args: &Params,
+	package: &Package,
+	input: Option<Value>,
+	timestamp: DateTime<Utc>,
+) -> Result<Vec<Compilation>> {
+	let mut policies = vec![];
+	for path in args.policies.iter().flatten() {
+		let contents = io::from_file(path)?;
+		let declared = Policy::parse(&contents)
+			.map_err(|err| Error::InvalidInput(format!("{}: {err}", path.display())))?;
+
+		policies.extend(declared);
+	}
+
+	let overrides = overrides::load(args.patches.as_deref().unwrap_or_default())?;
+
+	let config = Config::load()?;
+
+	// The ones of the config can't be widened from the command line
+	let allowlists: Vec<Namespaces> = [config.namespaces, args.allow_namespaces.clone()]
+		.into_iter()
+		.flatten()
+		.map(Namespaces::new)
+		.collect();
+
+	let treatment = Treatment {
+		policies,
+		allowlists,
+		overrides,
+	};
+
+	// Targets given on the command line replace the ones declared for the release
+	let declared = match (&args.targets, &args.release) {
+		(Some(targets), _) if !targets.is_empty() => targets.clone(),
+		(_, Some(release)) => {
+			let name = release_name(release, input.as_ref())?;
+			config.releases.get(&name).cloned().unwrap_or_default()
+		}
+		_ => vec![],
+	};
+	if declared.iter().any(String::is_empty) {
+		return Err(Error::InvalidInput("targets can't have empty names".to_string()).into());
+	}
+	let targets: Vec<Option<String>> = match declared.is_empty() {
+		true => vec![None],
+		false => declared.into_iter().map(Some).collect(),
+	};
+
+	targets
+		.into_iter()
+		.map(|target| {
+			compile(
+				args,
+				package.clone(),
+				input.clone(),
+				target,
+				timestamp,
+				&treatment,
+			)
+		})
+		.collect()
+}
+
+/// What the objects of every target go through once compiled, read once for
+/// all of them
+struct Treatment {
+	policies: Vec<Policy>,
+	allowlists: Vec<Namespaces>,
+	overrides: Vec<Override>,
+}
+
+fn compile(
+	args: &Params,
+	package: Package,
+	input: Option<Value>,
+	target: Option<String>,
+	timestamp: DateTime<Utc>,
+	treatment: &Treatment,
+) -> Result<Compilation> {
+	let Treatment {
+		policies,
+		allowlists,
+		overrides,
+	} = treatment;
+
+	let name = match &args.release {
+		Some(release) => Some(release_name(release, input.as_ref())?),
+		None => None,
+	};
+
+	let stamp = Stamp {
+		keys: Keys::prefixed(&args.label_prefix),
+		release: name.clone(),
+		package: Some(package.spec.name.clone()),
+		version: Some(package.spec.version.to_string()),
+	};
+
+	// Releases given no seed use the one stored for them, as values derived
+	// from nothing would rotate on every render
+	let seed = match (&args.seed, &name) {
+		(Some(seed), _) => Some(seed.clone()),
+		(None, Some(name)) if package.capabilities().contains(&Capability::Secrets) => {
+			store::seed(name, args.installing)?
+		}
+		_ => None,
+	};
+
+	let release = name.map(|name| Release {
+		name,
+		target: target.clone(),
+		timestamp: Some(timestamp),
+		seed,
+	});
+	let http = Http {
+		allow: args.allow_urls.clone().unwrap_or_default(),
+		cache: if args.no_http_cache {
+			None
+		} else {
+			http_cache()
+		},
+		ttl: Duration::from_secs(args.http_cache_ttl),
+	};
+	let progress = progress::bars();
+	let context = Context::builder()
+		.root(package.root.clone())
+		.release(release)
+		.progress(progress.clone())
+		.http(http)
+		.offline(args.offline)
+		.libraries(libraries(args))
+		.now(timestamp);
+
+	let context = if args.lookup {
+		context.lookup(Arc::new(Cluster::connect(target.as_deref())?))
+	} else {
+		context
+	};
+
+	let context = context.build().map_err(PackageError::from)?;
+
+	let digest = {
+		let input = serde_json::to_string(&input.clone().unwrap_or(Value::Null))?;
+
+		format!("{:x}", Sha256::digest(input.as_bytes()))
+	};
+	let metadata = json!({
+		"package": describe(&package)?,
+		"release": context.release().as_ref().map(|release| json!({
+			"name": release.name,
+			"target": release.target,
+			"timestamp": release.timestamp.map(|time| time.to_rfc3339()),
+		})),
+		"inputsDigest": digest,
+	});
+
+	let output = package.spec.output;
+	let name = package.spec.name.clone();
+	let mut sources = package.sources()?;
+	sources
+		.libraries
+		.splice(0..0, context.libraries().iter().cloned());
+	let started = Instant::now();
+	let budget = args
+		.max_memory
+		.as_ref()
+		.map(|memory| Budget::new(memory.as_f64().max(1.0) as usize));
+	let mut rendered = memory::within(budget, || package.compile_in(&context, input.clone()))?;
+	let elapsed = started.elapsed();
+
+	let mut metadata = metadata;
+	metadata["durations"] = json!({ "compile": elapsed.as_secs_f64() * 1000.0 });
+	audit::note(&metadata);
+
+	let files = match output {
+		Output::Kubernetes => None,
+		Output::Files => Some(package::artifacts(&rendered)?),
+		// Text is always manifested as a string, written after the package name
+		Output::Text => {
+			let text = rendered.as_str().unwrap_or_default().to_string();
+
+			Some(vec![(PathBuf::from("/").join(name), text)])
+		}
+	};
+
+	if let Some(files) = files {
+		if !overrides.is_empty() {
+			let reason = "the files the package renders, patches only apply to objects";
+
+			return Err(Error::InvalidOverride(reason.to_string()).into());
+		}
+
+		return Ok(Compilation {
+			rendered: Rendered::Files(files),
+			target,
+			metadata,
+			input,
+			only: vec![],
+			violations: vec![],
+			sources: None,
+		});
+	}
+
+	overrides::apply(&mut rendered, overrides)?;
+
+	let only: Vec<PathBuf> = args.only.clone().map(|v| v.into()).unwrap_or_default();
+	let except: Vec<PathBuf> = args.except.clone().map(|v| v.into()).unwrap_or_default();
+
+	let build = |except: Vec<PathBuf>| {
+		let builder = Kube::builder()
+			.only(only.clone())
+			.except(except)
+			.value(rendered.clone())
+			.progress(progress.clone())
+			.context(target.clone());
+
+		let builder = if args.no_labels {
+			builder
+		} else {
+			builder.stamp(stamp.clone())
+		};
+
+		builder.build()
+	};
+
+	let mut kube = build(except.clone())?;
+
+	let scoped = args.cluster_scoped;
+	let checked = !policies.is_empty() || !allowlists.is_empty() || scoped != Scoped::Allow;
+	let violations = match checked {
+		false => vec![],
+		true => {
+			let manifests = kube.render()?;
+			let objects = || {
+				manifests
+					.iter()
+					.map(|manifest| (manifest.path(), manifest.value()))
+			};
+
+			let clustered = match scoped {
+				Scoped::Allow => vec![],
+				Scoped::Reject | Scoped::Strip => scope::cluster_scoped(objects()),
+			};
+
+			let mut violations = vec![];
+			if scoped == Scoped::Strip && !clustered.is_empty() {
+				for path in &clustered {
+					eprintln!(
+						"Warning: {} is cluster-scoped, leaving it out",
+						path.display()
+					);
+				}
+
+				kube = build(except.into_iter().chain(clustered.clone()).collect())?;
+			}
+			if scoped == Scoped::Reject {
+				violations.extend(clustered.iter().map(|path| Violation {
+					policy: String::from("cluster-scoped"),
+					action: Action::Deny,
+					path: path.clone(),
+					message: String::from("is cluster-scoped, which tenants can't ship"),
+				}));
+			}
+
+			let kept = |path: &Path| !clustered.iter().any(|clustered| clustered == path);
+			let objects = objects().filter(|(path, _)| scoped != Scoped::Strip || kept(path));
+			violations.extend(policy::confine(allowlists, objects.clone()));
+			violations.extend(policy::evaluate(policies, objects));
+
+			violations
+		}
+	};
+
+	Ok(Compilation {
+		rendered: Rendered::Objects(Box::new(kube)),
+		target,
+		metadata,
+		input,
+		only,
+		violations,
+		sources: Some(sources),
+	})
+}
+
+/// Identifies the package by name, version, and digest, along the
+/// dependencies it locked
+pub fn describe(package: &Package) -> Result<Value> {
+	let dependencies: Vec<Value> = package
+		.locked()?
+		.into_iter()
+		.map(|locked| {
+			json!({
+				"name": locked.dependency.name(),
+				"remote": locked.dependency.remote,
+				"rev": locked.rev,
+			})
+		})
+		.collect();
+
+	Ok(json!({
+		"name": package.spec.name,
+		"version": package.spec.version.to_string(),
+		"digest": package.digest()?,
+		"dependencies": dependencies,
+	}))
+}
+
+/// Follows the XDG base directories, falling back to `~/.cache`
+fn http_cache() -> Option<PathBuf> {
+	let base = match env::var_os("XDG_CACHE_HOME") {
+		Some(cache) if !cache.is_empty() => PathBuf::from(cache),
+		_ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+	};
+
+	Some(base.join("kct").join("http"))
+}
+
+/// Resolves the release name, which can be a template over the input, e.g.
+/// `{{ input.tenant }}-api`, so it stays consistent with the values
+fn release_name(template: &str, input: Option<&Value>) -> Result<String, Error> {
+	if !template.contains("{{") && !template.contains("{%") {
+		return Ok(template.to_string());
+	}
+
+	let context = TeraContext::from_serialize(json!({ "input": input }))
+		.map_err(|err| Error::InvalidRelease(template.to_string(), err.to_string()))?;
+
+	let name = Tera::one_off(template, &context, false).map_err(|err| {
+		let reason = match err.source() {
+			Some(source) => source.to_string(),
+			None => err.to_string(),
+		};
+
+		Error::InvalidRelease(template.to_string(), reason)
+	})?;
+
+	match name.trim() {
+		"" => Err(Error::InvalidRelease(
+			template.to_string(),
+			String::from("it resolved to nothing"),
+		)),
+		name => Ok(name.to_string()),
+	}
 }
 
 fn merge_inputs(inputs: &[Value]) -> Result<Option<Value>, Error> {
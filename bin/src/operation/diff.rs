@@ -0,0 +1,62 @@
+use kct_helper::ignore::Ignore;
+use kct_helper::json;
+use serde_json::{Map, Value};
+
+/// Lines telling how the resources, keyed by their addresses as in plans,
+/// changed from `before` to `after`: `-` for the ones removed, `+` for new
+/// ones, and `~` for the changed ones, followed by the fields that changed
+/// besides the ignored ones
+pub fn diff(
+	before: &Map<String, Value>,
+	after: &Map<String, Value>,
+	ignore: &Ignore,
+) -> Vec<String> {
+	let mut lines = vec![];
+
+	for (address, resource) in before {
+		let changed = match after.get(address) {
+			None => {
+				lines.push(format!("- {address}"));
+				continue;
+			}
+			Some(changed) => changed,
+		};
+
+		if resource["hash"] == changed["hash"] {
+			continue;
+		}
+
+		// Files only tell they changed, as their contents aren't structured
+		let (mut before, mut after) = (resource["object"].clone(), changed["object"].clone());
+		ignore.strip(&mut before);
+		ignore.strip(&mut after);
+
+		if before.is_object() && before == after {
+			continue;
+		}
+
+		lines.push(format!("~ {address}"));
+
+		for pointer in json::changed(&before, &after) {
+			let show = |value: &Value| match value.pointer(&pointer) {
+				Some(value) => value.to_string(),
+				None => String::from("none"),
+			};
+
+			lines.push(format!(
+				"    {pointer}: {} → {}",
+				show(&before),
+				show(&after)
+			));
+		}
+	}
+
+	for address in after
+		.keys()
+		.filter(|address| !before.contains_key(*address))
+	{
+		lines.push(format!("+ {address}"));
+	}
+
+	lines
+}
@@ -1 +1,101 @@
+pub mod attestation;
 pub mod compile;
+pub mod diff;
+pub mod overrides;
+pub mod prompt;
+pub mod record;
+pub mod release;
+pub mod summary;
+
+use crate::progress;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use kct_helper::progress::Event;
+use kct_package::{self as package, Error as PackageError, Limits, Package, Store, Symlinks};
+use tempfile::TempDir;
+
+/// Directory archives are unpacked within, instead of the store
+const UNPACK_VAR: &str = "KCT_UNPACK_DIR";
+
+/// Loads the package at the path, the archive there when it's a file, or the
+/// archive piped into STDIN when it's `-`. Archives are unpacked into the
+/// store and reused from there, unless told otherwise or it can't be written,
+/// when they're unpacked into a directory removed once the returned guard is
+/// dropped.
+pub fn load(path: &Path) -> Result<(Package, Option<TempDir>)> {
+	let limits = Limits::default();
+	let mut archive = vec![];
+	let progress = progress::bars();
+
+	if path == Path::new("-") {
+		progress.report(Event::Fetching {
+			source: String::from("STDIN"),
+		});
+		io::stdin()
+			.lock()
+			.take(limits.total)
+			.read_to_end(&mut archive)?;
+	} else if path.is_file() {
+		progress.report(Event::Fetching {
+			source: path.display().to_string(),
+		});
+		File::open(path)
+			.with_context(|| format!("Unable to read {}", path.display()))?
+			.take(limits.total)
+			.read_to_end(&mut archive)?;
+	} else {
+		return Ok((Package::try_from(path)?, None));
+	}
+
+	progress.report(Event::Unpacking {
+		bytes: archive.len(),
+	});
+	let unpacked = |root: PathBuf| {
+		progress.report(Event::Unpacked { root: root.clone() });
+
+		root
+	};
+
+	let within = env::var_os(UNPACK_VAR);
+	if let (None, Some(store)) = (&within, data()) {
+		let store = store.join("store");
+
+		match Store::new(store).unpack(archive.as_slice(), Symlinks::default(), limits) {
+			Ok(root) => return Ok((Package::try_from(unpacked(root).as_path())?, None)),
+			// Read-only homes may still have a temporary directory
+			Err(PackageError::Store(_)) => (),
+			Err(err) => return Err(err.into()),
+		}
+	}
+
+	let dir = match within {
+		Some(within) => tempfile::tempdir_in(within),
+		None => tempfile::tempdir(),
+	}
+	.with_context(|| {
+		format!("Unable to unpack the package, point {UNPACK_VAR} to a writable directory")
+	})?;
+	let root = package::unpack(archive.as_slice(), dir.path(), Symlinks::default(), limits)?;
+	let root = unpacked(root);
+	let package = Package::try_from(root.as_path())?;
+
+	Ok((package, Some(dir)))
+}
+
+/// Where kct keeps what outlives its runs, following the XDG base
+/// directories and falling back to `~/.local/share`
+pub fn data() -> Option<PathBuf> {
+	let base = match env::var_os("XDG_DATA_HOME") {
+		Some(data) if !data.is_empty() => PathBuf::from(data),
+		_ => PathBuf::from(env::var_os("HOME")?)
+			.join(".local")
+			.join("share"),
+	};
+
+	Some(base.join("kct"))
+}
@@ -0,0 +1,159 @@
+use crate::error::Error;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use kct_helper::io;
+use kct_helper::patch::{json_patch, strategic_merge};
+use kct_kube::Kube;
+use serde_json::Value;
+
+/// Extensions of the files read from directories of overrides
+const EXTENSIONS: [&str; 3] = ["yaml", "yml", "json"];
+
+/// Patch of a rendered object, by its address, applied after the package
+/// renders for tweaks that can't wait for a new version of it
+pub struct Override {
+	/// File declaring it, to tell where failures come from
+	source: PathBuf,
+	target: String,
+	patch: Value,
+}
+
+/// Reads the overrides of the files, and of the YAML and JSON files within
+/// the directories, in the order of their names
+pub fn load(paths: &[PathBuf]) -> Result<Vec<Override>, Error> {
+	let mut files = vec![];
+	for path in paths {
+		if !path.is_dir() {
+			files.push(path.clone());
+			continue;
+		}
+
+		let invalid =
+			|err: std::io::Error| Error::InvalidInput(format!("{}: {err}", path.display()));
+		let mut within = fs::read_dir(path)
+			.map_err(invalid)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<Result<Vec<PathBuf>, _>>()
+			.map_err(invalid)?;
+		within.retain(|file| {
+			let extension = file.extension().and_then(|extension| extension.to_str());
+
+			file.is_file() && extension.is_some_and(|extension| EXTENSIONS.contains(&extension))
+		});
+		within.sort();
+
+		files.extend(within);
+	}
+
+	let mut overrides = vec![];
+	for file in files {
+		let contents = io::from_file(&file)?;
+		let invalid = |reason: String| Error::InvalidInput(format!("{}: {reason}", file.display()));
+
+		let declared =
+			match serde_yaml::from_str(&contents).map_err(|err| invalid(err.to_string()))? {
+				Value::Array(declared) => declared,
+				_ => return Err(invalid("it should be a list of overrides".to_string())),
+			};
+
+		for (i, declared) in declared.into_iter().enumerate() {
+			let target = match &declared["target"] {
+				Value::String(target) => target.clone(),
+				_ => {
+					return Err(invalid(format!(
+						"overrides[{i}] should have a target address"
+					)))
+				}
+			};
+
+			let patch = match &declared["patch"] {
+				patch @ (Value::Array(_) | Value::Object(_)) => patch.clone(),
+				_ => {
+					let reason = "should have a patch, as a list of JSON patch operations or a strategic merge";
+
+					return Err(invalid(format!("overrides[{i}] {reason}")));
+				}
+			};
+
+			overrides.push(Override {
+				source: file.clone(),
+				target,
+				patch,
+			});
+		}
+	}
+
+	Ok(overrides)
+}
+
+/// Patches the objects within the rendered value, every override having to
+/// match one of them
+pub fn apply(rendered: &mut Value, overrides: &[Override]) -> Result<(), Error> {
+	if overrides.is_empty() {
+		return Ok(());
+	}
+
+	let manifests = Kube::builder().value(rendered.clone()).build()?.render()?;
+
+	for overriding in overrides {
+		let failed = |reason: String| {
+			Error::InvalidOverride(format!(
+				"{} from {}: {reason}",
+				overriding.target,
+				overriding.source.display()
+			))
+		};
+
+		let mut matched = false;
+		for manifest in &manifests {
+			if !addressed(manifest.value(), &overriding.target) {
+				continue;
+			}
+
+			// Paths are the fields leading to the object, which can't have slashes
+			let pointer = manifest.path().to_string_lossy();
+			let object = match rendered.pointer_mut(&pointer) {
+				Some(object) => object,
+				None => continue,
+			};
+
+			match &overriding.patch {
+				Value::Array(operations) => {
+					json_patch(object, operations).map_err(|err| failed(err.to_string()))?
+				}
+				patch => strategic_merge(object, patch, &HashMap::new()),
+			}
+
+			matched = true;
+		}
+
+		if !matched {
+			return Err(failed("it matches no object".to_string()));
+		}
+	}
+
+	Ok(())
+}
+
+/// Whether the object is at the address, as `group/kind/[namespace/]name`
+/// like in plans, `core` being the group of the objects without one
+fn addressed(object: &Value, address: &str) -> bool {
+	let at = |object: &Value| {
+		let api_version = object["apiVersion"].as_str()?;
+		let group = api_version
+			.split_once('/')
+			.map_or("core", |(group, _)| group);
+		let kind = object["kind"].as_str()?;
+		let name = object["metadata"]["name"].as_str()?;
+
+		Some(match object["metadata"]["namespace"].as_str() {
+			Some(namespace) => format!("{group}/{kind}/{namespace}/{name}"),
+			None => format!("{group}/{kind}/{name}"),
+		})
+	};
+
+	at(object).as_deref() == Some(address)
+}
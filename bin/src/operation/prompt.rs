@@ -0,0 +1,183 @@
+use crate::error::Error;
+
+use std::io::{self, IsTerminal};
+
+use console::Term;
+use serde_json::{Map, Value};
+
+/// Asks for the inputs the schema requires that the input misses, along the
+/// ones required within them, returning the answers alone
+pub fn missing(schema: &Value, input: &Value) -> Result<Value, Error> {
+	if !io::stdin().is_terminal() {
+		return Err(Error::InvalidInput(
+			"--interactive needs a terminal to ask for the inputs".to_string(),
+		));
+	}
+
+	let term = Term::stderr();
+	let mut answers = Value::Object(Map::new());
+	ask(&term, schema, input, &mut answers, "input")?;
+
+	Ok(answers)
+}
+
+/// Walks the required properties of the schema, descending into the objects
+/// the input has and asking for the ones it doesn't
+fn ask(
+	term: &Term,
+	schema: &Value,
+	input: &Value,
+	answers: &mut Value,
+	path: &str,
+) -> Result<(), Error> {
+	let properties = match schema.get("properties").and_then(Value::as_object) {
+		Some(properties) => properties,
+		None => return Ok(()),
+	};
+	let required = schema
+		.get("required")
+		.and_then(Value::as_array)
+		.cloned()
+		.unwrap_or_default();
+
+	for name in required.iter().filter_map(Value::as_str) {
+		let property = properties.get(name).unwrap_or(&Value::Null);
+		let path = format!("{path}.{name}");
+
+		let given = input.get(name);
+		if given.is_some_and(|given| !given.is_object()) {
+			continue;
+		}
+
+		if property.get("properties").is_some() {
+			let mut nested = Value::Object(Map::new());
+			ask(
+				term,
+				property,
+				given.unwrap_or(&Value::Null),
+				&mut nested,
+				&path,
+			)?;
+
+			let answered = nested.as_object().is_some_and(|nested| !nested.is_empty());
+			if given.is_none() || answered {
+				answers[name] = nested;
+			}
+			continue;
+		}
+
+		if given.is_none() {
+			answers[name] = scalar(term, property, &path)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Asks for a single value until the answer fits the property, hiding what's
+/// typed for the ones marked `writeOnly`
+fn scalar(term: &Term, property: &Value, path: &str) -> Result<Value, Error> {
+	let kind = property.get("type").and_then(Value::as_str);
+	let choices = property.get("enum").and_then(Value::as_array);
+	let default = property.get("default");
+	let secret = property.get("writeOnly") == Some(&Value::Bool(true));
+
+	if let Some(description) = property.get("description").and_then(Value::as_str) {
+		write(term, description)?;
+	}
+	if let Some(choices) = choices {
+		for (i, choice) in choices.iter().enumerate() {
+			write(term, &format!("  {}) {choice}", i + 1))?;
+		}
+	}
+
+	let mut hints: Vec<String> = kind.map(String::from).into_iter().collect();
+	if let Some(default) = default {
+		hints.push(format!("default {default}"));
+	}
+	let label = match hints.is_empty() {
+		true => format!("{path}: "),
+		false => format!("{path} ({}): ", hints.join(", ")),
+	};
+
+	loop {
+		term.write_str(&label).map_err(prompt_error)?;
+		let answer = match secret {
+			true => term.read_secure_line(),
+			false => term.read_line(),
+		}
+		.map_err(prompt_error)?;
+		let answer = answer.trim();
+
+		let parsed = match (answer.is_empty(), default) {
+			(true, Some(default)) => Ok(default.clone()),
+			(true, None) => Err(format!("{path} is required")),
+			(false, _) => match choices {
+				Some(choices) => choose(answer, choices),
+				None => parse(answer, kind),
+			},
+		};
+
+		match parsed {
+			Ok(value) => return Ok(value),
+			Err(reason) => write(term, &reason)?,
+		}
+	}
+}
+
+/// The choice by its number, or the answer when it's one of them
+fn choose(answer: &str, choices: &[Value]) -> Result<Value, String> {
+	if let Some(choice) = answer
+		.parse::<usize>()
+		.ok()
+		.and_then(|i| i.checked_sub(1))
+		.and_then(|i| choices.get(i))
+	{
+		return Ok(choice.clone());
+	}
+
+	let candidates = [parse(answer, None)?, Value::String(answer.to_string())];
+	candidates
+		.into_iter()
+		.find(|candidate| choices.contains(candidate))
+		.ok_or_else(|| format!("{answer} isn't one of the choices, answer with its number"))
+}
+
+/// The answer as the type of the property, or as JSON falling back to a
+/// string when it has none
+fn parse(answer: &str, kind: Option<&str>) -> Result<Value, String> {
+	match kind {
+		Some("string") => Ok(Value::String(answer.to_string())),
+		Some("boolean") => match answer.to_lowercase().as_str() {
+			"y" | "yes" | "true" => Ok(Value::Bool(true)),
+			"n" | "no" | "false" => Ok(Value::Bool(false)),
+			_ => Err(format!("{answer} isn't a boolean, answer with yes or no")),
+		},
+		Some(kind @ ("integer" | "number")) => match serde_json::from_str::<Value>(answer) {
+			Ok(Value::Number(number)) if kind == "number" || !number.is_f64() => {
+				Ok(Value::Number(number))
+			}
+			_ => Err(format!("{answer} isn't a valid {kind}")),
+		},
+		Some(kind @ ("array" | "object")) => match serde_json::from_str::<Value>(answer) {
+			Ok(value)
+				if matches!(
+					(kind, &value),
+					("array", Value::Array(_)) | ("object", Value::Object(_))
+				) =>
+			{
+				Ok(value)
+			}
+			_ => Err(format!("{answer} isn't a valid {kind} in JSON")),
+		},
+		_ => Ok(serde_json::from_str(answer).unwrap_or(Value::String(answer.to_string()))),
+	}
+}
+
+fn write(term: &Term, line: &str) -> Result<(), Error> {
+	term.write_line(line).map_err(prompt_error)
+}
+
+fn prompt_error(err: io::Error) -> Error {
+	Error::InvalidInput(format!("unable to ask for the inputs, {err}"))
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Describes what a render was made from and what it produced, so it can be
+/// rebuilt later to tell whether it reproduces. Inputs are only kept as their
+/// digest, as they may hold secrets.
+pub fn record(metadata: &Value, outputs: BTreeMap<String, String>) -> Value {
+	let release = match &metadata["release"] {
+		Value::Null => Value::Null,
+		release => json!({
+			"name": release["name"],
+			"timestamp": release["timestamp"],
+		}),
+	};
+
+	json!({
+		"package": metadata["package"],
+		"release": release,
+		"inputsDigest": metadata["inputsDigest"],
+		"outputs": outputs,
+	})
+}
+
+/// SHA-256 of each document, in hex, by its path
+pub fn digests(documents: &[(PathBuf, String)]) -> BTreeMap<String, String> {
+	documents
+		.iter()
+		.map(|(path, contents)| (path.display().to_string(), digest(contents)))
+		.collect()
+}
+
+/// SHA-256 of the contents, in hex
+pub fn digest(contents: &str) -> String {
+	format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+/// Lines telling how the rebuilt record differs from the recorded one: `~`
+/// for what changed, `-` for outputs no longer produced, `+` for new ones
+pub fn diff(recorded: &Value, rebuilt: &Value) -> Vec<String> {
+	let mut lines = vec![];
+
+	let materials = [("package", "/package/digest"), ("inputs", "/inputsDigest")];
+	for (name, pointer) in materials {
+		let (before, after) = (recorded.pointer(pointer), rebuilt.pointer(pointer));
+
+		if before != after {
+			let show =
+				|value: Option<&Value>| value.and_then(Value::as_str).unwrap_or("none").to_string();
+
+			lines.push(format!("~ {name} {} → {}", show(before), show(after)));
+		}
+	}
+
+	let outputs = |record: &Value| match &record["outputs"] {
+		Value::Object(outputs) => outputs.clone(),
+		_ => Default::default(),
+	};
+	let (before, after) = (outputs(recorded), outputs(rebuilt));
+
+	for (path, digest) in &before {
+		match after.get(path) {
+			None => lines.push(format!("- {path}")),
+			Some(rebuilt) if rebuilt != digest => {
+				let show = |digest: &Value| {
+					digest
+						.as_str()
+						.unwrap_or_default()
+						.chars()
+						.take(12)
+						.collect::<String>()
+				};
+
+				lines.push(format!("~ {path} {} → {}", show(digest), show(rebuilt)));
+			}
+			Some(_) => (),
+		}
+	}
+
+	for path in after.keys().filter(|path| !before.contains_key(*path)) {
+		lines.push(format!("+ {path}"));
+	}
+
+	lines
+}
@@ -0,0 +1,242 @@
+use crate::error::Error;
+use crate::operation;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use kct_helper::io::{self, Error as IOError};
+use kct_kube::Kube;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{json, Map, Value};
+
+/// Directories of the data one holding what's stored of each release
+const INPUTS: &str = "releases";
+const MANIFESTS: &str = "manifests";
+const SEEDS: &str = "seeds";
+
+/// Inputs the release was last installed with, so upgrades can reuse them
+pub fn inputs(name: &str) -> Result<Value, Error> {
+	read(name, &path(name, INPUTS)?, "inputs")
+}
+
+/// Stores the inputs the release was installed with, readable by the owner
+/// alone as they may hold secrets
+pub fn store(name: &str, input: &Value) -> Result<(), Error> {
+	write(name, &path(name, INPUTS)?, input, "inputs")
+}
+
+/// Seed generated for the release when it's given none, so the values derived
+/// from it survive renders, created when asked to and missing
+pub fn seed(name: &str, create: bool) -> Result<Option<String>, Error> {
+	let path = path(name, SEEDS)?;
+	if path.exists() {
+		return match read(name, &path, "seed")? {
+			Value::String(seed) => Ok(Some(seed)),
+			_ => Err(Error::InvalidRelease(
+				name.to_string(),
+				String::from("its stored seed is invalid"),
+			)),
+		};
+	}
+
+	if !create {
+		return Ok(None);
+	}
+
+	let mut bytes = [0; 32];
+	SystemRandom::new().fill(&mut bytes).map_err(|_err| {
+		Error::InvalidRelease(
+			name.to_string(),
+			String::from("there's no randomness to generate its seed from"),
+		)
+	})?;
+	let seed: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+	write(name, &path, &json!(seed), "seed")?;
+
+	Ok(Some(seed))
+}
+
+/// Objects the release was last installed with, by target, as they went
+/// into the cluster
+pub fn manifests(name: &str) -> Result<Vec<Kube>, Error> {
+	let stored = read(name, &path(name, MANIFESTS)?, "manifests")?;
+	let invalid = || {
+		Error::InvalidRelease(
+			name.to_string(),
+			String::from("its stored manifests are invalid"),
+		)
+	};
+
+	let targets = match stored {
+		Value::Array(targets) => targets,
+		_ => return Err(invalid()),
+	};
+
+	targets
+		.into_iter()
+		.map(|mut target| {
+			let context = target["target"].as_str().map(String::from);
+			let selector = target["selector"].as_str().map(String::from);
+
+			Kube::builder()
+				.value(target["objects"].take())
+				.stamped(selector)
+				.context(context)
+				.build()
+				.map_err(|_err| invalid())
+		})
+		.collect()
+}
+
+/// Stores the objects of every target, as taken by [snapshot], so the release
+/// can be compared with the cluster later regardless of the package
+pub fn record(name: &str, snapshot: &Value) -> Result<(), Error> {
+	write(name, &path(name, MANIFESTS)?, snapshot, "manifests")
+}
+
+/// Objects of every target as they go into the cluster, stamped
+pub fn snapshot(kubes: &[Kube]) -> anyhow::Result<Value> {
+	let mut targets = vec![];
+
+	for kube in kubes {
+		let manifests = kube.render()?;
+
+		let mut objects = Value::Object(Map::new());
+		for manifest in manifests {
+			let (path, object): (PathBuf, Value) = manifest.into();
+			nest(&mut objects, &path, object);
+		}
+
+		targets.push(json!({
+			"target": kube.context(),
+			"selector": kube.selector(),
+			"objects": objects,
+		}));
+	}
+
+	Ok(Value::Array(targets))
+}
+
+/// Places the object back where it was rendered within the objects
+fn nest(objects: &mut Value, path: &Path, object: Value) {
+	let fields: Vec<String> = path
+		.iter()
+		.skip(1)
+		.map(|field| field.to_string_lossy().into_owned())
+		.collect();
+
+	let mut parent = objects;
+	for (i, field) in fields.iter().enumerate() {
+		let Value::Object(props) = parent else {
+			return;
+		};
+
+		if i + 1 == fields.len() {
+			props.insert(field.clone(), object);
+			return;
+		}
+
+		parent = props
+			.entry(field.clone())
+			.or_insert_with(|| Value::Object(Map::new()));
+	}
+}
+
+fn read(name: &str, path: &Path, what: &str) -> Result<Value, Error> {
+	if !path.exists() {
+		return Err(Error::InvalidRelease(
+			name.to_string(),
+			format!("it has no {what} stored, install it first"),
+		));
+	}
+
+	let contents = io::from_file(path)?;
+
+	serde_json::from_str(&contents).map_err(|err| {
+		Error::InvalidRelease(
+			name.to_string(),
+			format!("its stored {what} are invalid, {err}"),
+		)
+	})
+}
+
+fn write(name: &str, path: &Path, value: &Value, what: &str) -> Result<(), Error> {
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).map_err(|_err| IOError::UnableToWrite)?;
+	}
+
+	let mut options = OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	options.mode(0o600);
+
+	let contents = serde_json::to_string_pretty(value).map_err(|err| {
+		Error::InvalidRelease(
+			name.to_string(),
+			format!("its {what} can't be stored, {err}"),
+		)
+	})?;
+
+	options
+		.open(path)
+		.and_then(|mut file| file.write_all(format!("{contents}\n").as_bytes()))
+		.map_err(|_err| IOError::UnableToWrite.into())
+}
+
+/// Forgets the inputs, manifests, and seed of the release, as it's no longer
+/// installed
+pub fn forget(name: &str) -> Result<(), Error> {
+	for kind in [INPUTS, MANIFESTS, SEEDS] {
+		match fs::remove_file(path(name, kind)?) {
+			Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+				return Err(IOError::UnableToWrite.into())
+			}
+			_ => continue,
+		}
+	}
+
+	Ok(())
+}
+
+/// Releases with inputs stored, i.e. installed from here
+pub fn stored() -> Vec<String> {
+	let entries = operation::data().and_then(|data| fs::read_dir(data.join(INPUTS)).ok());
+
+	entries
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| {
+			let path = entry.ok()?.path();
+			let name = path.file_stem()?.to_str()?;
+
+			match path.extension()? == "json" {
+				true => Some(name.to_string()),
+				false => None,
+			}
+		})
+		.collect()
+}
+
+/// Within the directory of the kind in the data one, named after the release
+fn path(name: &str, kind: &str) -> Result<PathBuf, Error> {
+	let invalid = |reason: String| Error::InvalidRelease(name.to_string(), reason);
+
+	io::portable(name).map_err(invalid)?;
+	if name.contains('/') || name.starts_with('.') {
+		return Err(invalid(String::from(
+			"it can't name a file to store its inputs in",
+		)));
+	}
+
+	let data = operation::data().ok_or_else(|| {
+		invalid(String::from(
+			"there's no home to store its inputs in, set XDG_DATA_HOME",
+		))
+	})?;
+
+	Ok(data.join(kind).join(format!("{name}.json")))
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use kct_helper::quantity::Quantity;
+use serde_json::Value;
+
+/// Resources totalled across workloads
+const RESOURCES: [&str; 2] = ["cpu", "memory"];
+
+/// What reviewers check at a glance: objects by kind, the requests and
+/// limits of every container times its replicas, and the images they run
+#[derive(Debug, Default)]
+pub struct Summary {
+	kinds: BTreeMap<String, usize>,
+	requests: BTreeMap<&'static str, Quantity>,
+	limits: BTreeMap<&'static str, Quantity>,
+	images: BTreeSet<String>,
+}
+
+impl Summary {
+	pub fn add(&mut self, object: &Value) {
+		let kind = object["kind"].as_str().unwrap_or("Unknown");
+		*self.kinds.entry(kind.to_string()).or_default() += 1;
+
+		let (pod, replicas) = match pod(object) {
+			Some(pod) => pod,
+			None => return,
+		};
+
+		let containers = ["initContainers", "containers"]
+			.iter()
+			.filter_map(|field| pod[field].as_array())
+			.flatten();
+
+		for container in containers {
+			if let Some(image) = container["image"].as_str() {
+				self.images.insert(image.to_string());
+			}
+		}
+
+		// Init containers run before the others, so only these add up
+		for container in pod["containers"].as_array().into_iter().flatten() {
+			let resources = &container["resources"];
+
+			total(&mut self.requests, &resources["requests"], replicas);
+			total(&mut self.limits, &resources["limits"], replicas);
+		}
+	}
+}
+
+/// Spec of the pods the object runs along how many of them, one for the
+/// kinds that don't tell
+fn pod(object: &Value) -> Option<(&Value, u64)> {
+	let spec = &object["spec"];
+	let replicas = |field: &str| spec[field].as_u64().unwrap_or(1);
+
+	match object["kind"].as_str()? {
+		"Pod" => Some((spec, 1)),
+		"Deployment" | "StatefulSet" | "ReplicaSet" | "ReplicationController" => {
+			Some((&spec["template"]["spec"], replicas("replicas")))
+		}
+		"DaemonSet" => Some((&spec["template"]["spec"], 1)),
+		"Job" => Some((&spec["template"]["spec"], replicas("parallelism"))),
+		"CronJob" => {
+			let job = &spec["jobTemplate"]["spec"];
+			let parallelism = job["parallelism"].as_u64().unwrap_or(1);
+
+			Some((&job["template"]["spec"], parallelism))
+		}
+		_ => None,
+	}
+}
+
+/// Adds the resources times the replicas, skipping quantities that don't parse
+fn total(totals: &mut BTreeMap<&'static str, Quantity>, resources: &Value, replicas: u64) {
+	for resource in RESOURCES {
+		let quantity = match &resources[resource] {
+			Value::String(quantity) => quantity.parse::<Quantity>().ok(),
+			Value::Number(quantity) => quantity.to_string().parse::<Quantity>().ok(),
+			_ => None,
+		};
+		let quantity = quantity.and_then(|quantity| quantity.multiply(&replicas.to_string()).ok());
+
+		if let Some(quantity) = quantity {
+			let summed = match totals.get(resource) {
+				Some(total) => total.add(&quantity).unwrap_or(*total),
+				None => quantity,
+			};
+
+			totals.insert(resource, summed);
+		}
+	}
+}
+
+impl fmt::Display for Summary {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let count: usize = self.kinds.values().sum();
+		writeln!(f, "Objects: {count}")?;
+		for (kind, count) in &self.kinds {
+			writeln!(f, "  {kind}: {count}")?;
+		}
+
+		let describe = |totals: &BTreeMap<&str, Quantity>| match totals.is_empty() {
+			true => String::from("none"),
+			false => totals
+				.iter()
+				.map(|(resource, total)| format!("{resource} {total}"))
+				.collect::<Vec<String>>()
+				.join(", "),
+		};
+		writeln!(f, "Requests: {}", describe(&self.requests))?;
+		writeln!(f, "Limits: {}", describe(&self.limits))?;
+
+		match self.images.is_empty() {
+			true => writeln!(f, "Images: none"),
+			false => {
+				writeln!(f, "Images:")?;
+				for image in &self.images {
+					writeln!(f, "  {image}")?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "directory with the package to check dependencies for", value_hint = ValueHint::DirPath)]
+	package: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let upgrades = kct_package::outdated(&args.package)?;
+
+	for upgrade in upgrades {
+		let name = upgrade.dependency.name();
+
+		println!(
+			"{} {} → {} ({})",
+			name.display(),
+			upgrade.current,
+			upgrade.latest,
+			upgrade.change.as_str()
+		);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::operation::attestation::Attestation;
+use crate::operation::compile;
+use crate::progress;
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum, ValueHint};
+use kct_package::{Integrity, Package, Symlinks};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Links {
+	/// Pack what links point to, within the package or vendored
+	#[default]
+	Follow,
+	/// Pack links as links, within the package
+	Preserve,
+	/// Refuse links
+	Reject,
+}
+
+impl From<Links> for Symlinks {
+	fn from(links: Links) -> Self {
+		match links {
+			Links::Follow => Symlinks::Follow,
+			Links::Preserve => Symlinks::Preserve,
+			Links::Reject => Symlinks::Reject,
+		}
+	}
+}
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "directory with the package to archive", value_hint = ValueHint::DirPath)]
+	package: PathBuf,
+	#[arg(
+		help = "file to write the archive into, defaults to <name>-<version>.tgz",
+		long,
+		short,
+		value_hint = ValueHint::FilePath
+	)]
+	output: Option<PathBuf>,
+	#[arg(help = "how to pack symbolic links", long, value_enum, default_value_t)]
+	symlinks: Links,
+	#[command(flatten)]
+	attestation: Attestation,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let package = Package::try_from(args.package.as_path())?;
+	package.verify(Integrity::Verify)?;
+
+	let name = package.spec.name.clone();
+	let output = args
+		.output
+		.unwrap_or_else(|| PathBuf::from(format!("{name}-{}.tgz", package.spec.version)));
+
+	let mut archive = vec![];
+	kct_package::pack(&package.root, &name, args.symlinks.into(), &mut archive)?;
+	fs::write(&output, &archive)?;
+
+	let metadata = json!({
+		"package": compile::describe(&package)?,
+		"release": Value::Null,
+		"inputsDigest": Value::Null,
+	});
+	let subject = output
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_default();
+	let digest = format!("{:x}", Sha256::digest(&archive));
+	args.attestation
+		.attest("package", &metadata, &[(subject, digest)])?;
+
+	progress::message(&format!("Package archived at \"{}\"", output.display()));
+
+	Ok(())
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use kct_helper::progress::{self, Event, Progress, Reporter};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Hides progress and messages, leaving stdout to the output alone
+pub fn quiet() {
+	QUIET.store(true, Ordering::SeqCst);
+}
+
+/// Prints what's been accomplished, unless quiet
+pub fn message(message: &str) {
+	if !QUIET.load(Ordering::SeqCst) {
+		println!("{message}");
+	}
+}
+
+/// Renders progress to stderr, keeping stdout for what's been accomplished
+pub struct Bars {
+	compiling: ProgressBar,
+	pending: AtomicUsize,
+	objects: Mutex<Option<ProgressBar>>,
+}
+
+pub fn bars() -> Progress {
+	if QUIET.load(Ordering::SeqCst) {
+		return progress::silent();
+	}
+
+	Arc::new(Bars {
+		compiling: ProgressBar::new_spinner(),
+		pending: AtomicUsize::new(0),
+		objects: Mutex::new(None),
+	})
+}
+
+impl Bars {
+	fn start(&self, total: usize) {
+		let style = ProgressStyle::with_template("{bar:40} {pos}/{len} {msg}")
+			.expect("Progress template should be valid");
+		let bar = ProgressBar::new(total as u64).with_style(style);
+
+		*self.objects.lock().unwrap() = Some(bar);
+	}
+
+	fn finish(&self, message: String) {
+		let guard = self.objects.lock().unwrap();
+		match guard.as_ref() {
+			Some(bar) => {
+				bar.suspend(|| println!("{message}"));
+				bar.inc(1);
+
+				if bar.position() >= bar.length().unwrap_or_default() {
+					bar.finish_and_clear();
+				}
+			}
+			None => println!("{message}"),
+		}
+	}
+}
+
+impl Reporter for Bars {
+	fn report(&self, event: Event) {
+		match event {
+			Event::Fetching { source } => {
+				self.compiling
+					.enable_steady_tick(Duration::from_millis(100));
+				self.compiling.set_message(format!("reading {source}"));
+			}
+			Event::Unpacking { bytes } => {
+				self.compiling
+					.set_message(format!("unpacking {bytes} bytes"));
+			}
+			Event::Unpacked { .. } => {
+				if self.pending.load(Ordering::SeqCst) == 0 {
+					self.compiling.finish_and_clear();
+				}
+			}
+			Event::Compiling { package } => {
+				self.pending.fetch_add(1, Ordering::SeqCst);
+				self.compiling
+					.enable_steady_tick(Duration::from_millis(100));
+				self.compiling.set_message(format!("compiling {package}"));
+			}
+			Event::Including {
+				package,
+				position,
+				total,
+			} => {
+				self.compiling
+					.set_message(format!("including {package} ({position} of {total})"));
+			}
+			Event::Compiled { .. } => {
+				if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+					self.compiling.finish_and_clear();
+				}
+			}
+			Event::Applying { total } | Event::Deleting { total } => self.start(total),
+			Event::Applied { object } => self.finish(format!("{} created", object.display())),
+			Event::Deleted { object } => self.finish(format!("{} deleted", object.display())),
+		}
+	}
+}
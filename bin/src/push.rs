@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::progress;
+use crate::render;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use clap::Parser;
+use kct_helper::io::{self, Location};
+use kct_package::{Reference, Registry, Symlinks};
+use serde_json::Value;
+
+#[derive(Parser)]
+pub struct Args {
+	#[command(flatten)]
+	compile: compile::Params,
+	#[arg(help = "where to push the manifests, as oci://registry/repository:tag")]
+	reference: Reference,
+	#[arg(
+		help = "credentials for the registry as username:password, defaults to the ones of docker login",
+		long,
+		env = "KCT_REGISTRY_CREDS",
+		hide_env_values = true
+	)]
+	creds: Option<String>,
+	#[arg(help = "talk to the registry over plain HTTP", long)]
+	insecure: bool,
+	#[arg(
+		help = "where the manifests come from, such as the repository URL",
+		long
+	)]
+	source: Option<String>,
+	#[arg(help = "revision the manifests come from, such as the commit", long)]
+	revision: Option<String>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let compilations = compile::compilations(args.compile)?;
+	let created = compilations
+		.first()
+		.and_then(|compilation| compilation.metadata["release"]["timestamp"].as_str())
+		.map(String::from)
+		.unwrap_or_else(|| Utc::now().to_rfc3339());
+	let (documents, files) = render::documents(compilations)?;
+
+	// Laid out as `render -o manifests` writes them, which puts an object
+	// rendered at the root beside the directory
+	let dir = tempfile::tempdir()?;
+	let location = Location::Path(dir.path().join("manifests"));
+	match files {
+		true => location.materialize(documents)?,
+		false => location.write(documents)?,
+	}
+
+	let mut layer = vec![];
+	kct_package::pack(dir.path(), ".", Symlinks::default(), &mut layer)?;
+
+	let mut annotations = BTreeMap::new();
+	annotations.insert(String::from("org.opencontainers.image.created"), created);
+	if let Some(source) = args.source {
+		annotations.insert(String::from("org.opencontainers.image.source"), source);
+	}
+	if let Some(revision) = args.revision {
+		annotations.insert(String::from("org.opencontainers.image.revision"), revision);
+	}
+
+	let credentials = match args.creds {
+		Some(creds) => Some(split(&creds).ok_or_else(|| {
+			Error::InvalidInput(String::from("credentials should be username:password"))
+		})?),
+		None => docker_credentials(&args.reference.registry),
+	};
+	let registry = Registry {
+		credentials,
+		insecure: args.insecure,
+	};
+
+	let digest = kct_package::push(&args.reference, &registry, &layer, &annotations)?;
+	progress::message(&format!("Manifests pushed to {}@{digest}", args.reference));
+
+	Ok(())
+}
+
+fn split(credentials: &str) -> Option<(String, String)> {
+	let (username, password) = credentials.split_once(':')?;
+
+	Some((username.to_string(), password.to_string()))
+}
+
+/// Credentials `docker login` stored for the registry in plain text, those
+/// kept by credential helpers aren't read
+fn docker_credentials(registry: &str) -> Option<(String, String)> {
+	let dir = env::var_os("DOCKER_CONFIG")
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".docker")))?;
+
+	let config: Value =
+		serde_json::from_str(&io::from_file(&dir.join("config.json")).ok()?).ok()?;
+	let auth = config["auths"][registry]["auth"].as_str()?;
+	let auth = String::from_utf8(STANDARD.decode(auth).ok()?).ok()?;
+
+	split(&auth)
+}
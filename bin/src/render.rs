@@ -1,52 +1,613 @@
 use crate::error::Error;
-use crate::operation::compile;
+use crate::operation::attestation::Attestation;
+use crate::operation::compile::{self, Compilation, Rendered};
+use crate::operation::record;
+use crate::operation::summary::Summary;
+use crate::progress;
 
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, ValueHint};
 use kct_cli::Output;
 use kct_helper::io::{self, Location};
+use kct_helper::json;
 use kct_kube::Manifest;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use tracing::info_span;
+
+/// Placeholder for the name of each input within `--matrix` outputs
+const NAME: &str = "{name}";
+/// Version of the plan layout, only bumped along changes breaking its contract
+const PLAN_FORMAT: u8 = 1;
+
+/// Documents, by their paths, rendered as they're taken
+type Documents = Box<dyn Iterator<Item = (PathBuf, String)>>;
 
 #[derive(Parser)]
 pub struct Args {
-	#[arg(help = "directory to save compiled manifests", long, short)]
+	#[arg(help = "directory to save compiled manifests", long, short, value_hint = ValueHint::DirPath)]
 	output: Option<Output>,
+	#[arg(
+		help = "print objects along warnings and metadata as JSON",
+		long,
+		conflicts_with_all = ["output", "attest"]
+	)]
+	envelope: bool,
+	#[arg(
+		help = "print a single JSON document with the objects keyed by stable addresses along their hashes",
+		long,
+		conflicts_with_all = ["output", "envelope", "attest"]
+	)]
+	plan: bool,
+	#[arg(
+		help = "print the objects by kind, the resources their workloads request, and the images they run",
+		long,
+		conflicts_with_all = ["output", "envelope", "plan", "attest"]
+	)]
+	summary: bool,
+	#[arg(
+		help = "input files to render once each, into the output with {name} replaced by their names",
+		long,
+		num_args = 1..,
+		requires = "output",
+		conflicts_with_all = ["attest", "plan", "summary", "interactive"],
+		value_hint = ValueHint::FilePath
+	)]
+	matrix: Option<Vec<PathBuf>>,
+	#[arg(
+		help = "file to record what the render was made from and produced, checked by verify --reproduce",
+		long,
+		conflicts_with_all = ["envelope", "matrix"],
+		value_hint = ValueHint::FilePath
+	)]
+	record: Option<PathBuf>,
+	#[arg(
+		help = "file to write where each object is defined within the templates, as JSON",
+		long,
+		conflicts_with_all = ["envelope", "plan", "matrix"],
+		value_hint = ValueHint::FilePath
+	)]
+	emit_source_map: Option<PathBuf>,
+	#[arg(
+		help = "file to write the files the render read into, as a Makefile depfile of the output",
+		long,
+		requires = "output",
+		conflicts_with = "matrix",
+		value_hint = ValueHint::FilePath
+	)]
+	depfile: Option<PathBuf>,
+	#[arg(
+		help = "file listing the files the render may read, one per line, refusing it when it reads others",
+		long,
+		conflicts_with_all = ["envelope", "plan", "matrix"],
+		value_hint = ValueHint::FilePath
+	)]
+	declared_inputs: Option<PathBuf>,
+	#[command(flatten)]
+	attestation: Attestation,
 	#[command(flatten)]
 	compile: compile::Params,
 }
 
 pub fn run(args: Args) -> Result<()> {
-	let kube = compile::run(args.compile)?;
-	let manifests: Vec<Manifest> = kube.try_into()?;
-	let documents: Vec<(PathBuf, String)> = manifests
+	if args.envelope {
+		return envelope(args.compile);
+	}
+
+	if args.plan {
+		return plan(args.compile);
+	}
+
+	if args.summary {
+		return summary(args.compile);
+	}
+
+	if let Some(files) = &args.matrix {
+		let template = args
+			.output
+			.clone()
+			.map(Location::from)
+			.and_then(|location| location.path().map(|path| path.display().to_string()))
+			.ok_or_else(|| Error::InvalidInput(String::from("matrix needs an output directory")))?;
+
+		return compile::matrix(args.compile, files, |name, compilations| {
+			let output = match template.contains(NAME) {
+				true => template.replace(NAME, &name),
+				false => format!("{template}/{name}"),
+			};
+
+			emit(
+				compilations,
+				Some(output.parse()?),
+				None,
+				&Default::default(),
+			)
+		});
+	}
+
+	let compilations = compile::compilations(args.compile)?;
+
+	if let Some(path) = &args.declared_inputs {
+		declared(path)?;
+	}
+
+	if let Some(path) = &args.emit_source_map {
+		let map = source_map(&compilations)?;
+
+		io::write_contents(path, &format!("{}\n", serde_json::to_string_pretty(&map)?))?;
+	}
+
+	let target = args.output.clone().map(Location::from);
+	emit(
+		compilations,
+		args.output,
+		args.record.as_deref(),
+		&args.attestation,
+	)?;
+
+	if let (Some(path), Some(target)) = (&args.depfile, target.as_ref().and_then(Location::path)) {
+		io::write_contents(path, &depfile(target)?)?;
+	}
+
+	Ok(())
+}
+
+/// Refuses the render when it read files beyond the ones the manifest
+/// lists, each a file or a directory holding them, so builds wiring the
+/// render into a sandbox learn what they missed
+fn declared(manifest: &Path) -> Result<()> {
+	let base = env::current_dir()?;
+	let contents = io::from_file(manifest)?;
+	let mut allowed: Vec<PathBuf> = contents
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| absolute(&base, Path::new(line)))
+		.collect();
+	allowed.push(absolute(&base, manifest));
+
+	let undeclared: Vec<String> = io::reads()
 		.into_iter()
-		.map(|manifest| manifest.into())
+		.filter(|read| !allowed.iter().any(|allowed| read.starts_with(allowed)))
+		.map(|read| relative(&base, &read))
 		.collect();
 
-	let output = ensure_output_exists(&args.output)?;
-	match output {
-		out @ Location::Standard => out.write(documents)?,
+	if !undeclared.is_empty() {
+		return Err(Error::Undeclared(undeclared.join(", ")).into());
+	}
+
+	Ok(())
+}
 
-		out @ Location::Path(_) => {
-			out.write(documents)?;
-			let path = args
-				.output
-				.and_then(|o| {
-					let l: Location = o.into();
+/// Every file the render read as prerequisites of the output, the way
+/// compilers write depfiles for Make, Ninja, and Bazel
+fn depfile(target: &Path) -> Result<String> {
+	let base = env::current_dir()?;
+	let mut contents = format!("{}:", escape(&target.display().to_string()));
 
-					l.path().map(|p| p.display().to_string())
-				})
-				.unwrap();
+	for read in io::reads() {
+		contents.push_str(" \\\n  ");
+		contents.push_str(&escape(&relative(&base, &read)));
+	}
+	contents.push('\n');
 
-			println!("Manifests written at \"{path}\"");
+	Ok(contents)
+}
+
+fn absolute(base: &Path, path: &Path) -> PathBuf {
+	let path = base.join(path);
+
+	io::canonical(&path).unwrap_or(path)
+}
+
+/// The path from the base when it's within, as builds run from their roots
+fn relative(base: &Path, path: &Path) -> String {
+	path.strip_prefix(base)
+		.unwrap_or(path)
+		.display()
+		.to_string()
+}
+
+/// Escapes what Make would take as separators or variables
+fn escape(path: &str) -> String {
+	path.replace(' ', "\\ ")
+		.replace('#', "\\#")
+		.replace('$', "$$")
+}
+
+/// Writes the compilations into the output, or STDOUT when there's none,
+/// recording and attesting the render when asked to
+fn emit(
+	compilations: Vec<Compilation>,
+	output: Option<Output>,
+	record: Option<&Path>,
+	attestation: &Attestation,
+) -> Result<()> {
+	let metadata = compilations
+		.first()
+		.map(|compilation| compilation.metadata.clone())
+		.unwrap_or_default();
+	let (documents, files) = stream(compilations)?;
+
+	// Only digests are kept of what's written, as the documents are dropped
+	// once they are
+	let tracked = record.is_some() || attestation.requested();
+	let mut digests = BTreeMap::new();
+	let mut count = 0;
+	let documents = documents.inspect(|(path, contents)| {
+		count += 1;
+		if tracked {
+			digests.insert(path.display().to_string(), record::digest(contents));
 		}
+	});
+
+	let location = ensure_output_exists(&output)?;
+	let written = output
+		.map(Location::from)
+		.and_then(|location| location.path().map(|path| path.display().to_string()));
+
+	let span = info_span!("output", documents = tracing::field::Empty).entered();
+	match files {
+		true => location.materialize(documents)?,
+		false => location.write(documents)?,
+	}
+	span.record("documents", count);
+	drop(span);
+
+	if let Some(path) = record {
+		let record = record::record(&metadata, digests.clone());
+
+		io::write_contents(
+			path,
+			&format!("{}\n", serde_json::to_string_pretty(&record)?),
+		)?;
+	}
+
+	let subjects: Vec<(String, String)> = digests.into_iter().collect();
+	attestation.attest("render", &metadata, &subjects)?;
+
+	if let Some(path) = written {
+		let written = if files { "Files" } else { "Manifests" };
+		progress::message(&format!("{written} written at \"{path}\""));
 	}
 
 	Ok(())
 }
 
+/// Documents the compilations render into, headed by their targets, along
+/// whether they're files rather than manifests
+pub fn documents(compilations: Vec<Compilation>) -> Result<(Vec<(PathBuf, String)>, bool)> {
+	let (documents, files) = stream(compilations)?;
+
+	Ok((documents.collect(), files))
+}
+
+/// Same as `documents`, but objects are only serialized as they're taken, so
+/// a single one is held as YAML at a time
+fn stream(compilations: Vec<Compilation>) -> Result<(Documents, bool)> {
+	let mut streams: Vec<Documents> = vec![];
+	let mut files = false;
+
+	for compilation in compilations {
+		let rendered: Documents = match compilation.rendered {
+			Rendered::Objects(kube) => {
+				let manifests: Vec<Manifest> = (*kube).try_into()?;
+
+				Box::new(manifests.into_iter().map(|manifest| manifest.into()))
+			}
+			Rendered::Files(artifacts) => {
+				files = true;
+
+				Box::new(artifacts.into_iter())
+			}
+		};
+
+		match compilation.target {
+			None => streams.push(rendered),
+			Some(target) => streams.push(Box::new(rendered.enumerate().map(
+				move |(i, (path, contents))| {
+					let path = PathBuf::from("/")
+						.join(segment(&target))
+						.join(path.strip_prefix("/").unwrap_or(&path));
+					// Files are already headed by their paths, which include the target
+					let contents = match i {
+						0 if !files => format!("# target: {target}\n{contents}"),
+						_ => contents,
+					};
+
+					(path, contents)
+				},
+			))),
+		}
+	}
+
+	Ok((Box::new(streams.into_iter().flatten()), files))
+}
+
+/// Template file, line, column, and field defining each object, by its path
+/// headed by the target, leaving out the ones nowhere to be found, such as
+/// objects built by functions out of the package
+fn source_map(compilations: &[Compilation]) -> Result<Value> {
+	let mut map = Map::new();
+
+	for compilation in compilations {
+		let (kube, sources) = match (&compilation.rendered, &compilation.sources) {
+			(Rendered::Objects(kube), Some(sources)) => (kube, sources),
+			_ => continue,
+		};
+
+		let root = sources.layers.first().cloned().unwrap_or_default();
+		let canonical = io::canonical(&root).unwrap_or_else(|_| root.clone());
+
+		for manifest in kube.render()? {
+			let fields: Vec<&str> = manifest
+				.path()
+				.iter()
+				.skip(1)
+				.filter_map(|field| field.to_str())
+				.collect();
+
+			let source = match sources.locate(&fields) {
+				Some(source) => source,
+				None => continue,
+			};
+
+			let file = source
+				.file
+				.strip_prefix(&root)
+				.or_else(|_| source.file.strip_prefix(&canonical))
+				.unwrap_or(&source.file);
+			let path = match &compilation.target {
+				Some(target) => Path::new("/").join(segment(target)).join(fields.join("/")),
+				None => manifest.path().to_path_buf(),
+			};
+
+			map.insert(
+				path.display().to_string(),
+				json!({
+					"file": file,
+					"line": source.line,
+					"column": source.column,
+					"field": source.field,
+				}),
+			);
+		}
+	}
+
+	Ok(Value::Object(map))
+}
+
+/// Prints a JSON document per target with its objects, or files, along warnings and metadata
+fn envelope(params: compile::Params) -> Result<()> {
+	for compilation in compile::compilations(params)? {
+		let kube = match compilation.rendered {
+			Rendered::Objects(kube) => kube,
+			Rendered::Files(artifacts) => {
+				let files: Vec<Value> = artifacts
+					.into_iter()
+					.map(|(path, contents)| json!({ "path": path, "contents": contents }))
+					.collect();
+				let warnings: Vec<&str> = match files.is_empty() {
+					true => vec!["No files were rendered"],
+					false => vec![],
+				};
+
+				let envelope = json!({
+					"files": files,
+					"warnings": warnings,
+					"metadata": compilation.metadata,
+				});
+
+				println!("{}", serde_json::to_string_pretty(&envelope)?);
+				continue;
+			}
+		};
+
+		let started = Instant::now();
+		let manifests: Vec<Manifest> = (*kube).try_into()?;
+		let elapsed = started.elapsed();
+
+		let objects: Vec<(PathBuf, Value)> = manifests.into_iter().map(|m| m.into()).collect();
+
+		let mut warnings: Vec<String> = compilation
+			.only
+			.iter()
+			.filter(|only| !objects.iter().any(|(path, _)| path.starts_with(only)))
+			.map(|only| format!("--only {} matched no object", only.display()))
+			.collect();
+		if objects.is_empty() {
+			warnings.push(String::from("No objects were rendered"));
+		}
+		warnings.extend(
+			compilation
+				.violations
+				.iter()
+				.map(compile::describe_violation),
+		);
+
+		let mut metadata = compilation.metadata;
+		metadata["durations"]["render"] = json!(elapsed.as_secs_f64() * 1000.0);
+
+		let objects: Vec<Value> = objects
+			.into_iter()
+			.map(|(path, object)| json!({ "path": path, "object": object }))
+			.collect();
+
+		let envelope = json!({
+			"objects": objects,
+			"warnings": warnings,
+			"metadata": metadata,
+		});
+
+		println!("{}", serde_json::to_string_pretty(&envelope)?);
+	}
+
+	Ok(())
+}
+
+/// Prints the summary of each target, after a `# target: <name>` comment
+/// when there are several
+fn summary(params: compile::Params) -> Result<()> {
+	for compilation in compile::compilations(params)? {
+		if let Some(target) = &compilation.target {
+			println!("# target: {target}");
+		}
+
+		match compilation.rendered {
+			Rendered::Objects(kube) => {
+				let manifests: Vec<Manifest> = (*kube).try_into()?;
+				let mut summary = Summary::default();
+				for manifest in manifests {
+					let (_, object) = manifest.into();
+					summary.add(&object);
+				}
+
+				print!("{summary}");
+			}
+			Rendered::Files(files) => println!("Files: {}", files.len()),
+		}
+	}
+
+	Ok(())
+}
+
+/// Prints a single JSON document with the objects, or files, of every target
+/// keyed by their addresses, along SHA-256 digests of their contents as
+/// compact JSON with sorted keys, or as they are for files
+fn plan(params: compile::Params) -> Result<()> {
+	let compilations = compile::compilations(params)?;
+	let mut metadata = compilations
+		.first()
+		.map(|compilation| compilation.metadata.clone())
+		.unwrap_or_default();
+	// Durations change every time, unlike the rest of the plan
+	if let Value::Object(metadata) = &mut metadata {
+		metadata.remove("durations");
+	}
+
+	let plan = json!({
+		"formatVersion": PLAN_FORMAT,
+		"metadata": metadata,
+		"resources": resources(compilations)?,
+	});
+
+	println!("{}", serde_json::to_string_pretty(&plan)?);
+
+	Ok(())
+}
+
+/// Objects and files of the compilations keyed by their addresses, along
+/// their hashes, refusing two of them at the same address
+pub fn resources(compilations: Vec<Compilation>) -> Result<Map<String, Value>> {
+	let mut resources = Map::new();
+	for compilation in compilations {
+		let target = compilation.target.as_deref();
+
+		let entries: Vec<(String, Value)> = match compilation.rendered {
+			Rendered::Objects(kube) => {
+				let manifests: Vec<Manifest> = (*kube).try_into()?;
+
+				manifests
+					.into_iter()
+					.map(|manifest| {
+						let (path, object) = manifest.into();
+						let address = address(target, &object).ok_or_else(|| {
+							Error::InvalidPlan(format!("{} has no name", path.display()))
+						})?;
+						let hash = Sha256::digest(json::canonical(&object));
+
+						let entry = json!({
+							"target": target,
+							"apiVersion": object["apiVersion"],
+							"kind": object["kind"],
+							"namespace": object["metadata"]["namespace"],
+							"name": object["metadata"]["name"],
+							"path": path,
+							"hash": format!("sha256:{hash:x}"),
+							"object": object,
+						});
+
+						Ok((address, entry))
+					})
+					.collect::<Result<_>>()?
+			}
+			Rendered::Files(files) => files
+				.into_iter()
+				.map(|(path, contents)| {
+					let address = match target {
+						Some(target) => format!("{target}#{}", path.display()),
+						None => path.display().to_string(),
+					};
+					let hash = Sha256::digest(contents.as_bytes());
+
+					let entry = json!({
+						"target": target,
+						"path": path,
+						"hash": format!("sha256:{hash:x}"),
+						"contents": contents,
+					});
+
+					(address, entry)
+				})
+				.collect(),
+		};
+
+		for (address, entry) in entries {
+			if let Some(existing) = resources.insert(address.clone(), entry) {
+				let reason = format!("{address} is also rendered at {}", existing["path"]);
+
+				return Err(Error::InvalidPlan(reason).into());
+			}
+		}
+	}
+
+	Ok(resources)
+}
+
+/// Directory of the target, encoding what would take it out of a single one,
+/// as context names are free to have slashes or be dots
+fn segment(target: &str) -> String {
+	let dots = target.chars().all(|c| c == '.');
+
+	target
+		.chars()
+		.map(|c| match c {
+			'/' | '\\' | '%' | ':' => format!("%{:02X}", c as u32),
+			'.' if dots => String::from("%2E"),
+			c if c.is_control() => format!("%{:02X}", c as u32),
+			c => c.to_string(),
+		})
+		.collect()
+}
+
+/// Identifies the object regardless of where it's rendered or its version, as
+/// `[target#]group/kind/[namespace/]name`, `core` being the group of the
+/// objects without one
+fn address(target: Option<&str>, object: &Value) -> Option<String> {
+	let api_version = object["apiVersion"].as_str()?;
+	let group = match api_version.split_once('/') {
+		Some((group, _version)) => group,
+		None => "core",
+	};
+	let kind = object["kind"].as_str()?;
+	let name = object["metadata"]["name"].as_str()?;
+
+	let address = match object["metadata"]["namespace"].as_str() {
+		Some(namespace) => format!("{group}/{kind}/{namespace}/{name}"),
+		None => format!("{group}/{kind}/{name}"),
+	};
+
+	match target {
+		Some(target) => Some(format!("{target}#{address}")),
+		None => Some(address),
+	}
+}
+
 fn ensure_output_exists(output: &Option<Output>) -> Result<Location, Error> {
 	let location = output.as_ref().cloned().map(|out| out.into());
 
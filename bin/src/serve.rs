@@ -0,0 +1,183 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::progress;
+use crate::render;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use clap::Parser;
+use hyper::header::{CONTENT_TYPE, HOST, ORIGIN};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+
+/// Page rendering the form from the schema and the objects compiled from it
+const PAGE: &str = include_str!("serve.html");
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "address to serve the preview at",
+		long,
+		default_value = "127.0.0.1:8080"
+	)]
+	listen: SocketAddr,
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	// Compiled again on every change, so it must stay around
+	if args.compile.package() == Path::new("-") {
+		return Err(Error::InvalidInput(
+			"serve needs the directory of the package, it can't be read from STDIN".to_string(),
+		)
+		.into());
+	}
+
+	let params = Arc::new(args.compile);
+	let listen = args.listen;
+	let service = make_service_fn(move |_conn| {
+		let params = params.clone();
+
+		async move {
+			Ok::<_, Infallible>(service_fn(move |request| {
+				handle(params.clone(), listen, request)
+			}))
+		}
+	});
+
+	let server = Server::try_bind(&args.listen)?.serve(service);
+	progress::message(&format!("Previewing at http://{}", args.listen));
+
+	server
+		.with_graceful_shutdown(async {
+			tokio::signal::ctrl_c().await.ok();
+		})
+		.await?;
+
+	Ok(())
+}
+
+async fn handle(
+	params: Arc<compile::Params>,
+	listen: SocketAddr,
+	request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+	let response = match (request.method(), request.uri().path()) {
+		_ if !trusted(&request, listen) => Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body(Body::empty()),
+		(&Method::POST, "/render") if !json(&request) => Response::builder()
+			.status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+			.body(Body::empty()),
+		(&Method::GET, "/") => Response::builder()
+			.header(CONTENT_TYPE, "text/html; charset=utf-8")
+			.body(Body::from(PAGE)),
+		(&Method::GET, "/schema") => {
+			let described = blocking(move || {
+				let (schema, example) = compile::schema(&params)?;
+
+				Ok(json!({ "schema": schema, "example": example }))
+			})
+			.await;
+
+			reply(described)
+		}
+		(&Method::POST, "/render") => {
+			let body = hyper::body::to_bytes(request.into_body()).await;
+			let rendered = blocking(move || {
+				let body = body?;
+				let input: Value = serde_json::from_slice(&body)
+					.map_err(|err| Error::InvalidInput(err.to_string()))?;
+
+				let compilations = compile::preview(&params, input)?;
+				let (documents, _files) = render::documents(compilations)?;
+				let documents: Vec<Value> = documents
+					.into_iter()
+					.map(
+						|(path, contents)| json!({ "path": path.display().to_string(), "contents": contents }),
+					)
+					.collect();
+
+				Ok(json!({ "documents": documents }))
+			})
+			.await;
+
+			reply(rendered)
+		}
+		_ => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(Body::empty()),
+	};
+
+	Ok(response.unwrap_or_else(|_err| {
+		let mut response = Response::default();
+		*response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+
+		response
+	}))
+}
+
+/// Whether the request was sent to the address we listen at, from a page served by us,
+/// so other sites, or names rebound to our address, can't have the package compiled
+fn trusted(request: &Request<Body>, listen: SocketAddr) -> bool {
+	let host = request
+		.headers()
+		.get(HOST)
+		.and_then(|host| host.to_str().ok());
+	let Some(host) = host.filter(|host| served(host, listen)) else {
+		return false;
+	};
+
+	match request.headers().get(ORIGIN) {
+		None => true,
+		Some(origin) => origin.to_str().ok() == Some(&format!("http://{host}")),
+	}
+}
+
+/// Whether the host names the address we listen at, or localhost when it's a loopback
+fn served(host: &str, listen: SocketAddr) -> bool {
+	host == listen.to_string()
+		|| (listen.ip().is_loopback() && host == format!("localhost:{}", listen.port()))
+}
+
+/// Whether the body is declared as JSON, as forms of other sites can't send it without asking first
+fn json(request: &Request<Body>) -> bool {
+	let declared = request
+		.headers()
+		.get(CONTENT_TYPE)
+		.and_then(|kind| kind.to_str().ok());
+
+	declared
+		.and_then(|kind| kind.split(';').next())
+		.is_some_and(|kind| kind.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Runs the compilation away from the server, as it blocks until it's done
+async fn blocking<F>(work: F) -> Result<Value>
+where
+	F: FnOnce() -> Result<Value> + Send + 'static,
+{
+	tokio::task::spawn_blocking(work).await?
+}
+
+/// Replies with the value as JSON, or the error as the CLI would print it
+fn reply(result: Result<Value>) -> hyper::http::Result<Response<Body>> {
+	let (status, body) = match result {
+		Ok(value) => (StatusCode::OK, value),
+		Err(err) => (
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "error": format!("{err:?}") }),
+		),
+	};
+
+	Response::builder()
+		.status(status)
+		.header(CONTENT_TYPE, "application/json")
+		.body(Body::from(body.to_string()))
+}
@@ -0,0 +1,120 @@
+use crate::completions;
+use crate::diff::Noise;
+use crate::operation::release;
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use clap_complete::engine::ArgValueCompleter;
+use kct_kube::{Drift, State};
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+	#[default]
+	Table,
+	Json,
+}
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "release to report on",
+		value_name = "RELEASE",
+		add = ArgValueCompleter::new(completions::releases)
+	)]
+	name: String,
+	#[arg(help = "how to print the report", long, value_enum, default_value_t)]
+	format: Format,
+	#[command(flatten)]
+	noise: Noise,
+}
+
+/// Compares the objects the release was last installed with, as stored, to
+/// the live ones of every target
+pub async fn run(args: Args) -> Result<()> {
+	let ignore = args.noise.ignore()?;
+	let kubes = release::manifests(&args.name)?;
+	let mut report: Vec<Value> = vec![];
+
+	for kube in kubes {
+		let target = kube.context().map(String::from);
+		let drifts = kube.status(&ignore).await?;
+
+		match args.format {
+			Format::Table => {
+				if let Some(target) = &target {
+					println!("TARGET {target}");
+				}
+
+				print_table(&drifts);
+			}
+			Format::Json => report.extend(drifts.iter().map(|drift| {
+				let mut entry = to_json(drift);
+
+				if let Some(target) = &target {
+					entry["target"] = json!(target);
+				}
+
+				entry
+			})),
+		}
+	}
+
+	if let Format::Json = args.format {
+		println!("{}", serde_json::to_string_pretty(&report)?);
+	}
+
+	Ok(())
+}
+
+fn label(state: &State) -> &'static str {
+	match state {
+		State::Synced => "synced",
+		State::Missing => "missing",
+		State::Modified(_) => "modified",
+		State::Extra => "extra",
+	}
+}
+
+fn to_json(drift: &Drift) -> Value {
+	let fields = match &drift.state {
+		State::Modified(fields) => fields.clone(),
+		_ => vec![],
+	};
+
+	json!({
+		"object": drift.path.display().to_string(),
+		"state": label(&drift.state),
+		"fields": fields,
+	})
+}
+
+fn print_table(drifts: &[Drift]) {
+	let rows: Vec<(String, &str, String)> = drifts
+		.iter()
+		.map(|drift| {
+			let fields = match &drift.state {
+				State::Modified(fields) => fields.join(", "),
+				_ => String::new(),
+			};
+
+			(
+				drift.path.display().to_string(),
+				label(&drift.state),
+				fields,
+			)
+		})
+		.collect();
+
+	let width = rows
+		.iter()
+		.map(|(object, _, _)| object.len())
+		.chain(["OBJECT".len()])
+		.max()
+		.unwrap_or_default();
+
+	println!("{:width$}  {:8}  FIELDS", "OBJECT", "STATE");
+	for (object, state, fields) in rows {
+		println!("{object:width$}  {state:8}  {fields}");
+	}
+}
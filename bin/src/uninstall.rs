@@ -1,4 +1,4 @@
-use crate::operation::compile;
+use crate::operation::{compile, release};
 
 use anyhow::Result;
 use clap::Parser;
@@ -10,8 +10,19 @@ pub struct Args {
 }
 
 pub async fn run(args: Args) -> Result<()> {
-	let kube = compile::run(args.compile)?;
-	kube.uninstall().await?;
+	let compilations = compile::compilations(args.compile)?;
+	let name = compilations
+		.first()
+		.and_then(|compilation| compilation.metadata["release"]["name"].as_str())
+		.map(String::from);
+
+	for kube in compile::kubes(compilations)? {
+		kube.uninstall().await?;
+	}
+
+	if let Some(name) = name {
+		release::forget(&name)?;
+	}
 
 	Ok(())
 }
@@ -0,0 +1,37 @@
+use crate::completions;
+use crate::install;
+use crate::operation::{compile, release};
+
+use anyhow::Result;
+use clap::Parser;
+use clap_complete::engine::ArgValueCompleter;
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "release to upgrade",
+		value_name = "RELEASE",
+		conflicts_with = "release",
+		add = ArgValueCompleter::new(completions::releases)
+	)]
+	name: String,
+	#[arg(
+		help = "merge the inputs over the ones the release was last installed with",
+		long
+	)]
+	reuse_values: bool,
+	#[command(flatten)]
+	apply: install::Apply,
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	let reused = match args.reuse_values {
+		true => Some(release::inputs(&args.name)?),
+		false => None,
+	};
+	let params = args.compile.upgrading(args.name, reused);
+
+	install::install(params, &args.apply, "upgrade").await
+}
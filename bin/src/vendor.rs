@@ -0,0 +1,25 @@
+use crate::progress;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "directory with the package to vendor dependencies for", value_hint = ValueHint::DirPath)]
+	package: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let vendored = kct_package::vendor(&args.package)?;
+
+	for vendored in vendored {
+		let name = vendored.dependency.name();
+		let rev = vendored.rev.get(..12).unwrap_or(&vendored.rev);
+
+		progress::message(&format!("{} vendored at {rev}", name.display()));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::operation::record;
+use crate::progress;
+use crate::render;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, ValueHint};
+use kct_helper::io;
+use serde_json::Value;
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(
+		help = "record of a previous render to rebuild, with the same inputs, and compare",
+		long,
+		value_hint = ValueHint::FilePath
+	)]
+	reproduce: Option<PathBuf>,
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let path = match args.reproduce {
+		Some(path) => path,
+		None => {
+			compile::verify(args.compile)?;
+			progress::message("Dependencies match the lockfile");
+
+			return Ok(());
+		}
+	};
+
+	let recorded: Value = serde_json::from_str(&io::from_file(&path)?)
+		.map_err(|err| Error::InvalidInput(format!("{}: {err}", path.display())))?;
+
+	let compilations = compile::compilations(args.compile.reproducing(&recorded))?;
+	let metadata = compilations
+		.first()
+		.map(|compilation| compilation.metadata.clone())
+		.unwrap_or_default();
+	let (documents, _files) = render::documents(compilations)?;
+
+	let rebuilt = record::record(&metadata, record::digests(&documents));
+	let differences = record::diff(&recorded, &rebuilt);
+
+	if !differences.is_empty() {
+		for difference in &differences {
+			println!("{difference}");
+		}
+
+		return Err(Error::NotReproducible(differences.len()).into());
+	}
+
+	progress::message("Render reproduces the record");
+
+	Ok(())
+}
@@ -64,6 +64,135 @@ impl From<Set> for Value {
 	}
 }
 
+/// Values file with Helm semantics, YAML or JSON merged like the inputs
+#[derive(Clone)]
+pub struct Values(Value);
+
+impl FromStr for Values {
+	type Err = Error;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let location = Location::from_str(s).map_err(|err| Error::InvalidInput(err.to_string()))?;
+		let contents = location
+			.read()
+			.map_err(|err| Error::InvalidInput(err.to_string()))?;
+		// Empty files, as Helm charts often ship, set nothing
+		if contents.trim().is_empty() {
+			return Ok(Self(Value::Object(Default::default())));
+		}
+
+		let parsed: Value = serde_yaml::from_str(&contents)
+			.map_err(|err| Error::InvalidInput(format!("{s}: {err}")))?;
+
+		match parsed {
+			Value::Null => Ok(Self(Value::Object(Default::default()))),
+			parsed => Ok(Self(parsed)),
+		}
+	}
+}
+
+impl From<Values> for Value {
+	fn from(val: Values) -> Self {
+		val.0
+	}
+}
+
+/// Parameters set to strings as Helm's `--set-string`, `path=value` pairs
+/// separated by commas, escaped with `\` within paths and values
+#[derive(Clone)]
+pub struct SetString(Value);
+
+impl FromStr for SetString {
+	type Err = Error;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let mut result = Value::Null;
+
+		for (path, value) in assignments(s)? {
+			let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+			set_in(&mut result, &path, Value::String(value));
+		}
+
+		Ok(Self(result))
+	}
+}
+
+impl From<SetString> for Value {
+	fn from(val: SetString) -> Self {
+		val.0
+	}
+}
+
+/// Parameters set to the contents of files as Helm's `--set-file`,
+/// `path=file` pairs separated by commas
+#[derive(Clone)]
+pub struct SetFile(Value);
+
+impl FromStr for SetFile {
+	type Err = Error;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let mut result = Value::Null;
+
+		for (path, file) in assignments(s)? {
+			let path: Vec<&str> = path.iter().map(String::as_str).collect();
+			let contents = io::from_file(&PathBuf::from(&file))
+				.map_err(|err| Error::InvalidInput(format!("{file}: {err}")))?;
+
+			set_in(&mut result, &path, Value::String(contents));
+		}
+
+		Ok(Self(result))
+	}
+}
+
+impl From<SetFile> for Value {
+	fn from(val: SetFile) -> Self {
+		val.0
+	}
+}
+
+/// Splits Helm-style assignments into their paths and values, honoring
+/// escaped dots, commas, and equal signs
+fn assignments(s: &str) -> Result<Vec<(Vec<String>, String)>, Error> {
+	let invalid = || Error::InvalidInput(format!("{s} should be path=value[,path=value]"));
+
+	let mut assignments = vec![];
+	let mut path = vec![String::new()];
+	let mut value: Option<String> = None;
+	let mut chars = s.chars();
+
+	while let Some(char) = chars.next() {
+		let (char, escaped) = match char {
+			'\\' => (chars.next().ok_or_else(invalid)?, true),
+			char => (char, false),
+		};
+
+		match (char, escaped, &mut value) {
+			(',', false, value) => {
+				let assigned = value.take().ok_or_else(invalid)?;
+				assignments.push((std::mem::replace(&mut path, vec![String::new()]), assigned));
+			}
+			('.', false, None) => path.push(String::new()),
+			('=', false, value @ None) => *value = Some(String::new()),
+			(char, _, Some(value)) => value.push(char),
+			(char, _, None) => path.last_mut().expect("there's always a key").push(char),
+		}
+	}
+
+	assignments.push((path, value.ok_or_else(invalid)?));
+
+	if assignments
+		.iter()
+		.any(|(path, _)| path.iter().any(String::is_empty))
+	{
+		return Err(invalid());
+	}
+
+	Ok(assignments)
+}
+
 #[derive(Clone)]
 pub struct Output(Location);
 
@@ -1,12 +1,22 @@
-use crate::{error, Error, Release};
+use crate::{error, Error, Http, Release};
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use kct_helper::lookup::Lookup;
+use kct_helper::progress::{self, Progress};
+
 struct Internal {
 	root: PathBuf,
 	release: Option<Release>,
 	vendor: PathBuf,
+	libraries: Vec<PathBuf>,
+	progress: Progress,
+	now: DateTime<Utc>,
+	http: Http,
+	lookup: Option<Lookup>,
+	offline: bool,
 }
 
 #[derive(Clone)]
@@ -28,6 +38,36 @@ impl Context {
 	pub fn vendor(&self) -> &Path {
 		&self.0.vendor
 	}
+
+	/// Directories searched for libraries before the ones of the packages,
+	/// as `-J` of jsonnet, first match winning
+	pub fn libraries(&self) -> &[PathBuf] {
+		&self.0.libraries
+	}
+
+	pub fn progress(&self) -> &Progress {
+		&self.0.progress
+	}
+
+	/// Time frozen for the whole compilation, including subpackages
+	pub fn now(&self) -> &DateTime<Utc> {
+		&self.0.now
+	}
+
+	pub fn http(&self) -> &Http {
+		&self.0.http
+	}
+
+	/// Live objects templates can read, absent for offline compilations
+	pub fn lookup(&self) -> Option<&Lookup> {
+		self.0.lookup.as_ref()
+	}
+
+	/// Whether templates are refused anything reaching the network, leaving
+	/// them with what's vendored and cached
+	pub fn offline(&self) -> bool {
+		self.0.offline
+	}
 }
 
 #[derive(Default)]
@@ -35,6 +75,12 @@ pub struct ContextBuilder {
 	root: Option<PathBuf>,
 	release: Option<Release>,
 	vendor: Option<PathBuf>,
+	libraries: Option<Vec<PathBuf>>,
+	progress: Option<Progress>,
+	http: Option<Http>,
+	lookup: Option<Lookup>,
+	offline: Option<bool>,
+	now: Option<DateTime<Utc>>,
 }
 
 impl ContextBuilder {
@@ -60,15 +106,99 @@ impl ContextBuilder {
 		}
 	}
 
+	pub fn libraries(mut self, libraries: Vec<PathBuf>) -> Self {
+		match self.libraries {
+			Some(_) => self,
+			None => {
+				self.libraries = Some(libraries);
+
+				self
+			}
+		}
+	}
+
+	pub fn progress(mut self, progress: Progress) -> Self {
+		match self.progress {
+			Some(_) => self,
+			None => {
+				self.progress = Some(progress);
+
+				self
+			}
+		}
+	}
+
+	pub fn http(mut self, http: Http) -> Self {
+		match self.http {
+			Some(_) => self,
+			None => {
+				self.http = Some(http);
+
+				self
+			}
+		}
+	}
+
+	pub fn lookup(mut self, lookup: Lookup) -> Self {
+		match self.lookup {
+			Some(_) => self,
+			None => {
+				self.lookup = Some(lookup);
+
+				self
+			}
+		}
+	}
+
+	pub fn offline(mut self, offline: bool) -> Self {
+		match self.offline {
+			Some(_) => self,
+			None => {
+				self.offline = Some(offline);
+
+				self
+			}
+		}
+	}
+
+	/// Time templates see when the release has no timestamp, the current one
+	/// when absent
+	pub fn now(mut self, now: DateTime<Utc>) -> Self {
+		match self.now {
+			Some(_) => self,
+			None => {
+				self.now = Some(now);
+
+				self
+			}
+		}
+	}
+
 	pub fn build(self) -> Result<Context, Error> {
 		let root = self.root.ok_or(error::Context::NoRoot)?;
 		let release = self.release;
 		let vendor = self.vendor.unwrap_or_else(|| default_vendor(&root));
+		let libraries = self.libraries.unwrap_or_default();
+		let progress = self.progress.unwrap_or_else(progress::silent);
+		let now = release
+			.as_ref()
+			.and_then(|release| release.timestamp)
+			.or(self.now)
+			.unwrap_or_else(Utc::now);
+		let http = self.http.unwrap_or_default();
+		let lookup = self.lookup;
+		let offline = self.offline.unwrap_or_default();
 
 		let internal = Internal {
 			root,
 			release,
 			vendor,
+			libraries,
+			progress,
+			now,
+			http,
+			lookup,
+			offline,
 		};
 
 		Ok(Context(Arc::new(internal)))
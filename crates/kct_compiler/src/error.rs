@@ -1,3 +1,6 @@
+use std::fmt;
+use std::path::PathBuf;
+
 use kct_jsonnet::Error as JsonnetError;
 use thiserror::Error;
 
@@ -17,6 +20,37 @@ pub enum Error {
 	Context(#[from] Context),
 	#[error(transparent)]
 	Executable(#[from] JsonnetError),
+	#[error(transparent)]
+	Template(#[from] TemplateError),
+}
+
+/// Failure of a template rendered by the package, at the file, and the line
+/// and column when known, along the lines around it
+#[derive(Error, Debug, Clone)]
+pub struct TemplateError {
+	pub path: PathBuf,
+	pub line: Option<usize>,
+	pub column: Option<usize>,
+	pub snippet: Option<String>,
+	pub reason: String,
+}
+
+impl fmt::Display for TemplateError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}\n  --> {}", self.reason, self.path.display())?;
+
+		if let Some(line) = self.line {
+			write!(f, ":{line}")?;
+		}
+		if let Some(column) = self.column {
+			write!(f, ":{column}")?;
+		}
+		if let Some(snippet) = &self.snippet {
+			write!(f, "\n{snippet}")?;
+		}
+
+		Ok(())
+	}
 }
 
 #[derive(Error, Debug)]
@@ -9,19 +9,43 @@ use self::property::{Generator, Property};
 use self::property::{Name, Prop};
 
 pub use self::context::Context;
-pub use self::error::Error;
+pub use self::error::{Error, TemplateError};
 pub use self::target::{Target, TargetBuilder};
 pub use self::validator::Validator;
+pub use kct_jsonnet::{
+	lint, sdk, source, usage, Dialect, Format, Sdk, IMPORTS_TARGET, MAX_IMPORT_SIZE,
+};
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use kct_jsonnet::Executable;
 use serde_json::Value;
+use tracing::{debug, instrument};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Release {
 	pub name: String,
+	/// Destination being compiled when the release spans multiple clusters
+	pub target: Option<String>,
+	/// Moment the release happens, freezing the time seen by templates
+	pub timestamp: Option<DateTime<Utc>>,
+	/// Secret from which values that must survive renders, e.g. passwords, are derived
+	pub seed: Option<String>,
+}
+
+/// Requests templates can make at render time, none unless allowed
+#[derive(Clone, Debug, Default)]
+pub struct Http {
+	/// URL prefixes templates are allowed to request
+	pub allow: Vec<String>,
+	/// Directory keeping responses between renders, skipped when absent
+	pub cache: Option<PathBuf>,
+	/// How long cached responses are reused before requesting them again
+	pub ttl: Duration,
 }
 
 pub struct Input(pub Value);
@@ -45,8 +69,14 @@ impl System {
 		Ok(Executable {
 			vendor: self.context.vendor().to_path_buf(),
 			lib: self.target.lib().to_path_buf(),
+			libraries: [self.context.libraries(), self.target.libraries()].concat(),
+			aliases: self.target.aliases().clone(),
 			main: self.target.main().to_path_buf(),
+			layers: self.target.layers(),
 			props: self.properties(),
+			format: self.target.format(),
+			dialect: self.target.dialect().clone(),
+			sdk: self.target.sdk(),
 		})
 	}
 
@@ -124,6 +154,15 @@ impl Compiler {
 		}
 	}
 
+	pub fn context(&self) -> &Context {
+		&self.context
+	}
+
+	/// Input given to the templates, if any was
+	pub fn input(&self) -> Option<&Value> {
+		self.statics.get(&Name::Input).and_then(Prop::value)
+	}
+
 	pub fn with_dynamic_prop(mut self, prop: Option<Box<dyn Generator>>) -> Self {
 		if let Some(prop) = prop {
 			self.dynamics.insert(prop.name(), prop);
@@ -157,9 +196,11 @@ impl Compiler {
 		}
 	}
 
+	#[instrument(name = "compile", skip_all, fields(root = %self.context.root().display()))]
 	pub fn compile(self) -> Result<Value, Error> {
 		let system: System = self.try_into()?;
 		let executable = system.generate()?;
+		debug!(props = executable.props.len(), "executable generated");
 		let value = executable.run()?;
 
 		Ok(value)
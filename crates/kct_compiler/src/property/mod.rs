@@ -11,29 +11,107 @@ pub use kct_jsonnet::property::{Callback, Function, Property};
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 pub enum Name {
+	Base64,
+	Duration,
 	Files,
+	FilesDigest,
+	Http,
+	ImportBin,
 	Include,
 	Input,
+	JsonPath,
+	Kustomize,
+	Lookup,
+	Names,
+	Net,
 	Package,
+	Patch,
+	Pem,
+	Quantity,
+	Random,
+	ReadFile,
+	Regex,
 	Release,
+	Schema,
+	SchemaVersion,
+	Semver,
+	Time,
+	Tls,
+	Url,
+	Uuid,
+	UuidFrom,
 }
 
 impl Name {
-	pub fn all() -> [Name; 5] {
+	pub fn all() -> [Name; 29] {
 		use Name::*;
 
-		[Files, Include, Input, Package, Release]
+		[
+			Base64,
+			Duration,
+			Files,
+			FilesDigest,
+			Http,
+			ImportBin,
+			Include,
+			Input,
+			JsonPath,
+			Kustomize,
+			Lookup,
+			Names,
+			Net,
+			Package,
+			Patch,
+			Pem,
+			Quantity,
+			Random,
+			ReadFile,
+			Regex,
+			Release,
+			Schema,
+			SchemaVersion,
+			Semver,
+			Time,
+			Tls,
+			Url,
+			Uuid,
+			UuidFrom,
+		]
 	}
 
 	pub fn as_str(&self) -> &str {
 		use Name::*;
 
 		match self {
+			Base64 => "base64",
+			Duration => "duration",
 			Files => "files",
+			FilesDigest => "filesDigest",
+			Http => "http",
+			ImportBin => "importBin",
 			Include => "include",
+			JsonPath => "jsonpath",
+			Kustomize => "kustomize",
+			Lookup => "lookup",
+			Names => "names",
+			Net => "net",
 			Package => "package",
+			Patch => "patch",
+			Pem => "pem",
+			Quantity => "quantity",
+			Random => "random",
+			ReadFile => "readFile",
+			Regex => "regex",
 			Release => "release",
 			Input => "input",
+			Schema => "schema",
+			SchemaVersion => "schemaVersion",
+			Semver => "semver",
+			Time => "time",
+			Tls => "tls",
+			Url => "url",
+			Uuid => "uuid",
+			UuidFrom => "uuidFrom",
 		}
 	}
 }
@@ -55,6 +133,20 @@ impl Prop {
 		Prop(name, Property::Callable(name.as_str().to_string(), func))
 	}
 
+	/// Groups functions under the property, each named after its key
+	pub fn group(name: Name, members: Vec<(&str, Function)>) -> Self {
+		let members = members
+			.into_iter()
+			.map(|(member, func)| {
+				let qualified = format!("{}.{member}", name.as_str());
+
+				(member.to_string(), Property::Callable(qualified, func))
+			})
+			.collect();
+
+		Prop(name, Property::Object(members))
+	}
+
 	pub fn take(self) -> (Name, Property) {
 		(self.0, self.1)
 	}
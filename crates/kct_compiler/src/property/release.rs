@@ -10,6 +10,17 @@ impl From<&Release> for Value {
 		let mut map = Map::<String, Value>::new();
 		map.insert(String::from("name"), Value::String(release.name.clone()));
 
+		if let Some(target) = &release.target {
+			map.insert(String::from("target"), Value::String(target.clone()));
+		}
+
+		if let Some(timestamp) = &release.timestamp {
+			map.insert(
+				String::from("timestamp"),
+				Value::String(timestamp.to_rfc3339()),
+			);
+		}
+
 		Value::Object(map)
 	}
 }
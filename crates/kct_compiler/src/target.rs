@@ -1,3 +1,6 @@
+use kct_jsonnet::{sdk, Dialect, Format, Sdk};
+
+use std::collections::BTreeMap;
 use std::convert::From;
 use std::path::{Path, PathBuf};
 
@@ -5,6 +8,12 @@ pub struct Target {
 	dir: PathBuf,
 	main: PathBuf,
 	lib: PathBuf,
+	libraries: Vec<PathBuf>,
+	aliases: BTreeMap<String, PathBuf>,
+	bases: Vec<PathBuf>,
+	format: Format,
+	dialect: Dialect,
+	sdk: &'static Sdk,
 }
 
 impl Target {
@@ -19,6 +28,45 @@ impl Target {
 	pub fn lib(&self) -> &Path {
 		&self.lib
 	}
+
+	/// Directories the package searches for libraries after its libs, first
+	/// match winning
+	pub fn libraries(&self) -> &[PathBuf] {
+		&self.libraries
+	}
+
+	/// Files or directories the package imports by other names
+	pub fn aliases(&self) -> &BTreeMap<String, PathBuf> {
+		&self.aliases
+	}
+
+	/// Roots of the packages extended by this one, nearest first
+	pub fn bases(&self) -> &[PathBuf] {
+		&self.bases
+	}
+
+	/// How the main template is manifested, JSON unless it renders plain text
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	/// Parts of Jsonnet the templates may use
+	pub fn dialect(&self) -> &Dialect {
+		&self.dialect
+	}
+
+	/// Version of the SDK imported as `kct.libsonnet`, unless vendored
+	pub fn sdk(&self) -> &'static Sdk {
+		self.sdk
+	}
+
+	/// Package roots searched for templates and files, from this one down to its bases
+	pub fn layers(&self) -> Vec<PathBuf> {
+		let mut layers = vec![self.dir.clone()];
+		layers.extend(self.bases.iter().cloned());
+
+		layers
+	}
 }
 
 #[derive(Default)]
@@ -26,6 +74,12 @@ pub struct TargetBuilder {
 	dir: Option<PathBuf>,
 	main: Option<PathBuf>,
 	lib: Option<PathBuf>,
+	libraries: Option<Vec<PathBuf>>,
+	aliases: Option<BTreeMap<String, PathBuf>>,
+	bases: Option<Vec<PathBuf>>,
+	format: Option<Format>,
+	dialect: Option<Dialect>,
+	sdk: Option<&'static Sdk>,
 }
 
 impl TargetBuilder {
@@ -51,12 +105,95 @@ impl TargetBuilder {
 		}
 	}
 
+	pub fn libraries(mut self, libraries: Vec<PathBuf>) -> Self {
+		match self.libraries {
+			Some(_) => self,
+			None => {
+				self.libraries = Some(libraries);
+
+				self
+			}
+		}
+	}
+
+	pub fn aliases(mut self, aliases: BTreeMap<String, PathBuf>) -> Self {
+		match self.aliases {
+			Some(_) => self,
+			None => {
+				self.aliases = Some(aliases);
+
+				self
+			}
+		}
+	}
+
+	pub fn bases(mut self, bases: Vec<PathBuf>) -> Self {
+		match self.bases {
+			Some(_) => self,
+			None => {
+				self.bases = Some(bases);
+
+				self
+			}
+		}
+	}
+
+	pub fn format(mut self, format: Format) -> Self {
+		match self.format {
+			Some(_) => self,
+			None => {
+				self.format = Some(format);
+
+				self
+			}
+		}
+	}
+
+	pub fn dialect(mut self, dialect: Dialect) -> Self {
+		match self.dialect {
+			Some(_) => self,
+			None => {
+				self.dialect = Some(dialect);
+
+				self
+			}
+		}
+	}
+
+	pub fn sdk(mut self, sdk: &'static Sdk) -> Self {
+		match self.sdk {
+			Some(_) => self,
+			None => {
+				self.sdk = Some(sdk);
+
+				self
+			}
+		}
+	}
+
 	pub fn build(self) -> Result<Target, String> {
 		let dir = self.dir.ok_or_else(|| String::from("dir is required"))?;
 		let main = self.main.ok_or_else(|| String::from("main is required"))?;
 		let lib = self.lib.unwrap_or_else(|| default_lib(&dir));
+		let libraries = self.libraries.unwrap_or_default();
+		let aliases = self.aliases.unwrap_or_default();
+
+		let bases = self.bases.unwrap_or_default();
+		let format = self.format.unwrap_or_default();
+		let dialect = self.dialect.unwrap_or_default();
+		let sdk = self.sdk.unwrap_or_else(sdk::latest);
 
-		Ok(Target { dir, main, lib })
+		Ok(Target {
+			dir,
+			main,
+			lib,
+			libraries,
+			aliases,
+			bases,
+			format,
+			dialect,
+			sdk,
+		})
 	}
 }
 
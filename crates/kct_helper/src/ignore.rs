@@ -0,0 +1,253 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Fields servers populate or derive on their own, which diffs leave out
+const DEFAULTS: [&str; 10] = [
+	"/status",
+	"/metadata/managedFields",
+	"/metadata/creationTimestamp",
+	"/metadata/resourceVersion",
+	"/metadata/uid",
+	"/metadata/generation",
+	"/metadata/selfLink",
+	"/metadata/annotations/kubectl.kubernetes.io~1last-applied-configuration",
+	"/metadata/annotations/deployment.kubernetes.io~1revision",
+	"/spec/template/metadata/creationTimestamp",
+];
+
+/// Taints pods tolerate for five minutes unless they tell otherwise, as the
+/// admission controller adds them to every pod
+const DEFAULT_TOLERATIONS: [&str; 2] = [
+	"node.kubernetes.io/not-ready",
+	"node.kubernetes.io/unreachable",
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("Invalid ignore rule {0}, it should be a JSON pointer optionally after a kind and :")]
+	InvalidRule(String),
+}
+
+/// Field to leave out, as a JSON pointer where `*` matches any key or item,
+/// of every object or only the ones of a kind as `Kind:/pointer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+	kind: Option<String>,
+	segments: Vec<String>,
+}
+
+impl FromStr for Rule {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (kind, pointer) = match s.split_once(':') {
+			Some((kind, pointer)) if !kind.starts_with('/') => (Some(kind.to_string()), pointer),
+			_ => (None, s),
+		};
+
+		let segments = match pointer.strip_prefix('/') {
+			Some(pointer) if kind.as_deref() != Some("") => pointer
+				.split('/')
+				.map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+				.collect(),
+			_ => return Err(Error::InvalidRule(s.to_string())),
+		};
+
+		Ok(Rule { kind, segments })
+	}
+}
+
+/// Leaves out of objects what would only be noise when diffing them: the
+/// fields servers populate, the tolerations they default, and whatever the
+/// rules it was given match
+#[derive(Debug, Clone)]
+pub struct Ignore {
+	rules: Vec<Rule>,
+}
+
+impl Default for Ignore {
+	fn default() -> Self {
+		let rules = DEFAULTS
+			.iter()
+			.map(|pointer| pointer.parse().expect("default rules are valid"))
+			.collect();
+
+		Ignore { rules }
+	}
+}
+
+impl Ignore {
+	/// Also leaves out the fields matched by the rules
+	pub fn with(mut self, rules: impl IntoIterator<Item = Rule>) -> Self {
+		self.rules.extend(rules);
+
+		self
+	}
+
+	/// Removes the ignored fields from the object
+	pub fn strip(&self, object: &mut Value) {
+		let kind = object["kind"].as_str().map(String::from);
+
+		for rule in &self.rules {
+			match (&rule.kind, &kind) {
+				(Some(only), Some(kind)) if only != kind => continue,
+				(Some(_), None) => continue,
+				_ => remove(object, &rule.segments),
+			}
+		}
+
+		if kind.as_deref() == Some("Pod") {
+			untolerate(object);
+		}
+	}
+}
+
+fn remove(value: &mut Value, segments: &[String]) {
+	let (segment, rest) = match segments.split_first() {
+		Some(split) => split,
+		None => return,
+	};
+
+	match value {
+		Value::Object(fields) if rest.is_empty() && segment == "*" => fields.clear(),
+		Value::Object(fields) if rest.is_empty() => {
+			fields.remove(segment);
+		}
+		Value::Object(fields) if segment == "*" => {
+			fields.values_mut().for_each(|field| remove(field, rest));
+		}
+		Value::Object(fields) => {
+			if let Some(field) = fields.get_mut(segment) {
+				remove(field, rest);
+			}
+		}
+		Value::Array(items) if rest.is_empty() && segment == "*" => items.clear(),
+		Value::Array(items) if rest.is_empty() => {
+			if let Some(i) = segment.parse().ok().filter(|i| *i < items.len()) {
+				items.remove(i);
+			}
+		}
+		Value::Array(items) if segment == "*" => {
+			items.iter_mut().for_each(|item| remove(item, rest));
+		}
+		Value::Array(items) => {
+			if let Some(item) = segment.parse().ok().and_then(|i: usize| items.get_mut(i)) {
+				remove(item, rest);
+			}
+		}
+		_ => (),
+	}
+}
+
+/// Removes the tolerations added by default, and the list when nothing else
+/// was in it
+fn untolerate(pod: &mut Value) {
+	let spec = match pod.get_mut("spec").and_then(Value::as_object_mut) {
+		Some(spec) => spec,
+		None => return,
+	};
+
+	if let Some(Value::Array(tolerations)) = spec.get_mut("tolerations") {
+		tolerations.retain(|toleration| {
+			!DEFAULT_TOLERATIONS.iter().any(|key| {
+				toleration
+					== &json!({
+						"key": key,
+						"operator": "Exists",
+						"effect": "NoExecute",
+						"tolerationSeconds": 300,
+					})
+			})
+		});
+
+		if tolerations.is_empty() {
+			spec.remove("tolerations");
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::json;
+
+	use super::{Error, Ignore, Rule};
+
+	#[test]
+	fn strips_server_populated_fields() {
+		let mut object = json!({
+			"kind": "ConfigMap",
+			"metadata": {"name": "a", "uid": "1", "resourceVersion": "2", "managedFields": []},
+			"data": {"a": "b"},
+			"status": {},
+		});
+
+		Ignore::default().strip(&mut object);
+
+		assert_eq!(
+			object,
+			json!({"kind": "ConfigMap", "metadata": {"name": "a"}, "data": {"a": "b"}})
+		);
+	}
+
+	#[test]
+	fn strips_default_tolerations_of_pods() {
+		let defaulted = |key: &str| json!({"key": key, "operator": "Exists", "effect": "NoExecute", "tolerationSeconds": 300});
+		let own = json!({"key": "gpu", "operator": "Exists"});
+		let mut pod = json!({
+			"kind": "Pod",
+			"spec": {"tolerations": [defaulted("node.kubernetes.io/not-ready"), own.clone(), defaulted("node.kubernetes.io/unreachable")]},
+		});
+		let mut alone = json!({
+			"kind": "Pod",
+			"spec": {"tolerations": [defaulted("node.kubernetes.io/not-ready")]},
+		});
+
+		Ignore::default().strip(&mut pod);
+		Ignore::default().strip(&mut alone);
+
+		assert_eq!(pod, json!({"kind": "Pod", "spec": {"tolerations": [own]}}));
+		assert_eq!(alone, json!({"kind": "Pod", "spec": {}}));
+	}
+
+	#[test]
+	fn strips_what_rules_match() {
+		let rules = [
+			"Deployment:/spec/replicas",
+			"/spec/template/spec/containers/*/image",
+		];
+		let ignore = Ignore::default().with(rules.iter().map(|rule| rule.parse().unwrap()));
+
+		let spec = json!({"replicas": 2, "template": {"spec": {"containers": [{"name": "a", "image": "b"}]}}});
+		let mut deployment = json!({"kind": "Deployment", "spec": spec});
+		let mut set = json!({"kind": "StatefulSet", "spec": spec});
+
+		ignore.strip(&mut deployment);
+		ignore.strip(&mut set);
+
+		let containers = json!({"template": {"spec": {"containers": [{"name": "a"}]}}});
+		assert_eq!(
+			deployment,
+			json!({"kind": "Deployment", "spec": containers})
+		);
+		assert_eq!(set["spec"]["replicas"], json!(2));
+	}
+
+	#[test]
+	fn parses_rules() {
+		let rule: Rule = "/metadata/annotations/example.com~1a".parse().unwrap();
+		assert_eq!(rule.kind, None);
+		assert_eq!(rule.segments, ["metadata", "annotations", "example.com/a"]);
+
+		let rule: Rule = "Secret:/data".parse().unwrap();
+		assert_eq!(rule.kind.as_deref(), Some("Secret"));
+
+		for invalid in ["data", ":/data", "Secret:data"] {
+			assert_eq!(
+				invalid.parse::<Rule>(),
+				Err(Error::InvalidRule(invalid.to_string()))
+			);
+		}
+	}
+}
@@ -1,8 +1,10 @@
+use std::collections::BTreeSet;
 use std::env;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use anyhow::Result;
 use thiserror::Error;
@@ -23,6 +25,9 @@ pub enum Error {
 	NoCwd,
 }
 
+/// Files the process read so far, telling what a render depends on
+static READS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
 #[derive(Clone)]
 pub enum Location {
 	Standard,
@@ -63,17 +68,23 @@ impl Location {
 		}
 	}
 
-	pub fn write(self, documents: Vec<(PathBuf, String)>) -> Result<(), Error> {
+	/// Writes each document as it comes, so they don't have to be rendered
+	/// all at once
+	pub fn write(
+		self,
+		documents: impl IntoIterator<Item = (PathBuf, String)>,
+	) -> Result<(), Error> {
 		match self {
 			Self::Standard => {
-				let contents: String = documents
-					.into_iter()
-					.map(|(_path, object)| object)
-					.collect();
-
-				println!("{contents}");
+				let mut out = BufWriter::new(io::stdout().lock());
+				for (_path, object) in documents {
+					out.write_all(object.as_bytes())
+						.map_err(|_err| Error::UnableToWrite)?;
+				}
 
-				Ok(())
+				writeln!(out)
+					.and_then(|_| out.flush())
+					.map_err(|_err| Error::UnableToWrite)
 			}
 			Self::Path(root) => {
 				for (path, contents) in documents {
@@ -91,6 +102,46 @@ impl Location {
 			}
 		}
 	}
+
+	/// Writes files at their exact paths, unlike `write` which names them as
+	/// YAML documents, or prints them headed by their paths unless there's a single one
+	pub fn materialize(
+		self,
+		files: impl IntoIterator<Item = (PathBuf, String)>,
+	) -> Result<(), Error> {
+		match self {
+			Self::Standard => {
+				let mut files = files.into_iter().peekable();
+				let mut out = BufWriter::new(io::stdout().lock());
+
+				let single = files.next().map(|first| (first, files.peek().is_none()));
+				let written = match single {
+					None => Ok(()),
+					Some(((_path, contents), true)) => write!(out, "{contents}"),
+					Some((first, false)) => {
+						std::iter::once(first)
+							.chain(files)
+							.try_for_each(|(path, contents)| {
+								writeln!(out, "==> {} <==\n{contents}", path.display())
+							})
+					}
+				};
+
+				written
+					.and_then(|_| out.flush())
+					.map_err(|_err| Error::UnableToWrite)
+			}
+			Self::Path(root) => {
+				for (path, contents) in files {
+					let target = root.join(path.strip_prefix("/").unwrap_or(&path));
+
+					write_contents(&target, &contents)?;
+				}
+
+				Ok(())
+			}
+		}
+	}
 }
 
 pub fn from_file(file: &Path) -> Result<String, Error> {
@@ -103,10 +154,100 @@ pub fn from_file(file: &Path) -> Result<String, Error> {
 	}
 
 	let contents = fs::read_to_string(file).map_err(|_err| Error::UnableToRead)?;
+	record(file);
 
 	Ok(contents)
 }
 
+/// Notes the file as read by the process, absolute so the same file is
+/// noted once however it's reached
+pub fn record(file: &Path) {
+	let file = canonical(file).unwrap_or_else(|_err| file.to_path_buf());
+
+	if let Ok(mut reads) = READS.lock() {
+		reads.insert(file);
+	}
+}
+
+/// Longest path Windows takes without the verbatim prefix
+const MAX_PATH: usize = 260;
+
+/// Names Windows reserves for devices, whatever extension follows them
+const RESERVED: [&str; 22] = [
+	"CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+	"COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Absolute form of the path, as [Path::canonicalize] resolves it, but
+/// without the `\\?\` prefix Windows gives it whenever the path means the
+/// same without it, as most tools and readers expect the plain form
+pub fn canonical(path: &Path) -> io::Result<PathBuf> {
+	let canonical = path.canonicalize()?;
+
+	match canonical.to_str().and_then(plain) {
+		Some(plain) => Ok(PathBuf::from(plain)),
+		None => Ok(canonical),
+	}
+}
+
+/// Verbatim path of a drive without its prefix, when it's short enough and
+/// none of its names are reserved, since those need the prefix
+fn plain(path: &str) -> Option<&str> {
+	let rest = path.strip_prefix(r"\\?\")?;
+	let drive = rest.as_bytes();
+	let is_drive = drive.len() >= 3 && drive[0].is_ascii_alphabetic() && &drive[1..3] == b":\\";
+
+	let plain = is_drive
+		&& rest.len() < MAX_PATH
+		&& rest[3..].split('\\').all(|name| portable(name).is_ok());
+
+	plain.then_some(rest)
+}
+
+/// Whether a file can be named so on every platform, telling why otherwise,
+/// as Windows refuses some names the others take
+pub fn portable(name: &str) -> Result<(), String> {
+	if let Some(c) = name
+		.chars()
+		.find(|c| r#"<>:"|?*\"#.contains(*c) || c.is_control())
+	{
+		return Err(format!("{name} has {c:?}, which Windows doesn't take"));
+	}
+
+	if name.ends_with('.') || name.ends_with(' ') {
+		return Err(format!(
+			"{name} ends with a dot or a space, which Windows drops"
+		));
+	}
+
+	let stem = name.split('.').next().unwrap_or(name);
+	if RESERVED
+		.iter()
+		.any(|reserved| reserved.eq_ignore_ascii_case(stem))
+	{
+		return Err(format!("{name} is reserved by Windows for devices"));
+	}
+
+	Ok(())
+}
+
+/// The relative path with `/` between its names whatever the platform, as
+/// packages name their files
+pub fn slashed(path: &Path) -> String {
+	path.components()
+		.map(|component| component.as_os_str().to_string_lossy())
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+/// Files the process read so far, as [record] noted them
+pub fn reads() -> Vec<PathBuf> {
+	READS
+		.lock()
+		.map(|reads| reads.iter().cloned().collect())
+		.unwrap_or_default()
+}
+
 pub fn from_stdin() -> Result<String, Error> {
 	let mut contents = String::new();
 	io::stdin()
@@ -144,7 +285,7 @@ pub fn ensure_dir_exists(dir: &Path) -> Result<PathBuf, Error> {
 	} else if dir.is_dir() {
 		Ok(dir)
 	} else {
-		fs::create_dir(dir.clone()).map_err(|_err| Error::UnableToWrite)?;
+		fs::create_dir_all(dir.clone()).map_err(|_err| Error::UnableToWrite)?;
 
 		Ok(dir)
 	}
@@ -159,3 +300,42 @@ pub fn write_contents(path: &Path, contents: &str) -> Result<(), Error> {
 	fs::create_dir_all(parent).map_err(|_err| Error::UnableToWrite)?;
 	fs::write(path, contents).map_err(|_err| Error::UnableToWrite)
 }
+
+#[cfg(test)]
+mod test {
+	use std::path::Path;
+
+	use super::{plain, portable, slashed};
+
+	#[test]
+	fn simplifies_verbatim_paths() {
+		assert_eq!(plain(r"\\?\C:\kct\kcp"), Some(r"C:\kct\kcp"));
+		assert_eq!(plain("/kct/kcp"), None);
+	}
+
+	#[test]
+	fn keeps_verbatim_paths_needing_the_prefix() {
+		let long = format!(r"\\?\C:\{}", "kcp\\".repeat(100));
+
+		assert_eq!(plain(&long), None);
+		assert_eq!(plain(r"\\?\C:\kct\aux.yaml"), None);
+		assert_eq!(plain(r"\\?\UNC\server\share"), None);
+	}
+
+	#[test]
+	fn refuses_names_windows_does_not_take() {
+		assert!(portable("deployment.yaml").is_ok());
+		assert!(portable("auxiliary.yaml").is_ok());
+		assert!(portable("aux.yaml").is_err());
+		assert!(portable("Com1").is_err());
+		assert!(portable("a:b").is_err());
+		assert!(portable("trailing.").is_err());
+		assert!(portable("trailing ").is_err());
+	}
+
+	#[test]
+	fn separates_names_with_slashes() {
+		assert_eq!(slashed(Path::new("config/app.yaml")), "config/app.yaml");
+		assert_eq!(slashed(Path::new("config//app.yaml")), "config/app.yaml");
+	}
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use serde_json::{json, Map, Value};
 
 pub fn merge(left: &mut Value, right: &Value) {
@@ -58,6 +60,103 @@ pub fn set_in<'a>(target: &'a mut Value, path: &[&str], value: Value) -> &'a Val
 	target
 }
 
+/// Paths, as JSON pointers, where `expected` isn't contained in `actual`
+///
+/// Objects only need to have the fields from `expected` while arrays need to have the same length,
+/// given that we can't tell which item corresponds to which without knowing their merge keys.
+pub fn diverging(expected: &Value, actual: &Value) -> Vec<String> {
+	let mut found = vec![];
+
+	diverging_at(expected, actual, String::new(), &mut found);
+
+	found
+}
+
+fn diverging_at(expected: &Value, actual: &Value, at: String, found: &mut Vec<String>) {
+	match (expected, actual) {
+		(Value::Object(expected), Value::Object(actual)) => {
+			for (key, value) in expected {
+				let path = format!("{at}/{}", escape(key));
+
+				match actual.get(key) {
+					Some(other) => diverging_at(value, other, path, found),
+					None => found.push(path),
+				}
+			}
+		}
+		(Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+			for (i, (value, other)) in expected.iter().zip(actual).enumerate() {
+				diverging_at(value, other, format!("{at}/{i}"), found);
+			}
+		}
+		(expected, actual) if expected == actual => (),
+		_ => found.push(at),
+	}
+}
+
+/// Paths, as JSON pointers, of the fields that were added, removed, or
+/// changed from `before` to `after`
+///
+/// Arrays are compared item by item, those beyond the shorter one being added
+/// or removed, given that we can't tell which item corresponds to which
+/// without knowing their merge keys.
+pub fn changed(before: &Value, after: &Value) -> Vec<String> {
+	let mut found = vec![];
+
+	changed_at(Some(before), Some(after), String::new(), &mut found);
+
+	found
+}
+
+fn changed_at(before: Option<&Value>, after: Option<&Value>, at: String, found: &mut Vec<String>) {
+	match (before, after) {
+		(Some(Value::Object(before)), Some(Value::Object(after))) => {
+			let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+			for key in keys {
+				let path = format!("{at}/{}", escape(key));
+
+				changed_at(before.get(key), after.get(key), path, found);
+			}
+		}
+		(Some(Value::Array(before)), Some(Value::Array(after))) => {
+			for i in 0..before.len().max(after.len()) {
+				changed_at(before.get(i), after.get(i), format!("{at}/{i}"), found);
+			}
+		}
+		(before, after) if before == after => (),
+		_ => found.push(at),
+	}
+}
+
+/// Compact JSON with keys sorted at every level, so its digest doesn't
+/// depend on the order keys were written in
+pub fn canonical(value: &Value) -> Vec<u8> {
+	serde_json::to_vec(&sorted(value)).unwrap_or_default()
+}
+
+fn sorted(value: &Value) -> Value {
+	match value {
+		Value::Object(map) => {
+			let mut keys: Vec<&String> = map.keys().collect();
+			keys.sort();
+
+			let sorted = keys
+				.into_iter()
+				.map(|key| (key.clone(), sorted(&map[key])))
+				.collect();
+
+			Value::Object(sorted)
+		}
+		Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+		value => value.clone(),
+	}
+}
+
+fn escape(key: &str) -> String {
+	key.replace('~', "~0").replace('/', "~1")
+}
+
 fn build_path(path: &[&str], value: Value) -> Value {
 	let mut path = path.iter();
 
@@ -97,7 +196,7 @@ fn build_path(path: &[&str], value: Value) -> Value {
 mod test {
 	use serde_json::{json, Value};
 
-	use super::{get_in, merge, set_in};
+	use super::{canonical, changed, diverging, get_in, merge, set_in};
 
 	mod get_path {
 		use super::*;
@@ -210,4 +309,79 @@ mod test {
 			assert_eq!(left, expected)
 		}
 	}
+
+	mod diverging {
+		use super::*;
+
+		#[test]
+		fn ignores_extra_fields() {
+			let expected = json!({"a": {"b": 1}});
+			let actual = json!({"a": {"b": 1, "c": 2}, "d": 3});
+
+			assert!(diverging(&expected, &actual).is_empty());
+		}
+
+		#[test]
+		fn finds_missing_and_modified() {
+			let expected = json!({"a": {"b": 1, "c": [1, 2]}, "d/e": true});
+			let actual = json!({"a": {"b": 2, "c": [1, 3]}});
+
+			assert_eq!(
+				diverging(&expected, &actual),
+				vec!["/a/b", "/a/c/1", "/d~1e"]
+			);
+		}
+
+		#[test]
+		fn compares_arrays_as_whole_when_sizes_differ() {
+			let expected = json!({"a": [1, 2]});
+			let actual = json!({"a": [1]});
+
+			assert_eq!(diverging(&expected, &actual), vec!["/a"]);
+		}
+	}
+
+	mod canonical {
+		use super::*;
+
+		#[test]
+		fn sorts_keys_at_every_level() {
+			let value = json!({"b": {"d": 1, "c": [{"f": 1, "e": "x"}]}, "a": null});
+
+			assert_eq!(
+				String::from_utf8(canonical(&value)).unwrap(),
+				r#"{"a":null,"b":{"c":[{"e":"x","f":1}],"d":1}}"#
+			);
+		}
+	}
+
+	mod changed {
+		use super::*;
+
+		#[test]
+		fn finds_added_removed_and_modified() {
+			let before = json!({"a": {"b": 1, "c": 2}, "d/e": true});
+			let after = json!({"a": {"b": 2, "f": null}, "g": [1]});
+
+			assert_eq!(
+				changed(&before, &after),
+				vec!["/a/b", "/a/c", "/a/f", "/d~1e", "/g"]
+			);
+		}
+
+		#[test]
+		fn compares_arrays_item_by_item() {
+			let before = json!({"a": [1, 2, 3]});
+			let after = json!({"a": [1, 4]});
+
+			assert_eq!(changed(&before, &after), vec!["/a/1", "/a/2"]);
+		}
+
+		#[test]
+		fn nothing_when_equal() {
+			let value = json!({"a": [{"b": 1}], "c": "d"});
+
+			assert!(changed(&value, &value).is_empty());
+		}
+	}
 }
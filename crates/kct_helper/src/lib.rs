@@ -1,2 +1,9 @@
+pub mod ignore;
 pub mod io;
 pub mod json;
+pub mod lookup;
+pub mod memory;
+pub mod patch;
+pub mod policy;
+pub mod progress;
+pub mod quantity;
@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::Value;
+
+/// Reference to live objects, all of a kind when there's no name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+	pub api_version: String,
+	pub kind: String,
+	pub namespace: Option<String>,
+	pub name: Option<String>,
+}
+
+/// Source of live objects for templates, e.g. a cluster
+pub trait Finder: Send + Sync {
+	/// The object found, `None` when it doesn't exist, or a list of them when
+	/// the query has no name
+	fn find(&self, query: &Query) -> Result<Option<Value>>;
+}
+
+pub type Lookup = Arc<dyn Finder>;
@@ -0,0 +1,188 @@
+use std::cell::Cell;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Memory a compilation may hold, counted by the allocator for the threads
+/// working on it, so compilations running at once have their own and what
+/// was held before them doesn't count
+#[derive(Debug)]
+pub struct Budget {
+	limit: usize,
+	held: AtomicUsize,
+	exceeded: AtomicBool,
+}
+
+thread_local! {
+	/// Budget the allocations of the thread are counted against, none when
+	/// null. Kept as a pointer, as the allocator can't allocate to reach it.
+	static CURRENT: Cell<*const Budget> = const { Cell::new(ptr::null()) };
+	/// Budget of the work the thread hands to others, which may not be
+	/// charged itself
+	static HANDED: Cell<*const Budget> = const { Cell::new(ptr::null()) };
+}
+
+impl Budget {
+	pub fn new(limit: usize) -> Arc<Self> {
+		Arc::new(Self {
+			limit,
+			held: AtomicUsize::new(0),
+			exceeded: AtomicBool::new(false),
+		})
+	}
+
+	pub fn limit(&self) -> usize {
+		self.limit
+	}
+
+	/// Whether some thread went beyond the limit, failing the work at its
+	/// next check
+	pub fn exceeded(&self) -> bool {
+		self.exceeded.load(Ordering::Relaxed)
+	}
+}
+
+/// Counts the allocations of the work against the budget, when there's one,
+/// along the ones of the work it hands to other threads
+pub fn charged<T>(budget: Option<Arc<Budget>>, work: impl FnOnce() -> T) -> T {
+	let charging = budget.as_ref().map_or(ptr::null(), Arc::as_ptr);
+
+	let previous = CURRENT.with(|current| current.replace(charging));
+	let done = within(budget, work);
+	CURRENT.with(|current| current.set(previous));
+
+	done
+}
+
+/// Counts the allocations of the work the thread hands to others against
+/// the budget, when there's one, but not its own, as only the threads
+/// charged check it
+pub fn within<T>(budget: Option<Arc<Budget>>, work: impl FnOnce() -> T) -> T {
+	let budget = match budget {
+		Some(budget) => budget,
+		None => return work(),
+	};
+
+	let previous = HANDED.with(|handed| handed.replace(Arc::as_ptr(&budget)));
+	let done = work();
+	HANDED.with(|handed| handed.set(previous));
+
+	drop(budget);
+	done
+}
+
+/// Budget of the work the thread hands to others
+pub fn handed() -> Option<Arc<Budget>> {
+	let handed = HANDED.with(Cell::get);
+	if handed.is_null() {
+		return None;
+	}
+
+	// Kept alive by whoever set it, for as long as it's set
+	unsafe {
+		Arc::increment_strong_count(handed);
+		Some(Arc::from_raw(handed))
+	}
+}
+
+/// Whether the budget the thread is charged to was exceeded, for the work
+/// to fail where it can, as nothing fails it from within the allocator
+pub fn exceeded() -> bool {
+	let current = CURRENT.with(Cell::get);
+
+	// Alive as told in current
+	!current.is_null() && unsafe { &*current }.exceeded()
+}
+
+/// Counts the bytes as held by the budget of the thread. Going beyond it
+/// marks the budget as exceeded, as unwinding from within the allocator
+/// isn't possible, leaving the work to fail once it checks.
+pub fn hold(bytes: usize) {
+	let current = CURRENT.with(Cell::get);
+	if current.is_null() {
+		return;
+	}
+
+	// Alive as told in current
+	let budget = unsafe { &*current };
+	let held = budget.held.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+	if held > budget.limit {
+		budget.exceeded.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Counts the bytes as no longer held by the budget of the thread, which
+/// may have been allocated before it
+pub fn release(bytes: usize) {
+	let current = CURRENT.with(Cell::get);
+	if current.is_null() {
+		return;
+	}
+
+	// Memory held before charging may be released while at it
+	let budget = unsafe { &*current };
+	let mut held = budget.held.load(Ordering::Relaxed);
+	while let Err(actual) = budget.held.compare_exchange_weak(
+		held,
+		held.saturating_sub(bytes),
+		Ordering::Relaxed,
+		Ordering::Relaxed,
+	) {
+		held = actual;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{charged, exceeded, handed, hold, release, within, Budget};
+
+	#[test]
+	fn charges_the_thread_while_working() {
+		let budget = Budget::new(100);
+
+		charged(Some(budget.clone()), || {
+			hold(60);
+			release(80);
+			hold(90);
+
+			assert!(handed().is_some());
+		});
+		hold(1000);
+
+		assert!(handed().is_none());
+		assert!(!budget.exceeded());
+		assert_eq!(budget.held.load(std::sync::atomic::Ordering::Relaxed), 90);
+	}
+
+	#[test]
+	fn hands_without_charging() {
+		let budget = Budget::new(100);
+
+		within(Some(budget.clone()), || {
+			hold(1000);
+
+			assert!(handed().is_some());
+		});
+
+		assert!(!budget.exceeded());
+	}
+
+	#[test]
+	fn tells_threads_going_beyond() {
+		let budget = Budget::new(100);
+
+		let told = charged(Some(budget.clone()), || {
+			hold(50);
+			let within = exceeded();
+			hold(100);
+
+			(within, exceeded())
+		});
+		release(150);
+
+		assert_eq!(told, (false, true));
+		assert!(budget.exceeded());
+		assert!(!exceeded());
+	}
+}
@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+const DIRECTIVE: &str = "$patch";
+
+/// Merge keys Kubernetes uses for the lists in its own objects, by field name
+const MERGE_KEYS: [(&str, &str); 13] = [
+	("containers", "name"),
+	("initContainers", "name"),
+	("ephemeralContainers", "name"),
+	("env", "name"),
+	("volumes", "name"),
+	("volumeMounts", "mountPath"),
+	("volumeDevices", "devicePath"),
+	("imagePullSecrets", "name"),
+	("hostAliases", "ip"),
+	("conditions", "type"),
+	("topologySpreadConstraints", "topologyKey"),
+	("resourceClaims", "name"),
+	("ports", "port"),
+];
+
+/// Lists of scalars Kubernetes merges as sets instead of replacing
+const MERGED_SCALARS: [&str; 1] = ["finalizers"];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("Invalid patch operation {0}")]
+	InvalidOperation(String),
+	#[error("Nothing found at {0}")]
+	NotFound(String),
+	#[error("Test failed for {0}")]
+	TestFailed(String),
+}
+
+/// Applies a JSON merge patch (RFC 7386), where `null` removes fields
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+	let patch = match patch {
+		Value::Object(patch) => patch,
+		_ => {
+			*target = patch.clone();
+			return;
+		}
+	};
+
+	if !target.is_object() {
+		*target = Value::Object(Map::new());
+	}
+
+	let target = target.as_object_mut().unwrap();
+
+	for (key, value) in patch {
+		if value.is_null() {
+			target.remove(key);
+		} else {
+			merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+		}
+	}
+}
+
+/// Applies the operations of a JSON patch (RFC 6902), leaving the target
+/// untouched when any of them fails
+pub fn json_patch(target: &mut Value, operations: &[Value]) -> Result<(), Error> {
+	let mut patched = target.clone();
+
+	for operation in operations {
+		apply(&mut patched, operation)?;
+	}
+
+	*target = patched;
+
+	Ok(())
+}
+
+fn apply(target: &mut Value, operation: &Value) -> Result<(), Error> {
+	let field = |name: &str| {
+		operation
+			.get(name)
+			.ok_or_else(|| Error::InvalidOperation(operation.to_string()))
+	};
+	let pointer = |name: &str| {
+		field(name)?
+			.as_str()
+			.ok_or_else(|| Error::InvalidOperation(operation.to_string()))
+	};
+
+	let path = pointer("path")?;
+
+	match field("op")?.as_str() {
+		Some("add") => add(target, path, field("value")?.clone()),
+		Some("remove") => remove(target, path).map(|_| ()),
+		Some("replace") => {
+			let current = target
+				.pointer_mut(path)
+				.ok_or_else(|| Error::NotFound(path.to_string()))?;
+
+			*current = field("value")?.clone();
+
+			Ok(())
+		}
+		Some("move") => {
+			let value = remove(target, pointer("from")?)?;
+
+			add(target, path, value)
+		}
+		Some("copy") => {
+			let from = pointer("from")?;
+			let value = target
+				.pointer(from)
+				.ok_or_else(|| Error::NotFound(from.to_string()))?
+				.clone();
+
+			add(target, path, value)
+		}
+		Some("test") => match target.pointer(path) {
+			Some(current) if current == field("value")? => Ok(()),
+			_ => Err(Error::TestFailed(path.to_string())),
+		},
+		_ => Err(Error::InvalidOperation(operation.to_string())),
+	}
+}
+
+/// Splits a pointer into the one for its parent and the unescaped last token
+fn split(path: &str) -> Result<(&str, String), Error> {
+	let at = path
+		.rfind('/')
+		.ok_or_else(|| Error::NotFound(path.to_string()))?;
+	let token = path[at + 1..].replace("~1", "/").replace("~0", "~");
+
+	Ok((&path[..at], token))
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> Result<(), Error> {
+	if path.is_empty() {
+		*target = value;
+		return Ok(());
+	}
+
+	let (parent, token) = split(path)?;
+	let not_found = || Error::NotFound(path.to_string());
+
+	match target.pointer_mut(parent).ok_or_else(not_found)? {
+		Value::Object(object) => {
+			object.insert(token, value);
+		}
+		Value::Array(array) if token == "-" => array.push(value),
+		Value::Array(array) => match token.parse::<usize>() {
+			Ok(index) if index <= array.len() => array.insert(index, value),
+			_ => return Err(not_found()),
+		},
+		_ => return Err(not_found()),
+	}
+
+	Ok(())
+}
+
+fn remove(target: &mut Value, path: &str) -> Result<Value, Error> {
+	let (parent, token) = split(path)?;
+	let not_found = || Error::NotFound(path.to_string());
+
+	match target.pointer_mut(parent).ok_or_else(not_found)? {
+		Value::Object(object) => object.remove(&token).ok_or_else(not_found),
+		Value::Array(array) => match token.parse::<usize>() {
+			Ok(index) if index < array.len() => Ok(array.remove(index)),
+			_ => Err(not_found()),
+		},
+		_ => Err(not_found()),
+	}
+}
+
+/// Applies a strategic merge patch, merging lists by the keys Kubernetes uses
+/// for them, or the ones in `keys`, and replacing the others.
+///
+/// Supports the `$patch: replace` and `$patch: delete` directives both on
+/// objects and on items of merged lists.
+pub fn strategic_merge(target: &mut Value, patch: &Value, keys: &HashMap<String, String>) {
+	match (target, patch) {
+		(Value::Object(target), Value::Object(patch)) => merge_object(target, patch, keys),
+		(target, patch) => *target = clean(patch),
+	}
+}
+
+fn directive(value: &Value) -> Option<&str> {
+	value.get(DIRECTIVE).and_then(Value::as_str)
+}
+
+fn merge_object(
+	target: &mut Map<String, Value>,
+	patch: &Map<String, Value>,
+	keys: &HashMap<String, String>,
+) {
+	if patch.get(DIRECTIVE).and_then(Value::as_str) == Some("replace") {
+		*target = clean_map(patch);
+		return;
+	}
+
+	for (key, value) in patch {
+		if key == DIRECTIVE {
+			continue;
+		}
+
+		if value.is_null() || directive(value) == Some("delete") {
+			target.remove(key);
+			continue;
+		}
+
+		match (target.get_mut(key), value) {
+			(Some(Value::Object(current)), Value::Object(value)) => {
+				merge_object(current, value, keys)
+			}
+			(Some(Value::Array(current)), Value::Array(value)) => {
+				merge_list(key, current, value, keys)
+			}
+			_ => {
+				target.insert(key.clone(), clean(value));
+			}
+		}
+	}
+}
+
+fn merge_key<'a>(
+	field: &str,
+	items: &[&Value],
+	keys: &'a HashMap<String, String>,
+) -> Option<&'a str> {
+	if let Some(key) = keys.get(field) {
+		return Some(key);
+	}
+
+	// Container ports are keyed by their number while service ports by theirs
+	if field == "ports" && items.iter().any(|item| item.get("containerPort").is_some()) {
+		return Some("containerPort");
+	}
+
+	MERGE_KEYS
+		.iter()
+		.find(|(name, _)| *name == field)
+		.map(|(_, key)| *key)
+}
+
+fn merge_list(
+	field: &str,
+	target: &mut Vec<Value>,
+	patch: &[Value],
+	keys: &HashMap<String, String>,
+) {
+	if patch.iter().any(|item| directive(item) == Some("replace")) {
+		*target = patch
+			.iter()
+			.filter(|item| directive(item).is_none())
+			.map(clean)
+			.collect();
+		return;
+	}
+
+	let items: Vec<&Value> = target.iter().chain(patch).collect();
+	let key = match merge_key(field, &items, keys) {
+		Some(key) => key,
+		None if MERGED_SCALARS.contains(&field) => {
+			for item in patch {
+				if !target.contains(item) {
+					target.push(item.clone());
+				}
+			}
+			return;
+		}
+		None => {
+			*target = patch.iter().map(clean).collect();
+			return;
+		}
+	};
+
+	for item in patch {
+		let position = item.get(key).and_then(|id| {
+			target
+				.iter()
+				.position(|current| current.get(key) == Some(id))
+		});
+
+		match (position, item) {
+			(Some(position), _) if directive(item) == Some("delete") => {
+				target.remove(position);
+			}
+			(Some(position), Value::Object(item)) => match &mut target[position] {
+				Value::Object(current) => merge_object(current, item, keys),
+				current => *current = clean(&Value::Object(item.clone())),
+			},
+			(None, _) if directive(item) == Some("delete") => (),
+			_ => target.push(clean(item)),
+		}
+	}
+}
+
+/// Drops the directives so they don't end up in the final object
+fn clean(value: &Value) -> Value {
+	match value {
+		Value::Object(object) => Value::Object(clean_map(object)),
+		Value::Array(items) => Value::Array(items.iter().map(clean).collect()),
+		value => value.clone(),
+	}
+}
+
+fn clean_map(object: &Map<String, Value>) -> Map<String, Value> {
+	object
+		.iter()
+		.filter(|(key, _)| *key != DIRECTIVE)
+		.map(|(key, value)| (key.clone(), clean(value)))
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use serde_json::{json, Value};
+
+	use super::{json_patch, merge_patch, strategic_merge, Error};
+
+	mod merge_patch {
+		use super::*;
+
+		#[test]
+		fn merges_objects_and_removes_nulls() {
+			let mut target = json!({"a": {"b": 1, "c": 2}, "d": [1, 2]});
+
+			merge_patch(&mut target, &json!({"a": {"c": null, "e": 3}, "d": [3]}));
+
+			assert_eq!(target, json!({"a": {"b": 1, "e": 3}, "d": [3]}));
+		}
+
+		#[test]
+		fn replaces_non_objects() {
+			let mut target = json!({"a": 1});
+
+			merge_patch(&mut target, &json!([1]));
+
+			assert_eq!(target, json!([1]));
+		}
+	}
+
+	mod json_patch {
+		use super::*;
+
+		fn patch(target: Value, operations: Value) -> Result<Value, Error> {
+			let mut target = target;
+
+			json_patch(&mut target, operations.as_array().unwrap())?;
+
+			Ok(target)
+		}
+
+		#[test]
+		fn applies_every_operation() {
+			let patched = patch(
+				json!({"a": {"b": 1}, "list": [1, 2], "x/y": true}),
+				json!([
+					{"op": "add", "path": "/list/1", "value": 9},
+					{"op": "add", "path": "/list/-", "value": 3},
+					{"op": "remove", "path": "/x~1y"},
+					{"op": "replace", "path": "/a/b", "value": 2},
+					{"op": "copy", "from": "/a", "path": "/c"},
+					{"op": "move", "from": "/a/b", "path": "/d"},
+					{"op": "test", "path": "/d", "value": 2},
+				]),
+			);
+
+			assert_eq!(
+				patched.unwrap(),
+				json!({"a": {}, "c": {"b": 2}, "d": 2, "list": [1, 9, 2, 3]})
+			);
+		}
+
+		#[test]
+		fn fails_on_missing_paths() {
+			let patched = patch(json!({}), json!([{"op": "remove", "path": "/a"}]));
+
+			assert_eq!(patched, Err(Error::NotFound(String::from("/a"))));
+		}
+
+		#[test]
+		fn fails_on_failed_tests() {
+			let patched = patch(
+				json!({"a": 1}),
+				json!([{"op": "test", "path": "/a", "value": 2}]),
+			);
+
+			assert_eq!(patched, Err(Error::TestFailed(String::from("/a"))));
+		}
+
+		#[test]
+		fn leaves_target_untouched_on_failure() {
+			let mut target = json!({"a": 1});
+
+			let operations = vec![
+				json!({"op": "add", "path": "/b", "value": 2}),
+				json!({"op": "unknown", "path": "/a"}),
+			];
+
+			assert!(json_patch(&mut target, &operations).is_err());
+			assert_eq!(target, json!({"a": 1}));
+		}
+	}
+
+	mod strategic_merge {
+		use super::*;
+
+		fn merge(target: Value, patch: Value) -> Value {
+			let mut target = target;
+
+			strategic_merge(&mut target, &patch, &HashMap::new());
+
+			target
+		}
+
+		#[test]
+		fn merges_lists_by_key() {
+			let merged = merge(
+				json!({"containers": [
+					{"name": "app", "image": "app:1", "env": [{"name": "A", "value": "1"}]},
+					{"name": "sidecar", "image": "sidecar:1"},
+				]}),
+				json!({"containers": [
+					{"name": "app", "env": [{"name": "B", "value": "2"}]},
+					{"name": "extra", "image": "extra:1"},
+				]}),
+			);
+
+			assert_eq!(
+				merged,
+				json!({"containers": [
+					{"name": "app", "image": "app:1", "env": [{"name": "A", "value": "1"}, {"name": "B", "value": "2"}]},
+					{"name": "sidecar", "image": "sidecar:1"},
+					{"name": "extra", "image": "extra:1"},
+				]})
+			);
+		}
+
+		#[test]
+		fn keys_ports_by_kind() {
+			let merged = merge(
+				json!({"a": {"ports": [{"containerPort": 80, "name": "http"}]}, "b": {"ports": [{"port": 80}]}}),
+				json!({"a": {"ports": [{"containerPort": 80, "protocol": "TCP"}]}, "b": {"ports": [{"port": 80, "targetPort": 8080}]}}),
+			);
+
+			assert_eq!(
+				merged,
+				json!({
+					"a": {"ports": [{"containerPort": 80, "name": "http", "protocol": "TCP"}]},
+					"b": {"ports": [{"port": 80, "targetPort": 8080}]},
+				})
+			);
+		}
+
+		#[test]
+		fn replaces_lists_without_keys() {
+			let merged = merge(
+				json!({"args": ["a", "b"], "finalizers": ["a"]}),
+				json!({"args": ["c"], "finalizers": ["b", "a"]}),
+			);
+
+			assert_eq!(merged, json!({"args": ["c"], "finalizers": ["a", "b"]}));
+		}
+
+		#[test]
+		fn follows_directives() {
+			let merged = merge(
+				json!({
+					"env": [{"name": "A"}, {"name": "B"}],
+					"volumes": [{"name": "a"}],
+					"labels": {"a": "1"},
+					"selector": {"a": "1", "b": "2"},
+				}),
+				json!({
+					"env": [{"name": "A", "$patch": "delete"}],
+					"volumes": [{"$patch": "replace"}, {"name": "b"}],
+					"labels": {"$patch": "delete"},
+					"selector": {"$patch": "replace", "c": "3"},
+				}),
+			);
+
+			assert_eq!(
+				merged,
+				json!({
+					"env": [{"name": "B"}],
+					"volumes": [{"name": "b"}],
+					"selector": {"c": "3"},
+				})
+			);
+		}
+
+		#[test]
+		fn uses_custom_keys() {
+			let mut target = json!({"rules": [{"host": "a", "paths": [1]}]});
+			let keys = HashMap::from([(String::from("rules"), String::from("host"))]);
+
+			strategic_merge(
+				&mut target,
+				&json!({"rules": [{"host": "a", "tls": true}]}),
+				&keys,
+			);
+
+			assert_eq!(
+				target,
+				json!({"rules": [{"host": "a", "paths": [1], "tls": true}]})
+			);
+		}
+	}
+}
@@ -0,0 +1,314 @@
+use std::path::{Path, PathBuf};
+
+use cel_interpreter::{Context, Program, Value as Cel};
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("Invalid policies: {0}")]
+	Invalid(String),
+	#[error("Invalid rule for policy {0}: {1}")]
+	InvalidRule(String, String),
+}
+
+/// What happens to objects failing a policy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Action {
+	/// Refuses the render, the default
+	#[default]
+	Deny,
+	/// Only tells about the failure
+	Warn,
+}
+
+impl Action {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Action::Deny => "deny",
+			Action::Warn => "warn",
+		}
+	}
+}
+
+/// Rule every rendered object must satisfy, as a CEL expression over the
+/// `object` and its `path` that must be true
+pub struct Policy {
+	pub name: String,
+	pub action: Action,
+	message: Option<String>,
+	program: Program,
+}
+
+/// Object failing a policy, or one it couldn't be evaluated against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+	pub policy: String,
+	pub action: Action,
+	pub path: PathBuf,
+	pub message: String,
+}
+
+impl Policy {
+	/// Reads a list of policies from YAML or JSON, each with a `name`, its
+	/// `rule`, and optionally the `action` and the `message` for failures
+	pub fn parse(contents: &str) -> Result<Vec<Self>, Error> {
+		let value: Value =
+			serde_yaml::from_str(contents).map_err(|err| Error::Invalid(err.to_string()))?;
+
+		let declared = match value {
+			Value::Array(declared) => declared,
+			_ => return Err(Error::Invalid(String::from("expected a list"))),
+		};
+
+		declared.iter().map(Self::try_from).collect()
+	}
+
+	fn try_from(value: &Value) -> Result<Self, Error> {
+		let text = |key: &str| match value.get(key) {
+			None | Some(Value::Null) => Ok(None),
+			Some(Value::String(text)) => Ok(Some(text.clone())),
+			Some(_) => Err(Error::Invalid(format!("{key} should be a string"))),
+		};
+
+		let name = text("name")?.ok_or_else(|| Error::Invalid(String::from("missing name")))?;
+		let rule = text("rule")?.ok_or_else(|| Error::Invalid(format!("{name} has no rule")))?;
+		let action = match text("action")?.as_deref() {
+			None | Some("deny") => Action::Deny,
+			Some("warn") => Action::Warn,
+			Some(other) => return Err(Error::Invalid(format!("unknown action {other}"))),
+		};
+
+		let program = Program::compile(&rule)
+			.map_err(|err| Error::InvalidRule(name.clone(), err.to_string()))?;
+
+		Ok(Self {
+			name,
+			action,
+			message: text("message")?,
+			program,
+		})
+	}
+
+	/// Whether the object satisfies the rule, failing with why it couldn't
+	/// be evaluated
+	pub fn check(&self, path: &Path, object: &Value) -> Result<bool, String> {
+		let mut context = Context::default();
+		context
+			.add_variable("object", object)
+			.map_err(|err| err.to_string())?;
+		context
+			.add_variable("path", path.display().to_string())
+			.map_err(|err| err.to_string())?;
+
+		match self.program.execute(&context) {
+			Ok(Cel::Bool(satisfied)) => Ok(satisfied),
+			Ok(_) => Err(String::from("the rule isn't a boolean")),
+			Err(err) => Err(err.to_string()),
+		}
+	}
+}
+
+/// Checks every object against every policy, collecting the failures. Rules
+/// that can't be evaluated for an object count as failing it.
+pub fn evaluate<'a>(
+	policies: &[Policy],
+	objects: impl IntoIterator<Item = (&'a Path, &'a Value)>,
+) -> Vec<Violation> {
+	let mut violations = vec![];
+
+	for (path, object) in objects {
+		for policy in policies {
+			let message = match policy.check(path, object) {
+				Ok(true) => continue,
+				Ok(false) => policy
+					.message
+					.clone()
+					.unwrap_or_else(|| format!("doesn't satisfy {}", policy.name)),
+				Err(reason) => format!("unable to evaluate {}: {reason}", policy.name),
+			};
+
+			violations.push(Violation {
+				policy: policy.name.clone(),
+				action: policy.action,
+				path: path.to_path_buf(),
+				message,
+			});
+		}
+	}
+
+	violations
+}
+
+/// Namespaces objects may go into, by their names or prefixes ending in `*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespaces(Vec<String>);
+
+impl Namespaces {
+	pub fn new(allowed: Vec<String>) -> Self {
+		Namespaces(allowed)
+	}
+
+	pub fn allows(&self, namespace: &str) -> bool {
+		self.0
+			.iter()
+			.any(|allowed| match allowed.strip_suffix('*') {
+				Some(prefix) => namespace.starts_with(prefix),
+				None => namespace == allowed,
+			})
+	}
+
+	/// Why the object goes outside of the namespaces, if it does. Namespaces
+	/// themselves must be allowed, while objects without one would be
+	/// cluster-scoped or go into the default one.
+	fn escape(&self, object: &Value) -> Option<String> {
+		let metadata = &object["metadata"];
+		let (namespace, what) = match object["kind"].as_str() {
+			Some("Namespace") => (metadata["name"].as_str(), "is the namespace"),
+			_ => (metadata["namespace"].as_str(), "goes into"),
+		};
+
+		match namespace {
+			Some(namespace) if self.allows(namespace) => None,
+			Some(namespace) => Some(format!(
+				"{what} {namespace}, outside of {}",
+				self.0.join(", ")
+			)),
+			None => Some(String::from(
+				"has no namespace, so it's cluster-scoped or goes into the default one",
+			)),
+		}
+	}
+}
+
+/// Confines every object to the namespaces of every allowlist, denying the
+/// ones escaping any
+pub fn confine<'a>(
+	allowlists: &[Namespaces],
+	objects: impl IntoIterator<Item = (&'a Path, &'a Value)>,
+) -> Vec<Violation> {
+	objects
+		.into_iter()
+		.filter_map(|(path, object)| {
+			let message = allowlists
+				.iter()
+				.find_map(|namespaces| namespaces.escape(object))?;
+
+			Some(Violation {
+				policy: String::from("namespaces"),
+				action: Action::Deny,
+				path: path.to_path_buf(),
+				message,
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::Path;
+
+	use serde_json::json;
+
+	use super::{confine, evaluate, Action, Error, Namespaces, Policy};
+
+	const POLICIES: &str = r#"
+- name: pinned-images
+  rule: "!has(object.spec) || object.spec.containers.all(c, !c.image.endsWith(':latest'))"
+  message: Images must be pinned
+- name: named
+  rule: "path.startsWith('/app')"
+  action: warn
+"#;
+
+	#[test]
+	fn passes_satisfying_objects() {
+		let policies = Policy::parse(POLICIES).unwrap();
+		let object = json!({"spec": {"containers": [{"image": "nginx:1.25"}]}});
+
+		let violations = evaluate(&policies, [(Path::new("/app/pod"), &object)]);
+
+		assert_eq!(violations, vec![]);
+	}
+
+	#[test]
+	fn tells_failures_with_their_actions() {
+		let policies = Policy::parse(POLICIES).unwrap();
+		let object = json!({"spec": {"containers": [{"image": "nginx:latest"}]}});
+
+		let violations = evaluate(&policies, [(Path::new("/pod"), &object)]);
+
+		assert_eq!(violations.len(), 2);
+		assert_eq!(violations[0].action, Action::Deny);
+		assert_eq!(violations[0].message, "Images must be pinned");
+		assert_eq!(violations[1].action, Action::Warn);
+		assert_eq!(violations[1].message, "doesn't satisfy named");
+	}
+
+	#[test]
+	fn fails_rules_that_cant_be_evaluated() {
+		let policies =
+			Policy::parse("[{name: missing, rule: 'object.spec.replicas > 1'}]").unwrap();
+		let object = json!({"kind": "ConfigMap"});
+
+		let violations = evaluate(&policies, [(Path::new("/config"), &object)]);
+
+		assert_eq!(violations.len(), 1);
+		assert!(violations[0]
+			.message
+			.starts_with("unable to evaluate missing"));
+	}
+
+	#[test]
+	fn refuses_invalid_rules() {
+		let policies = Policy::parse("[{name: broken, rule: 'object.('}]");
+
+		assert!(matches!(policies, Err(Error::InvalidRule(name, _)) if name == "broken"));
+	}
+
+	#[test]
+	fn refuses_unknown_actions() {
+		let policies = Policy::parse("[{name: odd, rule: 'true', action: block}]");
+
+		assert!(matches!(policies, Err(Error::Invalid(_))));
+	}
+
+	#[test]
+	fn confines_objects_to_namespaces() {
+		let allowed = Namespaces::new(vec![String::from("team-a"), String::from("team-a-*")]);
+		let inside = json!({"kind": "Deployment", "metadata": {"namespace": "team-a-dev"}});
+		let outside = json!({"kind": "Deployment", "metadata": {"namespace": "team-b"}});
+		let namespace = json!({"kind": "Namespace", "metadata": {"name": "team-a"}});
+		let cluster = json!({"kind": "ClusterRole", "metadata": {"name": "admin"}});
+
+		let violations = confine(
+			&[allowed],
+			[
+				(Path::new("/inside"), &inside),
+				(Path::new("/outside"), &outside),
+				(Path::new("/namespace"), &namespace),
+				(Path::new("/cluster"), &cluster),
+			],
+		);
+		let paths: Vec<&Path> = violations.iter().map(|v| v.path.as_path()).collect();
+
+		assert_eq!(paths, vec![Path::new("/outside"), Path::new("/cluster")]);
+		assert_eq!(violations[0].action, Action::Deny);
+		assert_eq!(
+			violations[0].message,
+			"goes into team-b, outside of team-a, team-a-*"
+		);
+	}
+
+	#[test]
+	fn confines_objects_to_every_allowlist() {
+		let platform = Namespaces::new(vec![String::from("team-*")]);
+		let tenant = Namespaces::new(vec![String::from("team-a")]);
+		let object = json!({"kind": "Secret", "metadata": {"namespace": "team-b"}});
+
+		let violations = confine(&[platform, tenant], [(Path::new("/secret"), &object)]);
+
+		assert_eq!(violations.len(), 1);
+		assert_eq!(violations[0].message, "goes into team-b, outside of team-a");
+	}
+}
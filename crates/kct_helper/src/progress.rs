@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Milestones of long running operations
+///
+/// They're emitted in the order they happen, so a consumer can pair the beginning of a step with
+/// its end (e.g. `Applying` with multiple `Applied`) to render progress. Packages are identified by
+/// their name, and objects by the path where they were found on the rendered tree. Archives are
+/// fetched from a file or STDIN, where downloads are piped, and subpackages are numbered by the
+/// order they were first included in, out of the ones vendored that could be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	Fetching {
+		source: String,
+	},
+	Unpacking {
+		bytes: usize,
+	},
+	Unpacked {
+		root: PathBuf,
+	},
+	Compiling {
+		package: String,
+	},
+	Including {
+		package: String,
+		position: usize,
+		total: usize,
+	},
+	Compiled {
+		package: String,
+	},
+	Applying {
+		total: usize,
+	},
+	Applied {
+		object: PathBuf,
+	},
+	Deleting {
+		total: usize,
+	},
+	Deleted {
+		object: PathBuf,
+	},
+}
+
+pub trait Reporter: Send + Sync {
+	fn report(&self, event: Event);
+}
+
+/// Reporter for when nobody is listening
+pub struct Silent;
+
+impl Reporter for Silent {
+	fn report(&self, _event: Event) {}
+}
+
+pub type Progress = Arc<dyn Reporter>;
+
+pub fn silent() -> Progress {
+	Arc::new(Silent)
+}
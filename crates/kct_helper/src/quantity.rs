@@ -0,0 +1,311 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+const NANOS: i128 = 1_000_000_000;
+
+const BINARY: [(&str, u32); 6] = [
+	("Ki", 10),
+	("Mi", 20),
+	("Gi", 30),
+	("Ti", 40),
+	("Pi", 50),
+	("Ei", 60),
+];
+
+const DECIMAL: [(&str, i32); 10] = [
+	("n", -9),
+	("u", -6),
+	("m", -3),
+	("", 0),
+	("k", 3),
+	("M", 6),
+	("G", 9),
+	("T", 12),
+	("P", 15),
+	("E", 18),
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("Invalid quantity {0}")]
+	Invalid(String),
+	#[error("Quantity is too large")]
+	Overflow,
+}
+
+/// How the quantity was written, so results keep the same notation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	BinarySI,
+	DecimalSI,
+}
+
+/// Kubernetes resource quantity, e.g. `500m` or `1.5Gi`, stored as an exact
+/// amount of nano units, the smallest fraction Kubernetes accepts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quantity {
+	nanos: i128,
+	format: Format,
+}
+
+impl Quantity {
+	pub fn new(nanos: i128, format: Format) -> Self {
+		Quantity { nanos, format }
+	}
+
+	pub fn nanos(&self) -> i128 {
+		self.nanos
+	}
+
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	/// Value in base units, e.g. cores or bytes
+	pub fn as_f64(&self) -> f64 {
+		self.nanos as f64 / NANOS as f64
+	}
+
+	pub fn add(&self, other: &Quantity) -> Result<Quantity, Error> {
+		let nanos = self.nanos.checked_add(other.nanos).ok_or(Error::Overflow)?;
+
+		Ok(Quantity::new(nanos, self.format))
+	}
+
+	pub fn sub(&self, other: &Quantity) -> Result<Quantity, Error> {
+		let nanos = self.nanos.checked_sub(other.nanos).ok_or(Error::Overflow)?;
+
+		Ok(Quantity::new(nanos, self.format))
+	}
+
+	/// Multiplies by a decimal factor given as text, e.g. `1.5`, keeping the
+	/// arithmetic exact and rounding up to the next nano unit like Kubernetes
+	pub fn multiply(&self, factor: &str) -> Result<Quantity, Error> {
+		let (mantissa, scale) = decimal(factor).ok_or_else(|| Error::Invalid(factor.into()))?;
+		let divisor = 10i128.checked_pow(scale).ok_or(Error::Overflow)?;
+
+		let product = self.nanos.checked_mul(mantissa).ok_or(Error::Overflow)?;
+		let nanos = ceil_div(product, divisor);
+
+		Ok(Quantity::new(nanos, self.format))
+	}
+}
+
+impl PartialOrd for Quantity {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.nanos.cmp(&other.nanos))
+	}
+}
+
+fn ceil_div(value: i128, divisor: i128) -> i128 {
+	let quotient = value / divisor;
+
+	if value % divisor != 0 && value > 0 {
+		quotient + 1
+	} else {
+		quotient
+	}
+}
+
+/// Splits a decimal number into its digits and the amount of fractional ones
+fn decimal(raw: &str) -> Option<(i128, u32)> {
+	let (negative, raw) = match raw.strip_prefix('-') {
+		Some(rest) => (true, rest),
+		None => (false, raw.strip_prefix('+').unwrap_or(raw)),
+	};
+
+	let (whole, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+	if whole.is_empty() && fraction.is_empty() {
+		return None;
+	}
+
+	let digits = format!("{whole}{fraction}");
+	if !digits.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+
+	let mantissa: i128 = digits.parse().ok()?;
+	let mantissa = if negative { -mantissa } else { mantissa };
+
+	Some((mantissa, fraction.len() as u32))
+}
+
+impl FromStr for Quantity {
+	type Err = Error;
+
+	fn from_str(raw: &str) -> Result<Self, Self::Err> {
+		let invalid = || Error::Invalid(raw.to_string());
+		let trimmed = raw.trim();
+
+		let split = trimmed
+			.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+			.unwrap_or(trimmed.len());
+		let (number, suffix) = trimmed.split_at(split);
+		let (mantissa, scale) = decimal(number).ok_or_else(invalid)?;
+
+		let (nanos, format) = if let Some((_, power)) = BINARY.iter().find(|(s, _)| *s == suffix) {
+			let multiplier = (1i128 << power) * NANOS;
+			let value = mantissa.checked_mul(multiplier).ok_or(Error::Overflow)?;
+
+			(value / 10i128.pow(scale), Format::BinarySI)
+		} else {
+			let exponent = match DECIMAL.iter().find(|(s, _)| *s == suffix) {
+				Some((_, exponent)) => *exponent,
+				None => match suffix.strip_prefix(['e', 'E']) {
+					Some(exponent) => exponent.parse().map_err(|_err| invalid())?,
+					None => return Err(invalid()),
+				},
+			};
+
+			let shift = exponent + 9 - scale as i32;
+			let value = if shift >= 0 {
+				let multiplier = 10i128.checked_pow(shift as u32).ok_or(Error::Overflow)?;
+
+				mantissa.checked_mul(multiplier).ok_or(Error::Overflow)?
+			} else {
+				let divisor = 10i128
+					.checked_pow(shift.unsigned_abs())
+					.ok_or(Error::Overflow)?;
+
+				ceil_div(mantissa, divisor)
+			};
+
+			(value, Format::DecimalSI)
+		};
+
+		Ok(Quantity::new(nanos, format))
+	}
+}
+
+/// Writes the canonical form, using the largest suffix that keeps the value
+/// an integer. Binary quantities fall back to decimal ones below `1Ki` or when
+/// they can't be represented exactly, like Kubernetes does
+impl fmt::Display for Quantity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.format == Format::BinarySI && self.nanos % NANOS == 0 {
+			let units = self.nanos / NANOS;
+
+			let suffix = BINARY
+				.iter()
+				.rev()
+				.find(|(_, power)| units.abs() >= 1 << power && units % (1 << power) == 0);
+
+			if let Some((suffix, power)) = suffix {
+				return write!(f, "{}{suffix}", units >> power);
+			}
+		}
+
+		if self.nanos == 0 {
+			return write!(f, "0");
+		}
+
+		let (suffix, exponent) = DECIMAL
+			.iter()
+			.rev()
+			.find(|(_, exponent)| self.nanos % 10i128.pow((exponent + 9) as u32) == 0)
+			.unwrap_or(&DECIMAL[0]);
+
+		write!(
+			f,
+			"{}{suffix}",
+			self.nanos / 10i128.pow((exponent + 9) as u32)
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn quantity(raw: &str) -> Quantity {
+		raw.parse().unwrap()
+	}
+
+	mod parse {
+		use super::*;
+
+		#[test]
+		fn reads_decimal_suffixes() {
+			assert_eq!(quantity("500m").nanos(), 500_000_000);
+			assert_eq!(quantity("2").nanos(), 2 * NANOS);
+			assert_eq!(quantity("1.5k").nanos(), 1500 * NANOS);
+			assert_eq!(quantity("1e3").nanos(), 1000 * NANOS);
+		}
+
+		#[test]
+		fn reads_binary_suffixes() {
+			assert_eq!(quantity("1Ki").nanos(), 1024 * NANOS);
+			assert_eq!(quantity("1.5Gi").nanos(), 1_610_612_736 * NANOS);
+			assert_eq!(quantity("1.5Gi").format(), Format::BinarySI);
+		}
+
+		#[test]
+		fn rejects_invalid_ones() {
+			assert_eq!(
+				"1.5Xi".parse::<Quantity>(),
+				Err(Error::Invalid("1.5Xi".into()))
+			);
+			assert!("".parse::<Quantity>().is_err());
+			assert!("Gi".parse::<Quantity>().is_err());
+		}
+	}
+
+	mod format {
+		use super::*;
+
+		#[test]
+		fn uses_largest_exact_suffix() {
+			assert_eq!(quantity("1500m").to_string(), "1500m");
+			assert_eq!(quantity("2000m").to_string(), "2");
+			assert_eq!(quantity("0.5").to_string(), "500m");
+			assert_eq!(quantity("1.5Gi").to_string(), "1536Mi");
+			assert_eq!(quantity("0").to_string(), "0");
+		}
+
+		#[test]
+		fn falls_back_to_decimal() {
+			assert_eq!(quantity("0.5Ki").to_string(), "512");
+			assert_eq!(quantity("1000Mi").to_string(), "1000Mi");
+		}
+	}
+
+	mod arithmetic {
+		use super::*;
+
+		#[test]
+		fn adds_keeping_format() {
+			let sum = quantity("1Gi").add(&quantity("512Mi")).unwrap();
+
+			assert_eq!(sum.to_string(), "1536Mi");
+		}
+
+		#[test]
+		fn subtracts() {
+			let diff = quantity("1").sub(&quantity("250m")).unwrap();
+
+			assert_eq!(diff.to_string(), "750m");
+		}
+
+		#[test]
+		fn multiplies_exactly() {
+			assert_eq!(
+				quantity("64Gi").multiply("1.5").unwrap().to_string(),
+				"96Gi"
+			);
+			assert_eq!(quantity("100m").multiply("3").unwrap().to_string(), "300m");
+			assert_eq!(
+				quantity("1").multiply("0.0000000001").unwrap().to_string(),
+				"1n"
+			);
+		}
+
+		#[test]
+		fn compares() {
+			assert!(quantity("1Gi") > quantity("1G"));
+			assert!(quantity("500m") < quantity("1"));
+		}
+	}
+}
@@ -0,0 +1,91 @@
+use crate::error::Error;
+use crate::VARS_PREFIX;
+
+use std::path::Path;
+use std::rc::Rc;
+
+use jrsonnet_evaluator::EvaluationState;
+use serde_json::Value;
+
+/// Parts of Jsonnet templates may use, so packages keep to a restricted and
+/// portable subset of the language. Everything is allowed by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dialect {
+	/// Whether `std.extVar` reads variables other than the ones of kct
+	pub foreign_ext_vars: bool,
+	/// Whether `std.native` can be called
+	pub native: bool,
+	/// Longest string, in bytes, the rendered value may hold
+	pub max_string_size: Option<usize>,
+}
+
+impl Default for Dialect {
+	fn default() -> Self {
+		Self {
+			foreign_ext_vars: true,
+			native: true,
+			max_string_size: None,
+		}
+	}
+}
+
+impl Dialect {
+	/// Replaces the standard library with one failing on the calls the
+	/// dialect disallows, wherever they come from, aliases of `std` included
+	pub(crate) fn restrict(&self, state: &EvaluationState) -> Result<(), Error> {
+		let mut overrides = vec![];
+
+		if !self.foreign_ext_vars {
+			overrides.push(format!(
+				"extVar(name):: if std.startsWith(name, '{VARS_PREFIX}/') then std.extVar(name) \
+				else error 'std.extVar(%s) is disallowed by the package, only {VARS_PREFIX}/ variables can be read' % std.escapeStringJson(name)"
+			));
+		}
+
+		if !self.native {
+			overrides.push(String::from(
+				"native(name):: error 'std.native(%s) is disallowed by the package' % std.escapeStringJson(name)",
+			));
+		}
+
+		if overrides.is_empty() {
+			return Ok(());
+		}
+
+		let code = format!("std + {{ {} }}", overrides.join(", "));
+		let restricted =
+			state.evaluate_snippet_raw(Rc::from(Path::new("<dialect>")), code.into())?;
+		state
+			.settings_mut()
+			.globals
+			.insert("std".into(), restricted);
+
+		Ok(())
+	}
+
+	/// Fails on the first string of the rendered value longer than allowed
+	pub(crate) fn check(&self, value: &Value) -> Result<(), Error> {
+		match self.max_string_size {
+			None => Ok(()),
+			Some(max) => oversized(value, "$", max),
+		}
+	}
+}
+
+fn oversized(value: &Value, at: &str, max: usize) -> Result<(), Error> {
+	match value {
+		Value::String(string) if string.len() > max => Err(Error::StringTooLarge {
+			at: at.to_string(),
+			size: string.len(),
+			max,
+		}),
+		Value::Array(items) => items
+			.iter()
+			.enumerate()
+			.try_for_each(|(i, item)| oversized(item, &format!("{at}[{i}]"), max)),
+		Value::Object(fields) => fields
+			.iter()
+			.try_for_each(|(name, field)| oversized(field, &format!("{at}.{name}"), max)),
+		_ => Ok(()),
+	}
+}
@@ -8,8 +8,16 @@ use thiserror::Error;
 pub enum Error {
 	#[error("Render failed due to \"{0}\"")]
 	Render(String, #[source] Trace),
+	#[error("Unable to parse {0}: {1}")]
+	Parse(String, String),
 	#[error("Template couldn't be parsed as JSON")]
 	InvalidOutput(#[from] JsonError),
+	#[error("String at {at} has {size} bytes, more than the {max} allowed by the package")]
+	StringTooLarge { at: String, size: usize, max: usize },
+	#[error("Evaluation went beyond the {0} bytes of memory it may hold")]
+	MemoryExceeded(usize),
+	#[error("Evaluation stopped without an outcome, as its thread panicked")]
+	Stopped,
 }
 
 impl From<LocError> for Error {
@@ -1,79 +1,124 @@
+mod dialect;
 mod error;
+mod memo;
 mod resolver;
+mod worker;
 
+pub mod lint;
 pub mod property;
+pub mod sdk;
+pub mod source;
+pub mod usage;
 
 use crate::property::Property;
-use crate::resolver::*;
 
+pub use crate::dialect::Dialect;
 pub use crate::error::Error;
+pub use crate::resolver::{IMPORTS_TARGET, MAX_IMPORT_SIZE};
+pub use crate::sdk::Sdk;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
-use std::sync::mpsc;
-use std::thread;
 
 use anyhow::Result;
 use jrsonnet_evaluator::trace::{ExplainingFormat, PathResolver};
 use jrsonnet_evaluator::{EvaluationState, ManifestFormat};
 use serde_json::Value;
+use tracing::{debug, info_span, warn};
 
 const VARS_PREFIX: &str = "kct.io";
 
+/// How the evaluated template becomes the rendered value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+	/// Any value, manifested as JSON
+	#[default]
+	Json,
+	/// A string, manifested as it is into a JSON string
+	String,
+}
+
 pub struct Executable {
 	pub vendor: PathBuf,
 	pub lib: PathBuf,
+	/// Other directories searched for libraries, after `lib` and before
+	/// `vendor`, first match winning
+	pub libraries: Vec<PathBuf>,
+	/// Files or directories imports are aliased to, by the name imported
+	pub aliases: BTreeMap<String, PathBuf>,
 	pub main: PathBuf,
+	/// Package roots layered from the one compiled down to the ones it extends
+	pub layers: Vec<PathBuf>,
 	pub props: HashMap<String, Property>,
+	pub format: Format,
+	pub dialect: Dialect,
+	/// Version of the SDK `kct.libsonnet` stands for, unless vendored
+	pub sdk: &'static Sdk,
 }
 
 impl Executable {
 	pub fn run(self) -> Result<Value, Error> {
-		let (tx, rx) = mpsc::channel();
+		let span = info_span!("evaluate", main = %self.main.display());
 
-		thread::spawn(move || {
-			tx.send(self.render()).unwrap();
-		});
+		worker::run(move || {
+			let _entered = span.enter();
 
-		rx.recv().unwrap()
+			self.render()
+		})?
 	}
 
 	fn render(self) -> Result<Value, Error> {
+		if let Some(deprecation) = self.sdk.deprecation {
+			warn!(sdk = self.sdk.version, "SDK deprecated: {deprecation}");
+		}
+
 		let state = self.create_state();
+		self.dialect.restrict(&state)?;
+		let _bound = memo::bind(&state);
 		for (name, value) in self.props {
 			let name = format!("{VARS_PREFIX}/{}", name.as_str());
+			debug!(name, "injecting property");
 			state.add_ext_var(name.into(), value.into());
 		}
 
 		let parsed = state.evaluate_file_raw(&self.main).map_err(Error::from)?;
 
 		let rendered = state.manifest(parsed).map_err(Error::from)?.to_string();
+		debug!(bytes = rendered.len(), "manifested");
 
-		let json = serde_json::from_str(&rendered)?;
+		let value = match self.format {
+			Format::Json => serde_json::from_str(&rendered)?,
+			Format::String => Value::String(rendered),
+		};
+		self.dialect.check(&value)?;
 
-		Ok(json)
+		Ok(value)
 	}
 
 	fn create_state(&self) -> EvaluationState {
+		let format = match self.format {
+			Format::Json => ManifestFormat::Json(0),
+			Format::String => ManifestFormat::String,
+		};
+
 		let state = EvaluationState::default();
 		let resolver = PathResolver::Absolute;
 		state.set_trace_format(Box::new(ExplainingFormat { resolver }));
 
 		state.with_stdlib();
 
-		let relative_resolver = Box::new(RelativeImportResolver);
-
-		let lib_resolver = Box::new(LibImportResolver {
-			library_paths: vec![self.lib.clone(), self.vendor.clone()],
-		});
-
-		let resolver = AggregatedImportResolver::default()
-			.push(relative_resolver)
-			.push(lib_resolver);
-
+		let resolver = resolver::layered(
+			&self.layers,
+			&self.lib,
+			&self.libraries,
+			&self.aliases,
+			&self.vendor,
+			self.sdk,
+		);
+		let resolver = memo::MemoizedImportResolver::new(Box::new(resolver));
 		state.set_import_resolver(Box::new(resolver));
 
-		state.set_manifest_format(ManifestFormat::Json(0));
+		state.set_manifest_format(format);
 
 		state
 	}
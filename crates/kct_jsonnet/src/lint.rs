@@ -0,0 +1,523 @@
+use crate::error::Error;
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use jrsonnet_interner::IStr;
+use jrsonnet_parser::{
+	BinaryOpType, BindSpec, CompSpec, Expr, FieldName, LocExpr, Member, ObjBody, ParamsDesc,
+	ParserSettings,
+};
+
+/// Calls with a better replacement, along it
+const SUPERSEDED: [(&str, &str); 5] = [
+	("mod", "the % operator"),
+	("objectHasEx", "std.objectHas or std.objectHasAll"),
+	("objectFieldsEx", "std.objectFields or std.objectFieldsAll"),
+	("extVar", "the built-in objects of kct.libsonnet"),
+	("native", "the built-in objects of kct.libsonnet"),
+];
+
+/// Authoring mistakes told apart by the linter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+	/// Locals never read
+	UnusedVariable,
+	/// Standard library calls with a better replacement
+	DeprecatedStd,
+	/// Fields merged with `+:` into nothing, as nothing is beneath them
+	SuspiciousPlus,
+	/// Inputs the schema declares but no template reads
+	UnusedInput,
+	/// Inputs the templates read but the schema doesn't declare
+	UndefinedInput,
+	/// Files within `files` or `lib` nothing reads
+	DeadFile,
+}
+
+impl Rule {
+	pub fn all() -> [Rule; 6] {
+		[
+			Rule::UnusedVariable,
+			Rule::DeprecatedStd,
+			Rule::SuspiciousPlus,
+			Rule::UnusedInput,
+			Rule::UndefinedInput,
+			Rule::DeadFile,
+		]
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			Rule::UnusedVariable => "unused-variable",
+			Rule::DeprecatedStd => "deprecated-std",
+			Rule::SuspiciousPlus => "suspicious-plus",
+			Rule::UnusedInput => "unused-input",
+			Rule::UndefinedInput => "undefined-input",
+			Rule::DeadFile => "dead-file",
+		}
+	}
+}
+
+impl FromStr for Rule {
+	type Err = String;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Rule::all()
+			.into_iter()
+			.find(|rule| rule.as_str() == name)
+			.ok_or_else(|| format!("unknown lint rule {name}"))
+	}
+}
+
+/// Mistake found at a line and column, both starting at 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+	pub rule: Rule,
+	pub path: PathBuf,
+	pub line: usize,
+	pub column: usize,
+	pub message: String,
+}
+
+/// Checks the source against every rule but the disabled ones, telling the
+/// findings under the path
+pub fn lint(path: &Path, source: &str, disabled: &[Rule]) -> Result<Vec<Finding>, Error> {
+	let settings = ParserSettings {
+		loc_data: true,
+		file_name: Rc::from(path),
+	};
+	let expr = jrsonnet_parser::parse(source, &settings)
+		.map_err(|err| Error::Parse(path.display().to_string(), err.to_string()))?;
+
+	let mut linter = Linter {
+		path,
+		source,
+		findings: vec![],
+	};
+	linter.walk(&expr, 0, Beneath::Unknown);
+
+	let mut findings: Vec<Finding> = linter
+		.findings
+		.into_iter()
+		.filter(|finding| !disabled.contains(&finding.rule))
+		.collect();
+	findings.sort_by_key(|finding| (finding.line, finding.column));
+	findings.dedup();
+
+	Ok(findings)
+}
+
+/// What an object literal is merged over, as far as the syntax tells
+#[derive(Clone)]
+enum Beneath {
+	Unknown,
+	/// Nothing, as it's the first object added
+	Nothing,
+	/// Nothing, as it's the value of the field, named here, replacing what
+	/// was there
+	Replaced(String),
+}
+
+struct Linter<'a> {
+	path: &'a Path,
+	source: &'a str,
+	findings: Vec<Finding>,
+}
+
+impl Linter<'_> {
+	/// Visits the expression, `at` being the offset of the nearest location
+	/// as not every expression has one
+	fn walk(&mut self, expr: &LocExpr, at: usize, beneath: Beneath) {
+		let at = expr.1.as_ref().map(|location| location.1).unwrap_or(at);
+
+		match &*expr.0 {
+			Expr::LocalExpr(binds, body) => {
+				for bind in binds {
+					let others = binds.iter().filter(|other| other.name != bind.name);
+					let used = refers(body, &bind.name)
+						|| others.clone().any(|other| bind_refers(other, &bind.name));
+
+					if !used {
+						self.unused(&bind.name, bind.value.1.as_ref().map(|l| l.1).unwrap_or(at));
+					}
+				}
+			}
+			Expr::Obj(ObjBody::MemberList(members)) => {
+				for member in members {
+					match member {
+						Member::BindStmt(bind) if !member_refers(members, bind) => {
+							self.unused(
+								&bind.name,
+								bind.value.1.as_ref().map(|l| l.1).unwrap_or(at),
+							);
+						}
+						Member::Field(field) if field.plus => self.plus(&field.name, at, &beneath),
+						_ => (),
+					}
+				}
+			}
+			Expr::Index(target, index) => {
+				if let (Expr::Var(var), Expr::Str(name)) = (&*target.0, &*index.0) {
+					let superseded = SUPERSEDED.iter().find(|(call, _)| **call == **name);
+
+					if let (true, Some((call, replacement))) = (&**var == "std", superseded) {
+						let at = target.1.as_ref().map(|location| location.1).unwrap_or(at);
+						let message = format!("std.{call} is superseded by {replacement}");
+
+						self.report(Rule::DeprecatedStd, at, message);
+					}
+				}
+			}
+			_ => (),
+		}
+
+		match &*expr.0 {
+			Expr::BinaryOp(left, BinaryOpType::Add, right) => {
+				self.walk(left, at, Beneath::Nothing);
+				self.walk(right, at, Beneath::Unknown);
+			}
+			Expr::Obj(ObjBody::MemberList(members)) => {
+				for member in members {
+					match member {
+						Member::Field(field) => {
+							if let FieldName::Dyn(name) = &field.name {
+								self.walk(name, at, Beneath::Unknown);
+							}
+							for param in field.params.iter().flat_map(|params| params.iter()) {
+								if let Some(default) = &param.1 {
+									self.walk(default, at, Beneath::Unknown);
+								}
+							}
+
+							let beneath = match (field.plus, &field.params, &field.name) {
+								(false, None, FieldName::Fixed(name)) => {
+									Beneath::Replaced(name.to_string())
+								}
+								_ => Beneath::Unknown,
+							};
+							self.walk(&field.value, at, beneath);
+						}
+						Member::BindStmt(bind) => self.walk_bind(bind, at),
+						Member::AssertStmt(assert) => {
+							self.walk(&assert.0, at, Beneath::Unknown);
+							if let Some(message) = &assert.1 {
+								self.walk(message, at, Beneath::Unknown);
+							}
+						}
+					}
+				}
+			}
+			Expr::LocalExpr(binds, body) => {
+				for bind in binds {
+					self.walk_bind(bind, at);
+				}
+				self.walk(body, at, beneath);
+			}
+			Expr::Parened(inner) => self.walk(inner, at, beneath.clone()),
+			_ => {
+				for child in children(&expr.0) {
+					self.walk(child, at, Beneath::Unknown);
+				}
+			}
+		}
+	}
+
+	fn walk_bind(&mut self, bind: &BindSpec, at: usize) {
+		for param in bind.params.iter().flat_map(|params| params.iter()) {
+			if let Some(default) = &param.1 {
+				self.walk(default, at, Beneath::Unknown);
+			}
+		}
+
+		self.walk(&bind.value, at, Beneath::Unknown);
+	}
+
+	fn unused(&mut self, name: &IStr, at: usize) {
+		self.report(Rule::UnusedVariable, at, format!("{name} is never used"));
+	}
+
+	fn plus(&mut self, name: &FieldName, at: usize, beneath: &Beneath) {
+		let name = match name {
+			FieldName::Fixed(name) => name.to_string(),
+			FieldName::Dyn(_) => String::from("[...]"),
+		};
+
+		let message = match beneath {
+			Beneath::Unknown => return,
+			Beneath::Nothing => {
+				format!("{name}+: merges into nothing, as its object is the first one added")
+			}
+			Beneath::Replaced(parent) => format!(
+				"{name}+: merges into nothing, as {parent}: replaces what was there, did you mean {parent}+:?"
+			),
+		};
+
+		self.report(Rule::SuspiciousPlus, at, message);
+	}
+
+	fn report(&mut self, rule: Rule, at: usize, message: String) {
+		let (line, column) = position(self.source, at);
+
+		self.findings.push(Finding {
+			rule,
+			path: self.path.to_path_buf(),
+			line,
+			column,
+			message,
+		});
+	}
+}
+
+/// Line and column of the offset within the source, both starting at 1
+pub fn position(source: &str, at: usize) -> (usize, usize) {
+	let before = &source[..at.min(source.len())];
+	let line = before.matches('\n').count() + 1;
+	let column = before
+		.rsplit('\n')
+		.next()
+		.unwrap_or_default()
+		.chars()
+		.count()
+		+ 1;
+
+	(line, column)
+}
+
+/// Whether the local of an object is read by the rest of it
+fn member_refers(members: &[Member], bind: &BindSpec) -> bool {
+	members.iter().any(|member| match member {
+		Member::Field(field) => {
+			let name = match &field.name {
+				FieldName::Dyn(name) => refers(name, &bind.name),
+				FieldName::Fixed(_) => false,
+			};
+
+			name || function_refers(field.params.as_ref(), &field.value, &bind.name)
+		}
+		Member::BindStmt(other) => other.name != bind.name && bind_refers(other, &bind.name),
+		Member::AssertStmt(assert) => {
+			refers(&assert.0, &bind.name)
+				|| assert
+					.1
+					.as_ref()
+					.is_some_and(|message| refers(message, &bind.name))
+		}
+	})
+}
+
+fn bind_refers(bind: &BindSpec, name: &IStr) -> bool {
+	function_refers(bind.params.as_ref(), &bind.value, name)
+}
+
+/// Whether the body, or the defaults of the parameters, read the variable
+/// when no parameter shadows it
+fn function_refers(params: Option<&ParamsDesc>, body: &LocExpr, name: &IStr) -> bool {
+	let params = params.map(|params| params.iter()).into_iter().flatten();
+	let mut defaults = vec![];
+
+	for param in params {
+		if &param.0 == name {
+			return false;
+		}
+
+		defaults.extend(param.1.iter());
+	}
+
+	defaults.into_iter().any(|default| refers(default, name)) || refers(body, name)
+}
+
+/// Whether the expression reads the variable, minding the scopes shadowing it
+fn refers(expr: &LocExpr, name: &IStr) -> bool {
+	match &*expr.0 {
+		Expr::Var(var) => var == name,
+		Expr::LocalExpr(binds, body) => {
+			!binds.iter().any(|bind| &bind.name == name)
+				&& (binds.iter().any(|bind| bind_refers(bind, name)) || refers(body, name))
+		}
+		Expr::Function(params, body) => function_refers(Some(params), body, name),
+		Expr::Obj(ObjBody::MemberList(members)) => {
+			let shadowed = members
+				.iter()
+				.any(|member| matches!(member, Member::BindStmt(bind) if &bind.name == name));
+
+			!shadowed
+				&& members.iter().any(|member| match member {
+					Member::Field(field) => {
+						let dyn_name = match &field.name {
+							FieldName::Dyn(key) => refers(key, name),
+							FieldName::Fixed(_) => false,
+						};
+
+						dyn_name || function_refers(field.params.as_ref(), &field.value, name)
+					}
+					Member::BindStmt(bind) => bind_refers(bind, name),
+					Member::AssertStmt(assert) => {
+						refers(&assert.0, name)
+							|| assert
+								.1
+								.as_ref()
+								.is_some_and(|message| refers(message, name))
+					}
+				})
+		}
+		Expr::Obj(ObjBody::ObjComp(comp)) => {
+			let mut scoped = vec![&comp.key, &comp.value];
+			scoped.extend(comp.pre_locals.iter().map(|bind| &bind.value));
+			scoped.extend(comp.post_locals.iter().map(|bind| &bind.value));
+
+			let locals = comp.pre_locals.iter().chain(comp.post_locals.iter());
+			let shadowed = locals.into_iter().any(|bind| &bind.name == name);
+
+			comprehension_refers(&comp.compspecs, &scoped, shadowed, name)
+		}
+		Expr::ArrComp(body, specs) => comprehension_refers(specs, &[body], false, name),
+		_ => children(&expr.0)
+			.into_iter()
+			.any(|child| refers(child, name)),
+	}
+}
+
+/// Whether the specs, or what they scope when none shadows the variable,
+/// read it, as each `for` scopes the specs after it
+fn comprehension_refers(
+	specs: &[CompSpec],
+	scoped: &[&LocExpr],
+	shadowed: bool,
+	name: &IStr,
+) -> bool {
+	for spec in specs {
+		match spec {
+			CompSpec::ForSpec(spec) if refers(&spec.1, name) => return true,
+			CompSpec::ForSpec(spec) if &spec.0 == name => return false,
+			CompSpec::IfSpec(spec) if refers(&spec.0, name) => return true,
+			_ => (),
+		}
+	}
+
+	!shadowed && scoped.iter().any(|expr| refers(expr, name))
+}
+
+/// Expressions directly within the expression
+pub(crate) fn children(expr: &Expr) -> Vec<&LocExpr> {
+	match expr {
+		Expr::Literal(_)
+		| Expr::Str(_)
+		| Expr::Num(_)
+		| Expr::Var(_)
+		| Expr::Import(_)
+		| Expr::ImportStr(_)
+		| Expr::Intrinsic(_) => vec![],
+		Expr::Arr(items) => items.iter().collect(),
+		Expr::ArrComp(body, comp) => {
+			let mut exprs = vec![body];
+			exprs.extend(specs(comp));
+			exprs
+		}
+		Expr::Obj(body) => members(body),
+		Expr::ObjExtend(target, body) => {
+			let mut exprs = vec![target];
+			exprs.extend(members(body));
+			exprs
+		}
+		Expr::Parened(inner) | Expr::UnaryOp(_, inner) | Expr::ErrorStmt(inner) => vec![inner],
+		Expr::BinaryOp(left, _, right) | Expr::Index(left, right) => vec![left, right],
+		Expr::AssertExpr(assert, body) => {
+			let mut exprs = vec![&assert.0];
+			exprs.extend(assert.1.iter());
+			exprs.push(body);
+			exprs
+		}
+		Expr::LocalExpr(locals, body) => {
+			let mut exprs = binds(locals);
+			exprs.push(body);
+			exprs
+		}
+		Expr::Apply(target, args, _) => {
+			let mut exprs = vec![target];
+			exprs.extend(args.iter().map(|arg| &arg.1));
+			exprs
+		}
+		Expr::Function(params, body) => {
+			let mut exprs = defaults(Some(params));
+			exprs.push(body);
+			exprs
+		}
+		Expr::IfElse {
+			cond,
+			cond_then,
+			cond_else,
+		} => {
+			let mut exprs = vec![&cond.0, cond_then];
+			exprs.extend(cond_else.iter());
+			exprs
+		}
+		Expr::Slice(target, slice) => {
+			let mut exprs = vec![target];
+			exprs.extend(slice.start.iter());
+			exprs.extend(slice.end.iter());
+			exprs.extend(slice.step.iter());
+			exprs
+		}
+	}
+}
+
+fn defaults(params: Option<&ParamsDesc>) -> Vec<&LocExpr> {
+	params
+		.map(|params| params.iter().filter_map(|param| param.1.as_ref()).collect())
+		.unwrap_or_default()
+}
+
+fn binds(binds: &[BindSpec]) -> Vec<&LocExpr> {
+	binds
+		.iter()
+		.flat_map(|bind| {
+			let mut exprs = defaults(bind.params.as_ref());
+			exprs.push(&bind.value);
+			exprs
+		})
+		.collect()
+}
+
+fn specs(specs: &[CompSpec]) -> Vec<&LocExpr> {
+	specs
+		.iter()
+		.map(|spec| match spec {
+			CompSpec::ForSpec(spec) => &spec.1,
+			CompSpec::IfSpec(spec) => &spec.0,
+		})
+		.collect()
+}
+
+fn members(body: &ObjBody) -> Vec<&LocExpr> {
+	match body {
+		ObjBody::MemberList(members) => members
+			.iter()
+			.flat_map(|member| match member {
+				Member::Field(field) => {
+					let mut exprs = defaults(field.params.as_ref());
+					if let FieldName::Dyn(name) = &field.name {
+						exprs.push(name);
+					}
+					exprs.push(&field.value);
+					exprs
+				}
+				Member::BindStmt(bind) => binds(std::slice::from_ref(bind)),
+				Member::AssertStmt(assert) => {
+					let mut exprs = vec![&assert.0];
+					exprs.extend(assert.1.iter());
+					exprs
+				}
+			})
+			.collect(),
+		ObjBody::ObjComp(comp) => {
+			let mut exprs = binds(&comp.pre_locals);
+			exprs.push(&comp.key);
+			exprs.push(&comp.value);
+			exprs.extend(binds(&comp.post_locals));
+			exprs.extend(specs(&comp.compspecs));
+			exprs
+		}
+	}
+}
@@ -0,0 +1,187 @@
+use crate::resolver;
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use jrsonnet_evaluator::error::Error as JrError;
+use jrsonnet_evaluator::{EvaluationState, ImportResolver};
+use jrsonnet_interner::IStr;
+use jrsonnet_parser::{LocExpr, ParserSettings};
+use kct_helper::{io, memory};
+use tracing::trace;
+
+/// Extensions of the modules worth keeping, as the rest are imported as text
+const MODULES: [&str; 2] = ["jsonnet", "libsonnet"];
+
+/// Modules kept at most, the ones parsed first making room for new ones
+const MAX_PARSED: usize = 256;
+
+/// Modules parsed so far by the process, shared by every evaluation as
+/// expressions can't leave the thread parsing them
+static PARSED: Mutex<Modules> = Mutex::new(Modules {
+	parsed: None,
+	order: VecDeque::new(),
+});
+
+struct Modules {
+	parsed: Option<HashMap<PathBuf, Arc<Parsed>>>,
+	/// Paths by the order they were parsed in
+	order: VecDeque<PathBuf>,
+}
+
+impl Modules {
+	fn get(&self, id: &Path) -> Option<Arc<Parsed>> {
+		self.parsed.as_ref()?.get(id).cloned()
+	}
+
+	fn insert(&mut self, id: PathBuf, parsed: Parsed) {
+		let modules = self.parsed.get_or_insert_with(HashMap::new);
+
+		if modules.insert(id.clone(), Arc::new(parsed)).is_none() {
+			self.order.push_back(id);
+		}
+
+		while self.order.len() > MAX_PARSED {
+			if let Some(oldest) = self.order.pop_front() {
+				modules.remove(&oldest);
+			}
+		}
+	}
+}
+
+thread_local! {
+	/// State evaluating on this thread, receiving the modules it imports
+	static STATE: RefCell<Option<EvaluationState>> = const { RefCell::new(None) };
+}
+
+struct Parsed {
+	/// Modification time and length of the file when parsed, telling whether
+	/// it's still the same
+	stamp: Option<(SystemTime, u64)>,
+	source: String,
+	expr: Vec<u8>,
+}
+
+/// Keeps the state receiving the modules imported on this thread until dropped
+pub struct Bound;
+
+pub fn bind(state: &EvaluationState) -> Bound {
+	STATE.with(|current| current.replace(Some(state.clone())));
+
+	Bound
+}
+
+impl Drop for Bound {
+	fn drop(&mut self) {
+		STATE.with(|current| current.take());
+	}
+}
+
+/// Hands the modules it resolves to the state already parsed, when any
+/// evaluation in the process parsed them before, so subpackages importing
+/// the same libraries parse them once
+pub struct MemoizedImportResolver {
+	inner: Box<dyn ImportResolver>,
+}
+
+impl MemoizedImportResolver {
+	pub fn new(inner: Box<dyn ImportResolver>) -> Self {
+		Self { inner }
+	}
+}
+
+impl ImportResolver for MemoizedImportResolver {
+	fn resolve_file(
+		&self,
+		from: &Path,
+		path: &Path,
+	) -> jrsonnet_evaluator::error::Result<Rc<Path>> {
+		// Imports are where evaluations going beyond their memory stop
+		if memory::exceeded() {
+			let reason = String::from("evaluation went beyond its memory");
+
+			return Err(JrError::ImportCallbackError(reason).into());
+		}
+
+		let id = self.inner.resolve_file(from, path)?;
+		io::record(resolver::file(&id));
+
+		STATE.with(|current| {
+			let current = current.borrow();
+			let state = match current.as_ref() {
+				Some(state) => state,
+				None => return,
+			};
+
+			// Adding it again would throw away what the state evaluated
+			if state.get_source(&id).is_some() {
+				return;
+			}
+
+			if let Some((source, expr)) = parsed(&id) {
+				let _ = state.add_parsed_file(id.clone(), source, expr);
+			}
+		});
+
+		Ok(id)
+	}
+
+	fn load_file_contents(&self, id: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
+		self.inner.load_file_contents(id)
+	}
+
+	unsafe fn as_any(&self) -> &dyn Any {
+		panic!("this resolver can't be used as any")
+	}
+}
+
+/// Module at the id, parsed by an earlier evaluation or now, leaving the
+/// ones failing to parse to the evaluator so it reports why
+fn parsed(id: &Rc<Path>) -> Option<(IStr, LocExpr)> {
+	let file = resolver::file(id);
+	let extension = file.extension()?.to_str()?;
+	if !MODULES.contains(&extension) {
+		return None;
+	}
+
+	let stamp = fs::metadata(file)
+		.ok()
+		.and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+
+	// Taken only to look up, as deserializing may take a while
+	let found = PARSED.lock().ok()?.get(id);
+	if let Some(parsed) = found {
+		if parsed.stamp.is_some() && parsed.stamp == stamp {
+			trace!(module = %file.display(), "reusing parsed module");
+			let expr = bincode::deserialize(&parsed.expr).ok()?;
+
+			return Some((parsed.source.as_str().into(), expr));
+		}
+	}
+
+	// Larger ones are left to the evaluator as well, refusing to load them
+	let source = String::from_utf8(resolver::read(file).ok()?).ok()?;
+	let settings = ParserSettings {
+		loc_data: true,
+		file_name: id.clone(),
+	};
+	let expr = jrsonnet_parser::parse(&source, &settings).ok()?;
+
+	if let Ok(serialized) = bincode::serialize(&expr) {
+		let parsed = Parsed {
+			stamp,
+			source: source.clone(),
+			expr: serialized,
+		};
+
+		PARSED.lock().ok()?.insert(id.to_path_buf(), parsed);
+	}
+
+	Some((source.into(), expr))
+}
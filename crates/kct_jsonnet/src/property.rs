@@ -6,14 +6,17 @@ use std::rc::Rc;
 
 use jrsonnet_evaluator::error::{Error as JrError, LocError};
 use jrsonnet_evaluator::native::{NativeCallback, NativeCallbackHandler};
-use jrsonnet_evaluator::{FuncVal, Val};
+use jrsonnet_evaluator::{FuncVal, ObjValueBuilder, Val};
 use jrsonnet_gc::{unsafe_empty_trace, Finalize, Gc, Trace};
 use jrsonnet_parser::{Param, ParamsDesc};
+use kct_helper::memory;
 use serde_json::Value;
 
 pub enum Property {
 	Primitive(Value),
 	Callable(String, Function),
+	/// Groups related properties under a single object, e.g. `semver.compare`
+	Object(Vec<(String, Property)>),
 }
 
 impl Property {
@@ -30,6 +33,10 @@ impl fmt::Debug for Property {
 		match self {
 			Property::Primitive(val) => write!(f, "{val:?}"),
 			Property::Callable(name, func) => write!(f, "{}({})", name, func.params.join(", ")),
+			Property::Object(members) => f
+				.debug_map()
+				.entries(members.iter().map(|(k, v)| (k, v)))
+				.finish(),
 		}
 	}
 }
@@ -43,6 +50,15 @@ pub trait Callback: Send {
 	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String>;
 }
 
+impl<F> Callback for F
+where
+	F: Fn(HashMap<String, Value>) -> Result<Value, String> + Send,
+{
+	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String> {
+		self(params)
+	}
+}
+
 impl Finalize for Function {}
 unsafe impl Trace for Function {
 	unsafe_empty_trace!();
@@ -54,6 +70,12 @@ impl NativeCallbackHandler for Function {
 		_from: Option<Rc<Path>>,
 		args: &[Val],
 	) -> jrsonnet_evaluator::error::Result<Val> {
+		if memory::exceeded() {
+			let err = JrError::RuntimeError("evaluation went beyond its memory".into());
+
+			return Err(LocError::new(err));
+		}
+
 		let names = self.params.clone().into_iter();
 		let values = args.iter().map(|v| {
 			Value::try_from(v).expect("Extension functions should only receive valid JSON")
@@ -89,6 +111,14 @@ impl From<Property> for Val {
 
 				Val::Func(ext)
 			}
+			Property::Object(members) => {
+				let mut builder = ObjValueBuilder::new();
+				for (name, property) in members {
+					builder.member(name.into()).value(Val::from(property));
+				}
+
+				Val::Obj(builder.build())
+			}
 		}
 	}
 }
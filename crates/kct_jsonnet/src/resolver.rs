@@ -1,12 +1,100 @@
+use crate::sdk::{self, Sdk};
+
 use std::any::Any;
-use std::fs::File;
-use std::io::Read;
+use std::collections::BTreeMap;
+use std::fs;
+use std::iter;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use jrsonnet_evaluator::error::Error as JrError;
 use jrsonnet_evaluator::ImportResolver;
 use jrsonnet_interner::IStr;
+use kct_helper::io;
+use tracing::debug;
+
+const LIB_DIR: &str = "lib";
+const VENDOR_DIR: &str = "vendor";
+
+/// Target of the events telling how each import was resolved, or why every
+/// resolver declined it
+pub const IMPORTS_TARGET: &str = "kct::imports";
+
+/// Largest file templates may import, as each is held whole in memory by
+/// the evaluation importing it
+pub const MAX_IMPORT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Resolves the versions of the SDK and aliased imports to their targets,
+/// the rest relative to the importing file, then within the libs, the other
+/// library paths in order, and what's vendored, each layer after the
+/// package's own, and at last `kct.libsonnet` to the SDK pinned
+pub fn layered(
+	layers: &[PathBuf],
+	lib: &Path,
+	libraries: &[PathBuf],
+	aliases: &BTreeMap<String, PathBuf>,
+	vendor: &Path,
+	pinned: &'static Sdk,
+) -> AggregatedImportResolver {
+	let sdk_resolver = Box::new(SdkImportResolver { pinned: None });
+	let pinned_resolver = Box::new(SdkImportResolver {
+		pinned: Some(pinned),
+	});
+	let alias_resolver = Box::new(AliasImportResolver::new(aliases));
+	let relative_resolver = Box::new(RelativeImportResolver::new(layers));
+
+	let bases = layers.iter().skip(1);
+	let library_paths = iter::once(lib.to_path_buf())
+		.chain(bases.clone().map(|base| base.join(LIB_DIR)))
+		.chain(libraries.iter().cloned())
+		.chain(iter::once(vendor.to_path_buf()))
+		.chain(bases.map(|base| base.join(VENDOR_DIR)))
+		.collect();
+
+	let lib_resolver = Box::new(LibImportResolver { library_paths });
+
+	AggregatedImportResolver::default()
+		.push("sdk", sdk_resolver)
+		.push("alias", alias_resolver)
+		.push("relative", relative_resolver)
+		.push("lib", lib_resolver)
+		.push("sdk", pinned_resolver)
+}
+
+/// Bytes of the file, refusing the ones larger than imports may load
+pub fn read(file: &Path) -> Result<Vec<u8>, JrError> {
+	let missing = || JrError::ResolvedFileNotFound(file.to_path_buf());
+
+	let size = fs::metadata(file).map_err(|_e| missing())?.len();
+	if size > MAX_IMPORT_SIZE {
+		let reason = format!(
+			"{} has {size} bytes, more than the {MAX_IMPORT_SIZE} imports may load",
+			file.display()
+		);
+
+		return Err(JrError::ImportCallbackError(reason));
+	}
+
+	fs::read(file).map_err(|_e| missing())
+}
+
+/// Contents of the file imported as a module or with `importstr`
+fn load(file: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
+	let contents = read(file)?;
+
+	String::from_utf8(contents)
+		.map(IStr::from)
+		.map_err(|_e| JrError::ImportBadFileUtf8(file.to_path_buf()).into())
+}
+
+/// File behind the id of an import, as aggregated resolvers tell which one
+/// resolved it as the last component
+pub fn file(id: &Path) -> &Path {
+	match id.extension().is_some_and(|ext| ext == "resolver") {
+		true => id.parent().unwrap_or(id),
+		false => id,
+	}
+}
 
 pub struct LibImportResolver {
 	pub library_paths: Vec<PathBuf>,
@@ -27,54 +115,195 @@ impl ImportResolver for LibImportResolver {
 			}
 		}
 
+		debug!(
+			target: IMPORTS_TARGET,
+			import = %path.display(),
+			searched = ?self.library_paths,
+			"no library has it"
+		);
+
 		Err(JrError::ImportFileNotFound(from.to_path_buf(), path.to_path_buf()).into())
 	}
 
 	fn load_file_contents(&self, id: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
-		let mut file =
-			File::open(id).map_err(|_e| JrError::ResolvedFileNotFound(id.to_path_buf()))?;
-		let mut out = String::new();
-		file.read_to_string(&mut out)
-			.map_err(|_e| JrError::ImportBadFileUtf8(id.to_path_buf()))?;
-		Ok(out.into())
+		load(id)
 	}
 	unsafe fn as_any(&self) -> &dyn Any {
 		panic!("this resolver can't be used as any")
 	}
 }
 
-pub struct RelativeImportResolver;
+/// Resolves imports of the SDK embedded in kct, either of a version, as
+/// `kct.io/v1.libsonnet`, a module of it, or within it. When pinned, it only resolves
+/// `kct.libsonnet` to the version pinned, as packages may vendor their own.
+pub struct SdkImportResolver {
+	pinned: Option<&'static Sdk>,
+}
 
-impl ImportResolver for RelativeImportResolver {
+impl ImportResolver for SdkImportResolver {
 	fn resolve_file(
 		&self,
 		from: &Path,
 		path: &Path,
 	) -> jrsonnet_evaluator::error::Result<Rc<Path>> {
-		let mut target = from.to_path_buf();
-		target.push(path);
-
-		let resolved = if target.exists() {
-			Some(target.into())
-		} else {
-			from.parent()
-				.map(|p| p.join(path))
-				.and_then(|p| p.canonicalize().ok())
-				.map(|p| p.into())
+		let not_found = || JrError::ImportFileNotFound(from.to_path_buf(), path.to_path_buf());
+
+		let resolved = match self.pinned {
+			Some(pinned) if path == Path::new(sdk::UNVERSIONED) => Some(pinned.main()),
+			Some(_) => None,
+			None => match Sdk::locate(from) {
+				Some((version, within)) => version.resolve(&within, path),
+				None => sdk::import(path),
+			},
 		};
 
-		resolved.ok_or_else(|| {
+		resolved
+			.map(|resolved| resolved.into())
+			.ok_or_else(|| not_found().into())
+	}
+
+	fn load_file_contents(&self, id: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
+		Sdk::locate(id)
+			.and_then(|(version, within)| version.contents(&within))
+			.map(IStr::from)
+			.ok_or_else(|| JrError::ResolvedFileNotFound(id.to_path_buf()).into())
+	}
+
+	unsafe fn as_any(&self) -> &dyn Any {
+		panic!("this resolver can't be used as any")
+	}
+}
+
+/// Resolves imports by the names they're aliased to, either the whole import
+/// or its leading directories, as `k/` standing for a vendored directory.
+/// The longest alias matching wins.
+pub struct AliasImportResolver {
+	aliases: Vec<(PathBuf, PathBuf)>,
+}
+
+impl AliasImportResolver {
+	pub fn new(aliases: &BTreeMap<String, PathBuf>) -> Self {
+		let mut aliases: Vec<(PathBuf, PathBuf)> = aliases
+			.iter()
+			.map(|(name, target)| (PathBuf::from(name), target.clone()))
+			.collect();
+		aliases.sort_by_key(|(name, _)| std::cmp::Reverse(name.components().count()));
+
+		Self { aliases }
+	}
+}
+
+impl ImportResolver for AliasImportResolver {
+	fn resolve_file(
+		&self,
+		from: &Path,
+		path: &Path,
+	) -> jrsonnet_evaluator::error::Result<Rc<Path>> {
+		let aliased = self.aliases.iter().find_map(|(name, target)| {
+			let rest = path.strip_prefix(name).ok()?;
+
+			match rest.as_os_str().is_empty() {
+				true => Some(target.clone()),
+				false => Some(target.join(rest)),
+			}
+		});
+
+		// Aliases are final, so a missing target fails loading it instead of
+		// falling back to another file by the same name
+		aliased.map(|target| target.into()).ok_or_else(|| {
 			JrError::ImportFileNotFound(from.to_path_buf(), path.to_path_buf()).into()
 		})
 	}
 
+	fn load_file_contents(&self, id: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
+		load(id)
+	}
+
+	unsafe fn as_any(&self) -> &dyn Any {
+		panic!("this resolver can't be used as any")
+	}
+}
+
+/// Resolves imports relative to the importing file. When packages are layered,
+/// the same relative location is looked up on every layer, so the first one
+/// having the file shadows the others.
+pub struct RelativeImportResolver {
+	layers: Vec<PathBuf>,
+}
+
+impl RelativeImportResolver {
+	pub fn new(layers: &[PathBuf]) -> Self {
+		let layers = layers
+			.iter()
+			.map(|layer| io::canonical(layer).unwrap_or_else(|_| layer.clone()))
+			.collect();
+
+		Self { layers }
+	}
+
+	fn locate(&self, from: &Path) -> Option<PathBuf> {
+		let from = io::canonical(from).unwrap_or_else(|_| from.to_path_buf());
+
+		// Bases are within the package, so the nearest layer holds the file
+		self.layers
+			.iter()
+			.filter_map(|layer| from.strip_prefix(layer).ok())
+			.min_by_key(|relative| relative.components().count())
+			.map(Path::to_path_buf)
+	}
+}
+
+/// Import relative to the file, or the path where it was looked for
+fn resolve_relative(from: &Path, path: &Path) -> Result<Rc<Path>, PathBuf> {
+	let mut target = from.to_path_buf();
+	target.push(path);
+
+	if target.exists() {
+		return Ok(target.into());
+	}
+
+	let sibling = from.parent().map(|p| p.join(path)).unwrap_or(target);
+
+	io::canonical(&sibling)
+		.map(|p| p.into())
+		.map_err(|_err| sibling)
+}
+
+impl ImportResolver for RelativeImportResolver {
+	fn resolve_file(
+		&self,
+		from: &Path,
+		path: &Path,
+	) -> jrsonnet_evaluator::error::Result<Rc<Path>> {
+		let froms = match self.locate(from) {
+			None => vec![from.to_path_buf()],
+			Some(relative) => self
+				.layers
+				.iter()
+				.map(|layer| layer.join(&relative))
+				.collect(),
+		};
+
+		let mut searched = vec![];
+		for from in froms {
+			match resolve_relative(&from, path) {
+				Ok(resolved) => return Ok(resolved),
+				Err(candidate) => searched.push(candidate),
+			}
+		}
+
+		debug!(
+			target: IMPORTS_TARGET,
+			import = %path.display(),
+			?searched,
+			"no file relative to the importing one"
+		);
+
+		Err(JrError::ImportFileNotFound(from.to_path_buf(), path.to_path_buf()).into())
+	}
+
 	fn load_file_contents(&self, path: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
-		let mut file =
-			File::open(path).map_err(|_e| JrError::ResolvedFileNotFound(path.to_path_buf()))?;
-		let mut out = String::new();
-		file.read_to_string(&mut out)
-			.map_err(|_e| JrError::ImportBadFileUtf8(path.to_path_buf()))?;
-		Ok(out.into())
+		load(path)
 	}
 
 	unsafe fn as_any(&self) -> &dyn Any {
@@ -85,11 +314,14 @@ impl ImportResolver for RelativeImportResolver {
 #[derive(Default)]
 pub struct AggregatedImportResolver {
 	import_resolvers: Vec<Box<dyn ImportResolver>>,
+	/// Names of the resolvers, as told when debugging imports
+	names: Vec<&'static str>,
 }
 
 impl AggregatedImportResolver {
-	pub fn push(mut self, resolver: Box<dyn ImportResolver>) -> Self {
+	pub fn push(mut self, name: &'static str, resolver: Box<dyn ImportResolver>) -> Self {
 		self.import_resolvers.push(resolver);
+		self.names.push(name);
 
 		self
 	}
@@ -101,30 +333,51 @@ impl ImportResolver for AggregatedImportResolver {
 		from: &Path,
 		path: &Path,
 	) -> jrsonnet_evaluator::error::Result<Rc<Path>> {
+		debug!(
+			target: IMPORTS_TARGET,
+			from = %from.display(),
+			import = %path.display(),
+			"import requested"
+		);
+
 		for (i, resolver) in self.import_resolvers.iter().enumerate() {
 			let resolved = resolver.resolve_file(from, path);
 
-			if let Ok(ref path) = resolved {
-				let path = {
-					let base: PathBuf = path.to_path_buf();
-					let mut base = base;
+			if let Ok(ref resolved) = resolved {
+				debug!(
+					target: IMPORTS_TARGET,
+					import = %path.display(),
+					resolver = self.names[i],
+					resolved = %resolved.display(),
+					"import resolved"
+				);
+
+				let id = {
+					let mut base: PathBuf = resolved.to_path_buf();
 
 					base.push(format!("{i}.resolver"));
 
 					base
 				};
 
-				return Ok(path.into());
+				return Ok(id.into());
 			}
 		}
 
+		debug!(
+			target: IMPORTS_TARGET,
+			from = %from.display(),
+			import = %path.display(),
+			"import not found"
+		);
+
 		Err(JrError::ImportFileNotFound(from.to_path_buf(), path.to_path_buf()).into())
 	}
 
 	fn load_file_contents(&self, id: &Path) -> jrsonnet_evaluator::error::Result<IStr> {
 		let error = JrError::ResolvedFileNotFound(id.to_path_buf()).into();
 
-		let is_from_resolver = id.extension().map_or(false, |ext| ext.eq("resolver"));
+		let is_from_resolver = id.extension().is_some_and(|ext| ext.eq("resolver"));
 		if !is_from_resolver {
 			return Err(error);
 		}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use kct_helper::io;
+
+/// Import standing for the version of the SDK the package pins, when
+/// neither its libs nor what's vendored have it
+pub const UNVERSIONED: &str = "kct.libsonnet";
+/// Prefix of the imports of a specific version, as `kct.io/v1.libsonnet`
+pub const PREFIX: &str = "kct.io";
+
+/// Version of the SDK embedded in kct, which keeps working once superseded
+/// so upgrading kct doesn't change what templates render
+pub struct Sdk {
+	pub version: &'static str,
+	/// Why it shouldn't be used anymore, warned on every compile pinning it
+	pub deprecation: Option<&'static str>,
+	/// Sources by their path within the SDK, `main.libsonnet` the entrypoint
+	files: &'static [(&'static str, &'static str)],
+}
+
+/// Every version still supported, oldest first
+pub const VERSIONS: &[Sdk] = &[Sdk {
+	version: "v1",
+	deprecation: None,
+	files: &[
+		(
+			"main.libsonnet",
+			include_str!("../../../libsonnet/main.libsonnet"),
+		),
+		(
+			"sdk.libsonnet",
+			include_str!("../../../libsonnet/sdk.libsonnet"),
+		),
+		(
+			"k8s.libsonnet",
+			include_str!("../../../libsonnet/k8s.libsonnet"),
+		),
+		(
+			"sdk/chunk.libsonnet",
+			include_str!("../../../libsonnet/sdk/chunk.libsonnet"),
+		),
+		(
+			"sdk/dependsOn.libsonnet",
+			include_str!("../../../libsonnet/sdk/dependsOn.libsonnet"),
+		),
+		(
+			"sdk/inOrder.libsonnet",
+			include_str!("../../../libsonnet/sdk/inOrder.libsonnet"),
+		),
+	],
+}];
+
+pub fn find(version: &str) -> Option<&'static Sdk> {
+	VERSIONS.iter().find(|sdk| sdk.version == version)
+}
+
+/// Id of the file imported from the SDK, either the entrypoint of a version,
+/// as `kct.io/v1.libsonnet`, or a module within it, as `kct.io/v1/k8s.libsonnet`
+pub(crate) fn import(path: &Path) -> Option<PathBuf> {
+	let within = path.strip_prefix(PREFIX).ok()?;
+	let entrypoint = within.to_str()?.strip_suffix(".libsonnet").and_then(find);
+	if let Some(version) = entrypoint {
+		return Some(version.main());
+	}
+
+	let (version, file) = Sdk::locate(path)?;
+	version.resolve(Path::new(""), &file)
+}
+
+/// Version used by the packages pinning none
+pub fn latest() -> &'static Sdk {
+	VERSIONS
+		.last()
+		.expect("kct embeds at least one version of the SDK")
+}
+
+impl fmt::Debug for Sdk {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.version)
+	}
+}
+
+impl Sdk {
+	/// Id of the entrypoint, a path no package has as it's relative
+	pub fn main(&self) -> PathBuf {
+		self.id("main.libsonnet")
+	}
+
+	fn id(&self, file: impl AsRef<Path>) -> PathBuf {
+		Path::new(PREFIX).join(self.version).join(file)
+	}
+
+	/// Version and file within it behind the id, when it's of the SDK
+	pub(crate) fn locate(id: &Path) -> Option<(&'static Sdk, PathBuf)> {
+		let within = id.strip_prefix(PREFIX).ok()?;
+		let mut components = within.components();
+		let version = match components.next()? {
+			Component::Normal(version) => find(version.to_str()?)?,
+			_ => return None,
+		};
+
+		Some((version, components.as_path().to_path_buf()))
+	}
+
+	/// File imported from another of the SDK, or a directory of it,
+	/// relative to where it is
+	pub(crate) fn resolve(&self, from: &Path, import: &Path) -> Option<PathBuf> {
+		let dir = match self.contents(from) {
+			Some(_) => from.parent().unwrap_or(Path::new("")),
+			None => from,
+		};
+		let mut file = PathBuf::new();
+		for component in dir.join(import).components() {
+			match component {
+				Component::Normal(part) => file.push(part),
+				Component::ParentDir => {
+					file.pop();
+				}
+				_ => (),
+			}
+		}
+
+		self.contents(&file).map(|_| self.id(&file))
+	}
+
+	/// Source of the file within it, named with `/` whatever the platform
+	pub(crate) fn contents(&self, file: &Path) -> Option<&'static str> {
+		let file = io::slashed(file);
+
+		self.files
+			.iter()
+			.find(|(path, _)| *path == file)
+			.map(|(_, contents)| *contents)
+	}
+}
@@ -0,0 +1,322 @@
+use crate::lint::children;
+use crate::resolver;
+use crate::sdk::Sdk;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use jrsonnet_evaluator::ImportResolver;
+use jrsonnet_interner::IStr;
+use jrsonnet_parser::{BinaryOpType, Expr, FieldName, LocExpr, Member, ObjBody, ParserSettings};
+
+/// Imports followed before giving up, as they may cycle
+const MAX_IMPORTS: usize = 64;
+
+/// Templates of a package, to find what defines the values it renders
+#[derive(Clone, Debug)]
+pub struct Sources {
+	pub main: PathBuf,
+	/// Package roots layered from the one compiled down to the ones it extends
+	pub layers: Vec<PathBuf>,
+	pub lib: PathBuf,
+	/// Other directories searched for libraries, first match winning
+	pub libraries: Vec<PathBuf>,
+	/// Files or directories imports are aliased to, by the name imported
+	pub aliases: BTreeMap<String, PathBuf>,
+	pub vendor: PathBuf,
+	pub sdk: &'static Sdk,
+}
+
+/// Where a value is defined, at a line and column starting at 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Source {
+	pub file: PathBuf,
+	pub line: usize,
+	pub column: usize,
+	/// Path of the field within the file, dot separated, empty when the value
+	/// is the whole file
+	pub field: String,
+}
+
+impl Sources {
+	/// Follows the fields from the main template, through locals, imports,
+	/// and objects added together, to where the value at the end is defined.
+	/// Values computed by functions or conditionals are located at the
+	/// expression computing them, and the ones nowhere to be found as `None`.
+	pub fn locate(&self, fields: &[&str]) -> Option<Source> {
+		let resolver = resolver::layered(
+			&self.layers,
+			&self.lib,
+			&self.libraries,
+			&self.aliases,
+			&self.vendor,
+			self.sdk,
+		);
+		let mut search = Search {
+			resolver: &resolver,
+			files: HashMap::new(),
+			imports: 0,
+		};
+
+		let (file, expr) = search.parse(&self.main)?;
+		let found = search.resolve(&file, &expr, &Scope::default(), fields, &[], 0)?;
+
+		let source = search.files.get(&found.file)?.0.as_str();
+		let before = &source[..found.at.min(source.len())];
+
+		Some(Source {
+			line: before.matches('\n').count() + 1,
+			column: before
+				.rsplit('\n')
+				.next()
+				.unwrap_or_default()
+				.chars()
+				.count() + 1,
+			field: found.fields.join("."),
+			file: found.file,
+		})
+	}
+
+	/// Bytes of the file the main template would import at the path, found
+	/// through the same aliases and library paths, as `importbin` does
+	pub fn read(&self, import: &Path) -> Result<Vec<u8>, String> {
+		let resolver = resolver::layered(
+			&self.layers,
+			&self.lib,
+			&self.libraries,
+			&self.aliases,
+			&self.vendor,
+			self.sdk,
+		);
+		let id = resolver
+			.resolve_file(&self.main, import)
+			.map_err(|err| err.error().to_string())?;
+
+		resolver::read(resolver::file(&id)).map_err(|err| err.to_string())
+	}
+
+	/// Files imported from the entrypoints, directly or through the files
+	/// they import, found through the same aliases and library paths. The
+	/// ones failing to parse or resolve are followed no further.
+	pub fn imported(&self, entrypoints: &[PathBuf]) -> BTreeSet<PathBuf> {
+		let resolver = resolver::layered(
+			&self.layers,
+			&self.lib,
+			&self.libraries,
+			&self.aliases,
+			&self.vendor,
+			self.sdk,
+		);
+
+		let mut imported = BTreeSet::new();
+		let mut pending: Vec<PathBuf> = entrypoints.to_vec();
+		let mut seen: BTreeSet<PathBuf> = entrypoints.iter().cloned().collect();
+
+		while let Some(file) = pending.pop() {
+			let source = match std::fs::read_to_string(&file) {
+				Ok(source) => source,
+				Err(_) => continue,
+			};
+			let settings = ParserSettings {
+				loc_data: false,
+				file_name: Rc::from(file.as_path()),
+			};
+			let expr = match jrsonnet_parser::parse(&source, &settings) {
+				Ok(expr) => expr,
+				Err(_) => continue,
+			};
+
+			let mut found = vec![];
+			imports(&expr, &mut found);
+
+			for (import, parsed) in found {
+				let path = match resolver.resolve_file(&file, &import) {
+					Ok(id) => resolver::file(&id).to_path_buf(),
+					Err(_) => continue,
+				};
+
+				imported.insert(path.clone());
+				if parsed && seen.insert(path.clone()) {
+					pending.push(path);
+				}
+			}
+		}
+
+		imported
+	}
+}
+
+/// Paths imported within the expression, telling whether they're Jsonnet,
+/// and not strings, so their imports are followed too
+fn imports(expr: &LocExpr, found: &mut Vec<(PathBuf, bool)>) {
+	match &*expr.0 {
+		Expr::Import(path) => found.push((path.clone(), true)),
+		Expr::ImportStr(path) => found.push((path.clone(), false)),
+		other => {
+			for child in children(other) {
+				imports(child, found);
+			}
+		}
+	}
+}
+
+/// Locals in scope, along their own scopes, by name
+#[derive(Clone, Default)]
+struct Scope(HashMap<IStr, (LocExpr, Rc<Scope>)>);
+
+struct Found {
+	file: PathBuf,
+	at: usize,
+	fields: Vec<String>,
+}
+
+struct Search<'a> {
+	resolver: &'a dyn ImportResolver,
+	/// Sources and expressions of the files parsed, by path
+	files: HashMap<PathBuf, (String, LocExpr)>,
+	imports: usize,
+}
+
+impl Search<'_> {
+	fn parse(&mut self, path: &Path) -> Option<(PathBuf, LocExpr)> {
+		let path = path.to_path_buf();
+		if let Some((_, expr)) = self.files.get(&path) {
+			return Some((path, expr.clone()));
+		}
+
+		let source = std::fs::read_to_string(&path).ok()?;
+		let settings = ParserSettings {
+			loc_data: true,
+			file_name: Rc::from(path.as_path()),
+		};
+		let expr = jrsonnet_parser::parse(&source, &settings).ok()?;
+
+		self.files.insert(path.clone(), (source, expr.clone()));
+
+		Some((path, expr))
+	}
+
+	fn import(&mut self, from: &Path, import: &Path) -> Option<(PathBuf, LocExpr)> {
+		self.imports += 1;
+		if self.imports > MAX_IMPORTS {
+			return None;
+		}
+
+		let resolved = self.resolver.resolve_file(from, import).ok()?;
+
+		self.parse(resolver::file(&resolved))
+	}
+
+	/// Finds the definition of the value at the fields within the expression,
+	/// `within` being the fields followed so far in the file, and `at` the
+	/// offset of the nearest location as not every expression has one
+	fn resolve(
+		&mut self,
+		file: &Path,
+		expr: &LocExpr,
+		scope: &Scope,
+		fields: &[&str],
+		within: &[String],
+		at: usize,
+	) -> Option<Found> {
+		let at = expr.1.as_ref().map(|location| location.1).unwrap_or(at);
+		let here = || Found {
+			file: file.to_path_buf(),
+			at,
+			fields: within.to_vec(),
+		};
+
+		match &*expr.0 {
+			Expr::Parened(inner) => self.resolve(file, inner, scope, fields, within, at),
+			Expr::LocalExpr(binds, body) => {
+				let scope = bind(scope, binds.iter().map(|bind| (&bind.name, &bind.value)));
+
+				self.resolve(file, body, &scope, fields, within, at)
+			}
+			Expr::Var(name) => match scope.0.get(name) {
+				Some((value, outer)) => {
+					let (value, outer) = (value.clone(), outer.clone());
+
+					self.resolve(file, &value, &outer, fields, within, at)
+				}
+				None => Some(here()),
+			},
+			Expr::Import(path) => {
+				let (imported, expr) = self.import(file, path)?;
+
+				self.resolve(&imported, &expr, &Scope::default(), fields, &[], 0)
+			}
+			// What's added last wins, unless it doesn't have the field
+			Expr::BinaryOp(..) | Expr::ObjExtend(..) if fields.is_empty() => Some(here()),
+			Expr::BinaryOp(left, BinaryOpType::Add, right) => self
+				.resolve(file, right, scope, fields, within, at)
+				.or_else(|| self.resolve(file, left, scope, fields, within, at)),
+			Expr::ObjExtend(left, ObjBody::MemberList(members)) => self
+				.field(file, members, scope, fields, within, at)
+				.or_else(|| self.resolve(file, left, scope, fields, within, at)),
+			Expr::Obj(ObjBody::MemberList(_)) if fields.is_empty() => Some(here()),
+			Expr::Obj(ObjBody::MemberList(members)) => {
+				self.field(file, members, scope, fields, within, at)
+			}
+			_ => Some(here()),
+		}
+	}
+
+	/// Finds the first of the fields among the members, following the rest
+	/// within its value
+	fn field(
+		&mut self,
+		file: &Path,
+		members: &[Member],
+		scope: &Scope,
+		fields: &[&str],
+		within: &[String],
+		at: usize,
+	) -> Option<Found> {
+		let (name, rest) = fields.split_first()?;
+
+		let locals = members.iter().filter_map(|member| match member {
+			Member::BindStmt(bind) => Some((&bind.name, &bind.value)),
+			_ => None,
+		});
+		let scope = bind(scope, locals);
+
+		let field = members.iter().find_map(|member| match member {
+			Member::Field(field) => match &field.name {
+				FieldName::Fixed(fixed) if **fixed == **name => Some(field),
+				_ => None,
+			},
+			_ => None,
+		})?;
+
+		let mut within = within.to_vec();
+		within.push(name.to_string());
+
+		match field.params {
+			Some(_) => Some(Found {
+				file: file.to_path_buf(),
+				at,
+				fields: within,
+			}),
+			None => self.resolve(file, &field.value, &scope, rest, &within, at),
+		}
+	}
+}
+
+/// Scope with the locals added
+fn bind<'a>(scope: &Scope, locals: impl Iterator<Item = (&'a IStr, &'a LocExpr)>) -> Scope {
+	let locals: Vec<(&IStr, &LocExpr)> = locals.collect();
+	if locals.is_empty() {
+		return scope.clone();
+	}
+
+	// Bound values see the outer scope only, enough to follow aliases
+	let outer = Rc::new(scope.clone());
+	let mut bound = scope.clone();
+	for (name, value) in locals {
+		bound.0.insert(name.clone(), (value.clone(), outer.clone()));
+	}
+
+	bound
+}
@@ -0,0 +1,250 @@
+use crate::error::Error;
+use crate::lint::{children, position};
+use crate::VARS_PREFIX;
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::rc::Rc;
+
+use jrsonnet_interner::IStr;
+use jrsonnet_parser::{BindSpec, Expr, LocExpr, Member, ObjBody, ParserSettings};
+
+/// Read of the input at a line and column, both starting at 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+	/// Path within the input, dot separated and empty for the whole input
+	pub path: String,
+	pub line: usize,
+	pub column: usize,
+}
+
+/// Paths within the input the source reads, dot separated and empty for the
+/// whole input, found by following `.input` of `kct.libsonnet`, or its
+/// variable, through indexes and the locals aliasing them
+pub fn inputs(path: &Path, source: &str) -> Result<BTreeSet<String>, Error> {
+	Ok(references(path, source)?
+		.into_iter()
+		.map(|reference| reference.path)
+		.collect())
+}
+
+/// Every read of the input within the source, where it's read
+pub fn references(path: &Path, source: &str) -> Result<Vec<Reference>, Error> {
+	let settings = ParserSettings {
+		loc_data: true,
+		file_name: Rc::from(path),
+	};
+	let expr = jrsonnet_parser::parse(source, &settings)
+		.map_err(|err| Error::Parse(path.display().to_string(), err.to_string()))?;
+
+	let mut read = vec![];
+	walk(&expr, &Scope::new(), &mut read);
+
+	Ok(read
+		.into_iter()
+		.map(|(path, at)| {
+			let (line, column) = position(source, at);
+
+			Reference { path, line, column }
+		})
+		.collect())
+}
+
+/// Paths read, along the offset of the expression reading them
+type Read = Vec<(String, usize)>;
+
+/// Functions of `kct.libsonnet` reading files by glob, from `files`
+const GLOBBING: [&str; 2] = ["files", "filesDigest"];
+/// Functions of `kct.libsonnet` reading files by path, from the package root
+const READING: [&str; 3] = ["readFile", "importBin", "kustomize"];
+
+/// Files of the package the source reads through `kct.libsonnet`, as far as
+/// they're given as literals
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reads {
+	/// Globs within `files`
+	pub globs: Vec<String>,
+	/// Paths from the package root, of files or directories
+	pub paths: Vec<String>,
+}
+
+/// Globs and paths the source reads files with, through any object having
+/// the functions of `kct.libsonnet`, as it's usually aliased
+pub fn reads(path: &Path, source: &str) -> Result<Reads, Error> {
+	let settings = ParserSettings {
+		loc_data: true,
+		file_name: Rc::from(path),
+	};
+	let expr = jrsonnet_parser::parse(source, &settings)
+		.map_err(|err| Error::Parse(path.display().to_string(), err.to_string()))?;
+
+	let mut reads = Reads::default();
+	collect(&expr, &mut reads);
+
+	Ok(reads)
+}
+
+fn collect(expr: &LocExpr, reads: &mut Reads) {
+	if let Expr::Apply(target, args, _) = &*expr.0 {
+		let function = match &*target.0 {
+			Expr::Index(_, name) => match &*name.0 {
+				Expr::Str(name) => Some(name.to_string()),
+				_ => None,
+			},
+			_ => None,
+		};
+		// The first argument is the glob or path, named or not
+		let first = args.iter().find(|arg| {
+			matches!(
+				arg.0.as_deref(),
+				None | Some("glob") | Some("name") | Some("path")
+			)
+		});
+		let literal = first.and_then(|arg| match &*arg.1 .0 {
+			Expr::Str(literal) => Some(literal.to_string()),
+			_ => None,
+		});
+
+		if let (Some(function), Some(literal)) = (function, literal) {
+			if GLOBBING.contains(&function.as_str()) {
+				reads.globs.push(literal);
+			} else if READING.contains(&function.as_str()) {
+				reads.paths.push(literal);
+			}
+		}
+	}
+
+	for child in children(&expr.0) {
+		collect(child, reads);
+	}
+}
+
+/// Locals aliasing paths within the input
+type Scope = HashMap<IStr, Vec<String>>;
+
+fn walk(expr: &LocExpr, scope: &Scope, read: &mut Read) {
+	if let Some(path) = input(expr, scope) {
+		let at = expr
+			.1
+			.as_ref()
+			.map(|location| location.1)
+			.unwrap_or_default();
+		read.push((path.join("."), at));
+
+		return;
+	}
+
+	match &*expr.0 {
+		Expr::LocalExpr(binds, body) => {
+			let scope = bind(scope, binds, read);
+
+			walk(body, &scope, read);
+		}
+		Expr::Obj(ObjBody::MemberList(members)) => {
+			let locals: Vec<BindSpec> = members
+				.iter()
+				.filter_map(|member| match member {
+					Member::BindStmt(bind) => Some(bind.clone()),
+					_ => None,
+				})
+				.collect();
+			let scope = bind(scope, &locals, read);
+
+			for member in members {
+				match member {
+					Member::Field(field) => {
+						let scope = shadow(&scope, field.params.iter().flat_map(|p| p.iter()));
+						walk(&field.value, &scope, read);
+					}
+					Member::AssertStmt(assert) => {
+						walk(&assert.0, &scope, read);
+						if let Some(message) = &assert.1 {
+							walk(message, &scope, read);
+						}
+					}
+					Member::BindStmt(_) => (),
+				}
+			}
+		}
+		Expr::Function(params, body) => walk(body, &shadow(scope, params.iter()), read),
+		_ => {
+			for child in children(&expr.0) {
+				walk(child, scope, read);
+			}
+		}
+	}
+}
+
+/// Scope with the locals, the ones aliasing the input recorded instead of
+/// read, as only reading them through the alias counts
+fn bind(scope: &Scope, binds: &[BindSpec], read: &mut Read) -> Scope {
+	let mut bound = scope.clone();
+
+	for bind in binds {
+		let aliased = match bind.params {
+			None => input(&bind.value, scope),
+			Some(_) => None,
+		};
+
+		match aliased {
+			Some(path) => {
+				bound.insert(bind.name.clone(), path);
+			}
+			None => {
+				bound.remove(&bind.name);
+
+				let inner = shadow(scope, bind.params.iter().flat_map(|p| p.iter()));
+				walk(&bind.value, &inner, read);
+			}
+		}
+	}
+
+	bound
+}
+
+/// Scope without the names the parameters shadow
+fn shadow<'a>(scope: &Scope, params: impl Iterator<Item = &'a jrsonnet_parser::Param>) -> Scope {
+	let mut shadowed = scope.clone();
+	for param in params {
+		shadowed.remove(&param.0);
+	}
+
+	shadowed
+}
+
+/// Path within the input the expression is, if any
+fn input(expr: &LocExpr, scope: &Scope) -> Option<Vec<String>> {
+	match &*expr.0 {
+		Expr::Parened(inner) => input(inner, scope),
+		Expr::Var(name) => scope.get(name).cloned(),
+		Expr::Index(target, field) => {
+			let field = match &*field.0 {
+				Expr::Str(field) => field.to_string(),
+				_ => return input(target, scope),
+			};
+
+			match input(target, scope) {
+				Some(mut path) => {
+					path.push(field);
+
+					Some(path)
+				}
+				None if field == "input" => Some(vec![]),
+				None => None,
+			}
+		}
+		Expr::Apply(target, args, _) => {
+			let is_ext_var = matches!(
+				&*target.0,
+				Expr::Index(std, name) if matches!(&*std.0, Expr::Var(std) if &**std == "std")
+					&& matches!(&*name.0, Expr::Str(name) if &**name == "extVar")
+			);
+			let reads_input = args.iter().any(
+				|arg| matches!(&*arg.1.0, Expr::Str(var) if **var == *format!("{VARS_PREFIX}/input")),
+			);
+
+			(is_ext_var && reads_input).then(Vec::new)
+		}
+		_ => None,
+	}
+}
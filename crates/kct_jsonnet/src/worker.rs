@@ -0,0 +1,95 @@
+use crate::error::Error;
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use jrsonnet_gc::GcConfig;
+use kct_helper::memory::{self, Budget};
+use tracing::trace;
+
+/// Work handing its outcome back once the thread is ready for more
+type Job = Box<dyn FnOnce() -> Deliver + Send>;
+type Deliver = Box<dyn FnOnce()>;
+
+/// Threads done evaluating, waiting for the next template. Each keeps its
+/// heap, the stdlib it parsed, and the strings it interned, so compiling many
+/// times in a process reuses them instead of growing new ones every time
+static IDLE: Mutex<Vec<mpsc::Sender<Job>>> = Mutex::new(Vec::new());
+
+/// How often waiting on the work checks whether it went beyond its budget
+const POLL: Duration = Duration::from_millis(50);
+
+/// Runs the work on an idle thread, or a new one when they're all busy, as
+/// templates including others wait for them on their own thread. The work
+/// is charged to the budget the caller hands, failing once it goes beyond
+/// it without waiting for the work, which stops at its next check and drops
+/// what it held before its thread takes more.
+pub fn run<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T, Error> {
+	let budget = memory::handed();
+	let charging = budget.clone();
+
+	let (tx, rx) = mpsc::channel();
+	let mut job: Job = Box::new(move || {
+		let done = memory::charged(charging, work);
+
+		Box::new(move || {
+			let _ = tx.send(done);
+		})
+	});
+
+	while let Some(worker) = IDLE.lock().ok().and_then(|mut idle| idle.pop()) {
+		match worker.send(job) {
+			Ok(()) => return wait(rx, budget),
+			// Its thread panicked on some earlier work
+			Err(mpsc::SendError(returned)) => job = returned,
+		}
+	}
+
+	let (worker, jobs) = mpsc::channel::<Job>();
+	worker.send(job).unwrap();
+
+	thread::spawn(move || {
+		for job in jobs {
+			let deliver = job();
+			collect();
+
+			// Idle before delivering, so the next compile finds it waiting
+			if let Ok(mut idle) = IDLE.lock() {
+				idle.push(worker.clone());
+			}
+			deliver();
+		}
+	});
+
+	wait(rx, budget)
+}
+
+fn wait<T>(rx: mpsc::Receiver<T>, budget: Option<Arc<Budget>>) -> Result<T, Error> {
+	let budget = match budget {
+		Some(budget) => budget,
+		// Its thread panicked, dropping the work along what it'd send
+		None => return rx.recv().map_err(|_| Error::Stopped),
+	};
+	let exceeded = || Error::MemoryExceeded(budget.limit());
+
+	loop {
+		match rx.recv_timeout(POLL) {
+			// Failing at some check is how going beyond ends the work
+			Ok(_) if budget.exceeded() => return Err(exceeded()),
+			Ok(done) => return Ok(done),
+			Err(mpsc::RecvTimeoutError::Timeout) if budget.exceeded() => return Err(exceeded()),
+			Err(mpsc::RecvTimeoutError::Timeout) => continue,
+			Err(mpsc::RecvTimeoutError::Disconnected) => return Err(Error::Stopped),
+		}
+	}
+}
+
+/// Frees what the last evaluation left behind, and lowers the threshold it
+/// grew, so garbage is collected from the start of the next one again
+fn collect() {
+	jrsonnet_gc::force_collect();
+	jrsonnet_gc::configure(|config| *config = GcConfig::default());
+
+	trace!("collected evaluation");
+}
@@ -1,7 +1,11 @@
-use crate::{Manifest, Tracked};
-
-pub use crate::error::Root as Error;
-
+use crate::depends;
+use crate::error::Changed;
+use crate::merge::{self, Conflicts, Strategy, MANAGER};
+use crate::stamp::{self, Keys};
+use crate::{Check, Conflict, Drift, Manifest, State, Tracked};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -9,66 +13,345 @@ use async_trait::async_trait;
 use either::Either;
 use futures::TryFutureExt;
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition as CRD;
-use kube::api::{Api, DynamicObject as Dynamic, Patch, PatchParams, ResourceExt};
+use kct_helper::ignore::Ignore;
+use kct_helper::lookup::Query;
+use kct_helper::progress::{Event, Progress};
+use kube::api::{Api, DynamicObject as Dynamic, ListParams, Patch, PatchParams, ResourceExt};
+use kube::config::{KubeConfigOptions, Kubeconfig};
 use kube::core::GroupVersionKind;
-use kube::discovery::{Discovery, Scope};
+use kube::discovery::{verbs, Discovery, Scope};
 use kube::runtime::wait::{await_condition, conditions};
-use kube::Client as K8s;
+use kube::{Client as K8s, Config};
 
 pub struct Client {
 	internal: K8s,
 	discovery: Discovery,
+	progress: Progress,
 }
 
 impl Client {
-	pub async fn try_new() -> Result<Self> {
-		let internal = K8s::try_default().await?;
+	pub async fn try_new(context: Option<&str>, progress: Progress) -> Result<Self> {
+		let internal = match context {
+			None => K8s::try_default().await?,
+			Some(context) => {
+				let options = KubeConfigOptions {
+					context: Some(context.to_string()),
+					..Default::default()
+				};
+				let kubeconfig = Kubeconfig::read()?;
+				let config = Config::from_custom_kubeconfig(kubeconfig, &options).await?;
+
+				K8s::try_from(config)?
+			}
+		};
 		let discovery = Discovery::new(internal.clone()).run().await?;
 
 		Ok(Self {
 			internal,
 			discovery,
+			progress,
 		})
 	}
 
-	pub async fn apply(&mut self, manifests: Vec<Manifest>) -> Result<()> {
+	/// Applies the waves one after the other, waiting for the objects others
+	/// depend on to be ready before moving on
+	pub async fn apply(
+		&mut self,
+		waves: Vec<Vec<Manifest>>,
+		keys: &Keys,
+		strategy: &Strategy,
+	) -> Result<()> {
+		let total = waves.iter().map(Vec::len).sum();
+		self.progress.report(Event::Applying { total });
+
+		let awaited: BTreeSet<PathBuf> = waves
+			.iter()
+			.flatten()
+			.flat_map(|manifest| depends::dependencies(manifest.value()))
+			.collect();
+
+		for wave in waves {
+			let awaiting: Vec<Tracked<Dynamic>> = wave
+				.iter()
+				.filter(|manifest| awaited.contains(manifest.path()))
+				.map(|Tracked(path, doc)| {
+					Ok((path.clone(), serde_json::from_value(doc.clone())?).into())
+				})
+				.collect::<Result<_>>()?;
+
+			self.wave(wave, keys, strategy).await?;
+
+			let ready = awaiting.iter().map(|obj| obj.ready(self));
+			let _ = futures::future::try_join_all(ready).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn wave(
+		&mut self,
+		manifests: Vec<Manifest>,
+		keys: &Keys,
+		strategy: &Strategy,
+	) -> Result<()> {
 		let plan = Plan::try_new(manifests)?;
+		// Kinds they define are only discovered afterwards
+		let refresh = !plan.crds.is_empty();
 
 		let ssapply = PatchParams::apply("kct-crds").force();
 		let crds = plan
 			.crds
 			.into_iter()
-			.map(|crd| crd.apply(self, &ssapply).inspect_ok(Client::applied));
+			.map(|crd| crd.apply(self, &ssapply).inspect_ok(|p| self.applied(p)));
 		let _ = futures::future::try_join_all(crds).await?;
 
-		self.refresh().await?;
-		let ssapply = PatchParams::apply("kct-dyns").force();
-		let dynamics = plan
-			.dynamics
-			.into_iter()
-			.map(|obj| obj.apply(self, &ssapply).inspect_ok(Client::applied));
-		let _ = futures::future::try_join_all(dynamics).await?;
+		if refresh {
+			self.refresh().await?;
+		}
+		let ssapply = PatchParams::apply(MANAGER).force();
+		match strategy {
+			Strategy::ServerSide => {
+				let dynamics = plan
+					.dynamics
+					.into_iter()
+					.map(|obj| obj.apply(self, &ssapply).inspect_ok(|p| self.applied(p)));
+				let _ = futures::future::try_join_all(dynamics).await?;
+			}
+			Strategy::ThreeWay(conflicts) => {
+				let dynamics = plan.dynamics.into_iter().map(|obj| {
+					obj.merge(self, &keys.last_applied, conflicts)
+						.inspect_ok(|p| self.applied(p))
+				});
+				let _ = futures::future::try_join_all(dynamics).await?;
+			}
+		}
 
 		Ok(())
 	}
 
-	fn applied(path: &String) {
-		println!("{path} created")
+	/// Submits every object with server-side dry-run, which runs admission webhooks and schema
+	/// validation without persisting anything. Failures don't stop the validation, they're reported
+	/// along the object so the caller gets the whole picture at once. Objects of kinds defined by
+	/// the CRDs being validated are skipped when the cluster doesn't know them yet, as dry-run
+	/// doesn't register the definitions.
+	pub async fn validate(&mut self, manifests: Vec<Manifest>) -> Result<Vec<Check>> {
+		let plan = Plan::try_new(manifests)?;
+		let ssapply = PatchParams::apply("kct-dry").force().dry_run();
+
+		let defined: Vec<(String, String)> = plan
+			.crds
+			.iter()
+			.map(|crd| {
+				let spec = &crd.value().spec;
+
+				(spec.group.clone(), spec.names.kind.clone())
+			})
+			.collect();
+
+		let crds = plan.crds.into_iter().map(|crd| crd.check(self, &ssapply));
+		let mut checks = futures::future::join_all(crds).await;
+
+		let (unknown, known): (Vec<_>, Vec<_>) = plan.dynamics.into_iter().partition(|obj| {
+			let release = obj
+				.value()
+				.types
+				.as_ref()
+				.and_then(|tm| GroupVersionKind::try_from(tm).ok())
+				.is_some_and(|gvk| defined.contains(&(gvk.group, gvk.kind)));
+
+			release && obj.api(self).is_err()
+		});
+
+		let dynamics = known.into_iter().map(|obj| obj.check(self, &ssapply));
+		checks.extend(futures::future::join_all(dynamics).await);
+
+		checks.extend(unknown.into_iter().map(|obj| {
+			let reason =
+				"its kind is defined by a CRD of the release, which dry-run doesn't register";
+
+			Check::skipped(obj.path().to_path_buf(), reason.to_string())
+		}));
+
+		Ok(checks)
+	}
+
+	/// Compares the desired objects with their live counterparts
+	pub async fn status(
+		&mut self,
+		manifests: Vec<Manifest>,
+		ignore: &Ignore,
+	) -> Result<Vec<Drift>> {
+		let plan = Plan::try_new(manifests)?;
+
+		let crds = plan.crds.into_iter().map(|crd| crd.drift(self, ignore));
+		let mut drifts = futures::future::try_join_all(crds).await?;
+
+		let dynamics = plan.dynamics.into_iter().map(|obj| obj.drift(self, ignore));
+		drifts.extend(futures::future::try_join_all(dynamics).await?);
+
+		Ok(drifts)
+	}
+
+	/// Objects carrying the release label that weren't rendered, i.e. left behind by previous
+	/// versions of the release. Kinds that can't be listed are skipped, as they'd only tell about
+	/// more of them.
+	pub async fn extras(&self, selector: &str, manifests: &[Manifest]) -> Result<Vec<Drift>> {
+		let rendered: Vec<(String, Option<String>, String)> = manifests
+			.iter()
+			.filter_map(|Tracked(_, obj)| {
+				let kind = obj.get("kind")?.as_str()?;
+				let metadata = obj.get("metadata")?;
+				let namespace = metadata
+					.get("namespace")
+					.and_then(serde_json::Value::as_str);
+				let name = metadata.get("name")?.as_str()?;
+
+				Some((
+					kind.to_string(),
+					namespace.map(String::from),
+					name.to_string(),
+				))
+			})
+			.collect();
+
+		let params = ListParams::default().labels(selector);
+		let mut drifts = vec![];
+
+		for group in self.discovery.groups() {
+			for (ar, caps) in group.recommended_resources() {
+				if !caps.supports_operation(verbs::LIST) {
+					continue;
+				}
+
+				let api: Api<Dynamic> = Api::all_with(self.internal.clone(), &ar);
+				let found = match api.list(&params).await {
+					Ok(found) => found,
+					Err(_) => continue,
+				};
+
+				// Rendered without a namespace, they're applied to the default one
+				let namespaced = caps.scope == Scope::Namespaced;
+				let default = self.internal.default_namespace();
+
+				for obj in found {
+					let name = obj.name_any();
+					let namespace = obj.namespace();
+					let kept = rendered.iter().any(|(kind, within, named)| {
+						let within = match (within, namespaced) {
+							(None, true) => Some(default),
+							(within, _) => within.as_deref(),
+						};
+
+						*kind == ar.kind && *named == name && within == namespace.as_deref()
+					});
+					if kept {
+						continue;
+					}
+
+					let path = match obj.namespace() {
+						Some(ns) => format!("{}/{ns}/{name}", ar.kind),
+						None => format!("{}/{name}", ar.kind),
+					};
+
+					drifts.push(Drift {
+						path: PathBuf::from(path),
+						state: State::Extra,
+					});
+				}
+			}
+		}
+
+		Ok(drifts)
+	}
+
+	/// Releases with objects in the cluster, by the values of the release
+	/// label. Kinds that can't be listed are skipped, as they'd only tell
+	/// about more of them.
+	pub async fn releases(&self, label: &str) -> Result<BTreeSet<String>> {
+		let params = ListParams::default().labels(label);
+		let mut releases = BTreeSet::new();
+
+		for group in self.discovery.groups() {
+			for (ar, caps) in group.recommended_resources() {
+				if !caps.supports_operation(verbs::LIST) {
+					continue;
+				}
+
+				let api: Api<Dynamic> = Api::all_with(self.internal.clone(), &ar);
+				let found = match api.list(&params).await {
+					Ok(found) => found,
+					Err(_) => continue,
+				};
+
+				releases.extend(
+					found
+						.into_iter()
+						.filter_map(|obj| obj.labels().get(label).cloned()),
+				);
+			}
+		}
+
+		Ok(releases)
+	}
+
+	/// Rendered objects already in the cluster without the release label, or
+	/// with another release in it
+	pub async fn conflicts(
+		&self,
+		keys: &Keys,
+		release: &str,
+		manifests: &[Manifest],
+	) -> Result<Vec<Conflict>> {
+		let mut conflicts = vec![];
+
+		for Tracked(path, doc) in manifests {
+			let obj: Tracked<Dynamic> = (path.clone(), serde_json::from_value(doc.clone())?).into();
+
+			// Kinds the cluster doesn't know yet, e.g. from definitions in the
+			// release, can't have objects
+			let api = match obj.api(self) {
+				Ok(api) => api,
+				Err(_) => continue,
+			};
+
+			let live = match api.get_opt(&obj.value().name_any()).await? {
+				Some(live) => live,
+				None => continue,
+			};
+
+			let owner = live.labels().get(&keys.release).cloned();
+			if owner != Some(stamp::label(release)) {
+				conflicts.push(Conflict {
+					path: path.clone(),
+					owner,
+				});
+			}
+		}
+
+		Ok(conflicts)
+	}
+
+	fn applied(&self, path: &String) {
+		let object = PathBuf::from(path);
+
+		self.progress.report(Event::Applied { object })
 	}
 
 	pub async fn delete(&mut self, mut manifests: Vec<Manifest>) -> Result<()> {
 		manifests.reverse();
 
 		let plan = Plan::try_new(manifests)?;
+		let total = plan.crds.len() + plan.dynamics.len();
+		self.progress.report(Event::Deleting { total });
 
 		let dynamics = plan
 			.dynamics
 			.into_iter()
-			.map(|obj| obj.delete(self).inspect_ok(Client::deleted));
+			.map(|obj| obj.delete(self).inspect_ok(|p| self.deleted(p)));
 		let crds = plan
 			.crds
 			.into_iter()
-			.map(|obj| obj.delete(self).inspect_ok(Client::deleted));
+			.map(|obj| obj.delete(self).inspect_ok(|p| self.deleted(p)));
 
 		let _ = futures::future::try_join_all(dynamics).await?;
 		let _ = futures::future::try_join_all(crds).await?;
@@ -76,8 +359,47 @@ impl Client {
 		Ok(())
 	}
 
-	fn deleted(path: &String) {
-		println!("{path} deleted")
+	fn deleted(&self, path: &String) {
+		let object = PathBuf::from(path);
+
+		self.progress.report(Event::Deleted { object })
+	}
+
+	/// Reads live objects, listing all of a kind, within the namespace if any, when there's no name
+	pub async fn find(&self, query: &Query) -> Result<Option<serde_json::Value>> {
+		let (group, version) = match query.api_version.split_once('/') {
+			Some((group, version)) => (group, version),
+			None => ("", query.api_version.as_str()),
+		};
+
+		let gvk = GroupVersionKind::gvk(group, version, &query.kind);
+		let (ar, caps) = self.discovery.resolve_gvk(&gvk).ok_or(anyhow::anyhow!(
+			"unable to find {} in {}",
+			query.kind,
+			query.api_version
+		))?;
+
+		let client = self.internal.clone();
+		let api: Api<Dynamic> = match (&caps.scope, &query.namespace) {
+			(Scope::Namespaced, Some(namespace)) => Api::namespaced_with(client, namespace, &ar),
+			(Scope::Namespaced, None) if query.name.is_some() => {
+				Api::default_namespaced_with(client, &ar)
+			}
+			_ => Api::all_with(client, &ar),
+		};
+
+		match &query.name {
+			Some(name) => {
+				let found = api.get_opt(name).await?;
+
+				Ok(found.map(serde_json::to_value).transpose()?)
+			}
+			None => {
+				let found = api.list(&ListParams::default()).await?;
+
+				Ok(Some(serde_json::to_value(found.items)?))
+			}
+		}
 	}
 
 	async fn refresh(&mut self) -> Result<()> {
@@ -87,6 +409,37 @@ impl Client {
 	}
 }
 
+/// Time objects others depend on have to become ready
+const READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Condition types telling objects are ready, as workloads and most custom
+/// resources report them
+const READY_CONDITIONS: [&str; 3] = ["Ready", "Available", "Established"];
+
+/// Whether the object caught up with its latest generation and its readiness
+/// conditions hold, objects without either being ready once they exist
+fn ready(obj: &Dynamic) -> bool {
+	let status = &obj.data["status"];
+
+	let observed = status["observedGeneration"].as_i64();
+	if let (Some(generation), Some(observed)) = (obj.metadata.generation, observed) {
+		if observed < generation {
+			return false;
+		}
+	}
+
+	status["conditions"]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter(|condition| {
+			condition["type"]
+				.as_str()
+				.is_some_and(|kind| READY_CONDITIONS.contains(&kind))
+		})
+		.all(|condition| condition["status"] == "True")
+}
+
 struct Plan {
 	crds: Vec<Tracked<CRD>>,
 	dynamics: Vec<Tracked<Dynamic>>,
@@ -123,6 +476,10 @@ trait Object {
 
 	async fn apply(self, client: &Client, params: &PatchParams) -> Result<String>;
 
+	async fn check(self, client: &Client, params: &PatchParams) -> Check;
+
+	async fn drift(self, client: &Client, ignore: &Ignore) -> Result<Drift>;
+
 	async fn delete(self, client: &Client) -> Result<String>;
 
 	fn api(&self, client: &Client) -> Result<Api<Self::Kind>>;
@@ -143,6 +500,28 @@ impl Object for Tracked<Dynamic> {
 		Ok(format!("{}", self.path().display()))
 	}
 
+	async fn check(self, client: &Client, params: &PatchParams) -> Check {
+		let path = self.path().to_path_buf();
+		let result = self.apply(client, params).await;
+
+		Check::new(path, result.map(|_| ()))
+	}
+
+	async fn drift(self, client: &Client, ignore: &Ignore) -> Result<Drift> {
+		let name = self.value().name_any();
+		let live = self.api(client)?.get_opt(&name).await?;
+
+		let desired = serde_json::to_value(self.value())?;
+		let live = live.map(serde_json::to_value).transpose()?;
+
+		Ok(Drift::new(
+			self.path().to_path_buf(),
+			&desired,
+			live.as_ref(),
+			ignore,
+		))
+	}
+
 	async fn delete(self, client: &Client) -> Result<String> {
 		let name = self.value().name_any();
 		let api = self.api(client)?;
@@ -180,6 +559,69 @@ impl Object for Tracked<Dynamic> {
 	}
 }
 
+impl Tracked<Dynamic> {
+	/// Waits for the object to be ready, as far as its status tells, for
+	/// [READY_TIMEOUT] at most
+	async fn ready(&self, client: &Client) -> Result<()> {
+		let name = self.value().name_any();
+		let api = self.api(client)?;
+
+		let ready = await_condition(api, &name, |obj: Option<&Dynamic>| obj.is_some_and(ready));
+
+		tokio::time::timeout(READY_TIMEOUT, ready)
+			.await
+			.map_err(|_| {
+				anyhow::anyhow!(
+					"{} wasn't ready within {}s, objects depend on it",
+					self.path().display(),
+					READY_TIMEOUT.as_secs()
+				)
+			})??;
+
+		Ok(())
+	}
+
+	/// Patches the object with the three-way merge against the last applied
+	/// one, annotated under the key, recording the rendered one for the next
+	/// time. Objects not in the cluster yet are applied as they are.
+	async fn merge(self, client: &Client, key: &str, conflicts: &Conflicts) -> Result<String> {
+		let name = self.value().name_any();
+		let api = self.api(client)?;
+
+		let desired = serde_json::to_value(self.value())?;
+		let record = BTreeMap::from([(key.to_string(), serde_json::to_string(&desired)?)]);
+
+		let live = match api.get_opt(&name).await? {
+			Some(live) => serde_json::to_value(live)?,
+			None => {
+				let mut desired = desired;
+				stamp::insert(&mut desired, "annotations", record);
+
+				let ssapply = PatchParams::apply(MANAGER).force();
+				let _ = api.patch(&name, &ssapply, &Patch::Apply(desired)).await?;
+
+				return Ok(format!("{}", self.path().display()));
+			}
+		};
+
+		let last = live["metadata"]["annotations"][key]
+			.as_str()
+			.and_then(|last| serde_json::from_str::<serde_json::Value>(last).ok());
+
+		let mut patch = merge::three_way(last.as_ref(), &desired, &live, conflicts)
+			.map_err(|Changed(fields)| Changed(format!("{} {fields}", self.path().display())))?;
+		stamp::insert(&mut patch, "annotations", record);
+
+		let params = PatchParams {
+			field_manager: Some(MANAGER.to_string()),
+			..Default::default()
+		};
+		let _ = api.patch(&name, &params, &Patch::Merge(patch)).await?;
+
+		Ok(format!("{}", self.path().display()))
+	}
+}
+
 #[async_trait]
 impl Object for Tracked<CRD> {
 	type Kind = CRD;
@@ -207,6 +649,39 @@ impl Object for Tracked<CRD> {
 		Ok(format!("{}", self.path().display()))
 	}
 
+	// A dry-run doesn't create the definition, so we can't wait for it to be established
+	async fn check(self, client: &Client, params: &PatchParams) -> Check {
+		let path = self.path().to_path_buf();
+		let name = self.value().name_any();
+		let patch = Patch::Apply(self.value());
+
+		let result = match self.api(client) {
+			Ok(api) => api
+				.patch(&name, params, &patch)
+				.await
+				.map(|_| ())
+				.map_err(|err| anyhow::anyhow!(err)),
+			Err(err) => Err(err),
+		};
+
+		Check::new(path, result)
+	}
+
+	async fn drift(self, client: &Client, ignore: &Ignore) -> Result<Drift> {
+		let name = self.value().name_any();
+		let live = self.api(client)?.get_opt(&name).await?;
+
+		let desired = serde_json::to_value(self.value())?;
+		let live = live.map(serde_json::to_value).transpose()?;
+
+		Ok(Drift::new(
+			self.path().to_path_buf(),
+			&desired,
+			live.as_ref(),
+			ignore,
+		))
+	}
+
 	async fn delete(self, client: &Client) -> Result<String> {
 		let name = self.value().name_any();
 		let api = self.api(client)?;
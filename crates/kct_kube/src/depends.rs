@@ -0,0 +1,70 @@
+use crate::error::Dependency;
+use crate::Manifest;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+/// Annotation holding the paths of the objects one must come after, as
+/// `sdk.dependsOn` sets it
+pub const ANNOTATION: &str = "kct.io/depends-on";
+
+/// Paths of the objects the object depends on
+pub fn dependencies(object: &Value) -> Vec<PathBuf> {
+	object["metadata"]["annotations"][ANNOTATION]
+		.as_str()
+		.unwrap_or_default()
+		.split(',')
+		.map(str::trim)
+		.filter(|path| !path.is_empty())
+		.map(PathBuf::from)
+		.collect()
+}
+
+/// Groups the objects into waves, each only depending on the ones before it,
+/// keeping the order they were rendered in within each
+pub fn waves(manifests: Vec<Manifest>) -> Result<Vec<Vec<Manifest>>, Dependency> {
+	let paths: BTreeSet<PathBuf> = manifests.iter().map(|m| m.path().to_path_buf()).collect();
+
+	let mut pending: Vec<(Manifest, Vec<PathBuf>)> = vec![];
+	for manifest in manifests {
+		let dependencies = dependencies(manifest.value());
+
+		if let Some(unknown) = dependencies.iter().find(|path| !paths.contains(*path)) {
+			return Err(Dependency::Unknown(
+				manifest.path().display().to_string(),
+				unknown.display().to_string(),
+			));
+		}
+
+		pending.push((manifest, dependencies));
+	}
+
+	let mut waves = vec![];
+	let mut placed: BTreeSet<PathBuf> = BTreeSet::new();
+
+	while !pending.is_empty() {
+		let (ready, blocked): (Vec<_>, Vec<_>) = pending
+			.into_iter()
+			.partition(|(_, deps)| deps.iter().all(|dependency| placed.contains(dependency)));
+
+		if ready.is_empty() {
+			let cycle: Vec<String> = blocked
+				.iter()
+				.map(|(manifest, _)| manifest.path().display().to_string())
+				.collect();
+
+			return Err(Dependency::Cycle(cycle.join(", ")));
+		}
+
+		for (manifest, _) in &ready {
+			placed.insert(manifest.path().to_path_buf());
+		}
+
+		waves.push(ready.into_iter().map(|(manifest, _)| manifest).collect());
+		pending = blocked;
+	}
+
+	Ok(waves)
+}
@@ -8,8 +8,22 @@ pub enum Root {
 	Output(#[from] Output),
 	#[error("Your object is invalid")]
 	Object(#[from] Object),
+	#[error("Your dependencies are invalid")]
+	Dependency(#[from] Dependency),
 }
 
+/// Rendered objects already in the cluster outside of the release, as their
+/// paths along who owns them
+#[derive(Error, Debug)]
+#[error("Objects already exist outside of the release: {0}")]
+pub struct Conflict(pub String);
+
+/// Fields other managers changed since the last apply that the policies
+/// refuse to overwrite, as their paths along who changed them
+#[derive(Error, Debug)]
+#[error("Fields were changed outside of the release since the last apply: {0}")]
+pub struct Changed(pub String);
+
 #[derive(Error, Debug)]
 pub enum Output {
 	#[error("The path({0}) is invalid, it has to follow RFC 1123")]
@@ -26,6 +40,14 @@ pub enum Object {
 	Tracking(#[from] Tracking),
 }
 
+#[derive(Error, Debug)]
+pub enum Dependency {
+	#[error("{0} depends on {1}, which isn't rendered")]
+	Unknown(String, String),
+	#[error("The objects depend on each other: {0}")]
+	Cycle(String),
+}
+
 #[derive(Error, Debug)]
 pub enum Tracking {
 	#[error("Tracking needs to have 3 parts consisting of `field:depth:order`")]
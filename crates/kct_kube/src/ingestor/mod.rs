@@ -128,7 +128,7 @@ impl Ingestor {
 
 				members.push((tracking.track(track), v))
 			} else {
-				return Err(error::Output::Path(k.to_string()))?;
+				Err(error::Output::Path(k.to_string()))?;
 			}
 		}
 
@@ -1,22 +1,63 @@
 mod client;
+mod depends;
 mod ingestor;
+mod lookup;
 
 pub mod error;
+pub mod merge;
+pub mod scope;
+pub mod stamp;
 
 use self::client::Client;
 use self::ingestor::Ingestor;
+use self::merge::Strategy;
+use self::stamp::{Keys, Stamp};
 
 pub use crate::error::Root as Error;
+pub use crate::lookup::Cluster;
 
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use kct_helper::ignore::Ignore;
+use kct_helper::progress::{self, Progress};
 use once_cell::sync::Lazy;
 use serde_json::Value;
 use valico::json_schema::Scope;
 
 pub type Manifest = Tracked<Value>;
 
+/// Whether the error happened while reaching the cluster, like when it's
+/// unreachable, refuses the request, or isn't configured
+pub fn is_unreachable(err: &anyhow::Error) -> bool {
+	err.chain().any(|cause| {
+		cause.is::<kube::Error>()
+			|| cause.is::<kube::config::KubeconfigError>()
+			|| cause.is::<kube::config::InferConfigError>()
+	})
+}
+
+/// Contexts of the kubeconfig, none when there's no kubeconfig to read
+pub fn contexts() -> Vec<String> {
+	kube::config::Kubeconfig::read()
+		.map(|config| {
+			config
+				.contexts
+				.into_iter()
+				.map(|named| named.name)
+				.collect()
+		})
+		.unwrap_or_default()
+}
+
+/// Releases with objects in the cluster of the context, told by their labels
+pub async fn releases(context: Option<&str>, keys: &Keys) -> Result<BTreeSet<String>> {
+	let client = Client::try_new(context, progress::silent()).await?;
+
+	client.releases(&keys.release).await
+}
+
 #[derive(Debug)]
 pub struct Tracked<T>(PathBuf, T);
 
@@ -83,9 +124,100 @@ impl From<Manifest> for (PathBuf, String) {
 	}
 }
 
+/// Outcome of validating an object against the cluster, which couldn't
+/// when it tells why it was skipped
+#[derive(Debug)]
+pub struct Check {
+	pub path: PathBuf,
+	pub error: Option<String>,
+	pub skipped: Option<String>,
+}
+
+impl Check {
+	fn new(path: PathBuf, result: Result<()>) -> Self {
+		let error = result.err().map(|err| format!("{err:#}"));
+
+		Check {
+			path,
+			error,
+			skipped: None,
+		}
+	}
+
+	fn skipped(path: PathBuf, reason: String) -> Self {
+		Check {
+			path,
+			error: None,
+			skipped: Some(reason),
+		}
+	}
+
+	pub fn passed(&self) -> bool {
+		self.error.is_none()
+	}
+}
+
+/// How an object in the cluster diverges from what was rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+	Synced,
+	Missing,
+	Modified(Vec<String>),
+	Extra,
+}
+
+#[derive(Debug)]
+pub struct Drift {
+	pub path: PathBuf,
+	pub state: State,
+}
+
+impl Drift {
+	fn new(path: PathBuf, desired: &Value, live: Option<&Value>, ignore: &Ignore) -> Self {
+		let state = match live {
+			None => State::Missing,
+			Some(live) => {
+				let (mut desired, mut live) = (desired.clone(), live.clone());
+				ignore.strip(&mut desired);
+				ignore.strip(&mut live);
+
+				let fields = kct_helper::json::diverging(&desired, &live);
+
+				if fields.is_empty() {
+					State::Synced
+				} else {
+					State::Modified(fields)
+				}
+			}
+		};
+
+		Drift { path, state }
+	}
+}
+
+/// Rendered object found in the cluster under another release, or none
+#[derive(Debug)]
+pub struct Conflict {
+	pub path: PathBuf,
+	pub owner: Option<String>,
+}
+
+impl Conflict {
+	pub fn describe(&self) -> String {
+		match &self.owner {
+			Some(owner) => format!("{} (release {owner})", self.path.display()),
+			None => format!("{} (no release)", self.path.display()),
+		}
+	}
+}
+
 pub struct Kube {
 	value: Value,
 	ingestor: Ingestor,
+	progress: Progress,
+	stamp: Option<Stamp>,
+	stamped: Option<String>,
+	context: Option<String>,
 }
 
 impl Kube {
@@ -93,19 +225,96 @@ impl Kube {
 		Default::default()
 	}
 
+	/// Kubeconfig context where the objects go, the current one when absent
+	pub fn context(&self) -> Option<&str> {
+		self.context.as_deref()
+	}
+
+	/// Label selector matching the objects of the release, when there's one
+	pub fn selector(&self) -> Option<String> {
+		match &self.stamp {
+			Some(stamp) => stamp.selector(),
+			None => self.stamped.clone(),
+		}
+	}
+
 	pub fn render(&self) -> Result<Vec<Manifest>, Error> {
-		self.ingestor.ingest(&self.value)
+		let mut manifests = self.ingestor.ingest(&self.value)?;
+
+		if let Some(stamp) = &self.stamp {
+			for Tracked(_, object) in manifests.iter_mut() {
+				stamp.apply(object);
+			}
+		}
+
+		Ok(manifests)
+	}
+
+	/// Objects grouped into the waves they're applied in, each waiting on the
+	/// ones before it that others depend on
+	pub fn waves(&self) -> Result<Vec<Vec<Manifest>>, Error> {
+		Ok(depends::waves(self.render()?)?)
+	}
+
+	/// Applies the objects by the strategy, refusing the ones already in the
+	/// cluster outside of the release unless taking their ownership
+	pub async fn install(self, take_ownership: bool, strategy: &Strategy) -> Result<()> {
+		let mut client = Client::try_new(self.context.as_deref(), self.progress.clone()).await?;
+		let manifests = self.render()?;
+
+		// Two releases applying the same objects would silently undo each other
+		let stamped = self.stamp.as_ref().filter(|_| !take_ownership);
+		if let Some(Stamp {
+			keys,
+			release: Some(release),
+			..
+		}) = stamped
+		{
+			let conflicts = client.conflicts(keys, release, &manifests).await?;
+
+			if !conflicts.is_empty() {
+				let described: Vec<String> = conflicts.iter().map(Conflict::describe).collect();
+
+				return Err(error::Conflict(described.join(", ")).into());
+			}
+		}
+
+		let keys = self
+			.stamp
+			.as_ref()
+			.map(|stamp| stamp.keys.clone())
+			.unwrap_or_default();
+
+		client
+			.apply(depends::waves(manifests)?, &keys, strategy)
+			.await
+	}
+
+	pub async fn validate(self) -> Result<Vec<Check>> {
+		let mut client = Client::try_new(self.context.as_deref(), self.progress.clone()).await?;
+		let manifests = self.render()?;
+
+		client.validate(manifests).await
 	}
 
-	pub async fn install(self) -> Result<()> {
-		let mut client = Client::try_new().await?;
+	/// Compares the objects with the live ones, leaving out the fields ignored
+	pub async fn status(self, ignore: &Ignore) -> Result<Vec<Drift>> {
+		let mut client = Client::try_new(self.context.as_deref(), self.progress.clone()).await?;
 		let manifests = self.render()?;
+		let selector = self.selector();
+
+		let mut drifts = match selector {
+			Some(selector) => client.extras(&selector, &manifests).await?,
+			None => vec![],
+		};
+
+		drifts.splice(0..0, client.status(manifests, ignore).await?);
 
-		client.apply(manifests).await
+		Ok(drifts)
 	}
 
 	pub async fn uninstall(self) -> Result<()> {
-		let mut client = Client::try_new().await?;
+		let mut client = Client::try_new(self.context.as_deref(), self.progress.clone()).await?;
 		let manifests = self.render()?;
 
 		client.delete(manifests).await
@@ -125,6 +334,10 @@ pub struct Builder {
 	value: Option<Value>,
 	only: Vec<PathBuf>,
 	except: Vec<PathBuf>,
+	progress: Option<Progress>,
+	stamp: Option<Stamp>,
+	stamped: Option<String>,
+	context: Option<String>,
 }
 
 impl Builder {
@@ -151,10 +364,43 @@ impl Builder {
 		self
 	}
 
+	pub fn progress(mut self, progress: Progress) -> Self {
+		self.progress = Some(progress);
+
+		self
+	}
+
+	pub fn stamp(mut self, stamp: Stamp) -> Self {
+		self.stamp = Some(stamp);
+
+		self
+	}
+
+	/// Objects were stamped when rendered, by the release the selector matches
+	pub fn stamped(mut self, selector: Option<String>) -> Self {
+		self.stamped = selector;
+
+		self
+	}
+
+	pub fn context(mut self, context: Option<String>) -> Self {
+		self.context = context;
+
+		self
+	}
+
 	pub fn build(self) -> Result<Kube, Error> {
 		let value = self.value.ok_or(Error::MissingValue)?;
 		let ingestor = Ingestor::new(self.only, self.except);
-
-		Ok(Kube { ingestor, value })
+		let progress = self.progress.unwrap_or_else(progress::silent);
+
+		Ok(Kube {
+			ingestor,
+			value,
+			progress,
+			stamp: self.stamp,
+			stamped: self.stamped,
+			context: self.context,
+		})
 	}
 }
@@ -0,0 +1,31 @@
+use crate::client::Client;
+
+use anyhow::Result;
+use kct_helper::lookup::{Finder, Query};
+use kct_helper::progress;
+use serde_json::Value;
+use tokio::runtime::Handle;
+use tokio::task;
+
+/// Cluster templates can read while compiling, which happens synchronously,
+/// so requests block on the runtime that connected to it
+pub struct Cluster {
+	client: Client,
+	handle: Handle,
+}
+
+impl Cluster {
+	pub fn connect(context: Option<&str>) -> Result<Self> {
+		let handle = Handle::try_current()?;
+		let client =
+			task::block_in_place(|| handle.block_on(Client::try_new(context, progress::silent())))?;
+
+		Ok(Self { client, handle })
+	}
+}
+
+impl Finder for Cluster {
+	fn find(&self, query: &Query) -> Result<Option<Value>> {
+		task::block_in_place(|| self.handle.block_on(self.client.find(query)))
+	}
+}
@@ -0,0 +1,207 @@
+use crate::error::Changed;
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+/// Manager the objects are patched with, so its own changes aren't taken as
+/// the ones of others
+pub const MANAGER: &str = "kct-dyns";
+
+/// How objects are put in the cluster
+#[derive(Debug, Clone, Default)]
+pub enum Strategy {
+	/// Server-side apply, forcing the fields the objects set
+	#[default]
+	ServerSide,
+	/// Merge of the last applied object, the rendered one, and the live one,
+	/// settling the fields others changed since by the policies
+	ThreeWay(Conflicts),
+}
+
+/// What to do with fields another manager changed since the last apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+	/// Set them as rendered
+	#[default]
+	Overwrite,
+	/// Leave them as they're live
+	Keep,
+	/// Refuse to apply
+	Fail,
+}
+
+impl FromStr for Policy {
+	type Err = String;
+
+	fn from_str(policy: &str) -> Result<Self, Self::Err> {
+		match policy {
+			"overwrite" => Ok(Policy::Overwrite),
+			"keep" => Ok(Policy::Keep),
+			"fail" => Ok(Policy::Fail),
+			_ => Err(format!("{policy} isn't one of overwrite, keep, or fail")),
+		}
+	}
+}
+
+/// Policies by the manager that changed the field, `*` standing for the ones
+/// not named and for fields without a manager
+#[derive(Debug, Clone, Default)]
+pub struct Conflicts(BTreeMap<String, Policy>);
+
+impl Conflicts {
+	pub fn policy(&self, manager: Option<&str>) -> Policy {
+		manager
+			.and_then(|manager| self.0.get(manager))
+			.or_else(|| self.0.get("*"))
+			.copied()
+			.unwrap_or_default()
+	}
+}
+
+impl FromIterator<(String, Policy)> for Conflicts {
+	fn from_iter<I: IntoIterator<Item = (String, Policy)>>(iter: I) -> Self {
+		Conflicts(iter.into_iter().collect())
+	}
+}
+
+/// JSON merge patch taking the live object to the rendered one. Fields the
+/// last applied object had that are no longer rendered are removed, the ones
+/// it never had are left to whoever set them, like controllers scaling
+/// replicas. Lists are replaced as a whole, as merge patches do.
+pub fn three_way(
+	last: Option<&Value>,
+	desired: &Value,
+	live: &Value,
+	conflicts: &Conflicts,
+) -> Result<Value, Changed> {
+	let mut merge = Merge {
+		recorded: last.is_some(),
+		live,
+		conflicts,
+		path: vec![],
+		changed: vec![],
+	};
+
+	let empty = Map::new();
+	let patch = merge.diff(
+		last.and_then(Value::as_object),
+		desired.as_object().unwrap_or(&empty),
+		live.as_object().unwrap_or(&empty),
+	);
+
+	match merge.changed.is_empty() {
+		true => Ok(Value::Object(patch)),
+		false => Err(Changed(merge.changed.join(", "))),
+	}
+}
+
+struct Merge<'a> {
+	/// Whether there's a last applied object to tell others' changes apart
+	recorded: bool,
+	live: &'a Value,
+	conflicts: &'a Conflicts,
+	path: Vec<String>,
+	changed: Vec<String>,
+}
+
+impl Merge<'_> {
+	fn diff(
+		&mut self,
+		last: Option<&Map<String, Value>>,
+		desired: &Map<String, Value>,
+		live: &Map<String, Value>,
+	) -> Map<String, Value> {
+		let mut patch = Map::new();
+
+		for (key, wanted) in desired {
+			self.path.push(key.clone());
+			let before = last.and_then(|last| last.get(key));
+
+			match (wanted, live.get(key)) {
+				(Value::Object(wanted), Some(Value::Object(current))) => {
+					let nested = self.diff(before.and_then(Value::as_object), wanted, current);
+
+					if !nested.is_empty() {
+						patch.insert(key.clone(), Value::Object(nested));
+					}
+				}
+				(wanted, Some(current)) if wanted == current => (),
+				(wanted, current) => {
+					if self.settle(before, current) {
+						patch.insert(key.clone(), wanted.clone());
+					}
+				}
+			}
+
+			self.path.pop();
+		}
+
+		let dropped = last
+			.into_iter()
+			.flatten()
+			.filter(|(key, _)| !desired.contains_key(*key));
+
+		for (key, before) in dropped {
+			let current = match live.get(key) {
+				Some(current) => current,
+				None => continue,
+			};
+
+			self.path.push(key.clone());
+			if self.settle(Some(before), Some(current)) {
+				patch.insert(key.clone(), Value::Null);
+			}
+			self.path.pop();
+		}
+
+		patch
+	}
+
+	/// Whether the field at the path should be patched, as nobody else changed
+	/// it since the last apply or the policy of who did says so
+	fn settle(&mut self, before: Option<&Value>, current: Option<&Value>) -> bool {
+		if !self.recorded || current.is_none() || current == before {
+			return true;
+		}
+
+		let manager = manager(self.live, &self.path);
+		match self.conflicts.policy(manager.as_deref()) {
+			Policy::Overwrite => true,
+			Policy::Keep => false,
+			Policy::Fail => {
+				let path = format!("/{}", self.path.join("/"));
+
+				self.changed.push(match manager {
+					Some(manager) => format!("{path} ({manager})"),
+					None => path,
+				});
+
+				false
+			}
+		}
+	}
+}
+
+/// Latest manager of the field besides kct, by the managed fields of the live
+/// object
+fn manager(live: &Value, path: &[String]) -> Option<String> {
+	let entries = live.pointer("/metadata/managedFields")?.as_array()?;
+
+	entries
+		.iter()
+		.filter(|entry| entry["manager"] != MANAGER)
+		.filter(|entry| {
+			let mut fields = &entry["fieldsV1"];
+
+			path.iter().all(|key| {
+				fields = &fields[format!("f:{key}")];
+
+				!fields.is_null()
+			})
+		})
+		.max_by_key(|entry| entry["time"].as_str().unwrap_or_default())
+		.and_then(|entry| entry["manager"].as_str())
+		.map(String::from)
+}
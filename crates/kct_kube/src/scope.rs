@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Kinds Kubernetes has outside of namespaces, by their API group
+const CLUSTER_SCOPED: [(&str, &str); 24] = [
+	("", "Namespace"),
+	("", "Node"),
+	("", "PersistentVolume"),
+	("", "ComponentStatus"),
+	("rbac.authorization.k8s.io", "ClusterRole"),
+	("rbac.authorization.k8s.io", "ClusterRoleBinding"),
+	("apiextensions.k8s.io", "CustomResourceDefinition"),
+	("apiregistration.k8s.io", "APIService"),
+	(
+		"admissionregistration.k8s.io",
+		"MutatingWebhookConfiguration",
+	),
+	(
+		"admissionregistration.k8s.io",
+		"ValidatingWebhookConfiguration",
+	),
+	("admissionregistration.k8s.io", "ValidatingAdmissionPolicy"),
+	(
+		"admissionregistration.k8s.io",
+		"ValidatingAdmissionPolicyBinding",
+	),
+	("storage.k8s.io", "StorageClass"),
+	("storage.k8s.io", "CSIDriver"),
+	("storage.k8s.io", "CSINode"),
+	("storage.k8s.io", "VolumeAttachment"),
+	("scheduling.k8s.io", "PriorityClass"),
+	("node.k8s.io", "RuntimeClass"),
+	("networking.k8s.io", "IngressClass"),
+	("certificates.k8s.io", "CertificateSigningRequest"),
+	("policy", "PodSecurityPolicy"),
+	("flowcontrol.apiserver.k8s.io", "FlowSchema"),
+	("flowcontrol.apiserver.k8s.io", "PriorityLevelConfiguration"),
+	("resource.k8s.io", "DeviceClass"),
+];
+
+/// Paths of the objects outside of namespaces, by the kinds Kubernetes has
+/// and the ones the definitions among the objects declare as `Cluster`.
+/// Other custom kinds can't be told apart without the cluster, so they're
+/// taken as namespaced.
+pub fn cluster_scoped<'a>(
+	objects: impl IntoIterator<Item = (&'a Path, &'a Value)> + Clone,
+) -> Vec<PathBuf> {
+	let defined: BTreeSet<(String, String)> = objects
+		.clone()
+		.into_iter()
+		.filter(|(_, object)| {
+			object["kind"] == "CustomResourceDefinition" && object["spec"]["scope"] == "Cluster"
+		})
+		.filter_map(|(_, object)| {
+			let group = object["spec"]["group"].as_str()?;
+			let kind = object["spec"]["names"]["kind"].as_str()?;
+
+			Some((group.to_string(), kind.to_string()))
+		})
+		.collect();
+
+	objects
+		.into_iter()
+		.filter(|(_, object)| {
+			let kind = object["kind"].as_str().unwrap_or_default();
+			let group = match object["apiVersion"]
+				.as_str()
+				.unwrap_or_default()
+				.split_once('/')
+			{
+				Some((group, _version)) => group,
+				None => "",
+			};
+
+			CLUSTER_SCOPED.contains(&(group, kind))
+				|| defined.contains(&(group.to_string(), kind.to_string()))
+		})
+		.map(|(path, _)| path.to_path_buf())
+		.collect()
+}
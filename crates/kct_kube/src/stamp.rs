@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+pub const DEFAULT_PREFIX: &str = "kct.io";
+
+/// Longest value labels take
+const MAX_LABEL: usize = 63;
+
+/// Keys used to mark objects as belonging to a release
+///
+/// The release and package are labels, so tools can select objects by them, while the version and
+/// hash are annotations because their values don't always fit the label rules. The last applied
+/// object is only annotated when merging with it.
+#[derive(Debug, Clone)]
+pub struct Keys {
+	pub release: String,
+	pub package: String,
+	pub version: String,
+	pub hash: String,
+	pub last_applied: String,
+}
+
+impl Keys {
+	pub fn prefixed(prefix: &str) -> Self {
+		Keys {
+			release: format!("{prefix}/release"),
+			package: format!("{prefix}/package"),
+			version: format!("{prefix}/version"),
+			hash: format!("{prefix}/hash"),
+			last_applied: format!("{prefix}/last-applied"),
+		}
+	}
+}
+
+impl Default for Keys {
+	fn default() -> Self {
+		Keys::prefixed(DEFAULT_PREFIX)
+	}
+}
+
+/// Tracking information injected into every rendered object
+#[derive(Debug, Clone, Default)]
+pub struct Stamp {
+	pub keys: Keys,
+	pub release: Option<String>,
+	pub package: Option<String>,
+	pub version: Option<String>,
+}
+
+impl Stamp {
+	/// Label selector matching the objects of the stamped release
+	pub fn selector(&self) -> Option<String> {
+		self.release
+			.as_ref()
+			.map(|release| format!("{}={}", self.keys.release, label(release)))
+	}
+
+	pub fn apply(&self, object: &mut Value) {
+		let hash = hash(object);
+
+		let mut labels = BTreeMap::new();
+		if let Some(release) = &self.release {
+			labels.insert(self.keys.release.clone(), label(release));
+		}
+		if let Some(package) = &self.package {
+			labels.insert(self.keys.package.clone(), label(package));
+		}
+
+		let mut annotations = BTreeMap::new();
+		if let Some(version) = &self.version {
+			annotations.insert(self.keys.version.clone(), version.clone());
+		}
+		annotations.insert(self.keys.hash.clone(), hash);
+
+		insert(object, "labels", labels);
+		insert(object, "annotations", annotations);
+	}
+}
+
+/// Digest of the object as rendered, before any stamp, so it only changes along the package
+pub fn hash(object: &Value) -> String {
+	let contents = serde_json::to_vec(object).unwrap_or_default();
+	let digest = Sha256::digest(contents);
+
+	digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Value as labels take it: alphanumeric at both ends, with dashes,
+/// underscores, and dots between, and 63 characters at most. Values that
+/// don't fit are suffixed with their digest, so they stay apart.
+pub fn label(value: &str) -> String {
+	let fits = value.len() <= MAX_LABEL
+		&& value
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+		&& value.starts_with(|c: char| c.is_ascii_alphanumeric())
+		&& value.ends_with(|c: char| c.is_ascii_alphanumeric());
+	if fits || value.is_empty() {
+		return value.to_string();
+	}
+
+	let digest = Sha256::digest(value.as_bytes());
+	let digest: String = digest[..4]
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect();
+
+	let replaced: String = value
+		.chars()
+		.map(
+			|c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+				true => c,
+				false => '-',
+			},
+		)
+		.take(MAX_LABEL - digest.len() - 1)
+		.collect();
+	let trimmed = replaced.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+
+	match trimmed.is_empty() {
+		true => digest,
+		false => format!("{trimmed}-{digest}"),
+	}
+}
+
+pub(crate) fn insert(object: &mut Value, field: &str, entries: BTreeMap<String, String>) {
+	let metadata = match object {
+		Value::Object(object) => object
+			.entry("metadata")
+			.or_insert_with(|| Value::Object(Map::new())),
+		_ => return,
+	};
+
+	if !metadata.is_object() {
+		*metadata = Value::Object(Map::new());
+	}
+
+	let target = match metadata {
+		Value::Object(metadata) => metadata
+			.entry(field)
+			.or_insert_with(|| Value::Object(Map::new())),
+		_ => return,
+	};
+
+	if !target.is_object() {
+		*target = Value::Object(Map::new());
+	}
+
+	if let Value::Object(target) = target {
+		for (key, value) in entries {
+			target.insert(key, Value::String(value));
+		}
+	}
+}
@@ -26,7 +26,7 @@ fn assert_manifests(ok: Return, times: usize) {
 	assert!(ok.is_ok());
 
 	let manifest = manifest();
-	let manifests: Vec<Value> = iter::repeat(manifest).take(times).collect();
+	let manifests: Vec<Value> = iter::repeat_n(manifest, times).collect();
 
 	let rendered: Vec<Value> = ok
 		.unwrap()
@@ -513,3 +513,350 @@ mod filter {
 		assert_manifests(found, 0);
 	}
 }
+
+mod stamp {
+	use super::*;
+
+	use kct_kube::stamp::{self, Keys, Stamp};
+
+	fn stamped(val: Value, stamp: Stamp) -> Vec<Value> {
+		let kube = Kube::builder().value(val).stamp(stamp).build().unwrap();
+		let manifests: Vec<Manifest> = kube.try_into().unwrap();
+
+		manifests
+			.into_iter()
+			.map(|manifest| {
+				let (_, val): (PathBuf, Value) = manifest.into();
+
+				val
+			})
+			.collect()
+	}
+
+	#[test]
+	fn marks_objects_with_release() {
+		let stamp = Stamp {
+			release: Some(String::from("rc")),
+			package: Some(String::from("kcp")),
+			version: Some(String::from("1.0.0")),
+			..Default::default()
+		};
+
+		let found = stamped(json!({"a": manifest()}), stamp);
+
+		assert_eq!(
+			found,
+			vec![json!({
+				"kind": "Deployment",
+				"apiVersion": "apps/v1",
+				"metadata": {
+					"labels": {
+						"kct.io/release": "rc",
+						"kct.io/package": "kcp",
+					},
+					"annotations": {
+						"kct.io/version": "1.0.0",
+						"kct.io/hash": stamp::hash(&manifest()),
+					}
+				}
+			})]
+		);
+	}
+
+	#[test]
+	fn uses_custom_keys() {
+		let stamp = Stamp {
+			keys: Keys::prefixed("example.com"),
+			release: Some(String::from("rc")),
+			..Default::default()
+		};
+
+		let found = stamped(json!({"a": manifest()}), stamp.clone());
+		let labels = &found[0]["metadata"]["labels"];
+
+		assert_eq!(labels, &json!({"example.com/release": "rc"}));
+		assert_eq!(
+			stamp.selector(),
+			Some(String::from("example.com/release=rc"))
+		);
+	}
+
+	#[test]
+	fn fits_values_into_labels() {
+		let long = "a".repeat(80);
+		let fitted = stamp::label(&long);
+
+		assert_eq!(stamp::label("web"), "web");
+		assert!(fitted.len() <= 63);
+		assert_ne!(fitted, stamp::label(&"a".repeat(81)));
+		assert!(stamp::label("my release/v1")
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')));
+	}
+
+	#[test]
+	fn hashes_only_rendered_contents() {
+		let stamp = Stamp::default();
+
+		let first = stamped(json!({"a": manifest()}), stamp.clone());
+		let second = stamped(json!({"b": manifest()}), stamp);
+
+		assert_eq!(
+			first[0]["metadata"]["annotations"]["kct.io/hash"],
+			second[0]["metadata"]["annotations"]["kct.io/hash"]
+		);
+	}
+}
+
+mod merge {
+	use super::*;
+
+	use kct_kube::merge::{three_way, Conflicts, Policy};
+
+	fn deployment(replicas: u64, image: &str) -> Value {
+		json!({
+			"kind": "Deployment",
+			"apiVersion": "apps/v1",
+			"spec": { "replicas": replicas, "image": image }
+		})
+	}
+
+	/// Live deployment whose replicas the autoscaler set
+	fn scaled(replicas: u64, image: &str) -> Value {
+		let mut live = deployment(replicas, image);
+		live["metadata"] = json!({
+			"managedFields": [
+				{ "manager": "kct-dyns", "time": "2024-01-01T00:00:00Z", "fieldsV1": { "f:spec": { "f:replicas": {}, "f:image": {} } } },
+				{ "manager": "hpa", "time": "2024-01-02T00:00:00Z", "fieldsV1": { "f:spec": { "f:replicas": {} } } }
+			]
+		});
+		live["status"] = json!({ "ready": replicas });
+
+		live
+	}
+
+	fn conflicts(policy: Policy) -> Conflicts {
+		[(String::from("hpa"), policy)].into_iter().collect()
+	}
+
+	#[test]
+	fn patches_only_what_changed() {
+		let last = deployment(2, "app:1");
+		let live = scaled(2, "app:1");
+
+		let patch = three_way(
+			Some(&last),
+			&deployment(2, "app:2"),
+			&live,
+			&Default::default(),
+		);
+
+		assert_eq!(patch.unwrap(), json!({ "spec": { "image": "app:2" } }));
+	}
+
+	#[test]
+	fn removes_fields_no_longer_rendered() {
+		let mut last = deployment(2, "app:1");
+		last["spec"]["paused"] = json!(true);
+		let mut live = scaled(2, "app:1");
+		live["spec"]["paused"] = json!(true);
+		live["spec"]["strategy"] = json!("Recreate");
+
+		let patch = three_way(
+			Some(&last),
+			&deployment(2, "app:1"),
+			&live,
+			&Default::default(),
+		);
+
+		assert_eq!(patch.unwrap(), json!({ "spec": { "paused": null } }));
+	}
+
+	#[test]
+	fn settles_changes_of_other_managers_by_policy() {
+		let last = deployment(2, "app:1");
+		let desired = deployment(2, "app:2");
+		let live = scaled(5, "app:1");
+
+		let overwritten = three_way(Some(&last), &desired, &live, &conflicts(Policy::Overwrite));
+		let kept = three_way(Some(&last), &desired, &live, &conflicts(Policy::Keep));
+		let failed = three_way(Some(&last), &desired, &live, &conflicts(Policy::Fail));
+
+		assert_eq!(
+			overwritten.unwrap(),
+			json!({ "spec": { "replicas": 2, "image": "app:2" } })
+		);
+		assert_eq!(kept.unwrap(), json!({ "spec": { "image": "app:2" } }));
+		assert_matches!(failed, Err(error::Changed(fields)) if fields == "/spec/replicas (hpa)");
+	}
+
+	#[test]
+	fn falls_back_to_any_manager() {
+		let last = deployment(2, "app:1");
+		let live = scaled(5, "app:1");
+		let conflicts = [(String::from("*"), Policy::Keep)].into_iter().collect();
+
+		let patch = three_way(Some(&last), &deployment(2, "app:1"), &live, &conflicts);
+
+		assert_eq!(patch.unwrap(), json!({}));
+	}
+
+	#[test]
+	fn overwrites_without_a_last_applied_object() {
+		let live = scaled(5, "app:1");
+
+		let patch = three_way(
+			None,
+			&deployment(2, "app:1"),
+			&live,
+			&conflicts(Policy::Fail),
+		);
+
+		assert_eq!(patch.unwrap(), json!({ "spec": { "replicas": 2 } }));
+	}
+}
+
+mod depends {
+	use super::*;
+
+	fn waves(val: Value) -> Result<Vec<Vec<PathBuf>>, Error> {
+		let kube = Kube::builder().value(val).build()?;
+		let waves = kube.waves()?;
+
+		Ok(waves
+			.into_iter()
+			.map(|wave| wave.iter().map(|m| m.path().to_path_buf()).collect())
+			.collect())
+	}
+
+	fn paths(waves: &[&[&str]]) -> Vec<Vec<PathBuf>> {
+		waves
+			.iter()
+			.map(|wave| wave.iter().map(PathBuf::from).collect())
+			.collect()
+	}
+
+	#[test]
+	fn applies_dependents_after_their_dependencies() {
+		let val = render(
+			r#"{
+				cluster: sdk.dependsOn(['/operator/deployment'], { a: manifest('Cluster'), b: manifest('Cluster') }),
+				operator: { crd: manifest('CustomResourceDefinition'), deployment: manifest() },
+				backup: sdk.dependsOn(['/cluster/a'], manifest('Backup')),
+			}"#,
+		);
+
+		assert_eq!(
+			waves(val).unwrap(),
+			paths(&[
+				&["/operator/crd", "/operator/deployment"],
+				&["/cluster/a", "/cluster/b"],
+				&["/backup"],
+			])
+		);
+	}
+
+	#[test]
+	fn adds_to_existing_dependencies() {
+		let val = render(
+			r#"{
+				a: manifest(),
+				b: manifest(),
+				c: sdk.dependsOn(['/b'], sdk.dependsOn(['/a'], manifest())),
+			}"#,
+		);
+
+		let kube = Kube::builder().value(val).build().unwrap();
+		let manifests = kube.render().unwrap();
+
+		assert_eq!(
+			manifests[2].value()["metadata"]["annotations"]["kct.io/depends-on"],
+			"/a,/b"
+		);
+	}
+
+	#[test]
+	fn refuses_unknown_dependencies() {
+		let val = render(r#"{ a: sdk.dependsOn(['/b'], manifest()) }"#);
+
+		assert_matches!(
+			waves(val),
+			Err(Error::Dependency(error::Dependency::Unknown(path, dependency))) if path == "/a" && dependency == "/b"
+		);
+	}
+
+	#[test]
+	fn refuses_cycles() {
+		let val = render(
+			r#"{
+				a: sdk.dependsOn(['/b'], manifest()),
+				b: sdk.dependsOn(['/a'], manifest()),
+				c: manifest(),
+			}"#,
+		);
+
+		assert_matches!(
+			waves(val),
+			Err(Error::Dependency(error::Dependency::Cycle(paths))) if paths == "/a, /b"
+		);
+	}
+}
+
+mod scope {
+	use super::*;
+
+	use std::path::Path;
+
+	use kct_kube::scope::cluster_scoped;
+
+	#[test]
+	fn finds_cluster_scoped_objects() {
+		let objects = [
+			(
+				"/role",
+				json!({"kind": "ClusterRole", "apiVersion": "rbac.authorization.k8s.io/v1"}),
+			),
+			(
+				"/namespace",
+				json!({"kind": "Namespace", "apiVersion": "v1"}),
+			),
+			("/deployment", manifest()),
+			(
+				"/crd",
+				json!({
+					"kind": "CustomResourceDefinition",
+					"apiVersion": "apiextensions.k8s.io/v1",
+					"spec": { "group": "example.com", "scope": "Cluster", "names": { "kind": "Tenant" } }
+				}),
+			),
+			(
+				"/tenant",
+				json!({"kind": "Tenant", "apiVersion": "example.com/v1"}),
+			),
+			(
+				"/backup",
+				json!({"kind": "Backup", "apiVersion": "example.com/v1"}),
+			),
+			(
+				"/fake",
+				json!({"kind": "ClusterRole", "apiVersion": "example.com/v1"}),
+			),
+		];
+
+		let found = cluster_scoped(
+			objects
+				.iter()
+				.map(|(path, object)| (Path::new(*path), object)),
+		);
+
+		assert_eq!(
+			found,
+			vec![
+				PathBuf::from("/role"),
+				PathBuf::from("/namespace"),
+				PathBuf::from("/crd"),
+				PathBuf::from("/tenant"),
+			]
+		);
+	}
+}
@@ -0,0 +1,116 @@
+use std::convert::TryFrom;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kct_package::Package;
+use kct_testing::dir::TempDir;
+use kct_testing::Fixture;
+use serde_json::{json, Value};
+
+/// Libraries importing each other, one after the other
+const DEPTH: usize = 64;
+/// Templates within the files folder, rendered on every compile
+const TEMPLATES: usize = 256;
+/// Objects rendered, each holding a string of the size below
+const OBJECTS: usize = 128;
+const OBJECT_SIZE: usize = 64 * 1024;
+
+/// Fixture package with the files, taking any object as input, and the one
+/// compiling it as its example
+fn fixture(files: Vec<(String, String)>, input: Value) -> TempDir {
+	let schema = json!({ "type": "object" }).to_string();
+	let example = input.to_string();
+	let files = files
+		.iter()
+		.map(|(path, contents)| (path.as_str(), contents.as_str()))
+		.chain([("schema.json", schema.as_str()), ("example.json", &example)])
+		.collect();
+
+	Fixture::custom(files, vec![])
+}
+
+/// Libraries each adding a field over the one it imports, so resolving the
+/// main template walks the whole chain
+fn deep_imports() -> TempDir {
+	let mut files = vec![];
+	for depth in 0..DEPTH {
+		let contents = match depth {
+			0 => String::from("{ level0: 0 }"),
+			_ => format!(
+				"(import 'level{}.libsonnet') + {{ level{depth}: {depth} }}",
+				depth - 1
+			),
+		};
+
+		files.push((format!("lib/level{depth}.libsonnet"), contents));
+	}
+
+	let main = format!(
+		"local levels = import 'level{}.libsonnet';\n\n{{ levels: {{ apiVersion: 'v1', kind: 'ConfigMap', metadata: {{ name: 'levels' }}, data: levels }} }}",
+		DEPTH - 1
+	);
+	files.push((String::from("templates/main.jsonnet"), main));
+
+	fixture(files, json!({}))
+}
+
+/// Templates looping over the input, all rendered by a single glob
+fn file_templates() -> TempDir {
+	let mut files = vec![];
+	for template in 0..TEMPLATES {
+		let contents =
+			"[settings]\n{% for key, value in settings %}{{ key }} = {{ value }}\n{% endfor %}";
+
+		files.push((
+			format!("files/settings/{template}.ini"),
+			contents.to_string(),
+		));
+	}
+
+	let main = "local _ = import 'kct.libsonnet';\n\n{ settings: { apiVersion: 'v1', kind: 'ConfigMap', metadata: { name: 'settings' }, data: { files: _.files('settings/*.ini', _.input) } } }";
+	files.push((String::from("templates/main.jsonnet"), main.to_string()));
+
+	let settings: Value = (0..32)
+		.map(|setting| (format!("key{setting}"), json!(format!("value{setting}"))))
+		.collect::<serde_json::Map<String, Value>>()
+		.into();
+
+	fixture(files, json!({ "settings": settings }))
+}
+
+/// Many objects holding large strings, as dashboards in ConfigMaps do
+fn big_outputs() -> TempDir {
+	let main = format!(
+		"local _ = import 'kct.libsonnet';\n\n{{ ['dashboard' + i]: {{ apiVersion: 'v1', kind: 'ConfigMap', metadata: {{ name: 'dashboard-' + i }}, data: {{ dashboard: _.input.dashboard }} }} for i in std.range(1, {OBJECTS}) }}"
+	);
+	let files = vec![(String::from("templates/main.jsonnet"), main)];
+
+	fixture(files, json!({ "dashboard": "x".repeat(OBJECT_SIZE) }))
+}
+
+/// Loads the package and compiles it with its example, as every command does
+fn compile(dir: &TempDir) -> Value {
+	let package = Package::try_from(dir.path()).unwrap();
+	let input = package.example.clone();
+
+	package.compile(input, None).unwrap()
+}
+
+fn benches(c: &mut Criterion) {
+	let mut group = c.benchmark_group("compile");
+	group.sample_size(20);
+
+	let packages = [
+		("deep_imports", deep_imports()),
+		("file_templates", file_templates()),
+		("big_outputs", big_outputs()),
+	];
+
+	for (name, dir) in &packages {
+		group.bench_function(*name, |b| b.iter(|| compile(dir)));
+	}
+
+	group.finish();
+}
+
+criterion_group!(compile_path, benches);
+criterion_main!(compile_path);
@@ -0,0 +1,588 @@
+use crate::error::Error;
+use crate::lock::VENDOR_DIR;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use kct_helper::io;
+use tar::{Archive, Builder, Entry, EntryType, Header};
+
+use super::SPEC_FILE;
+
+/// How symbolic links are packed and unpacked, as archives may come from
+/// anywhere. Links pointing outside of the package are refused either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Symlinks {
+	/// Packs what links point to, vendored dependencies being the only ones
+	/// that may be outside of the package, as local ones are linked from
+	/// anywhere. Links in archives are unpacked as links, the default
+	#[default]
+	Follow,
+	/// Packs links as links, unpacking them as links as well
+	Preserve,
+	/// Refuses links, packing and unpacking
+	Reject,
+}
+
+/// Bounds on what archives may hold once decompressed, as a small archive
+/// may unpack into far more than it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+	/// Entries, directories and links included
+	pub entries: u64,
+	/// Bytes of a single file
+	pub file: u64,
+	/// Bytes of every file together
+	pub total: u64,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			entries: 10_000,
+			file: 64 * 1024 * 1024,
+			total: 256 * 1024 * 1024,
+		}
+	}
+}
+
+/// Bytes tar takes around each entry at most, its header, long name, and
+/// padding
+const ENTRY_OVERHEAD: u64 = 4096;
+
+/// Unpacks a gzipped tarball with a package into the directory, returning
+/// the package root. Archives holding the package within a single directory,
+/// as `tar -czf package.tgz package` does, have it as their root. Entries
+/// escaping the directory, either by their paths or as links, and the ones
+/// Windows couldn't write are refused before anything is written, as are
+/// archives going beyond the limits.
+pub fn unpack(
+	archive: impl Read,
+	into: &Path,
+	symlinks: Symlinks,
+	limits: Limits,
+) -> Result<PathBuf, Error> {
+	// Read whole first, so entries are checked before anything is written
+	let tarball = decompress(archive, &limits)?;
+	check(&tarball, symlinks, limits)?;
+
+	extract(&tarball, into)
+}
+
+/// Refuses the tarball when any of its entries would be refused unpacking it
+pub(crate) fn check(tarball: &[u8], symlinks: Symlinks, limits: Limits) -> Result<(), Error> {
+	let invalid = |err: std::io::Error| Error::InvalidArchive(err.to_string());
+	let mut tally = Tally::new(limits);
+	let mut linked = BTreeMap::new();
+
+	for entry in Archive::new(tarball).entries().map_err(invalid)? {
+		let entry = entry.map_err(invalid)?;
+		let path = entry.path().map_err(invalid)?;
+		portable(&path)?;
+		tally.count(&path, &entry)?;
+
+		let path: PathBuf = path
+			.components()
+			.filter(|component| matches!(component, Component::Normal(_)))
+			.collect();
+		through(&path, &linked)?;
+
+		if let Some(target) = link(&entry, symlinks)? {
+			let kind = entry.header().entry_type();
+			let target = resolve(&path, &target, kind, &linked)?;
+
+			if kind.is_symlink() {
+				linked.insert(path, target);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes the checked tarball into the directory, returning the package root
+pub(crate) fn extract(tarball: &[u8], into: &Path) -> Result<PathBuf, Error> {
+	let invalid = |err: std::io::Error| Error::InvalidArchive(err.to_string());
+
+	Archive::new(tarball).unpack(into).map_err(invalid)?;
+
+	root(into)
+}
+
+/// Root of the package unpacked into the directory, either itself or the
+/// single directory within it
+pub(crate) fn root(into: &Path) -> Result<PathBuf, Error> {
+	if into.join(SPEC_FILE).exists() {
+		return Ok(into.to_path_buf());
+	}
+
+	let entries: Vec<PathBuf> = fs::read_dir(into)
+		.map_err(|err| Error::InvalidArchive(err.to_string()))?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.collect();
+
+	match entries.as_slice() {
+		[root] if root.join(SPEC_FILE).exists() => Ok(root.clone()),
+		_ => Err(Error::NoSpec),
+	}
+}
+
+/// Reads a gzipped tarball with a package into memory, by the paths of its
+/// files within the package root, found as `unpack` does. Nothing is written,
+/// so archives can be looked into where the filesystem is read-only. Links
+/// are read as the files they point to, which counts towards the limits.
+pub fn read(
+	archive: impl Read,
+	symlinks: Symlinks,
+	limits: Limits,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>, Error> {
+	let invalid = |err: std::io::Error| Error::InvalidArchive(err.to_string());
+
+	let tarball = decompress(archive, &limits)?;
+	let mut tally = Tally::new(limits);
+	let mut files = BTreeMap::new();
+	let mut links = vec![];
+	let mut linked = BTreeMap::new();
+	let mut archive = Archive::new(tarball.as_slice());
+
+	for entry in archive.entries().map_err(invalid)? {
+		let mut entry = entry.map_err(invalid)?;
+		let path = entry.path().map_err(invalid)?.into_owned();
+		let escapes = path
+			.components()
+			.any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+		if escapes {
+			return Err(Error::InvalidArchive(format!(
+				"{} is outside of the package",
+				path.display()
+			)));
+		}
+
+		portable(&path)?;
+		tally.count(&path, &entry)?;
+		let path: PathBuf = path.components().collect();
+		through(&path, &linked)?;
+
+		if let Some(target) = link(&entry, symlinks)? {
+			let kind = entry.header().entry_type();
+			let target = resolve(&path, &target, kind, &linked)?;
+
+			if kind.is_symlink() {
+				linked.insert(path.clone(), target.clone());
+			}
+			links.push((path, target));
+			continue;
+		}
+
+		if !entry.header().entry_type().is_file() {
+			continue;
+		}
+
+		let mut contents = vec![];
+		entry.read_to_end(&mut contents).map_err(invalid)?;
+		files.insert(path, contents);
+	}
+
+	follow(&mut files, links)?;
+	tally.followed(&files)?;
+
+	if files.contains_key(Path::new(SPEC_FILE)) {
+		return Ok(files);
+	}
+
+	let roots: Vec<&Path> = files
+		.keys()
+		.filter(|path| path.ends_with(SPEC_FILE) && path.components().count() == 2)
+		.filter_map(|path| path.parent())
+		.collect();
+
+	match roots.as_slice() {
+		[root] if files.keys().all(|path| path.starts_with(root)) => {
+			let root = root.to_path_buf();
+
+			Ok(files
+				.into_iter()
+				.filter_map(|(path, contents)| {
+					let path = path.strip_prefix(&root).ok()?.to_path_buf();
+					Some((path, contents))
+				})
+				.collect())
+		}
+		_ => Err(Error::NoSpec),
+	}
+}
+
+/// Decompressed tarball, refused once it's larger than the limits allow for
+/// the files and what tar takes around them
+pub(crate) fn decompress(archive: impl Read, limits: &Limits) -> Result<Vec<u8>, Error> {
+	let bound = limits
+		.entries
+		.saturating_mul(ENTRY_OVERHEAD)
+		.saturating_add(limits.total);
+
+	let mut tarball = vec![];
+	GzDecoder::new(archive)
+		.take(bound.saturating_add(1))
+		.read_to_end(&mut tarball)
+		.map_err(|err| Error::InvalidArchive(err.to_string()))?;
+
+	match tarball.len() as u64 > bound {
+		true => Err(Error::ArchiveTooLarge(format!(
+			"it decompresses into more than {bound} bytes"
+		))),
+		false => Ok(tarball),
+	}
+}
+
+/// What the archive holds so far, counted against the limits
+struct Tally {
+	limits: Limits,
+	entries: u64,
+	total: u64,
+}
+
+impl Tally {
+	fn new(limits: Limits) -> Self {
+		Self {
+			limits,
+			entries: 0,
+			total: 0,
+		}
+	}
+
+	fn count<R: Read>(&mut self, path: &Path, entry: &Entry<'_, R>) -> Result<(), Error> {
+		let limits = &self.limits;
+
+		self.entries += 1;
+		if self.entries > limits.entries {
+			return Err(Error::ArchiveTooLarge(format!(
+				"it has more than {} entries",
+				limits.entries
+			)));
+		}
+
+		// Their holes would take more than the archive tells
+		if entry.header().entry_type().is_gnu_sparse() {
+			return Err(Error::InvalidArchive(format!(
+				"{} is a sparse file, which isn't taken",
+				path.display()
+			)));
+		}
+
+		let size = entry.size();
+		if size > limits.file {
+			return Err(Error::ArchiveTooLarge(format!(
+				"{} has {size} bytes, more than the {} of a file",
+				path.display(),
+				limits.file
+			)));
+		}
+
+		self.total = self.total.saturating_add(size);
+		if self.total > limits.total {
+			return Err(Error::ArchiveTooLarge(format!(
+				"its files have more than {} bytes",
+				limits.total
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// Counts the files again once links are followed, as each copies what
+	/// it points to
+	fn followed(&self, files: &BTreeMap<PathBuf, Vec<u8>>) -> Result<(), Error> {
+		let total: u64 = files.values().map(|contents| contents.len() as u64).sum();
+
+		match total > self.limits.total {
+			true => Err(Error::ArchiveTooLarge(format!(
+				"its files have more than {} bytes once links are followed",
+				self.limits.total
+			))),
+			false => Ok(()),
+		}
+	}
+}
+
+/// Target of the entry when it's a link, refusing it when links aren't taken
+fn link<R: Read>(entry: &Entry<'_, R>, symlinks: Symlinks) -> Result<Option<PathBuf>, Error> {
+	let kind = entry.header().entry_type();
+	if !kind.is_symlink() && !kind.is_hard_link() {
+		return Ok(None);
+	}
+
+	let path = entry
+		.path()
+		.map(|path| path.display().to_string())
+		.unwrap_or_default();
+	if symlinks == Symlinks::Reject {
+		return Err(Error::InvalidArchive(format!(
+			"{path} is a link, which isn't taken"
+		)));
+	}
+
+	match entry.link_name() {
+		Ok(Some(target)) => Ok(Some(target.into_owned())),
+		_ => Err(Error::InvalidArchive(format!("{path} links to nothing"))),
+	}
+}
+
+/// Refuses entries at or within symbolic links unpacked before them, as
+/// they'd be written wherever the links point
+fn through(path: &Path, linked: &BTreeMap<PathBuf, PathBuf>) -> Result<(), Error> {
+	match path
+		.ancestors()
+		.find(|ancestor| linked.contains_key(*ancestor))
+	{
+		Some(link) => Err(Error::InvalidArchive(format!(
+			"{} goes through the link {}",
+			path.display(),
+			link.display()
+		))),
+		None => Ok(()),
+	}
+}
+
+/// Where the link at the path points within the archive, relative to the
+/// link for symbolic ones and to the root for hard ones, refusing the ones
+/// escaping it. Going through symbolic links unpacked before goes where they
+/// point, as the filesystem would.
+fn resolve(
+	path: &Path,
+	target: &Path,
+	kind: EntryType,
+	linked: &BTreeMap<PathBuf, PathBuf>,
+) -> Result<PathBuf, Error> {
+	let base = match kind.is_hard_link() {
+		true => Path::new(""),
+		false => path.parent().unwrap_or(Path::new("")),
+	};
+
+	let mut resolved = PathBuf::new();
+	for component in base.components().chain(target.components()) {
+		if let Some(linked) = linked.get(&resolved) {
+			resolved = linked.clone();
+		}
+
+		let within = match component {
+			Component::Normal(name) => {
+				resolved.push(name);
+				true
+			}
+			Component::CurDir => true,
+			Component::ParentDir => resolved.pop(),
+			Component::RootDir | Component::Prefix(_) => false,
+		};
+
+		if !within {
+			return Err(Error::InvalidArchive(format!(
+				"{} links to {}, outside of the package",
+				path.display(),
+				target.display()
+			)));
+		}
+	}
+
+	Ok(resolved)
+}
+
+/// Copies what the links point to into their paths, in as many passes as
+/// links pointing to links need
+fn follow(
+	files: &mut BTreeMap<PathBuf, Vec<u8>>,
+	mut links: Vec<(PathBuf, PathBuf)>,
+) -> Result<(), Error> {
+	while !links.is_empty() {
+		let pending = links.len();
+
+		links.retain(|(path, target)| {
+			let linked: Vec<(PathBuf, Vec<u8>)> = files
+				.iter()
+				.filter_map(|(file, contents)| {
+					let within = file.strip_prefix(target).ok()?;
+					let linked = match within.as_os_str().is_empty() {
+						true => path.clone(),
+						false => path.join(within),
+					};
+
+					Some((linked, contents.clone()))
+				})
+				.collect();
+
+			let resolved = !linked.is_empty();
+			files.extend(linked);
+
+			!resolved
+		});
+
+		if links.len() == pending {
+			let (path, target) = &links[0];
+
+			return Err(Error::InvalidArchive(format!(
+				"{} links to {}, which the package doesn't have",
+				path.display(),
+				target.display()
+			)));
+		}
+	}
+
+	Ok(())
+}
+
+/// Packs the package at the root into a gzipped tarball, within a directory
+/// named after the prefix so it can be unpacked as is. Entries are sorted and
+/// stripped of times and owners, so the same contents make the same archive.
+/// Git metadata is left out.
+pub fn pack(root: &Path, prefix: &str, symlinks: Symlinks, into: impl Write) -> Result<(), Error> {
+	let invalid = |err: std::io::Error| Error::InvalidArchive(err.to_string());
+
+	let mut packer = Packer {
+		builder: Builder::new(GzEncoder::new(into, Compression::default())),
+		root,
+		canonical: io::canonical(root).map_err(invalid)?,
+		symlinks,
+	};
+	packer.append(root, Path::new(prefix)).map_err(invalid)?;
+
+	packer
+		.builder
+		.into_inner()
+		.and_then(|encoder| encoder.finish())
+		.map_err(invalid)?;
+
+	Ok(())
+}
+
+/// Archive being packed, along the root its links are checked against
+struct Packer<'a, W: Write> {
+	builder: Builder<GzEncoder<W>>,
+	root: &'a Path,
+	/// Where the root is once links are followed
+	canonical: PathBuf,
+	symlinks: Symlinks,
+}
+
+impl<W: Write> Packer<'_, W> {
+	fn append(&mut self, dir: &Path, path: &Path) -> std::io::Result<()> {
+		let mut entries = fs::read_dir(dir)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<std::io::Result<Vec<PathBuf>>>()?;
+		entries.sort();
+
+		let mut header = Header::new_gnu();
+		header.set_entry_type(EntryType::Directory);
+		header.set_mode(0o755);
+		header.set_size(0);
+		self.builder
+			.append_data(&mut header, path, std::io::empty())?;
+
+		for entry in entries {
+			let file_name = entry.file_name().unwrap_or_default();
+			let name = path.join(file_name);
+
+			if entry.file_name() == Some(".git".as_ref()) {
+				continue;
+			}
+
+			// Archives are meant to be unpacked anywhere, Windows included
+			io::portable(&file_name.to_string_lossy())
+				.map_err(|reason| refused(format!("{}: {reason}", entry.display())))?;
+
+			if fs::symlink_metadata(&entry)?.file_type().is_symlink()
+				&& !self.linked(&entry, &name)?
+			{
+				continue;
+			}
+
+			if entry.is_dir() {
+				self.append(&entry, &name)?;
+			} else if entry.is_file() {
+				let contents = fs::read(&entry)?;
+
+				let mut header = Header::new_gnu();
+				header.set_entry_type(EntryType::Regular);
+				header.set_mode(0o644);
+				header.set_size(contents.len() as u64);
+				self.builder
+					.append_data(&mut header, name, contents.as_slice())?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Whether what the link points to should be packed in its place, having
+	/// packed it as a link otherwise
+	fn linked(&mut self, entry: &Path, name: &Path) -> std::io::Result<bool> {
+		let relative = entry.strip_prefix(self.root).unwrap_or(entry);
+		let target = fs::read_link(entry)?;
+
+		match self.symlinks {
+			Symlinks::Reject => Err(refused(format!(
+				"{} is a symbolic link, which isn't packed",
+				entry.display()
+			))),
+			Symlinks::Preserve => {
+				resolve(relative, &target, EntryType::Symlink, &BTreeMap::new())
+					.map_err(|err| refused(err.to_string()))?;
+
+				let mut header = Header::new_gnu();
+				header.set_entry_type(EntryType::Symlink);
+				header.set_mode(0o777);
+				header.set_size(0);
+				self.builder
+					.append_link(&mut header, name, io::slashed(&target))?;
+
+				Ok(false)
+			}
+			Symlinks::Follow => {
+				// Dangling ones have nothing to pack
+				let followed = match io::canonical(entry) {
+					Ok(followed) => followed,
+					Err(_) => return Ok(false),
+				};
+
+				let vendored = relative.starts_with(VENDOR_DIR);
+				if !vendored && !followed.starts_with(&self.canonical) {
+					return Err(refused(format!(
+						"{} links to {}, outside of the package",
+						entry.display(),
+						target.display()
+					)));
+				}
+
+				let parent = entry.parent().map(io::canonical).transpose()?;
+				if parent.is_some_and(|parent| parent.starts_with(&followed)) {
+					return Err(refused(format!(
+						"{} links to a directory holding it",
+						entry.display()
+					)));
+				}
+
+				Ok(true)
+			}
+		}
+	}
+}
+
+fn refused(reason: String) -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidInput, reason)
+}
+
+/// Refuses entries Windows couldn't write, so archives unpack the same on
+/// every platform
+fn portable(path: &Path) -> Result<(), Error> {
+	for component in path.components() {
+		if let Component::Normal(name) = component {
+			io::portable(&name.to_string_lossy())
+				.map_err(|reason| Error::InvalidArchive(format!("{}: {reason}", path.display())))?;
+		}
+	}
+
+	Ok(())
+}
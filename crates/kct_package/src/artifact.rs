@@ -0,0 +1,56 @@
+use crate::error::Error;
+
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+
+/// Turns the value rendered by packages with files as output into their
+/// paths and contents. Strings are written as they are while other values
+/// are serialized by extension, YAML for `.yaml` and `.yml` and JSON otherwise.
+/// Null values are skipped, so templates can toggle files with conditionals.
+pub fn artifacts(value: &Value) -> Result<Vec<(PathBuf, String)>, Error> {
+	let files = match value {
+		Value::Object(files) => files,
+		_ => {
+			let reason = String::from("the rendered value should map paths to contents");
+
+			return Err(Error::InvalidArtifact(String::from("/"), reason));
+		}
+	};
+
+	let mut artifacts = vec![];
+
+	for (name, contents) in files {
+		let path = Path::new(name);
+		let invalid = |reason: &str| Error::InvalidArtifact(name.clone(), reason.to_string());
+
+		let is_relative = path
+			.components()
+			.all(|component| matches!(component, Component::Normal(_)));
+		if name.is_empty() || !is_relative {
+			return Err(invalid("paths should be relative and within the output"));
+		}
+
+		let extension = path.extension().and_then(|ext| ext.to_str());
+		let contents = match (contents, extension) {
+			(Value::Null, _) => continue,
+			(Value::String(contents), _) => contents.clone(),
+			(contents, Some("yaml" | "yml")) => {
+				serde_yaml::to_string(contents).map_err(|err| invalid(&err.to_string()))?
+			}
+			(contents, _) => {
+				let json = serde_json::to_string_pretty(contents)
+					.map_err(|err| invalid(&err.to_string()))?;
+
+				format!("{json}\n")
+			}
+		};
+
+		artifacts.push((PathBuf::from("/").join(path), contents));
+	}
+
+	// Maps may keep insertion order, so sorting keeps renders comparable
+	artifacts.sort();
+
+	Ok(artifacts)
+}
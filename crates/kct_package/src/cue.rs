@@ -0,0 +1,117 @@
+use crate::error::Error;
+
+use std::env;
+use std::env::consts::EXE_SUFFIX;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use kct_compiler::Validator;
+use kct_helper::io;
+use serde_json::Value;
+
+/// Command vetting inputs, instead of `cue`
+const COMMAND_VAR: &str = "KCT_CUE";
+
+/// Constraints on the input written in CUE, from the package and the ones it
+/// extends, which are unified as CUE does with its files
+pub struct Cue {
+	pub files: Vec<PathBuf>,
+}
+
+/// Vets the input, concrete as every value must be, against the files. The
+/// input goes through STDIN, so nothing is written along the way
+fn vet(files: &[PathBuf], input: &Value) -> Result<(), String> {
+	for file in files {
+		io::record(file);
+	}
+
+	let unable = |err: std::io::Error| format!("Unable to run cue: {err}");
+	let mut child = command()
+		.arg("vet")
+		.arg("-c")
+		.args(files)
+		.args(["json:", "-"])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(unable)?;
+
+	// Dropped once written, so cue sees the end of the input
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin
+			.write_all(input.to_string().as_bytes())
+			.map_err(unable)?;
+	}
+
+	let output = child.wait_with_output().map_err(unable)?;
+
+	if !output.status.success() {
+		let reason = String::from_utf8_lossy(&output.stderr);
+
+		return Err(format!(
+			"input doesn't match your constraints: {}",
+			reason.trim()
+		));
+	}
+
+	Ok(())
+}
+
+/// Program vetting inputs along the arguments it takes before ours
+fn words() -> (String, Vec<String>) {
+	let configured = env::var(COMMAND_VAR).ok();
+	let mut words: Vec<String> = configured
+		.map(|configured| configured.split_whitespace().map(String::from).collect())
+		.unwrap_or_default();
+
+	match words.is_empty() {
+		true => (String::from("cue"), words),
+		false => (words.remove(0), words),
+	}
+}
+
+fn command() -> Command {
+	let (program, words) = words();
+
+	let mut command = Command::new(program);
+	command.args(words);
+
+	command
+}
+
+/// Refuses to compile when the program vetting inputs is missing, rather
+/// than failing as if the input didn't match the constraints
+pub fn available() -> Result<(), Error> {
+	let (program, _) = words();
+	let path = Path::new(&program);
+
+	let found = match path.components().count() > 1 {
+		true => path.is_file(),
+		false => env::var_os("PATH").is_some_and(|paths| {
+			env::split_paths(&paths).any(|dir| {
+				dir.join(&program).is_file() || dir.join(format!("{program}{EXE_SUFFIX}")).is_file()
+			})
+		}),
+	};
+
+	match found {
+		true => Ok(()),
+		false => Err(Error::MissingCue(program)),
+	}
+}
+
+impl From<Cue> for Validator {
+	fn from(cue: Cue) -> Self {
+		let predicate = move |input: &Value| -> Result<(), String> {
+			if !input.is_object() {
+				return Err("input is not an object".to_string());
+			}
+
+			vet(&cue.files, input)
+		};
+
+		Validator::new(Box::new(predicate))
+	}
+}
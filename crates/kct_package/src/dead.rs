@@ -0,0 +1,210 @@
+use crate::error::Error;
+use crate::spec::{Delimiters, Kind};
+use crate::{jsonnet, Package, LIB_DIR, TEMPLATES_DIR};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::OverrideBuilder;
+use kct_compiler::lint::{Finding, Rule};
+use kct_compiler::usage::{self, Reads};
+use kct_compiler::Error as CompilerError;
+use kct_helper::io;
+use walkdir::WalkDir;
+
+const FILES_DIR: &str = "files";
+/// Tera tags bringing in other templates
+const TERA_REFERENCES: [&str; 3] = ["include", "extends", "import"];
+/// Prefix of the globs looking into the files of vendored packages
+const VENDOR_PREFIX: &str = "vendor:";
+
+/// Files of the package nothing reads, the ones within `files` no glob,
+/// path, nor other template brings in, and the ones within `lib` no
+/// template imports. Libraries are spared the latter, as their libs are
+/// what they offer.
+pub(crate) fn lint(package: &Package) -> Result<Vec<Finding>, Error> {
+	let mut reads = Reads::default();
+	let mut entrypoints = vec![];
+
+	let mut layer = Some(package);
+	while let Some(current) = layer {
+		for path in jsonnet(&current.root) {
+			let relative = path.strip_prefix(&current.root).unwrap_or(&path);
+			let source = io::from_file(&path).map_err(|err| {
+				Error::InvalidArtifact(relative.display().to_string(), err.to_string())
+			})?;
+
+			let read = usage::reads(relative, &source).map_err(CompilerError::from)?;
+			reads.globs.extend(read.globs);
+			reads.paths.extend(read.paths);
+
+			if relative.starts_with(TEMPLATES_DIR) {
+				entrypoints.push(path);
+			}
+		}
+
+		layer = current.base.as_deref();
+	}
+
+	let mut findings = vec![];
+
+	for file in unmatched(package, &reads)? {
+		findings.push(finding(
+			file.clone(),
+			format!(
+				"{} is never matched by the globs of the templates",
+				file.display()
+			),
+		));
+	}
+
+	if package.spec.kind != Kind::Library {
+		let imported: BTreeSet<PathBuf> = package
+			.sources()?
+			.imported(&entrypoints)
+			.iter()
+			.filter_map(|path| io::canonical(path).ok())
+			.collect();
+
+		for file in listing(&package.root, LIB_DIR) {
+			let canonical = io::canonical(&package.root.join(&file)).ok();
+			let used = canonical.is_some_and(|canonical| imported.contains(&canonical))
+				|| read(&reads.paths, &file);
+
+			if !used {
+				findings.push(finding(
+					file.clone(),
+					format!("{} is never imported by the templates", file.display()),
+				));
+			}
+		}
+	}
+
+	Ok(findings
+		.into_iter()
+		.filter(|finding| !package.spec.disabled_lints.contains(&finding.rule))
+		.collect())
+}
+
+/// Files within `files` of the package, from its root, neither matched by
+/// the globs, read by the paths, nor brought in by templates that are
+fn unmatched(package: &Package, reads: &Reads) -> Result<Vec<PathBuf>, Error> {
+	let dir = package.root.join(FILES_DIR);
+	let files = listing(&package.root, FILES_DIR);
+	if files.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let mut builder = OverrideBuilder::new(&dir);
+	for glob in &reads.globs {
+		if glob.starts_with(VENDOR_PREFIX) {
+			continue;
+		}
+
+		builder.add(glob).map_err(|err| {
+			Error::InvalidArtifact(glob.clone(), format!("Invalid glob provided: {err}"))
+		})?;
+	}
+	let matcher = builder
+		.build()
+		.map_err(|err| Error::InvalidArtifact(FILES_DIR.into(), err.to_string()))?;
+
+	let mut used: BTreeSet<PathBuf> = files
+		.iter()
+		.filter(|file| {
+			let within = file.strip_prefix(FILES_DIR).unwrap_or(file);
+
+			matcher.matched(within, false).is_whitelist() || read(&reads.paths, file)
+		})
+		.cloned()
+		.collect();
+
+	// Templates bring in the others they include, extend, or import
+	let delimiters = &package.spec.templating.delimiters;
+	let mut pending: Vec<PathBuf> = used.iter().cloned().collect();
+	while let Some(file) = pending.pop() {
+		let contents = match fs::read_to_string(package.root.join(&file)) {
+			Ok(contents) => contents,
+			Err(_) => continue,
+		};
+
+		for name in references(&contents, delimiters) {
+			let referenced = Path::new(FILES_DIR).join(name);
+			if files.contains(&referenced) && used.insert(referenced.clone()) {
+				pending.push(referenced);
+			}
+		}
+	}
+
+	Ok(files
+		.into_iter()
+		.filter(|file| !used.contains(file))
+		.collect())
+}
+
+/// Names of the templates the Tera template brings in through its tags
+fn references(contents: &str, delimiters: &Delimiters) -> Vec<String> {
+	let (open, close) = &delimiters.block;
+	let mut names = vec![];
+
+	let mut rest = contents;
+	while let Some(start) = rest.find(open.as_str()) {
+		let tag = &rest[start + open.len()..];
+		let end = tag.find(close.as_str()).unwrap_or(tag.len());
+		let body = tag[..end].trim_start_matches('-').trim();
+		rest = &tag[end..];
+
+		let (keyword, arguments) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+		if !TERA_REFERENCES.contains(&keyword) {
+			continue;
+		}
+
+		// Every quoted name, as include takes a list to pick the first of
+		for quote in ['"', '\''] {
+			let quoted: Vec<&str> = arguments.split(quote).collect();
+			names.extend(
+				quoted
+					.iter()
+					.skip(1)
+					.step_by(2)
+					.map(|name| name.to_string()),
+			);
+		}
+	}
+
+	names
+}
+
+/// Whether any of the paths, from the package root, is the file or a
+/// directory holding it
+fn read(paths: &[String], file: &Path) -> bool {
+	paths.iter().any(|path| {
+		let path = Path::new(path.trim_start_matches("./"));
+
+		file.starts_with(path)
+	})
+}
+
+/// Files within the directory of the package, from its root, sorted
+fn listing(root: &Path, dir: &str) -> Vec<PathBuf> {
+	let mut files: Vec<PathBuf> = WalkDir::new(root.join(dir))
+		.into_iter()
+		.filter_map(Result::ok)
+		.filter(|entry| entry.file_type().is_file())
+		.filter_map(|entry| entry.path().strip_prefix(root).ok().map(Path::to_path_buf))
+		.collect();
+	files.sort();
+
+	files
+}
+
+fn finding(path: PathBuf, message: String) -> Finding {
+	Finding {
+		rule: Rule::DeadFile,
+		path,
+		line: 1,
+		column: 1,
+		message,
+	}
+}
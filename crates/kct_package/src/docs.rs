@@ -0,0 +1,211 @@
+use crate::error::Error;
+use crate::inputs;
+use crate::spec::{Kind, Output};
+use crate::Package;
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use globwalk::GlobWalkerBuilder;
+use serde_json::{json, Value};
+
+const TEMPLATES_FOLDER: &str = "templates";
+
+pub(crate) struct Parameter {
+	pub path: String,
+	/// Type declared by the schema, a name or a list of them
+	kind: Option<Value>,
+	required: bool,
+	default: Option<Value>,
+	description: String,
+}
+
+pub fn markdown(package: &Package) -> String {
+	let mut doc = String::new();
+	let spec = &package.spec;
+
+	writeln!(doc, "# {}\n", spec.name).unwrap();
+	writeln!(doc, "- Version: `{}`", spec.version).unwrap();
+	if spec.kind == Kind::Library {
+		writeln!(doc, "- Kind: `library`").unwrap();
+	}
+	if spec.output != Output::Kubernetes {
+		writeln!(doc, "- Output: `{}`", spec.output.as_str()).unwrap();
+	}
+	if let Some(extends) = &spec.extends {
+		writeln!(doc, "- Extends: `{}`", extends.display()).unwrap();
+	}
+	let capabilities = package.capabilities();
+	if !capabilities.is_empty() {
+		let capabilities: Vec<String> = capabilities
+			.iter()
+			.map(|capability| format!("`{}`", capability.as_str()))
+			.collect();
+
+		writeln!(doc, "- Capabilities: {}", capabilities.join(", ")).unwrap();
+	}
+
+	if let Some(description) = package
+		.latest_schema()
+		.and_then(|schema| schema.value().get("description"))
+		.and_then(Value::as_str)
+	{
+		writeln!(doc, "\n{description}").unwrap();
+	}
+
+	if let Some(schema) = package.latest_schema() {
+		let mut parameters = vec![];
+		collect(schema.value(), "", &mut parameters);
+
+		writeln!(doc, "\n## Parameters\n").unwrap();
+		writeln!(
+			doc,
+			"| Parameter | Type | Required | Default | Description |"
+		)
+		.unwrap();
+		writeln!(doc, "| --- | --- | --- | --- | --- |").unwrap();
+		for param in parameters {
+			let required = if param.required { "yes" } else { "no" };
+			let default = param
+				.default
+				.map(|default| format!("`{}`", escape(&default.to_string())))
+				.unwrap_or_default();
+
+			writeln!(
+				doc,
+				"| `{}` | {} | {} | {} | {} |",
+				param.path,
+				escape(&kind(param.kind.as_ref())),
+				required,
+				default,
+				escape(&param.description)
+			)
+			.unwrap();
+		}
+	}
+
+	if let Some(example) = &package.example {
+		let example = serde_json::to_string_pretty(example).unwrap();
+
+		writeln!(doc, "\n## Example\n").unwrap();
+		writeln!(doc, "```json\n{example}\n```").unwrap();
+	}
+
+	let templates = templates(package);
+	if !templates.is_empty() {
+		writeln!(doc, "\n## Templates\n").unwrap();
+		for template in templates {
+			writeln!(doc, "- `{}`", template.display()).unwrap();
+		}
+	}
+
+	doc
+}
+
+pub(crate) fn collect(schema: &Value, prefix: &str, parameters: &mut Vec<Parameter>) {
+	let properties = match schema.get("properties").and_then(Value::as_object) {
+		Some(properties) => properties,
+		None => return,
+	};
+
+	let required: Vec<&str> = schema
+		.get("required")
+		.and_then(Value::as_array)
+		.map(|required| required.iter().filter_map(Value::as_str).collect())
+		.unwrap_or_default();
+
+	for (name, property) in properties {
+		let path = if prefix.is_empty() {
+			name.clone()
+		} else {
+			format!("{prefix}.{name}")
+		};
+
+		let description = property
+			.get("description")
+			.and_then(Value::as_str)
+			.unwrap_or_default()
+			.to_string();
+
+		parameters.push(Parameter {
+			path: path.clone(),
+			kind: property.get("type").cloned(),
+			required: required.contains(&name.as_str()),
+			default: property.get("default").cloned(),
+			description,
+		});
+
+		collect(property, &path, parameters);
+	}
+}
+
+/// Catalog of the inputs in JSON, each telling whether the templates read
+/// it, either by itself, through what holds it, or through what it holds
+pub fn inputs(package: &Package) -> Result<Value, Error> {
+	let mut parameters = vec![];
+	if let Some(schema) = package.latest_schema() {
+		collect(schema.value(), "", &mut parameters);
+	}
+	// By path, whatever order the schema declares them in
+	parameters.sort_by(|a, b| a.path.cmp(&b.path));
+
+	let read = package.read_inputs()?;
+	let inputs: Vec<Value> = parameters
+		.into_iter()
+		.map(|param| {
+			let referenced = inputs::referenced(&read, &param.path);
+
+			json!({
+				"path": param.path,
+				"type": param.kind,
+				"required": param.required,
+				"default": param.default,
+				"description": param.description,
+				"referenced": referenced,
+			})
+		})
+		.collect();
+
+	Ok(json!({
+		"name": package.spec.name,
+		"version": package.spec.version.to_string(),
+		"inputs": inputs,
+	}))
+}
+
+fn kind(kind: Option<&Value>) -> String {
+	match kind {
+		Some(Value::String(kind)) => kind.clone(),
+		Some(Value::Array(kinds)) => kinds
+			.iter()
+			.filter_map(Value::as_str)
+			.collect::<Vec<_>>()
+			.join(" | "),
+		_ => String::from("any"),
+	}
+}
+
+fn templates(package: &Package) -> Vec<PathBuf> {
+	let dir = package.root.join(TEMPLATES_FOLDER);
+	if !dir.exists() {
+		return vec![];
+	}
+
+	let walker = match GlobWalkerBuilder::new(&dir, "**/*.{jsonnet,libsonnet}").build() {
+		Ok(walker) => walker,
+		Err(_) => return vec![],
+	};
+
+	let mut templates: Vec<PathBuf> = walker
+		.filter_map(Result::ok)
+		.filter_map(|entry| entry.path().strip_prefix(&dir).ok().map(PathBuf::from))
+		.collect();
+
+	templates.sort();
+
+	templates
+}
+
+fn escape(cell: &str) -> String {
+	cell.replace('|', "\\|").replace('\n', " ")
+}
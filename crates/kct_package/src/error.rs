@@ -19,6 +19,50 @@ pub enum Error {
 	NoExample,
 	#[error("Invalid example file")]
 	InvalidExample,
+	#[error("Unable to find the extended package")]
+	NoBase,
+	#[error("Package extends itself")]
+	CyclicBase,
+	#[error(
+		"Extended package {0} is outside of the package, it should be within it, such as vendored"
+	)]
+	OutsideBase(String),
+	#[error("Package \"{0}\" is a library, it can only be vendored by other packages")]
+	Library(String),
+	#[error("Missing jsonnetfile.json, which declares the dependencies")]
+	NoDependencies,
+	#[error("Invalid jsonnetfile.json")]
+	InvalidDependencies,
+	#[error("Unable to fetch {0}: {1}")]
+	Fetch(String, String),
+	#[error("Dependency {0} is required at {1}, which can't be satisfied together")]
+	Conflict(String, String),
+	#[error("Dependencies require each other: {0}")]
+	Cycle(String),
+	#[error("Missing jsonnetfile.lock.json, required to verify vendored dependencies")]
+	NoLock,
+	#[error("Invalid jsonnetfile.lock.json")]
+	InvalidLock,
+	#[error("Dependency {0} is locked but not vendored")]
+	NotVendored(String),
+	#[error("Vendored {0} doesn't match the lockfile, vendor it again or accept the changes with --update")]
+	Drift(String),
+	#[error("Fetched {0} doesn't match the lockfile, {1}")]
+	Tampered(String, String),
+	#[error("Invalid package archive: {0}")]
+	InvalidArchive(String),
+	#[error("Package archive is too large, {0}")]
+	ArchiveTooLarge(String),
+	#[error("Unable to store the package: {0}")]
+	Store(String),
+	#[error("Invalid file {0}: {1}")]
+	InvalidArtifact(String, String),
+	#[error("Invalid artifact reference {0}, it should be registry/repository:tag")]
+	InvalidReference(String),
+	#[error("Unable to push {0}: {1}")]
+	Push(String, String),
+	#[error("Unable to find {0}, which vets inputs against CUE constraints, install it or point KCT_CUE to it")]
+	MissingCue(String),
 	#[error(transparent)]
 	Compilation(#[from] CompilerError),
 }
@@ -0,0 +1,333 @@
+use serde_json::Value;
+use url::Url;
+use valico::json_schema::Scope;
+
+/// Keywords combining subschemas, explained branch by branch instead of
+/// through the errors of the validator, which don't tell the branches apart
+const COMBINING: [&str; 2] = ["one_of", "any_of"];
+
+/// Why the input fails the schema, walking composed schemas to name the
+/// branches of `if`, `oneOf`, `anyOf`, and `allOf` matched or not
+pub(crate) fn explain(scope: &Scope, id: &Url, schema: &Value, input: &Value) -> Vec<String> {
+	let explainer = Explainer { scope, id };
+	let mut reasons = vec![];
+
+	for reason in explainer.node(schema, "", input, "") {
+		if !reasons.contains(&reason) {
+			reasons.push(reason);
+		}
+	}
+
+	reasons
+}
+
+struct Explainer<'a> {
+	scope: &'a Scope,
+	id: &'a Url,
+}
+
+impl Explainer<'_> {
+	/// Whether the value is valid against the subschema at the pointer, as
+	/// far as it can be resolved
+	fn valid(&self, pointer: &str, value: &Value) -> bool {
+		let mut url = self.id.clone();
+		url.set_fragment(Some(pointer));
+
+		match self.scope.resolve(&url) {
+			Some(schema) => schema.validate(value).is_valid(),
+			None => true,
+		}
+	}
+
+	fn node(&self, schema: &Value, pointer: &str, value: &Value, path: &str) -> Vec<String> {
+		if !schema.is_object() || self.valid(pointer, value) {
+			return vec![];
+		}
+
+		let mut reasons = self.own(pointer, value, path, schema);
+
+		if let Some(condition) = schema.get("if") {
+			reasons.extend(self.conditional(schema, condition, pointer, value, path));
+		}
+
+		for keyword in ["oneOf", "anyOf"] {
+			if let Some(Value::Array(branches)) = schema.get(keyword) {
+				reasons.extend(self.alternatives(keyword, branches, pointer, value, path));
+			}
+		}
+
+		if let Some(Value::Array(branches)) = schema.get("allOf") {
+			for (i, branch) in branches.iter().enumerate() {
+				reasons.extend(self.node(branch, &format!("{pointer}/allOf/{i}"), value, path));
+			}
+		}
+
+		if let (Some(Value::Object(properties)), Value::Object(fields)) =
+			(schema.get("properties"), value)
+		{
+			for (name, property) in properties {
+				if let Some(field) = fields.get(name) {
+					reasons.extend(self.node(
+						property,
+						&format!("{pointer}/properties/{}", escape(name)),
+						field,
+						&format!("{path}/{name}"),
+					));
+				}
+			}
+		}
+
+		if let (Some(items), Value::Array(values)) = (schema.get("items"), value) {
+			for (i, item) in values.iter().enumerate() {
+				reasons.extend(self.node(
+					items,
+					&format!("{pointer}/items"),
+					item,
+					&format!("{path}/{i}"),
+				));
+			}
+		}
+
+		if reasons.is_empty() {
+			reasons.push(format!("{} doesn't match the schema", display(path)));
+		}
+
+		reasons
+	}
+
+	/// Failures of the keywords of the subschema itself, and not of the
+	/// subschemas within it, explained on their own
+	fn own(&self, pointer: &str, value: &Value, path: &str, schema: &Value) -> Vec<String> {
+		let mut url = self.id.clone();
+		url.set_fragment(Some(pointer));
+		let state = match self.scope.resolve(&url) {
+			Some(resolved) => resolved.validate_in(value, path),
+			None => return vec![],
+		};
+
+		state
+			.errors
+			.iter()
+			.filter(|err| !COMBINING.contains(&err.get_code()))
+			.filter_map(|err| {
+				let at = err.get_path();
+				let parent = at.rsplit_once('/').map(|(parent, _)| parent);
+
+				match err.get_code() {
+					"required" if parent == Some(path) => {
+						Some(format!("{} is required", display(at)))
+					}
+					_ if at != path => None,
+					"const" => Some(format!(
+						"{} should be {}",
+						display(at),
+						schema.get("const").unwrap_or(&Value::Null)
+					)),
+					"enum" => Some(format!(
+						"{} should be one of {}",
+						display(at),
+						schema.get("enum").unwrap_or(&Value::Null)
+					)),
+					_ => Some(match err.get_detail() {
+						Some(detail) => format!("{}: {detail}", display(at)),
+						None => format!("{}: {}", display(at), err.get_title()),
+					}),
+				}
+			})
+			.collect()
+	}
+
+	fn conditional(
+		&self,
+		schema: &Value,
+		condition: &Value,
+		pointer: &str,
+		value: &Value,
+		path: &str,
+	) -> Vec<String> {
+		let (branch, why) = match self.valid(&format!("{pointer}/if"), value) {
+			true => ("then", "matches"),
+			false => ("else", "doesn't match"),
+		};
+		let applied = match schema.get(branch) {
+			Some(applied) => applied,
+			None => return vec![],
+		};
+
+		let reasons = self.node(applied, &format!("{pointer}/{branch}"), value, path);
+		if reasons.is_empty() {
+			return vec![];
+		}
+
+		let condition = describe(condition);
+		vec![format!(
+			"{} {why} the if condition{condition}, so its {branch} applies: {}",
+			display(path),
+			reasons.join(", ")
+		)]
+	}
+
+	fn alternatives(
+		&self,
+		keyword: &str,
+		branches: &[Value],
+		pointer: &str,
+		value: &Value,
+		path: &str,
+	) -> Vec<String> {
+		let pointers: Vec<String> = (0..branches.len())
+			.map(|i| format!("{pointer}/{keyword}/{i}"))
+			.collect();
+		let valid: Vec<usize> = (0..branches.len())
+			.filter(|i| self.valid(&pointers[*i], value))
+			.collect();
+
+		match (keyword, valid.len()) {
+			("anyOf", 1..) | ("oneOf", 1) => return vec![],
+			("oneOf", 2..) => {
+				let matched: Vec<String> = valid
+					.iter()
+					.map(|i| name(keyword, *i, &branches[*i]))
+					.collect();
+
+				return vec![format!(
+					"{} matches {}, but should match only one of its oneOf",
+					display(path),
+					matched.join(" and ")
+				)];
+			}
+			_ => (),
+		}
+
+		// The branch picked by the value of its discriminator, when the
+		// branches tell themselves apart by a constant field
+		let discriminated: Vec<usize> = (0..branches.len())
+			.filter(|i| {
+				discriminator(&branches[*i])
+					.is_some_and(|(field, constant)| value.get(field) == Some(constant))
+			})
+			.collect();
+
+		if let [picked] = discriminated.as_slice() {
+			let (field, _) = discriminator(&branches[*picked]).unwrap();
+			let reasons = self.node(&branches[*picked], &pointers[*picked], value, path);
+
+			return vec![format!(
+				"{} is {} by its {field}, but {}",
+				display(path),
+				name(keyword, *picked, &branches[*picked]),
+				reasons.join(", ")
+			)];
+		}
+
+		let constants: Vec<(&str, &Value)> = branches.iter().filter_map(discriminator).collect();
+		let field = constants.first().map(|(field, _)| *field);
+		let shared = field.is_some_and(|field| {
+			constants.len() == branches.len() && constants.iter().all(|(other, _)| *other == field)
+		});
+		if let (true, Some(field)) = (shared, field) {
+			let expected: Vec<String> = constants
+				.iter()
+				.map(|(_, constant)| constant.to_string())
+				.collect();
+			let actual = value
+				.get(field)
+				.map(Value::to_string)
+				.unwrap_or(String::from("missing"));
+
+			return vec![format!(
+				"{} is {actual}, but the branches of the {keyword} of {} take {}",
+				display(&format!("{path}/{field}")),
+				display(path),
+				expected.join(", ")
+			)];
+		}
+
+		let failures: Vec<String> = branches
+			.iter()
+			.enumerate()
+			.map(|(i, branch)| {
+				let reasons = self.node(branch, &pointers[i], value, path);
+
+				format!(
+					"{} fails as {}",
+					name(keyword, i, branch),
+					reasons.join(", ")
+				)
+			})
+			.collect();
+
+		vec![format!(
+			"{} matches none of its {keyword}: {}",
+			display(path),
+			failures.join("; ")
+		)]
+	}
+}
+
+/// Field of the branch holding a constant, telling it apart from the others
+fn discriminator(branch: &Value) -> Option<(&str, &Value)> {
+	let properties = branch.get("properties")?.as_object()?;
+
+	properties.iter().find_map(|(field, property)| {
+		let constant = property
+			.get("const")
+			.or_else(|| match property.get("enum") {
+				Some(Value::Array(values)) if values.len() == 1 => values.first(),
+				_ => None,
+			})?;
+
+		Some((field.as_str(), constant))
+	})
+}
+
+/// Name of the branch, its title when it has one
+fn name(keyword: &str, i: usize, branch: &Value) -> String {
+	match branch.get("title").and_then(Value::as_str) {
+		Some(title) => format!("{keyword}[{i}] ({title})"),
+		None => format!("{keyword}[{i}]"),
+	}
+}
+
+/// The condition as its title, or the fields it requires
+fn describe(condition: &Value) -> String {
+	if let Some(title) = condition.get("title").and_then(Value::as_str) {
+		return format!(" ({title})");
+	}
+
+	let constants: Vec<String> = condition
+		.get("properties")
+		.and_then(Value::as_object)
+		.map(|properties| {
+			properties
+				.iter()
+				.filter_map(|(field, property)| {
+					property
+						.get("const")
+						.map(|constant| format!("{field} is {constant}"))
+				})
+				.collect()
+		})
+		.unwrap_or_default();
+
+	match constants.is_empty() {
+		true => String::new(),
+		false => format!(" ({})", constants.join(" and ")),
+	}
+}
+
+/// Path within the input as written in templates, as `input.database.host`
+fn display(pointer: &str) -> String {
+	let fields: String = pointer
+		.split('/')
+		.skip(1)
+		.map(|field| format!(".{field}"))
+		.collect();
+
+	format!("input{fields}")
+}
+
+/// Name as a segment of a JSON pointer
+fn escape(name: &str) -> String {
+	name.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,202 @@
+use crate::property::nanos;
+
+use std::sync::OnceLock;
+
+use kct_helper::quantity::Quantity;
+use regex::Regex;
+use serde_json::Value;
+use valico::json_schema::errors;
+use valico::json_schema::keywords::format::FormatBuilders;
+use valico::json_schema::schema::WalkContext;
+use valico::json_schema::validators::{BoxedValidator, Validator};
+use valico::json_schema::{Scope, ValidationState};
+
+type Check = fn(&str) -> Result<(), String>;
+
+/// Formats of strings Kubernetes takes, checked on top of the ones of JSON
+/// Schema so values it would refuse are caught before rendering
+const FORMATS: [(&str, Check); 5] = [
+	("dns-1123-label", label),
+	("quantity", quantity),
+	("duration", duration),
+	("image-reference", image),
+	("cron", cron),
+];
+
+/// Adds the formats to the ones the schemas validate
+pub(crate) fn kubernetes(builders: &mut FormatBuilders) {
+	for (name, check) in FORMATS {
+		let builder = move |_def: &Value, _ctx: &WalkContext<'_>| {
+			Ok(Some(Box::new(Format(check)) as BoxedValidator))
+		};
+
+		builders.insert(name.to_string(), Box::new(builder));
+	}
+}
+
+struct Format(Check);
+
+impl Validator for Format {
+	fn validate(&self, value: &Value, path: &str, _scope: &Scope) -> ValidationState {
+		let mut state = ValidationState::new();
+
+		// Like the formats of JSON Schema, only strings are checked
+		if let Some(Err(detail)) = value.as_str().map(self.0) {
+			state.errors.push(Box::new(errors::Format {
+				path: path.to_string(),
+				detail,
+			}));
+		}
+
+		state
+	}
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+	cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// Names of most objects, as `my-app`
+fn label(value: &str) -> Result<(), String> {
+	static LABEL: OnceLock<Regex> = OnceLock::new();
+
+	if value.len() > 63 {
+		return Err(format!("{value} is longer than 63 characters"));
+	}
+
+	match regex(&LABEL, r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$").is_match(value) {
+		true => Ok(()),
+		false => Err(format!(
+			"{value} should be lowercase letters, digits, and dashes, starting and ending with a letter or a digit"
+		)),
+	}
+}
+
+/// Amounts of resources, as `500m` or `1.5Gi`
+fn quantity(value: &str) -> Result<(), String> {
+	match value.parse::<Quantity>() {
+		Ok(_) => Ok(()),
+		Err(_) => Err(format!("{value} isn't a quantity, as 500m or 1.5Gi")),
+	}
+}
+
+/// Spans of time, as `1h30m`
+fn duration(value: &str) -> Result<(), String> {
+	match nanos(value) {
+		Ok(_) => Ok(()),
+		Err(_) => Err(format!("{value} isn't a duration, as 1h30m")),
+	}
+}
+
+/// Images to pull, as `nginx:1.25`, `registry:5000/team/app@sha256:...`
+fn image(value: &str) -> Result<(), String> {
+	static IMAGE: OnceLock<Regex> = OnceLock::new();
+
+	let domain = r"(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9])(?:\.(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9]))*(?::[0-9]+)?";
+	let component = r"[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*";
+	let tag = r"[\w][\w.-]{0,127}";
+	let digest = r"[A-Za-z][A-Za-z0-9]*(?:[-_+.][A-Za-z][A-Za-z0-9]*)*:[0-9a-fA-F]{32,}";
+	let pattern =
+		format!("^(?P<name>(?:{domain}/)?{component}(?:/{component})*)(?::{tag})?(?:@{digest})?$");
+
+	let invalid = || format!("{value} should be as [registry/]repository[:tag][@digest]");
+	let captures = regex(&IMAGE, &pattern)
+		.captures(value)
+		.ok_or_else(invalid)?;
+
+	match captures["name"].len() > 255 {
+		true => Err(format!(
+			"the repository of {value} is longer than 255 characters"
+		)),
+		false => Ok(()),
+	}
+}
+
+/// Fields of schedules, by their range and the names they take
+const CRON_FIELDS: [(&str, u32, u32, &[&str]); 5] = [
+	("minute", 0, 59, &[]),
+	("hour", 0, 23, &[]),
+	("day of month", 1, 31, &[]),
+	(
+		"month",
+		1,
+		12,
+		&[
+			"jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+		],
+	),
+	(
+		"day of week",
+		0,
+		6,
+		&["sun", "mon", "tue", "wed", "thu", "fri", "sat"],
+	),
+];
+
+/// Shorthands of schedules CronJobs take in place of fields
+const CRON_SHORTHANDS: [&str; 7] = [
+	"@yearly",
+	"@annually",
+	"@monthly",
+	"@weekly",
+	"@daily",
+	"@midnight",
+	"@hourly",
+];
+
+/// Schedules of CronJobs, as `*/15 * * * *` or `@daily`
+fn cron(value: &str) -> Result<(), String> {
+	if CRON_SHORTHANDS.contains(&value) {
+		return Ok(());
+	}
+
+	if let Some(every) = value.strip_prefix("@every ") {
+		return duration(every)
+			.map_err(|_| format!("{every} of {value} isn't a duration, as 1h30m"));
+	}
+
+	let fields: Vec<&str> = value.split_whitespace().collect();
+	if fields.len() != CRON_FIELDS.len() {
+		return Err(format!(
+			"{value} should have 5 fields, minute, hour, day of month, month, and day of week"
+		));
+	}
+
+	for (field, (name, min, max, names)) in fields.iter().zip(CRON_FIELDS) {
+		for part in field.split(',') {
+			if !schedule(part, min, max, names) {
+				return Err(format!("{part} isn't a valid {name} of {value}"));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Whether the part of the field is `*`, a value, or a range, with an
+/// optional step
+fn schedule(part: &str, min: u32, max: u32, names: &[&str]) -> bool {
+	let value = |raw: &str| -> Option<u32> {
+		let lower = raw.to_lowercase();
+		let value = match names.iter().position(|name| *name == lower) {
+			Some(i) => i as u32 + min,
+			None => raw.parse().ok()?,
+		};
+
+		(min..=max).contains(&value).then_some(value)
+	};
+
+	let range = match part.split_once('/') {
+		Some((range, step)) if step.parse::<u32>().is_ok_and(|step| step > 0) => range,
+		Some(_) => return false,
+		None => part,
+	};
+
+	match range.split_once('-') {
+		_ if range == "*" || range == "?" => true,
+		Some((start, end)) => {
+			matches!((value(start), value(end)), (Some(start), Some(end)) if start <= end)
+		}
+		None => value(range).is_some(),
+	}
+}
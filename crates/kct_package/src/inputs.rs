@@ -0,0 +1,173 @@
+use crate::docs::{self, Parameter};
+use crate::error::Error;
+use crate::{jsonnet, Package, SCHEMAS_DIR, SCHEMA_FILE};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use kct_compiler::lint::{self, Finding, Rule};
+use kct_compiler::usage;
+use kct_compiler::Error as CompilerError;
+use kct_helper::io;
+use serde_json::Value;
+
+/// Keywords of schemas whose properties can't be told without resolving them
+const OPAQUE: [&str; 5] = ["$ref", "allOf", "anyOf", "oneOf", "patternProperties"];
+
+/// Whether the field is read, either by itself, through what holds it, or
+/// through what it holds
+pub(crate) fn referenced(read: &BTreeSet<String>, field: &str) -> bool {
+	read.iter().any(|path| {
+		path.is_empty()
+			|| path == field
+			|| field.starts_with(&format!("{path}."))
+			|| path.starts_with(&format!("{field}."))
+	})
+}
+
+/// Inputs the schema declares that no template reads, and the ones the
+/// templates of the package read that the schema doesn't declare
+pub(crate) fn lint(package: &Package) -> Result<Vec<Finding>, Error> {
+	let schema = match package.latest_schema() {
+		Some(schema) => schema.value(),
+		None => return Ok(vec![]),
+	};
+
+	let mut findings = vec![];
+
+	let read = package.read_inputs()?;
+	let mut parameters: Vec<Parameter> = vec![];
+	docs::collect(schema, "", &mut parameters);
+	parameters.sort_by(|a, b| a.path.cmp(&b.path));
+
+	// Only the outermost of the unused, as what they hold is unused as well
+	let mut unused: Vec<String> = vec![];
+	for param in parameters {
+		let within_unused = unused
+			.iter()
+			.any(|field| param.path.starts_with(&format!("{field}.")));
+		if within_unused || referenced(&read, &param.path) {
+			continue;
+		}
+
+		let (path, line, column) = declaration(package, &param.path);
+		findings.push(Finding {
+			rule: Rule::UnusedInput,
+			path,
+			line,
+			column,
+			message: format!(
+				"input {} is declared by the schema but never read by the templates",
+				param.path
+			),
+		});
+		unused.push(param.path);
+	}
+
+	for path in jsonnet(&package.root) {
+		let relative = path.strip_prefix(&package.root).unwrap_or(&path);
+		let source = io::from_file(&path).map_err(|err| {
+			Error::InvalidArtifact(relative.display().to_string(), err.to_string())
+		})?;
+
+		for reference in usage::references(relative, &source).map_err(CompilerError::from)? {
+			let fields: Vec<&str> = reference.path.split('.').collect();
+			if reference.path.is_empty() || declares(schema, &fields) {
+				continue;
+			}
+
+			findings.push(Finding {
+				rule: Rule::UndefinedInput,
+				path: relative.to_path_buf(),
+				line: reference.line,
+				column: reference.column,
+				message: format!(
+					"input {} is read but not declared by the schema",
+					reference.path
+				),
+			});
+		}
+	}
+
+	Ok(findings
+		.into_iter()
+		.filter(|finding| !package.spec.disabled_lints.contains(&finding.rule))
+		.collect())
+}
+
+/// Whether the schema declares the fields, or may hold them as far as it
+/// can be told without resolving references and combinations
+fn declares(schema: &Value, fields: &[&str]) -> bool {
+	let (name, rest) = match fields.split_first() {
+		Some(split) => split,
+		None => return true,
+	};
+
+	if OPAQUE.iter().any(|keyword| schema.get(keyword).is_some()) {
+		return true;
+	}
+
+	let declared = schema
+		.get("properties")
+		.and_then(Value::as_object)
+		.and_then(|properties| properties.get(*name));
+
+	match (declared, schema.get("additionalProperties")) {
+		(Some(property), _) => declares(property, rest),
+		(None, Some(Value::Bool(allowed))) => *allowed,
+		(None, Some(additional)) => declares(additional, rest),
+		// Any field is fine where the schema declares none
+		(None, None) => schema.get("properties").is_none(),
+	}
+}
+
+/// File, line, and column declaring the field, the schema of the nearest
+/// package having it, as the schema may be merged from the extended ones.
+/// Packages with many versions have it looked up in the latest.
+fn declaration(package: &Package, field: &str) -> (PathBuf, usize, usize) {
+	let schema = match package.versions.names().last() {
+		Some(latest) => Path::new(SCHEMAS_DIR).join(format!("{latest}.json")),
+		None => PathBuf::from(SCHEMA_FILE),
+	};
+	let mut layer = Some(package);
+
+	while let Some(current) = layer {
+		let file = current.root.join(&schema);
+		let found = io::from_file(&file)
+			.ok()
+			.and_then(|source| locate(&source, field).map(|at| lint::position(&source, at)));
+
+		if let Some((line, column)) = found {
+			let path = match std::ptr::eq(current, package) {
+				true => schema,
+				false => file,
+			};
+
+			return (path, line, column);
+		}
+
+		layer = current.base.as_deref();
+	}
+
+	(schema, 1, 1)
+}
+
+/// Offset of the key of the field within the source, found by looking up
+/// each of its names as a key after the previous one
+fn locate(source: &str, field: &str) -> Option<usize> {
+	let (mut at, mut key_at) = (0, None);
+
+	for name in field.split('.') {
+		let key = format!("\"{name}\"");
+		let (offset, _) = source[at..].match_indices(&key).find(|(offset, _)| {
+			source[at + offset + key.len()..]
+				.trim_start()
+				.starts_with(':')
+		})?;
+
+		key_at = Some(at + offset);
+		at += offset + key.len();
+	}
+
+	key_at
+}
@@ -1,42 +1,98 @@
+mod archive;
+mod artifact;
+mod cue;
+mod dead;
+mod docs;
 mod error;
+mod explain;
+mod format;
+mod inputs;
+mod lock;
+mod oci;
+mod outdated;
 mod property;
 mod schema;
 mod spec;
+mod store;
+mod vendor;
 
+pub use crate::archive::{pack, read, unpack, Limits, Symlinks};
+pub use crate::artifact::artifacts;
 pub use crate::error::Error;
-
-use crate::property::{Files, Include};
-use crate::schema::Schema;
+pub use crate::lock::Integrity;
+pub use crate::oci::{push, Reference, Registry};
+pub use crate::outdated::{outdated, Change, Upgrade};
+pub use crate::spec::{Capability, Delimiters, Kind, Output, Templating};
+pub use crate::store::Store;
+pub use crate::vendor::{vendor, Dependency, Vendored};
+
+use crate::cue::Cue;
+use crate::property::{
+	Base64, Duration, Files, FilesDigest, ImportBin, Include, Names, Net, Pem, Random, ReadFile,
+	Templates,
+};
+use crate::property::{
+	Http, JsonPath, Kustomize, Lookup, Patch, Quantity, Regex, Semver, Time, Tls, Url, Uuid,
+	UuidFrom,
+};
+use crate::schema::{Schema, Versions};
 use crate::spec::Spec;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use globwalk::GlobWalkerBuilder;
+use kct_compiler::lint::{self, Finding};
 use kct_compiler::property::{Name, Prop};
-use kct_compiler::{Compiler, Release, Target, TargetBuilder};
-use kct_compiler::{Context, Input};
+use kct_compiler::source::Sources;
+use kct_compiler::usage;
+use kct_compiler::{sdk, Compiler, Dialect, Format, Release, Sdk, Target, TargetBuilder};
+use kct_compiler::{Context, Error as CompilerError, Input, Validator};
 use kct_helper::io;
+use kct_helper::progress::Event;
 use serde_json::{Map, Value};
+use tracing::{debug, instrument, warn};
 
 const SCHEMA_FILE: &str = "schema.json";
+const SCHEMAS_DIR: &str = "schemas";
+const CUE_FILE: &str = "schema.cue";
 const SPEC_FILE: &str = "kcp.json";
 const EXAMPLE_FILE: &str = "example.json";
 const MAIN_FILE: &str = "templates/main.jsonnet";
+const TEMPLATES_DIR: &str = "templates";
+const LIB_DIR: &str = "lib";
+const VENDOR_DIR: &str = "vendor";
 
 #[derive(Debug, Clone)]
 pub struct Package {
 	pub root: PathBuf,
-	pub main: PathBuf,
+	/// Compilation entrypoint, only optional for libraries
+	pub main: Option<PathBuf>,
 	pub spec: Spec,
 	pub schema: Option<Schema>,
+	/// Schemas by the `apiVersion` of the inputs, instead of a single one
+	pub versions: Versions,
+	/// CUE file constraining inputs beside, or instead of, the schema
+	pub cue: Option<PathBuf>,
 	pub example: Option<Value>,
+	/// Package being extended, whose templates, files, and schema are inherited
+	pub base: Option<Box<Package>>,
+	templates: Templates,
 }
 
 impl TryFrom<&Path> for Package {
 	type Error = Error;
 
+	#[instrument(name = "load", skip_all, fields(root = %root.display()))]
 	fn try_from(root: &Path) -> Result<Self, Self::Error> {
+		Self::load(root, &mut vec![])
+	}
+}
+
+impl Package {
+	fn load(root: &Path, extending: &mut Vec<PathBuf>) -> Result<Self, Error> {
 		let root = PathBuf::from(root);
 
 		let spec = {
@@ -50,6 +106,31 @@ impl TryFrom<&Path> for Package {
 			}
 		};
 
+		let base = match &spec.extends {
+			None => None,
+			Some(extends) => {
+				let canonical = io::canonical(&root).unwrap_or_else(|_| root.clone());
+				let path = io::canonical(&root.join(extends)).map_err(|_err| Error::NoBase)?;
+
+				// Packages may come from archives of anyone, so bases are only
+				// taken from within the one compiled, such as its vendored
+				let compiled = extending.first().unwrap_or(&canonical);
+				if !path.starts_with(compiled) {
+					return Err(Error::OutsideBase(extends.display().to_string()));
+				}
+
+				if path == canonical || extending.contains(&path) {
+					return Err(Error::CyclicBase);
+				}
+
+				extending.push(canonical);
+				let base = Self::load(&path, extending)?;
+				extending.pop();
+
+				Some(Box::new(base))
+			}
+		};
+
 		let schema = {
 			let mut path = root.clone();
 			path.push(SCHEMA_FILE);
@@ -61,6 +142,18 @@ impl TryFrom<&Path> for Package {
 			}
 		};
 
+		let versions = {
+			let dir = root.join(SCHEMAS_DIR);
+
+			match (dir.is_dir(), &schema) {
+				(false, _) => Versions::default(),
+				(true, None) => Versions::load(&dir)?,
+				(true, Some(_)) => return Err(Error::InvalidSchema),
+			}
+		};
+
+		let cue = Some(root.join(CUE_FILE)).filter(|path| path.exists());
+
 		let example = {
 			let mut path = root.clone();
 			path.push(EXAMPLE_FILE);
@@ -82,16 +175,43 @@ impl TryFrom<&Path> for Package {
 			let mut path = root.clone();
 			path.push(MAIN_FILE);
 
-			if path.exists() {
-				path
-			} else {
-				return Err(Error::NoMain);
+			match (&base, spec.kind) {
+				_ if path.exists() => Some(path),
+				(Some(base), _) if base.main.is_some() => base.main.clone(),
+				(_, Kind::Library) => None,
+				_ => return Err(Error::NoMain),
+			}
+		};
+
+		let (schema, versions, example) = match &base {
+			None => (schema, versions, example),
+			Some(base) => {
+				// Versions of either replace the schema of the other, as an
+				// input can't be of a version and of none at once
+				let (schema, versions) = match (schema, &base.schema) {
+					(Some(_), _) if !base.versions.is_empty() => return Err(Error::InvalidSchema),
+					(None, Some(inherited)) if !versions.is_empty() => {
+						(None, versions.over(inherited)?)
+					}
+					(Some(schema), Some(inherited)) => (Some(schema.extend(inherited)?), versions),
+					(schema, inherited) => (schema.or_else(|| inherited.clone()), versions),
+				};
+				let versions = versions.extend(&base.versions)?;
+
+				(schema, versions, example.or_else(|| base.example.clone()))
 			}
 		};
 
-		match (&schema, &example) {
-			(None, Some(_)) => return Err(Error::NoSchema),
-			(Some(_), None) => return Err(Error::NoExample),
+		let constrained = schema.is_some()
+			|| !versions.is_empty()
+			|| cue.is_some()
+			|| base
+				.as_ref()
+				.is_some_and(|base| !base.constraints().is_empty());
+
+		match (constrained, &example) {
+			(false, Some(_)) => return Err(Error::NoSchema),
+			(true, None) => return Err(Error::NoExample),
 			_ => (),
 		};
 
@@ -100,24 +220,250 @@ impl TryFrom<&Path> for Package {
 			main,
 			spec,
 			schema,
+			versions,
+			cue,
 			example,
+			base,
+			templates: Templates::default(),
 		};
 
+		debug!(
+			name = package.spec.name,
+			version = %package.spec.version,
+			schema = package.schema.is_some(),
+			cue = package.cue.is_some(),
+			"package loaded"
+		);
+
 		Ok(package)
 	}
-}
 
-impl Package {
+	/// Roots of the packages extended by this one, nearest first
+	pub fn bases(&self) -> Vec<PathBuf> {
+		let mut bases = vec![];
+		let mut current = self.base.as_deref();
+
+		while let Some(base) = current {
+			bases.push(base.root.clone());
+			current = base.base.as_deref();
+		}
+
+		bases
+	}
+
+	/// Capabilities declared by the package or any package it extends, whose
+	/// templates it renders
+	pub fn capabilities(&self) -> Vec<Capability> {
+		let mut capabilities = self.spec.capabilities.clone();
+		let mut current = self.base.as_deref();
+
+		while let Some(base) = current {
+			capabilities.extend(base.spec.capabilities.iter());
+			current = base.base.as_deref();
+		}
+
+		capabilities.sort();
+		capabilities.dedup();
+
+		capabilities
+	}
+
+	/// Kustomizations declared by the package or any package it extends, as
+	/// directories relative to them
+	pub fn kustomizations(&self) -> Vec<PathBuf> {
+		let mut kustomizations = self.spec.kustomize.clone();
+		let mut current = self.base.as_deref();
+
+		while let Some(base) = current {
+			kustomizations.extend(base.spec.kustomize.iter().cloned());
+			current = base.base.as_deref();
+		}
+
+		kustomizations
+	}
+
+	/// Directories searched for libraries by the package or any package it
+	/// extends, in the order they're searched: nearest package first, and the
+	/// last one each declares first, as with `-J`
+	pub fn libraries(&self) -> Vec<PathBuf> {
+		let mut libraries = vec![];
+		let mut current = Some(self);
+
+		while let Some(package) = current {
+			let declared = package.spec.libraries.iter().rev();
+			libraries.extend(declared.map(|dir| package.root.join(dir)));
+			current = package.base.as_deref();
+		}
+
+		libraries
+	}
+
+	/// Files or directories imported by other names in the package or any
+	/// package it extends, the nearest declaring an alias winning
+	pub fn aliases(&self) -> BTreeMap<String, PathBuf> {
+		let mut aliases = match self.base.as_deref() {
+			Some(base) => base.aliases(),
+			None => BTreeMap::new(),
+		};
+
+		for (name, target) in self.spec.aliases.iter() {
+			aliases.insert(name.clone(), self.root.join(target));
+		}
+
+		aliases
+	}
+
+	/// Parts of Jsonnet the templates may use, as restricted by the nearest
+	/// package declaring them, so children inherit what their bases restrict
+	pub fn dialect(&self) -> Dialect {
+		match (&self.spec.dialect, self.base.as_deref()) {
+			(Some(dialect), _) => dialect.clone(),
+			(None, Some(base)) => base.dialect(),
+			(None, None) => Dialect::default(),
+		}
+	}
+
+	/// Version of the SDK pinned by the nearest package pinning one, the
+	/// latest when none does
+	pub fn sdk(&self) -> &'static Sdk {
+		match (self.spec.sdk, self.base.as_deref()) {
+			(Some(sdk), _) => sdk,
+			(None, Some(base)) => base.sdk(),
+			(None, None) => sdk::latest(),
+		}
+	}
+
+	/// CUE files constraining inputs from the package or any package it
+	/// extends, nearest first
+	pub fn constraints(&self) -> Vec<PathBuf> {
+		let mut constraints: Vec<PathBuf> = self.cue.iter().cloned().collect();
+		let mut current = self.base.as_deref();
+
+		while let Some(base) = current {
+			constraints.extend(base.cue.iter().cloned());
+			current = base.base.as_deref();
+		}
+
+		constraints
+	}
+
+	/// Templates of the package, to locate what defines each rendered value
+	pub fn sources(&self) -> Result<Sources, Error> {
+		let target = Target::try_from(self)?;
+
+		Ok(Sources {
+			main: target.main().to_path_buf(),
+			layers: target.layers(),
+			lib: target.lib().to_path_buf(),
+			libraries: target.libraries().to_vec(),
+			aliases: target.aliases().clone(),
+			vendor: self.root.join(VENDOR_DIR),
+			sdk: target.sdk(),
+		})
+	}
+
+	/// Lints the Jsonnet within the templates and libs of the package, but
+	/// not the packages it extends, skipping the rules it disabled
+	pub fn lint(&self) -> Result<Vec<Finding>, Error> {
+		let mut findings = vec![];
+
+		for path in jsonnet(&self.root) {
+			let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+			let source = io::from_file(&path).map_err(|err| {
+				Error::InvalidArtifact(relative.display().to_string(), err.to_string())
+			})?;
+
+			findings.extend(
+				lint::lint(relative, &source, &self.spec.disabled_lints)
+					.map_err(CompilerError::from)?,
+			);
+		}
+
+		Ok(findings)
+	}
+
+	/// Lints the inputs against the templates, finding the ones the schema
+	/// declares but nothing reads, and the ones the templates of the package
+	/// read but the schema doesn't declare
+	pub fn lint_inputs(&self) -> Result<Vec<Finding>, Error> {
+		inputs::lint(self)
+	}
+
+	/// Lints the files of the package, finding the ones within `files` and
+	/// `lib` nothing reads, so they aren't archived and shipped for nothing
+	pub fn lint_files(&self) -> Result<Vec<Finding>, Error> {
+		dead::lint(self)
+	}
+
+	/// Paths within the input read by the Jsonnet of the package, or of any
+	/// package it extends, dot separated and empty for the whole input
+	pub fn read_inputs(&self) -> Result<BTreeSet<String>, Error> {
+		let mut read = match self.base.as_deref() {
+			Some(base) => base.read_inputs()?,
+			None => BTreeSet::new(),
+		};
+
+		for path in jsonnet(&self.root) {
+			let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+			let source = io::from_file(&path).map_err(|err| {
+				Error::InvalidArtifact(relative.display().to_string(), err.to_string())
+			})?;
+
+			read.extend(usage::inputs(relative, &source).map_err(CompilerError::from)?);
+		}
+
+		Ok(read)
+	}
+
+	/// Checks the dependencies vendored by the package, and the packages it
+	/// extends, against their lockfiles
+	pub fn verify(&self, integrity: Integrity) -> Result<(), Error> {
+		lock::verify(&self.root, integrity)?;
+
+		match &self.base {
+			Some(base) => base.verify(integrity),
+			None => Ok(()),
+		}
+	}
+
+	/// SHA-256 over the contents of the package, vendored dependencies
+	/// included, in Base64 like the sums in the lockfile
+	pub fn digest(&self) -> std::io::Result<String> {
+		lock::digest(&self.root)
+	}
+
+	/// Dependencies vendored from git as recorded in the lockfile, none
+	/// without one
+	pub fn locked(&self) -> Result<Vec<Vendored>, Error> {
+		vendor::locked(&self.root)
+	}
+
+	/// Describes the package in Markdown, with its parameters and example
+	pub fn docs(&self) -> String {
+		docs::markdown(self)
+	}
+
+	/// Catalog of every input the schema declares, telling whether the
+	/// templates read it, for tools building forms out of packages
+	pub fn inputs(&self) -> Result<Value, Error> {
+		docs::inputs(self)
+	}
+
 	pub fn compile(self, input: Option<Value>, release: Option<Release>) -> Result<Value, Error> {
-		let target = (&self).into();
 		let context = Context::builder()
 			.root(self.root.clone())
 			.release(release)
 			.build()?;
 
+		self.compile_in(&context, input)
+	}
+
+	pub fn compile_in(self, context: &Context, input: Option<Value>) -> Result<Value, Error> {
+		let target = Target::try_from(&self)?;
+
 		let input = input.map(|v| (&Input(v)).into());
 
-		let compiler = Compiler::new(&context)
+		let compiler = Compiler::new(context)
 			.with_target(target)
 			.with_static_prop(input);
 
@@ -125,38 +471,163 @@ impl Package {
 	}
 
 	pub fn compile_with(self, compiler: Compiler) -> Result<Value, Error> {
+		let progress = compiler.context().progress().clone();
+		let package = self.spec.name.clone();
+
+		progress.report(Event::Compiling {
+			package: package.clone(),
+		});
+
+		if !self.constraints().is_empty() {
+			cue::available()?;
+		}
+
+		let templates = self.templates.clone();
 		let compiler = self.augment(compiler);
+		let value = compiler
+			.compile()
+			.map_err(|err| match templates.failure() {
+				Some(failure) => CompilerError::Template(failure),
+				None => err,
+			})?;
 
-		let value = compiler.compile()?;
+		progress.report(Event::Compiled { package });
 
 		Ok(value)
 	}
 
+	/// Schema inputs are written against today, the latest version when the
+	/// package has many
+	pub fn latest_schema(&self) -> Option<&Schema> {
+		self.schema.as_ref().or_else(|| self.versions.latest())
+	}
+
+	/// Schema validating the input, along its version when the package has
+	/// many, failing when the input doesn't declare a known one
+	pub fn select(
+		&self,
+		input: Option<&Value>,
+	) -> Result<(Option<Schema>, Option<String>), String> {
+		if self.versions.is_empty() {
+			return Ok((self.schema.clone(), None));
+		}
+
+		let input = input.unwrap_or(&Value::Null);
+		let (version, schema) = self.versions.select(input)?;
+		if schema.is_deprecated() {
+			warn!(
+				package = self.spec.name,
+				version, "input uses a deprecated schema version, move on to a later one"
+			);
+		}
+
+		Ok((Some(schema.clone()), Some(version.to_string())))
+	}
+
 	fn augment(self, compiler: Compiler) -> Compiler {
+		let capabilities = self.capabilities();
+		let declared = |capability| capabilities.contains(&capability);
+
+		let (schema, version) = match self.select(compiler.input()) {
+			Ok((schema, version)) => (Ok(schema), version),
+			Err(reason) => (Err(reason), None),
+		};
+
 		let mut compiler = compiler
 			.with_static_prop(Some((&self).into()))
-			.with_dynamic_prop(Some(Box::new(Files)))
-			.with_dynamic_prop(Some(Box::new(Include)));
-
-		compiler = match self.schema {
-			Some(schema) => compiler.with_check(schema.into()),
-			None => compiler,
+			.with_static_prop(
+				schema
+					.as_ref()
+					.ok()
+					.and_then(Option::as_ref)
+					.map(Prop::from),
+			)
+			.with_static_prop(
+				version.map(|version| Prop::primitive(Name::SchemaVersion, version.into())),
+			)
+			.with_dynamic_prop(Some(Box::new(Files {
+				layers: property::layers(&self),
+				templates: self.templates.clone(),
+			})))
+			.with_dynamic_prop(Some(Box::new(FilesDigest {
+				layers: property::layers(&self),
+				templates: self.templates.clone(),
+			})))
+			.with_dynamic_prop(Some(Box::new(Include)))
+			.with_dynamic_prop(Some(Box::new(ReadFile)))
+			.with_dynamic_prop(Some(Box::new(ImportBin)))
+			.with_dynamic_prop(Some(Box::new(Semver)))
+			.with_dynamic_prop(Some(Box::new(Regex)))
+			.with_dynamic_prop(Some(Box::new(Uuid)))
+			.with_dynamic_prop(Some(Box::new(UuidFrom)))
+			.with_dynamic_prop(Some(Box::new(Time)))
+			.with_dynamic_prop(Some(Box::new(Duration)))
+			.with_dynamic_prop(Some(Box::new(Base64)))
+			.with_dynamic_prop(Some(Box::new(Url)))
+			.with_dynamic_prop(Some(Box::new(Pem)))
+			.with_dynamic_prop(Some(Box::new(Tls)))
+			.with_dynamic_prop(Some(Box::new(Random {
+				declared: declared(Capability::Secrets),
+			})))
+			.with_dynamic_prop(Some(Box::new(Net)))
+			.with_dynamic_prop(Some(Box::new(Quantity)))
+			.with_dynamic_prop(Some(Box::new(Names)))
+			.with_dynamic_prop(Some(Box::new(Patch)))
+			.with_dynamic_prop(Some(Box::new(JsonPath)))
+			.with_dynamic_prop(Some(Box::new(Kustomize {
+				declared: self.kustomizations(),
+			})))
+			.with_dynamic_prop(Some(Box::new(Http {
+				declared: declared(Capability::Network),
+			})))
+			.with_dynamic_prop(Some(Box::new(Lookup {
+				declared: declared(Capability::Lookup),
+			})));
+
+		let files = self.constraints();
+		compiler = match schema {
+			Ok(Some(schema)) => compiler.with_check(schema.into()),
+			Ok(None) => compiler,
+			Err(reason) => compiler.with_check(Validator::new(Box::new(move |_: &Value| {
+				Err(reason.clone())
+			}))),
+		};
+		compiler = match files.is_empty() {
+			true => compiler,
+			false => compiler.with_check(Cue { files }.into()),
 		};
 
 		compiler
 	}
 }
 
-impl From<&Package> for Target {
-	fn from(package: &Package) -> Self {
+impl TryFrom<&Package> for Target {
+	type Error = Error;
+
+	fn try_from(package: &Package) -> Result<Self, Self::Error> {
 		let dir = package.root.clone();
-		let main = package.main.clone();
+		let main = match (&package.main, package.spec.kind) {
+			(_, Kind::Library) => return Err(Error::Library(package.spec.name.clone())),
+			(None, _) => return Err(Error::NoMain),
+			(Some(main), _) => main.clone(),
+		};
 
-		TargetBuilder::default()
+		let target = TargetBuilder::default()
 			.dir(dir)
 			.main(main)
+			.bases(package.bases())
+			.libraries(package.libraries())
+			.aliases(package.aliases())
+			.dialect(package.dialect())
+			.sdk(package.sdk())
+			.format(match package.spec.output {
+				Output::Text => Format::String,
+				_ => Format::Json,
+			})
 			.build()
-			.unwrap()
+			.unwrap();
+
+		Ok(target)
 	}
 }
 
@@ -181,3 +652,29 @@ impl From<&Package> for Prop {
 		Prop::primitive(Name::Package, val.into())
 	}
 }
+
+/// Jsonnet within the templates and libs of the package at the root
+fn jsonnet(root: &Path) -> Vec<PathBuf> {
+	let mut paths = vec![];
+
+	for dir in [TEMPLATES_DIR, LIB_DIR] {
+		let dir = root.join(dir);
+		if !dir.exists() {
+			continue;
+		}
+
+		let walker = match GlobWalkerBuilder::new(&dir, "**/*.{jsonnet,libsonnet}").build() {
+			Ok(walker) => walker,
+			Err(_) => continue,
+		};
+		let mut found: Vec<PathBuf> = walker
+			.filter_map(Result::ok)
+			.map(|entry| entry.path().to_path_buf())
+			.collect();
+		found.sort();
+
+		paths.extend(found);
+	}
+
+	paths
+}
@@ -0,0 +1,152 @@
+use crate::error::Error;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use kct_helper::io;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+pub(crate) const LOCK_FILE: &str = "jsonnetfile.lock.json";
+pub(crate) const VENDOR_DIR: &str = "vendor";
+
+/// How the vendored dependencies are checked against the lockfile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Integrity {
+	/// Refuses drifts when there's a lockfile, the default
+	#[default]
+	Verify,
+	/// Also requires the lockfile and every locked dependency to be vendored
+	Frozen,
+	/// Records the current digests into the lockfile instead of refusing drifts
+	Update,
+}
+
+/// Checks the dependencies vendored at the root against the digests Jsonnet
+/// Bundler recorded in the lockfile
+pub fn verify(root: &Path, integrity: Integrity) -> Result<(), Error> {
+	let path = root.join(LOCK_FILE);
+
+	if !path.exists() {
+		return match integrity {
+			Integrity::Frozen => Err(Error::NoLock),
+			_ => Ok(()),
+		};
+	}
+
+	let contents = io::from_file(&path).map_err(|_err| Error::InvalidLock)?;
+	let mut lock: Value = serde_json::from_str(&contents).map_err(|_err| Error::InvalidLock)?;
+
+	let dependencies = match lock.get_mut("dependencies") {
+		None => return Ok(()),
+		Some(Value::Array(dependencies)) => dependencies,
+		Some(_) => return Err(Error::InvalidLock),
+	};
+
+	let mut updated = false;
+
+	for dependency in dependencies.iter_mut() {
+		// Local dependencies are symlinked, so there's nothing to drift
+		let name = match vendored_name(dependency)? {
+			Some(name) => name,
+			None => continue,
+		};
+
+		let dir = root.join(VENDOR_DIR).join(&name);
+		let display = name.display().to_string();
+
+		if !dir.exists() {
+			match integrity {
+				Integrity::Frozen => return Err(Error::NotVendored(display)),
+				_ => continue,
+			}
+		}
+
+		let sum = digest(&dir).map_err(|_err| Error::NotVendored(display.clone()))?;
+
+		match (dependency.get("sum").and_then(Value::as_str), integrity) {
+			(Some(recorded), _) if recorded == sum => (),
+			(_, Integrity::Update) => {
+				dependency["sum"] = Value::String(sum);
+				updated = true;
+			}
+			(Some(_), _) => return Err(Error::Drift(display)),
+			(None, _) => (),
+		}
+	}
+
+	if updated {
+		let contents = serde_json::to_string_pretty(&lock).map_err(|_err| Error::InvalidLock)?;
+
+		io::write_contents(&path, &format!("{contents}\n")).map_err(|_err| Error::InvalidLock)?;
+	}
+
+	Ok(())
+}
+
+/// Directory within `vendor` holding a git dependency, derived from its
+/// remote and subdirectory as Jsonnet Bundler does
+fn vendored_name(dependency: &Value) -> Result<Option<PathBuf>, Error> {
+	let git = match dependency.pointer("/source/git") {
+		None => return Ok(None),
+		Some(git) => git,
+	};
+
+	let remote = git
+		.get("remote")
+		.and_then(Value::as_str)
+		.ok_or(Error::InvalidLock)?;
+	let subdir = git.get("subdir").and_then(Value::as_str);
+
+	Ok(Some(name(remote, subdir)))
+}
+
+/// Directory within `vendor` for a repository, its host and path without
+/// scheme, user, or `.git`, followed by the subdirectory
+pub(crate) fn name(remote: &str, subdir: Option<&str>) -> PathBuf {
+	let remote = remote.split_once("://").map_or(remote, |(_, rest)| rest);
+	let remote = remote.split_once('@').map_or(remote, |(_, rest)| rest);
+	let remote = remote.strip_suffix(".git").unwrap_or(remote);
+	let remote = remote.replace(':', "/");
+
+	let mut name = PathBuf::from(remote.trim_matches('/'));
+	if let Some(subdir) = subdir.map(|subdir| subdir.trim_matches('/')) {
+		if !subdir.is_empty() {
+			name.push(subdir);
+		}
+	}
+
+	name
+}
+
+/// SHA-256 over the contents of every file in the directory, walked in
+/// lexical order, encoded as Base64 like the sums in the lockfile
+pub(crate) fn digest(dir: &Path) -> std::io::Result<String> {
+	let mut hasher = Sha256::new();
+
+	walk(&io::canonical(dir)?, &mut hasher)?;
+
+	Ok(STANDARD.encode(hasher.finalize()))
+}
+
+fn walk(dir: &Path, hasher: &mut Sha256) -> std::io::Result<()> {
+	let mut entries = fs::read_dir(dir)?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<std::io::Result<Vec<PathBuf>>>()?;
+	entries.sort();
+
+	for entry in entries {
+		let metadata = fs::symlink_metadata(&entry)?;
+
+		if metadata.is_dir() {
+			walk(&entry, hasher)?;
+		} else if entry.is_file() {
+			hasher.update(fs::read(&entry)?);
+			io::record(&entry);
+		}
+	}
+
+	Ok(())
+}
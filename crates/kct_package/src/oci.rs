@@ -0,0 +1,257 @@
+use crate::error::Error;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use ureq::{Agent, AgentBuilder, Response};
+
+const MANIFEST_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_TYPE: &str = "application/vnd.cncf.flux.config.v1+json";
+const CONTENT_TYPE: &str = "application/vnd.cncf.flux.content.v1.tar+gzip";
+
+/// Where an artifact goes, as `[oci://]registry/repository[:tag]`, tagged
+/// `latest` when there's no tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+	pub registry: String,
+	pub repository: String,
+	pub tag: String,
+}
+
+impl FromStr for Reference {
+	type Err = Error;
+
+	fn from_str(reference: &str) -> Result<Self, Self::Err> {
+		let invalid = || Error::InvalidReference(reference.to_string());
+
+		let rest = reference.strip_prefix("oci://").unwrap_or(reference);
+		let (registry, rest) = rest.split_once('/').ok_or_else(invalid)?;
+		let (repository, tag) = match rest.rsplit_once(':') {
+			Some((repository, tag)) => (repository, tag),
+			None => (rest, "latest"),
+		};
+
+		if registry.is_empty() || repository.is_empty() || tag.is_empty() {
+			return Err(invalid());
+		}
+
+		Ok(Reference {
+			registry: registry.to_string(),
+			repository: repository.to_string(),
+			tag: tag.to_string(),
+		})
+	}
+}
+
+impl fmt::Display for Reference {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}:{}", self.registry, self.repository, self.tag)
+	}
+}
+
+/// How to reach the registry
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+	/// Username and password, or token, sent when the registry asks for them
+	pub credentials: Option<(String, String)>,
+	/// Whether to talk plain HTTP, as local registries do
+	pub insecure: bool,
+}
+
+/// Pushes the gzipped tarball as the single layer of an artifact in the
+/// format Flux's OCIRepository consumes, with the annotations on its
+/// manifest, returning the digest of the manifest
+pub fn push(
+	reference: &Reference,
+	registry: &Registry,
+	layer: &[u8],
+	annotations: &BTreeMap<String, String>,
+) -> Result<String, Error> {
+	let failed = |reason: String| Error::Push(reference.to_string(), reason);
+
+	let mut session = Session {
+		agent: AgentBuilder::new().build(),
+		base: match registry.insecure {
+			true => format!("http://{}", reference.registry),
+			false => format!("https://{}", reference.registry),
+		},
+		registry,
+		authorization: None,
+	};
+
+	let config = b"{}";
+	let config_digest = session
+		.upload(&reference.repository, config)
+		.map_err(failed)?;
+	let layer_digest = session
+		.upload(&reference.repository, layer)
+		.map_err(failed)?;
+
+	let manifest = json!({
+		"schemaVersion": 2,
+		"mediaType": MANIFEST_TYPE,
+		"config": {
+			"mediaType": CONFIG_TYPE,
+			"digest": config_digest,
+			"size": config.len(),
+		},
+		"layers": [{
+			"mediaType": CONTENT_TYPE,
+			"digest": layer_digest,
+			"size": layer.len(),
+		}],
+		"annotations": annotations,
+	});
+	let manifest = serde_json::to_vec(&manifest).map_err(|err| failed(err.to_string()))?;
+
+	let url = format!(
+		"{}/v2/{}/manifests/{}",
+		session.base, reference.repository, reference.tag
+	);
+	session
+		.send("PUT", &url, Some(MANIFEST_TYPE), &manifest)
+		.map_err(failed)?;
+
+	Ok(digest(&manifest))
+}
+
+struct Session<'a> {
+	agent: Agent,
+	base: String,
+	registry: &'a Registry,
+	/// Header the registry accepted, or asked for, reused for every request
+	authorization: Option<String>,
+}
+
+impl Session<'_> {
+	/// Uploads the blob in a single request, returning its digest
+	fn upload(&mut self, repository: &str, blob: &[u8]) -> Result<String, String> {
+		let digest = digest(blob);
+
+		let url = format!("{}/v2/{repository}/blobs/uploads/", self.base);
+		let started = self.send("POST", &url, None, &[])?;
+		let location = started
+			.header("Location")
+			.ok_or_else(|| String::from("the registry didn't tell where to upload"))?;
+
+		let location = match location.starts_with('/') {
+			true => format!("{}{location}", self.base),
+			false => location.to_string(),
+		};
+		let separator = if location.contains('?') { '&' } else { '?' };
+		let url = format!("{location}{separator}digest={digest}");
+
+		self.send("PUT", &url, Some("application/octet-stream"), blob)?;
+
+		Ok(digest)
+	}
+
+	/// Sends the request, authorizing it as the registry asks when refused
+	fn send(
+		&mut self,
+		method: &str,
+		url: &str,
+		content_type: Option<&str>,
+		body: &[u8],
+	) -> Result<Response, String> {
+		let request = |authorization: Option<&str>| {
+			let request = self.agent.request(method, url);
+			let request = match content_type {
+				Some(content_type) => request.set("Content-Type", content_type),
+				None => request,
+			};
+			let request = match authorization {
+				Some(authorization) => request.set("Authorization", authorization),
+				None => request,
+			};
+
+			request.send_bytes(body).map_err(Box::new)
+		};
+
+		let refused = match request(self.authorization.as_deref()).map_err(|err| *err) {
+			Err(ureq::Error::Status(401, response)) if self.authorization.is_none() => response,
+			result => return result.map_err(describe),
+		};
+
+		let challenge = refused.header("WWW-Authenticate").unwrap_or_default();
+		let authorization = self.authorize(challenge)?;
+		let response = request(Some(&authorization)).map_err(|err| describe(*err))?;
+		self.authorization = Some(authorization);
+
+		Ok(response)
+	}
+
+	/// Authorization answering the challenge, exchanging the credentials for
+	/// a token when the registry delegates to a token service
+	fn authorize(&self, challenge: &str) -> Result<String, String> {
+		let basic = self
+			.registry
+			.credentials
+			.as_ref()
+			.map(|(username, password)| {
+				format!(
+					"Basic {}",
+					STANDARD.encode(format!("{username}:{password}"))
+				)
+			});
+
+		let (scheme, params) = challenge.split_once(' ').unwrap_or((challenge, ""));
+		if !scheme.eq_ignore_ascii_case("bearer") {
+			return basic.ok_or_else(|| String::from("the registry requires credentials"));
+		}
+
+		let params: BTreeMap<&str, &str> = params
+			.split(',')
+			.filter_map(|param| param.trim().split_once('='))
+			.map(|(key, value)| (key, value.trim_matches('"')))
+			.collect();
+		let realm = params
+			.get("realm")
+			.ok_or_else(|| String::from("the registry didn't tell where to get a token"))?;
+
+		let request = self.agent.get(realm);
+		let request = params
+			.iter()
+			.filter(|(key, _)| **key == "service" || **key == "scope")
+			.fold(request, |request, (key, value)| request.query(key, value));
+		let request = match &basic {
+			Some(basic) => request.set("Authorization", basic),
+			None => request,
+		};
+
+		let token = request
+			.call()
+			.map_err(describe)?
+			.into_string()
+			.map_err(|err| err.to_string())?;
+		let token: Value = serde_json::from_str(&token).map_err(|err| err.to_string())?;
+		let token = token
+			.get("token")
+			.or_else(|| token.get("access_token"))
+			.and_then(Value::as_str)
+			.ok_or_else(|| String::from("the token service didn't answer with a token"))?;
+
+		Ok(format!("Bearer {token}"))
+	}
+}
+
+fn describe(err: ureq::Error) -> String {
+	match err {
+		ureq::Error::Status(status, response) => {
+			let url = response.get_url().to_string();
+			let body = response.into_string().unwrap_or_default();
+
+			format!("{url} answered {status} {}", body.trim())
+		}
+		err => err.to_string(),
+	}
+}
+
+fn digest(contents: &[u8]) -> String {
+	format!("sha256:{:x}", Sha256::digest(contents))
+}
@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::vendor::{self, Dependency, Repository, MANIFEST_FILE};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use kct_helper::io;
+use semver::Version;
+use serde_json::Value;
+
+/// How far an upgrade goes, following semantic versioning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+	Patch,
+	Minor,
+	Major,
+}
+
+impl Change {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Change::Patch => "patch",
+			Change::Minor => "minor",
+			Change::Major => "major",
+		}
+	}
+
+	fn between(current: &Version, latest: &Version) -> Self {
+		if latest.major != current.major {
+			Change::Major
+		} else if latest.minor != current.minor {
+			Change::Minor
+		} else {
+			Change::Patch
+		}
+	}
+}
+
+/// Newer release of a dependency than the one in use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upgrade {
+	pub dependency: Dependency,
+	/// Tag in use, the one the locked commit points to
+	pub current: String,
+	/// Highest tag the remote offers
+	pub latest: String,
+	pub change: Change,
+}
+
+/// Compares the dependencies declared by the package at the root with the
+/// tags their remotes offer. What's in use is read from the lockfile, or the
+/// declared version when not vendored yet, and dependencies following
+/// branches or untagged commits are left out as there's nothing to compare.
+pub fn outdated(root: &Path) -> Result<Vec<Upgrade>, Error> {
+	let contents =
+		io::from_file(&root.join(MANIFEST_FILE)).map_err(|_err| Error::NoDependencies)?;
+	let manifest: Value =
+		serde_json::from_str(&contents).map_err(|_err| Error::InvalidDependencies)?;
+	let locked: HashMap<PathBuf, String> = vendor::locked(root)?
+		.into_iter()
+		.map(|locked| (locked.dependency.name(), locked.rev))
+		.collect();
+
+	let mut upgrades = vec![];
+	for dependency in vendor::dependencies(&manifest)? {
+		let name = dependency.name();
+		let display = name.display().to_string();
+		let failed = |reason: String| Error::Fetch(display.clone(), reason);
+
+		let repository = Repository::clone(&dependency.remote).map_err(failed)?;
+		let tags = repository.tags().map_err(failed)?;
+
+		let current = match locked.get(&name) {
+			Some(rev) => tags
+				.iter()
+				.filter(|(tag, _)| repository.rev(tag).as_ref() == Ok(rev))
+				.max_by(|(_, a), (_, b)| a.cmp(b))
+				.cloned(),
+			None => None,
+		};
+		let current = current.or_else(|| {
+			let tag = dependency.version.as_deref()?;
+
+			vendor::parse_tag(tag).map(|version| (tag.to_string(), version))
+		});
+
+		let (current, version) = match current {
+			Some(current) => current,
+			None => continue,
+		};
+
+		// Pre-releases are only offered to who already follows them
+		let latest = tags
+			.into_iter()
+			.filter(|(_, latest)| latest.pre.is_empty() || !version.pre.is_empty())
+			.max_by(|(_, a), (_, b)| a.cmp(b));
+
+		if let Some((latest, newer)) = latest.filter(|(_, newer)| newer > &version) {
+			upgrades.push(Upgrade {
+				change: Change::between(&version, &newer),
+				dependency,
+				current,
+				latest,
+			});
+		}
+	}
+
+	Ok(upgrades)
+}
@@ -0,0 +1,156 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::{json, Value};
+
+/// Everything but the unreserved characters from RFC 3986
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'-')
+	.remove(b'_')
+	.remove(b'.')
+	.remove(b'~');
+
+const PEM_WIDTH: usize = 64;
+
+pub struct Base64;
+
+pub struct Url;
+
+pub struct Pem;
+
+fn decode_base64(raw: &str) -> Result<Vec<u8>, String> {
+	let compact: String = raw.split_whitespace().collect();
+
+	STANDARD
+		.decode(compact)
+		.map_err(|err| format!("Invalid base64: {err}"))
+}
+
+fn base64_encode(params: HashMap<String, Value>) -> Result<Value, String> {
+	let text = param(&params, "str")?;
+
+	Ok(json!(STANDARD.encode(text)))
+}
+
+fn base64_decode(params: HashMap<String, Value>) -> Result<Value, String> {
+	let decoded = decode_base64(param(&params, "str")?)?;
+	let text = String::from_utf8(decoded)
+		.map_err(|_err| String::from("Decoded value isn't valid UTF-8"))?;
+
+	Ok(json!(text))
+}
+
+fn url_encode(params: HashMap<String, Value>) -> Result<Value, String> {
+	let text = param(&params, "str")?;
+
+	Ok(json!(utf8_percent_encode(text, COMPONENT).to_string()))
+}
+
+fn url_decode(params: HashMap<String, Value>) -> Result<Value, String> {
+	let text = param(&params, "str")?;
+	let decoded = percent_decode_str(text)
+		.decode_utf8()
+		.map_err(|_err| String::from("Decoded value isn't valid UTF-8"))?;
+
+	Ok(json!(decoded))
+}
+
+fn pem_wrap(params: HashMap<String, Value>) -> Result<Value, String> {
+	let label = param(&params, "label")?;
+	let data = decode_base64(param(&params, "data")?)?;
+	let encoded = STANDARD.encode(data);
+
+	let mut pem = format!("-----BEGIN {label}-----\n");
+	for line in encoded.as_bytes().chunks(PEM_WIDTH) {
+		pem.push_str(std::str::from_utf8(line).unwrap());
+		pem.push('\n');
+	}
+	pem.push_str(&format!("-----END {label}-----\n"));
+
+	Ok(json!(pem))
+}
+
+fn pem_unwrap(params: HashMap<String, Value>) -> Result<Value, String> {
+	let text = param(&params, "pem")?;
+
+	let mut blocks = vec![];
+	let mut current: Option<(String, String)> = None;
+	for line in text.lines().map(str::trim) {
+		if let Some(label) = line
+			.strip_prefix("-----BEGIN ")
+			.and_then(|l| l.strip_suffix("-----"))
+		{
+			current = Some((label.to_string(), String::new()));
+		} else if let Some(label) = line
+			.strip_prefix("-----END ")
+			.and_then(|l| l.strip_suffix("-----"))
+		{
+			match current.take() {
+				Some((begin, data)) if begin == label => {
+					decode_base64(&data)?;
+					blocks.push(json!({ "label": begin, "data": data }));
+				}
+				_ => return Err(format!("Unexpected end of PEM block {label}")),
+			}
+		} else if let Some((_, data)) = current.as_mut() {
+			data.push_str(line);
+		}
+	}
+
+	if let Some((label, _)) = current {
+		return Err(format!("PEM block {label} isn't closed"));
+	}
+
+	Ok(Value::Array(blocks))
+}
+
+impl Generator for Base64 {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("encode", function(&["str"], base64_encode)),
+			("decode", function(&["str"], base64_decode)),
+		];
+
+		Prop::group(Name::Base64, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Base64
+	}
+}
+
+impl Generator for Url {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("encode", function(&["str"], url_encode)),
+			("decode", function(&["str"], url_decode)),
+		];
+
+		Prop::group(Name::Url, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Url
+	}
+}
+
+impl Generator for Pem {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("wrap", function(&["label", "data"], pem_wrap)),
+			("unwrap", function(&["pem"], pem_unwrap)),
+		];
+
+		Prop::group(Name::Pem, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Pem
+	}
+}
@@ -1,19 +1,35 @@
-use std::collections::HashMap;
+use crate::spec::{Delimiters, Templating};
+use crate::Package;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use globwalk::{DirEntry, GlobWalkerBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::Match;
 use kct_compiler::property::{Callback, Function, Generator, Name, Prop};
-use kct_compiler::Runtime;
+use kct_compiler::{Runtime, TemplateError};
+use kct_helper::io;
 use serde_json::{Map, Value};
-use tera::{Context, Tera};
+use sha2::{Digest, Sha256};
+use tera::{Context, Template, Tera};
+use walkdir::WalkDir;
 
 const TEMPLATES_FOLDER: &str = "files";
+/// Prefix of the globs looking into the files of vendored packages
+const VENDOR_PREFIX: &str = "vendor:";
 
-pub struct Files;
+pub struct Files {
+	pub layers: Vec<Layer>,
+	pub templates: Templates,
+}
 
 struct Handler {
-	root: PathBuf,
+	folders: Folders,
 }
 
 impl Callback for Handler {
@@ -26,7 +42,12 @@ impl Callback for Handler {
 
 		let input = params.get("input").cloned().unwrap_or(Value::Null);
 
-		let compiled = compile_template(&self.root, file, &input)?;
+		let compiled: Vec<String> = self
+			.folders
+			.compile(file, &input)?
+			.into_iter()
+			.map(|(_path, contents)| contents)
+			.collect();
 
 		if compiled.is_empty() {
 			Err(format!("No template found for glob {file}"))
@@ -42,10 +63,10 @@ impl Callback for Handler {
 
 impl Generator for Files {
 	fn generate(&self, runtime: &Runtime) -> Prop {
-		let root = runtime.target().dir().to_path_buf();
-
 		let params = vec![String::from("name"), String::from("input")];
-		let handler = Handler { root };
+		let handler = Handler {
+			folders: Folders::new(runtime, &self.layers, &self.templates),
+		};
 		let function = Function {
 			params,
 			handler: Box::new(handler),
@@ -59,42 +80,616 @@ impl Generator for Files {
 	}
 }
 
-fn compile_template(root: &Path, glob: &str, input: &Value) -> Result<Vec<String>, String> {
-	let mut templates_dir = root.to_path_buf();
-	templates_dir.push(TEMPLATES_FOLDER);
+pub struct FilesDigest {
+	pub layers: Vec<Layer>,
+	pub templates: Templates,
+}
+
+struct DigestHandler {
+	folders: Folders,
+}
+
+impl Callback for DigestHandler {
+	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String> {
+		let glob = match params.get("name") {
+			Some(Value::String(glob)) => glob,
+			_ => return Err("name should be a string".into()),
+		};
+
+		let input = params.get("input").cloned().unwrap_or(Value::Null);
+
+		let compiled = self.folders.compile(glob, &input)?;
+
+		if compiled.is_empty() {
+			return Err(format!("No template found for glob {glob}"));
+		}
+
+		// Paths take part in the digest so renaming a file also changes it,
+		// while the null separators keep path and contents from blending.
+		// They're named as on every platform, so the digest is the same.
+		let mut hasher = Sha256::new();
+		for (path, contents) in compiled {
+			hasher.update(name(&path).as_bytes());
+			hasher.update([0]);
+			hasher.update(contents.as_bytes());
+			hasher.update([0]);
+		}
+
+		Ok(Value::String(format!("{:x}", hasher.finalize())))
+	}
+}
+
+impl Generator for FilesDigest {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let params = vec![String::from("name"), String::from("input")];
+		let handler = DigestHandler {
+			folders: Folders::new(runtime, &self.layers, &self.templates),
+		};
+		let function = Function {
+			params,
+			handler: Box::new(handler),
+		};
+
+		Prop::callable(Name::FilesDigest, function)
+	}
+
+	fn name(&self) -> Name {
+		Name::FilesDigest
+	}
+}
+
+/// Root of a package whose files are rendered, along how it renders them
+#[derive(Clone)]
+pub struct Layer {
+	root: PathBuf,
+	templating: Templating,
+}
+
+/// Layers of the package, from it down to the ones it extends
+pub fn layers(package: &Package) -> Vec<Layer> {
+	let mut layers = vec![];
+	let mut current = Some(package);
+
+	while let Some(package) = current {
+		layers.push(Layer {
+			root: package.root.clone(),
+			templating: package.spec.templating.clone(),
+		});
+		current = package.base.as_deref();
+	}
+
+	layers
+}
+
+/// Files folders of the package and of the vendored ones its globs look into
+struct Folders {
+	layers: Vec<Layer>,
+	vendor: PathBuf,
+	templates: Templates,
+}
+
+impl Folders {
+	fn new(runtime: &Runtime, layers: &[Layer], templates: &Templates) -> Self {
+		Self {
+			layers: layers.to_vec(),
+			vendor: runtime.context().vendor().to_path_buf(),
+			templates: templates.clone(),
+		}
+	}
+
+	fn compile(&self, glob: &str, input: &Value) -> Result<Vec<(PathBuf, String)>, String> {
+		let glob = match glob.strip_prefix(VENDOR_PREFIX) {
+			None => return self.templates.compile(&self.layers, glob, input),
+			Some(glob) => glob,
+		};
+
+		let (name, glob) = match glob.split_once('/') {
+			Some((name, glob)) if is_name(name) && !glob.is_empty() => (name, glob),
+			_ => {
+				return Err(format!(
+					"Vendored files should be globbed as {VENDOR_PREFIX}<package>/<glob>"
+				))
+			}
+		};
+
+		let package = Package::try_from(self.vendor.join(name).as_path())
+			.map_err(|err| format!("Unable to load vendored package {name}: {err}"))?;
+
+		self.templates.compile(&layers(&package), glob, input)
+	}
+}
+
+/// Whether it names a directory within the vendor, and not something above it
+fn is_name(name: &str) -> bool {
+	let mut components = Path::new(name).components();
+
+	matches!(
+		(components.next(), components.next()),
+		(Some(Component::Normal(_)), None)
+	)
+}
+
+/// Templates within the files folders, parsed together on first use, so they
+/// can include or extend each other, and kept for as long as the package is
+/// loaded. Loading the package again is what picks up changes to them.
+/// Vendored packages have their own, as their templates only see each other.
+#[derive(Clone, Default)]
+pub struct Templates {
+	parsed: Arc<Mutex<HashMap<Vec<PathBuf>, Loaded>>>,
+	/// Last template failing, as Jsonnet only carries messages back from the
+	/// natives
+	failure: Arc<Mutex<Option<TemplateError>>>,
+}
+
+/// Templates parsed from the files folders of some layers
+type Loaded = Arc<Result<Parsed, String>>;
+
+struct Parsed {
+	/// Listing of each folder, from the package down to the ones it extends
+	listings: Vec<(PathBuf, Vec<Entry>)>,
+	tera: Tera,
+	/// Files, by name, to tell where templates failing are
+	files: HashMap<String, PathBuf>,
+	/// Contents of the files passed through as they are, by name
+	raw: HashMap<String, String>,
+	/// Why the templates that couldn't be read or parsed, by name, failed
+	broken: HashMap<String, TemplateError>,
+}
+
+/// Path within the folder, in the order walked
+struct Entry {
+	relative: PathBuf,
+	is_dir: bool,
+}
+
+impl fmt::Debug for Templates {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let parsed = self
+			.parsed
+			.lock()
+			.map(|parsed| parsed.len())
+			.unwrap_or_default();
+
+		f.debug_tuple("Templates").field(&parsed).finish()
+	}
+}
+
+impl Templates {
+	/// Template whose failure failed the compilation, if any did
+	pub fn failure(&self) -> Option<TemplateError> {
+		self.failure.lock().ok()?.take()
+	}
 
-	if !templates_dir.exists() {
-		return Err(String::from("No files folder to search for templates"));
+	fn compile(
+		&self,
+		layers: &[Layer],
+		glob: &str,
+		input: &Value,
+	) -> Result<Vec<(PathBuf, String)>, String> {
+		self.render(layers, glob, input)
+			.map_err(|failure| match failure {
+				Failure::Message(message) => message,
+				Failure::Template(err) => {
+					let message = err.to_string();
+					if let Ok(mut failure) = self.failure.lock() {
+						failure.replace(err);
+					}
+
+					message
+				}
+			})
 	}
 
-	let globwalker = GlobWalkerBuilder::new(templates_dir, glob)
+	fn render(
+		&self,
+		layers: &[Layer],
+		glob: &str,
+		input: &Value,
+	) -> Result<Vec<(PathBuf, String)>, Failure> {
+		let roots = layers.iter().map(|layer| layer.root.clone()).collect();
+		let parsed = self
+			.parsed
+			.lock()
+			.map_err(|err| err.to_string())?
+			.entry(roots)
+			.or_insert_with(|| Arc::new(Parsed::load(layers)))
+			.clone();
+		let parsed = parsed
+			.as_ref()
+			.as_ref()
+			.map_err(|err| Failure::from(err.clone()))?;
+
+		let context = match input {
+			Value::Null => Context::from_serialize(Value::Object(Map::new())).unwrap(),
+			_ => Context::from_serialize(input).unwrap(),
+		};
+
+		parsed
+			.find_templates(glob)?
+			.into_iter()
+			.map(|relative| {
+				let name = name(&relative);
+				if let Some(err) = parsed.broken.get(&name) {
+					return Err(Failure::Template(err.clone()));
+				}
+
+				if let Some(contents) = parsed.raw.get(&name) {
+					return Ok((relative, contents.clone()));
+				}
+
+				let rendered = parsed.tera.render(&name, &context).map_err(|err| {
+					let path = parsed.files.get(&name).cloned().unwrap_or_default();
+					let source = fs::read_to_string(&path).ok();
+
+					Failure::Template(failure(path, source.as_deref(), &err))
+				})?;
+
+				Ok((relative, rendered))
+			})
+			.collect()
+	}
+}
+
+impl Parsed {
+	fn load(layers: &[Layer]) -> Result<Self, String> {
+		let folders: Vec<(PathBuf, &Templating)> = layers
+			.iter()
+			.map(|layer| (layer.root.join(TEMPLATES_FOLDER), &layer.templating))
+			.filter(|(dir, _)| dir.exists())
+			.collect();
+
+		if folders.is_empty() {
+			return Err(String::from("No files folder to search for templates"));
+		}
+
+		let mut listings = vec![];
+		// Files are named by their path within the folder, so the first layer
+		// declaring one shadows the same file on the packages it extends
+		let mut contents: BTreeMap<String, String> = BTreeMap::new();
+		let mut files = HashMap::new();
+		let mut raw = HashMap::new();
+		let mut broken = HashMap::new();
+		for (dir, templating) in folders {
+			let entries = list(&dir)?;
+			let passed = matcher(&dir, &templating.raw)?;
+
+			for entry in entries.iter().filter(|entry| !entry.is_dir) {
+				let name = name(&entry.relative);
+				let seen = contents.contains_key(&name) || raw.contains_key(&name);
+				if seen || broken.contains_key(&name) {
+					continue;
+				}
+
+				let path = dir.join(&entry.relative);
+				files.insert(name.clone(), path.clone());
+
+				let content = match fs::read_to_string(&path) {
+					Ok(content) => {
+						io::record(&path);
+						content
+					}
+					Err(err) => {
+						let reason = format!("Unable to read templates: {err}");
+						broken.insert(name, TemplateError::at(path, reason));
+						continue;
+					}
+				};
+
+				// Checkouts on Windows may end lines with CRLF, which would
+				// make it into what templates render
+				if passed.matched(&entry.relative, false).is_whitelist() {
+					raw.insert(name, content);
+					continue;
+				}
+
+				let content = content.replace("\r\n", "\n");
+				if templating.delimiters == Delimiters::default() {
+					contents.insert(name, content);
+				} else {
+					contents.insert(name, translate(&content, &templating.delimiters));
+				}
+			}
+
+			listings.push((dir, entries));
+		}
+
+		let tera = match parse(&contents) {
+			Ok(tera) => tera,
+			// Only the ones failing should fail, so the rest are parsed again
+			// without them
+			Err(_) => {
+				let (valid, invalid): (Vec<_>, Vec<_>) = contents
+					.into_iter()
+					.partition(|(name, content)| Template::new(name, None, content).is_ok());
+
+				for (name, content) in invalid {
+					let err = Template::new(&name, None, &content).unwrap_err();
+					let path = files.get(&name).cloned().unwrap_or_default();
+					// Delimiters of other kinds keep lines where they were
+					let source = fs::read_to_string(&path).unwrap_or(content);
+
+					broken.insert(name, failure(path, Some(&source), &err));
+				}
+
+				match parse(valid.iter().map(|(name, content)| (name, content))) {
+					Ok(tera) => tera,
+					Err(err) => {
+						for (name, _) in valid {
+							let path = files.get(&name).cloned().unwrap_or_default();
+							broken.insert(name, failure(path, None, &err));
+						}
+
+						parse([]).map_err(|err| err.to_string())?
+					}
+				}
+			}
+		};
+
+		Ok(Self {
+			listings,
+			tera,
+			files,
+			raw,
+			broken,
+		})
+	}
+
+	/// Matches the glob against the listing of every folder, skipping what's
+	/// within the directories it ignores as walking with it would
+	fn find_templates(&self, glob: &str) -> Result<BTreeSet<PathBuf>, Failure> {
+		let mut templates = BTreeSet::new();
+
+		for (dir, entries) in &self.listings {
+			let matcher = matcher(dir, &[glob])
+				.map_err(|reason| Failure::Template(TemplateError::at(dir.clone(), reason)))?;
+
+			let mut skipped: Option<&Path> = None;
+			for entry in entries {
+				if skipped.is_some_and(|skipped| entry.relative.starts_with(skipped)) {
+					continue;
+				}
+
+				match matcher.matched(&entry.relative, entry.is_dir) {
+					Match::Whitelist(_) if !entry.is_dir => {
+						templates.insert(entry.relative.clone());
+					}
+					Match::Ignore(_) if entry.is_dir => skipped = Some(&entry.relative),
+					_ => {}
+				}
+			}
+		}
+
+		Ok(templates)
+	}
+}
+
+/// Tera with the templates, escaping all of them as one off renders did
+fn parse<'a>(templates: impl IntoIterator<Item = (&'a String, &'a String)>) -> tera::Result<Tera> {
+	let mut tera = Tera::default();
+	tera.autoescape_on(vec![""]);
+	tera.add_raw_templates(templates)?;
+
+	Ok(tera)
+}
+
+/// Matcher of the globs within the folder, matching nothing without them
+fn matcher(dir: &Path, globs: &[impl AsRef<str>]) -> Result<Override, String> {
+	let mut builder = OverrideBuilder::new(dir);
+	for glob in globs {
+		let glob = glob.as_ref();
+		builder
+			.add(glob)
+			.map_err(|err| format!("Invalid glob provided ({glob}): {err}"))?;
+	}
+
+	builder
 		.build()
-		.map_err(|err| format!("Invalid glob provided ({glob}): {err}"))?;
+		.map_err(|err| format!("Invalid glob provided: {err}"))
+}
+
+/// Rewrites the template written with the delimiters into one with Tera's,
+/// printing what looks like Tera's own tags as they are
+fn translate(template: &str, delimiters: &Delimiters) -> String {
+	let native = Delimiters::default();
+	let kinds = [
+		(&delimiters.variable, &native.variable),
+		(&delimiters.block, &native.block),
+		(&delimiters.comment, &native.comment),
+	];
+
+	let mut translated = String::with_capacity(template.len());
+	let mut rest = template;
+	let mut raw = false;
+
+	loop {
+		// Within raw blocks only the block tags are looked for, to find the end
+		let tag = kinds
+			.iter()
+			.filter(|(custom, _)| !raw || *custom == &delimiters.block)
+			.filter_map(|kind| rest.find(kind.0 .0.as_str()).map(|at| (at, kind)))
+			.min_by_key(|(at, (custom, _))| (*at, Reverse(custom.0.len())));
+		// Tera's own openers of the kinds with other delimiters are escaped
+		let escaped = kinds
+			.iter()
+			.filter(|(custom, native)| !raw && custom.0 != native.0)
+			.filter_map(|(_, native)| rest.find(native.0.as_str()).map(|at| (at, native)))
+			.min_by_key(|(at, _)| *at);
 
-	let entries: Vec<DirEntry> = globwalker
-		.collect::<Result<_, _>>()
-		.map_err(|err| format!("Unable to resolve globs: {err}"))?;
+		if let Some((at, native)) = escaped {
+			if tag.is_none_or(|(tag, _)| at < tag) {
+				translated.push_str(&rest[..at]);
+				translated.push_str(&format!("{{{{ \"{}\" }}}}", native.0));
+				rest = &rest[at + native.0.len()..];
 
-	let mut paths: Vec<PathBuf> = entries.into_iter().map(DirEntry::into_path).collect();
+				continue;
+			}
+		}
 
-	paths.sort();
+		let (at, (custom, native)) = match tag {
+			Some(tag) => tag,
+			None => break,
+		};
+		let inner = &rest[at + custom.0.len()..];
+		let (body, after) = match inner.find(custom.1.as_str()) {
+			Some(end) => (&inner[..end], &inner[end + custom.1.len()..]),
+			None => break,
+		};
 
-	let contents: Vec<String> = paths
-		.into_iter()
-		.map(fs::read_to_string)
-		.collect::<Result<_, _>>()
-		.map_err(|err| format!("Unable to read templates: {err}"))?;
+		let block = *custom == &delimiters.block;
+		let keyword = body.trim_matches(|c: char| c == '-' || c.is_whitespace());
+		match (raw, block, keyword) {
+			(false, true, "raw") => raw = true,
+			(true, true, "endraw") => raw = false,
+			// Tags within raw blocks are printed as they are
+			(true, _, _) => {
+				let length = rest.len() - after.len();
+				translated.push_str(&rest[..length]);
+				rest = after;
+
+				continue;
+			}
+			_ => (),
+		}
 
-	let context = match input {
-		Value::Null => Context::from_serialize(Value::Object(Map::new())).unwrap(),
-		_ => Context::from_serialize(input).unwrap(),
+		translated.push_str(&rest[..at]);
+		translated.push_str(&native.0);
+		translated.push_str(body);
+		translated.push_str(&native.1);
+		rest = after;
+	}
+
+	translated.push_str(rest);
+
+	translated
+}
+
+/// Name of the file within the folder, as templates include it
+fn name(relative: &Path) -> String {
+	io::slashed(relative)
+}
+
+fn list(dir: &Path) -> Result<Vec<Entry>, String> {
+	let mut entries = vec![];
+
+	for entry in WalkDir::new(dir).min_depth(1) {
+		let entry = entry.map_err(|err| format!("Unable to resolve globs: {err}"))?;
+		let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+
+		entries.push(Entry {
+			relative: relative.to_path_buf(),
+			is_dir: entry.file_type().is_dir(),
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Why a call failed, the templates failing telling where
+enum Failure {
+	Message(String),
+	Template(TemplateError),
+}
+
+impl From<String> for Failure {
+	fn from(message: String) -> Self {
+		Failure::Message(message)
+	}
+}
+
+trait At {
+	fn at(path: PathBuf, reason: String) -> Self;
+}
+
+impl At for TemplateError {
+	fn at(path: PathBuf, reason: String) -> Self {
+		TemplateError {
+			path,
+			line: None,
+			column: None,
+			snippet: None,
+			reason,
+		}
+	}
+}
+
+/// Failure of the template at the path, located with what Tera tells. Only
+/// parsing tells the line and column, while rendering tells the variable
+/// missing, located at its first use
+fn failure(path: PathBuf, source: Option<&str>, err: &tera::Error) -> TemplateError {
+	let mut reasons = vec![];
+	let mut current: Option<&dyn std::error::Error> = Some(err);
+	while let Some(err) = current {
+		reasons.push(err.to_string());
+		current = err.source();
+	}
+
+	// Parsing errors come formatted by pest, starting with ` --> line:column`
+	// and ending with what was expected
+	let parsed = reasons.iter().find_map(|reason| {
+		let position = reason.trim_start().strip_prefix("--> ")?.lines().next()?;
+		let (line, column) = position.split_once(':')?;
+		let expected = reason
+			.lines()
+			.rev()
+			.find_map(|line| line.trim().strip_prefix("= "));
+
+		Some((
+			line.parse().ok()?,
+			column.parse().ok()?,
+			expected?.to_string(),
+		))
+	});
+
+	let (line, column, reason) = match parsed {
+		Some((line, column, expected)) => (Some(line), Some(column), expected),
+		None => {
+			let missing = reasons.iter().find_map(|reason| {
+				let variable = reason.strip_prefix("Variable `")?.split('`').next()?;
+
+				source?
+					.lines()
+					.position(|line| line.contains(variable))
+					.map(|line| line + 1)
+			});
+
+			// The innermost is the one telling why
+			(missing, None, reasons.pop().unwrap_or_default())
+		}
 	};
 
-	let compiled: Vec<String> = contents
-		.into_iter()
-		.map(|content| Tera::one_off(&content, &context, true))
-		.collect::<Result<_, _>>()
-		.map_err(|err| format!("Unable to compile templates: {err}"))?;
+	let snippet = line.and_then(|line| source.map(|source| snippet(source, line, column)));
+
+	TemplateError {
+		path,
+		line,
+		column,
+		snippet,
+		reason: format!("Unable to compile templates: {reason}"),
+	}
+}
+
+/// Lines around the one given, pointing at the column when known
+fn snippet(source: &str, line: usize, column: Option<usize>) -> String {
+	// Errors at the end of the file point past its last line break
+	let mut lines: Vec<&str> = source.split('\n').collect();
+	if lines.len() > line && lines.last() == Some(&"") {
+		lines.pop();
+	}
+
+	let first = line.saturating_sub(2).max(1);
+	let last = (line + 1).min(lines.len());
+	let width = last.to_string().len();
+
+	let mut snippet = vec![];
+	for number in first..=last {
+		let text = lines.get(number - 1).copied().unwrap_or_default();
+		snippet.push(format!("{number:>width$} | {text}"));
+
+		if let (true, Some(column)) = (number == line, column) {
+			let padding = " ".repeat(column.saturating_sub(1));
+			snippet.push(format!("{:>width$} | {padding}^", ""));
+		}
+	}
 
-	Ok(compiled)
+	snippet.join("\n")
 }
@@ -0,0 +1,166 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::{Http as Settings, Runtime};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed before giving up, each checked against the allowed
+const REDIRECTS: usize = 5;
+
+pub struct Http {
+	/// Whether the package declared the network capability
+	pub declared: bool,
+}
+
+/// Allowed when it shares scheme, host, and port with an allowed prefix
+/// whose path segments it begins with, so `https://example.com` doesn't let
+/// `https://example.com.evil.org` through, nor `/api` lets `/apiary`
+fn is_allowed(url: &Url, allow: &[String]) -> bool {
+	allow
+		.iter()
+		.filter_map(|raw| Url::parse(raw).ok())
+		.any(|prefix| {
+			prefix.scheme() == url.scheme()
+				&& prefix.host() == url.host()
+				&& prefix.port_or_known_default() == url.port_or_known_default()
+				&& within(url.path(), prefix.path())
+		})
+}
+
+fn within(path: &str, prefix: &str) -> bool {
+	match path.strip_prefix(prefix) {
+		Some(rest) => rest.is_empty() || prefix.ends_with('/') || rest.starts_with('/'),
+		None => false,
+	}
+}
+
+fn refused(raw: &str) -> String {
+	format!("{raw} isn't allowed, HTTP requests are disabled unless you allow them")
+}
+
+/// Requests the URL following its redirects by hand, as each of them must
+/// be allowed as well
+fn fetch(allow: &[String], raw: &str, url: Url) -> Result<String, String> {
+	let agent = ureq::AgentBuilder::new()
+		.timeout(TIMEOUT)
+		.redirects(0)
+		.build();
+	let mut url = url;
+
+	for _ in 0..=REDIRECTS {
+		let response = agent
+			.get(url.as_str())
+			.call()
+			.map_err(|err| format!("Unable to request {raw}: {err}"))?;
+
+		if !(300..400).contains(&response.status()) {
+			return response
+				.into_string()
+				.map_err(|err| format!("Unable to read {raw}: {err}"));
+		}
+
+		let location = response
+			.header("location")
+			.ok_or_else(|| format!("Unable to request {raw}: {url} redirects nowhere"))?;
+		let next = url.join(location).map_err(|err| {
+			format!("Unable to request {raw}: {url} redirects to {location}: {err}")
+		})?;
+
+		if !is_allowed(&next, allow) {
+			return Err(format!("{raw} redirects to {}", refused(next.as_str())));
+		}
+
+		url = next;
+	}
+
+	Err(format!(
+		"Unable to request {raw}: it redirects more than {REDIRECTS} times"
+	))
+}
+
+fn cached(path: &PathBuf, ttl: Duration) -> Option<String> {
+	let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+	let age = SystemTime::now()
+		.duration_since(modified)
+		.unwrap_or_default();
+
+	if age < ttl {
+		fs::read_to_string(path).ok()
+	} else {
+		None
+	}
+}
+
+fn get(settings: &Settings, offline: bool, raw: &str) -> Result<Value, String> {
+	let url = Url::parse(raw).map_err(|err| format!("Invalid URL {raw}: {err}"))?;
+
+	if !is_allowed(&url, &settings.allow) {
+		return Err(refused(raw));
+	}
+
+	let entry = settings.cache.as_ref().map(|dir| {
+		let digest = format!("{:x}", Sha256::digest(url.as_str().as_bytes()));
+
+		dir.join(digest)
+	});
+
+	// Offline, whatever was cached is better than nothing, however old
+	let ttl = match offline {
+		true => Duration::MAX,
+		false => settings.ttl,
+	};
+
+	if let Some(body) = entry.as_ref().and_then(|path| cached(path, ttl)) {
+		return Ok(json!(body));
+	}
+
+	if offline {
+		return Err(format!(
+			"{raw} isn't cached, requests are refused when compiling offline"
+		));
+	}
+
+	let body = fetch(&settings.allow, raw, url)?;
+
+	// Failing to cache only costs another request on the next render
+	if let Some(path) = entry {
+		let _ = path
+			.parent()
+			.map_or(Ok(()), fs::create_dir_all)
+			.and_then(|_| fs::write(&path, &body));
+	}
+
+	Ok(json!(body))
+}
+
+impl Generator for Http {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let settings = runtime.context().http().clone();
+		let offline = runtime.context().offline();
+		let declared = self.declared;
+		let handler = move |params: HashMap<String, Value>| {
+			if !declared {
+				return Err(String::from(
+					"The package must declare the network capability to make requests",
+				));
+			}
+
+			get(&settings, offline, param(&params, "url")?)
+		};
+
+		Prop::group(Name::Http, vec![("get", function(&["url"], handler))])
+	}
+
+	fn name(&self) -> Name {
+		Name::Http
+	}
+}
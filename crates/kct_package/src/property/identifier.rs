@@ -0,0 +1,58 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use serde_json::{json, Value};
+use uuid::Uuid as Id;
+
+pub struct Uuid;
+
+pub struct UuidFrom;
+
+fn random(_params: HashMap<String, Value>) -> Result<Value, String> {
+	Ok(json!(Id::new_v4().to_string()))
+}
+
+/// Namespaces can be a UUID, one of the RFC 4122 names, or any other string,
+/// which is turned into a UUID itself so packages can use their own names
+fn namespace(raw: &str) -> Id {
+	match raw {
+		"dns" => Id::NAMESPACE_DNS,
+		"url" => Id::NAMESPACE_URL,
+		"oid" => Id::NAMESPACE_OID,
+		"x500" => Id::NAMESPACE_X500,
+		_ => Id::parse_str(raw).unwrap_or_else(|_| Id::new_v5(&Id::NAMESPACE_URL, raw.as_bytes())),
+	}
+}
+
+fn deterministic(params: HashMap<String, Value>) -> Result<Value, String> {
+	let namespace = namespace(param(&params, "namespace")?);
+	let name = param(&params, "name")?;
+
+	Ok(json!(Id::new_v5(&namespace, name.as_bytes()).to_string()))
+}
+
+impl Generator for Uuid {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		Prop::callable(Name::Uuid, function(&[], random))
+	}
+
+	fn name(&self) -> Name {
+		Name::Uuid
+	}
+}
+
+impl Generator for UuidFrom {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		Prop::callable(
+			Name::UuidFrom,
+			function(&["namespace", "name"], deterministic),
+		)
+	}
+
+	fn name(&self) -> Name {
+		Name::UuidFrom
+	}
+}
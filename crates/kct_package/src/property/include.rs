@@ -1,16 +1,64 @@
-use crate::Package;
+use crate::spec::Spec;
+use crate::{Kind, Package};
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
 use kct_compiler::property::{Callback, Function, Generator, Name, Prop};
-use kct_compiler::{Compiler, Context, Input, Runtime};
+use kct_compiler::{Compiler, Context, Input, Runtime, Target};
+use kct_helper::progress::Event;
 use serde_json::Value;
 
 pub struct Include;
 
 struct Handler {
 	context: Context,
+	/// Subpackages by the order they were first included in
+	included: Mutex<Vec<PathBuf>>,
+	/// Subpackages within the vendor directory, counted once something is
+	/// included
+	total: OnceLock<usize>,
+}
+
+impl Handler {
+	fn total(&self) -> usize {
+		*self
+			.total
+			.get_or_init(|| subpackages(self.context.vendor()))
+	}
+}
+
+/// Packages within the directory that can be included, reading only their
+/// manifests and not going into them, so their own vendored ones are left out
+fn subpackages(dir: &Path) -> usize {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(_) => return 0,
+	};
+
+	entries
+		.filter_map(Result::ok)
+		.map(|entry| {
+			let path = entry.path();
+			let manifest = path.join("kcp.json");
+
+			if manifest.is_file() {
+				return match Spec::try_from(manifest) {
+					Ok(spec) if spec.kind != Kind::Library => 1,
+					_ => 0,
+				};
+			}
+
+			// Links are only followed to packages, so they can't loop
+			match entry.file_type() {
+				Ok(kind) if kind.is_dir() => subpackages(&path),
+				_ => 0,
+			}
+		})
+		.sum()
 }
 
 impl Callback for Handler {
@@ -24,10 +72,28 @@ impl Callback for Handler {
 		let root = self.context.vendor().join(package);
 		let package = Package::try_from(root.as_path()).map_err(|err| err.to_string())?;
 
+		let position = {
+			let mut included = self.included.lock().unwrap();
+			match included.iter().position(|included| *included == root) {
+				Some(index) => index + 1,
+				None => {
+					included.push(root.clone());
+					included.len()
+				}
+			}
+		};
+		let total = self.total().max(position);
+		self.context.progress().report(Event::Including {
+			package: package.spec.name.clone(),
+			position,
+			total,
+		});
+
+		let target = Target::try_from(&package).map_err(|err| err.to_string())?;
 		let prop = params.get("input").cloned().map(|v| (&Input(v)).into());
 		let compiler = Compiler::new(&self.context)
 			.with_static_prop(prop)
-			.with_target((&package).into());
+			.with_target(target);
 
 		let rendered = package
 			.compile_with(compiler)
@@ -41,7 +107,11 @@ impl Generator for Include {
 	fn generate(&self, runtime: &Runtime) -> Prop {
 		let context = runtime.context().clone();
 		let params = vec![String::from("name"), String::from("input")];
-		let handler = Handler { context };
+		let handler = Handler {
+			context,
+			included: Mutex::new(vec![]),
+			total: OnceLock::new(),
+		};
 		let function = Function {
 			params,
 			handler: Box::new(handler),
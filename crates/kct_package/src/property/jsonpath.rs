@@ -0,0 +1,30 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use serde_json::Value;
+use serde_json_path::JsonPath as Path;
+
+pub struct JsonPath;
+
+fn query(params: HashMap<String, Value>) -> Result<Value, String> {
+	let raw = param(&params, "path")?;
+	let path = Path::parse(raw).map_err(|err| format!("Invalid JSONPath {raw}: {err}"))?;
+	let target = params.get("target").unwrap_or(&Value::Null);
+
+	let found = path.query(target).all().into_iter().cloned().collect();
+
+	Ok(Value::Array(found))
+}
+
+impl Generator for JsonPath {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		Prop::callable(Name::JsonPath, function(&["target", "path"], query))
+	}
+
+	fn name(&self) -> Name {
+		Name::JsonPath
+	}
+}
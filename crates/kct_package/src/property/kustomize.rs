@@ -0,0 +1,148 @@
+use super::{function, param};
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use kct_helper::io;
+use serde_json::Value;
+use walkdir::WalkDir;
+
+/// Command building kustomizations, instead of `kustomize build` or, when
+/// missing, `kubectl kustomize`
+const COMMAND_VAR: &str = "KCT_KUSTOMIZE";
+
+pub struct Kustomize {
+	/// Directories, relative to the package, it declared to build
+	pub declared: Vec<PathBuf>,
+}
+
+fn build(layers: &[PathBuf], declared: &[PathBuf], path: &str) -> Result<Value, String> {
+	let relative = Path::new(path.trim_end_matches('/'));
+
+	if !declared.iter().any(|dir| dir == relative) {
+		return Err(format!(
+			"Directory {path} should be declared in kustomize within kcp.json"
+		));
+	}
+
+	let dir = locate(layers, relative)?;
+	// Whatever kustomize reads lies within, as it refuses what's beyond
+	for entry in WalkDir::new(&dir).into_iter().filter_map(Result::ok) {
+		if entry.file_type().is_file() {
+			io::record(entry.path());
+		}
+	}
+
+	let output = command()
+		.arg(&dir)
+		.output()
+		.map_err(|err| format!("Unable to run kustomize for {path}: {err}"))?;
+
+	if !output.status.success() {
+		let reason = String::from_utf8_lossy(&output.stderr);
+
+		return Err(format!("Unable to build {path}: {}", reason.trim()));
+	}
+
+	let rendered = String::from_utf8_lossy(&output.stdout);
+	let objects = documents(&rendered)
+		.into_iter()
+		.map(|document| serde_yaml::from_str(&document))
+		.filter(|object| !matches!(object, Ok(Value::Null)))
+		.collect::<Result<Vec<Value>, _>>()
+		.map_err(|err| format!("Unable to read what {path} builds into: {err}"))?;
+
+	Ok(Value::Array(objects))
+}
+
+/// Splits the YAML stream at the lines separating its documents
+fn documents(stream: &str) -> Vec<String> {
+	let mut documents = vec![String::new()];
+
+	for line in stream.lines() {
+		match line.trim_end() {
+			"---" => documents.push(String::new()),
+			_ => {
+				let current = documents.last_mut().expect("there's always a document");
+				current.push_str(line);
+				current.push('\n');
+			}
+		}
+	}
+
+	documents
+}
+
+fn command() -> Command {
+	let configured = env::var(COMMAND_VAR).ok();
+	let mut words: Vec<String> = match configured {
+		Some(configured) => configured.split_whitespace().map(String::from).collect(),
+		None if found("kustomize") => vec![String::from("kustomize"), String::from("build")],
+		None => vec![String::from("kubectl"), String::from("kustomize")],
+	};
+
+	let program = match words.is_empty() {
+		true => String::from("kustomize"),
+		false => words.remove(0),
+	};
+
+	let mut command = Command::new(program);
+	command.args(words);
+
+	command
+}
+
+fn found(program: &str) -> bool {
+	env::var_os("PATH")
+		.map(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+		.unwrap_or(false)
+}
+
+/// Finds the directory on the first layer having it, refusing the ones
+/// escaping the package root
+fn locate(layers: &[PathBuf], path: &Path) -> Result<PathBuf, String> {
+	for layer in layers {
+		let root = match io::canonical(layer) {
+			Ok(root) => root,
+			Err(_) => continue,
+		};
+
+		let dir = match io::canonical(&root.join(path)) {
+			Ok(dir) => dir,
+			Err(_) => continue,
+		};
+
+		if !dir.starts_with(&root) {
+			return Err(format!(
+				"Directory {} is outside the package",
+				path.display()
+			));
+		}
+
+		if dir.is_dir() {
+			return Ok(dir);
+		}
+	}
+
+	Err(format!("No directory found at {}", path.display()))
+}
+
+impl Generator for Kustomize {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let layers = runtime.target().layers();
+		let declared = self.declared.clone();
+
+		let build = function(&["path"], move |params| {
+			build(&layers, &declared, param(&params, "path")?)
+		});
+
+		Prop::callable(Name::Kustomize, build)
+	}
+
+	fn name(&self) -> Name {
+		Name::Kustomize
+	}
+}
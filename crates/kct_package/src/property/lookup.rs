@@ -0,0 +1,85 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use kct_helper::lookup::{Lookup as Source, Query};
+use serde_json::Value;
+
+pub struct Lookup {
+	/// Whether the package declared the lookup capability
+	pub declared: bool,
+}
+
+/// Both `null` and empty strings mean any, as in Helm's `lookup`
+fn optional(params: &HashMap<String, Value>, name: &str) -> Result<Option<String>, String> {
+	match params.get(name) {
+		None | Some(Value::Null) => Ok(None),
+		Some(Value::String(value)) if value.is_empty() => Ok(None),
+		Some(Value::String(value)) => Ok(Some(value.clone())),
+		_ => Err(format!("{name} should be a string")),
+	}
+}
+
+fn find(
+	source: &Option<Source>,
+	offline: bool,
+	params: HashMap<String, Value>,
+) -> Result<Value, String> {
+	let query = Query {
+		api_version: param(&params, "apiVersion")?.to_string(),
+		kind: param(&params, "kind")?.to_string(),
+		namespace: optional(&params, "namespace")?,
+		name: optional(&params, "name")?,
+	};
+
+	if offline {
+		return Err(format!(
+			"Unable to look up {} {} when compiling offline",
+			query.kind,
+			query.name.as_deref().unwrap_or_default()
+		));
+	}
+
+	// Compilations without a cluster behave as if nothing exists
+	let source = match source {
+		Some(source) => source,
+		None => return Ok(Value::Null),
+	};
+
+	let found = source.find(&query).map_err(|err| {
+		format!(
+			"Unable to look up {} {}: {err}",
+			query.kind,
+			query.name.as_deref().unwrap_or_default()
+		)
+	})?;
+
+	Ok(found.unwrap_or(Value::Null))
+}
+
+impl Generator for Lookup {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let source = runtime.context().lookup().cloned();
+		let offline = runtime.context().offline();
+		let declared = self.declared;
+		let handler = move |params: HashMap<String, Value>| {
+			if !declared {
+				return Err(String::from(
+					"The package must declare the lookup capability to read objects",
+				));
+			}
+
+			find(&source, offline, params)
+		};
+
+		let params = ["apiVersion", "kind", "namespace", "name"];
+
+		Prop::callable(Name::Lookup, function(&params, handler))
+	}
+
+	fn name(&self) -> Name {
+		Name::Lookup
+	}
+}
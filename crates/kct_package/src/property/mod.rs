@@ -1,5 +1,60 @@
+mod encoding;
 mod files;
+mod http;
+mod identifier;
 mod include;
+mod jsonpath;
+mod kustomize;
+mod lookup;
+mod names;
+mod net;
+mod patch;
+mod quantity;
+mod random;
+mod read_file;
+mod regex;
+mod time;
+mod tls;
+mod version;
 
-pub use self::files::Files;
+pub use self::encoding::{Base64, Pem, Url};
+pub use self::files::{layers, Files, FilesDigest, Templates};
+pub use self::http::Http;
+pub use self::identifier::{Uuid, UuidFrom};
 pub use self::include::Include;
+pub use self::jsonpath::JsonPath;
+pub use self::kustomize::Kustomize;
+pub use self::lookup::Lookup;
+pub use self::names::Names;
+pub use self::net::Net;
+pub use self::patch::Patch;
+pub use self::quantity::Quantity;
+pub use self::random::Random;
+pub use self::read_file::{ImportBin, ReadFile};
+pub use self::regex::Regex;
+pub(crate) use self::time::nanos;
+pub use self::time::{Duration, Time};
+pub use self::tls::Tls;
+pub use self::version::Semver;
+
+use std::collections::HashMap;
+
+use kct_compiler::property::Function;
+use serde_json::Value;
+
+fn param<'a>(params: &'a HashMap<String, Value>, name: &str) -> Result<&'a str, String> {
+	match params.get(name) {
+		Some(Value::String(value)) => Ok(value),
+		_ => Err(format!("{name} should be a string")),
+	}
+}
+
+fn function<F>(params: &[&str], handler: F) -> Function
+where
+	F: Fn(HashMap<String, Value>) -> Result<Value, String> + Send + 'static,
+{
+	Function {
+		params: params.iter().map(|p| p.to_string()).collect(),
+		handler: Box::new(handler),
+	}
+}
@@ -0,0 +1,168 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub struct Names;
+
+const LABEL_LENGTH: usize = 63;
+const SUBDOMAIN_LENGTH: usize = 253;
+const HASH_LENGTH: usize = 8;
+
+struct Rule {
+	allowed: fn(char) -> bool,
+	lowercase: bool,
+	length: usize,
+}
+
+/// Replaces every run of forbidden characters with a single dash and trims
+/// the edges so the result begins and ends with an alphanumeric character
+fn sanitize(raw: &str, rule: &Rule) -> String {
+	let mut sanitized = String::with_capacity(raw.len());
+
+	for c in raw.chars() {
+		let c = if rule.lowercase {
+			c.to_ascii_lowercase()
+		} else {
+			c
+		};
+
+		if (rule.allowed)(c) {
+			sanitized.push(c);
+		} else if !sanitized.ends_with('-') {
+			sanitized.push('-');
+		}
+	}
+
+	sanitized
+		.trim_matches(|c: char| !c.is_ascii_alphanumeric())
+		.to_string()
+}
+
+/// Names over the limit are cut and suffixed with a digest of the whole
+/// name, so long names sharing a prefix still end up different
+fn fit(name: String, length: usize) -> Result<String, String> {
+	if name.len() <= length {
+		return Ok(name);
+	}
+
+	if length <= HASH_LENGTH + 1 {
+		return Err(format!("maxLength should be over {}", HASH_LENGTH + 1));
+	}
+
+	let digest = format!("{:x}", Sha256::digest(name.as_bytes()));
+	let kept =
+		name[..length - HASH_LENGTH - 1].trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+
+	Ok(format!("{kept}-{}", &digest[..HASH_LENGTH]))
+}
+
+fn length(params: &HashMap<String, Value>, default: usize) -> Result<usize, String> {
+	match params.get("maxLength") {
+		None | Some(Value::Null) => Ok(default),
+		Some(Value::Number(length)) => match length.as_u64() {
+			Some(length) if length as usize <= default => Ok(length as usize),
+			_ => Err(format!("maxLength should be a number up to {default}")),
+		},
+		_ => Err(String::from("maxLength should be a number")),
+	}
+}
+
+fn apply(params: &HashMap<String, Value>, rule: Rule) -> Result<String, String> {
+	let raw = param(params, "name")?;
+	let sanitized = sanitize(raw, &rule);
+
+	if sanitized.is_empty() {
+		return Err(format!("{raw} has no characters valid for a name"));
+	}
+
+	fit(sanitized, rule.length)
+}
+
+fn label(params: HashMap<String, Value>) -> Result<Value, String> {
+	let rule = Rule {
+		allowed: |c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-',
+		lowercase: true,
+		length: length(&params, LABEL_LENGTH)?,
+	};
+
+	Ok(json!(apply(&params, rule)?))
+}
+
+fn dns1035(params: HashMap<String, Value>) -> Result<Value, String> {
+	let raw = param(&params, "name")?;
+	let name = raw
+		.trim_start_matches(|c: char| !c.is_ascii_alphabetic())
+		.to_string();
+
+	if name.is_empty() {
+		return Err(format!("{raw} has no letter to begin a name with"));
+	}
+
+	let mut params = params;
+	params.insert(String::from("name"), json!(name));
+
+	label(params)
+}
+
+fn subdomain(params: HashMap<String, Value>) -> Result<Value, String> {
+	let rule = Rule {
+		allowed: |c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.',
+		lowercase: true,
+		length: length(&params, SUBDOMAIN_LENGTH)?,
+	};
+
+	let raw = param(&params, "name")?;
+	let sanitized = sanitize(raw, &rule);
+
+	// Each part between dots has to be a label on its own
+	let parts: Vec<&str> = sanitized
+		.split('.')
+		.map(|part| part.trim_matches('-'))
+		.filter(|part| !part.is_empty())
+		.collect();
+
+	if parts.is_empty() {
+		return Err(format!("{raw} has no characters valid for a name"));
+	}
+
+	Ok(json!(fit(parts.join("."), rule.length)?))
+}
+
+fn label_value(params: HashMap<String, Value>) -> Result<Value, String> {
+	let rule = Rule {
+		allowed: |c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.',
+		lowercase: false,
+		length: length(&params, LABEL_LENGTH)?,
+	};
+
+	let sanitized = sanitize(param(&params, "name")?, &rule);
+
+	// Unlike names, label values are allowed to be empty
+	if sanitized.is_empty() {
+		return Ok(json!(""));
+	}
+
+	Ok(json!(fit(sanitized, rule.length)?))
+}
+
+impl Generator for Names {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("label", function(&["name", "maxLength"], label)),
+			("dns1035", function(&["name", "maxLength"], dns1035)),
+			("subdomain", function(&["name", "maxLength"], subdomain)),
+			("labelValue", function(&["name", "maxLength"], label_value)),
+		];
+
+		Prop::group(Name::Names, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Names
+	}
+}
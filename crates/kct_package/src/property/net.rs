@@ -0,0 +1,195 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use serde_json::{json, Value};
+
+pub struct Net;
+
+/// Addresses handled as integers, keeping the family to convert them back
+#[derive(Clone, Copy)]
+struct Address {
+	value: u128,
+	bits: u32,
+}
+
+impl Address {
+	fn parse(raw: &str) -> Result<Self, String> {
+		let ip: IpAddr = raw
+			.trim()
+			.parse()
+			.map_err(|_err| format!("Invalid IP address {raw}"))?;
+
+		Ok(match ip {
+			IpAddr::V4(ip) => Address {
+				value: u32::from(ip) as u128,
+				bits: 32,
+			},
+			IpAddr::V6(ip) => Address {
+				value: u128::from(ip),
+				bits: 128,
+			},
+		})
+	}
+
+	fn max(&self) -> u128 {
+		match self.bits {
+			128 => u128::MAX,
+			bits => (1 << bits) - 1,
+		}
+	}
+
+	fn offset(self, by: i128) -> Option<Self> {
+		let value = if by < 0 {
+			self.value.checked_sub(by.unsigned_abs())?
+		} else {
+			self.value.checked_add(by as u128)?
+		};
+
+		(value <= self.max()).then_some(Address { value, ..self })
+	}
+}
+
+impl std::fmt::Display for Address {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.bits {
+			32 => write!(f, "{}", Ipv4Addr::from(self.value as u32)),
+			_ => write!(f, "{}", Ipv6Addr::from(self.value)),
+		}
+	}
+}
+
+struct Cidr {
+	network: Address,
+	prefix: u32,
+}
+
+impl Cidr {
+	fn parse(raw: &str) -> Result<Self, String> {
+		let (ip, prefix) = raw
+			.split_once('/')
+			.ok_or_else(|| format!("Invalid CIDR {raw}, it should look like 10.0.0.0/16"))?;
+
+		let address = Address::parse(ip)?;
+		let prefix: u32 = prefix
+			.parse()
+			.ok()
+			.filter(|prefix| *prefix <= address.bits)
+			.ok_or_else(|| format!("Invalid prefix length on {raw}"))?;
+
+		let host_bits = address.bits - prefix;
+		let mask = address.max() ^ low_bits(host_bits);
+		let network = Address {
+			value: address.value & mask,
+			..address
+		};
+
+		Ok(Cidr { network, prefix })
+	}
+
+	fn host_bits(&self) -> u32 {
+		self.network.bits - self.prefix
+	}
+}
+
+impl std::fmt::Display for Cidr {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}/{}", self.network, self.prefix)
+	}
+}
+
+fn low_bits(amount: u32) -> u128 {
+	match amount {
+		0 => 0,
+		128 => u128::MAX,
+		bits => (1 << bits) - 1,
+	}
+}
+
+fn integer(params: &HashMap<String, Value>, name: &str) -> Result<i128, String> {
+	params
+		.get(name)
+		.and_then(Value::as_i64)
+		.map(i128::from)
+		.ok_or_else(|| format!("{name} should be an integer"))
+}
+
+/// Same semantics as Terraform's `cidrsubnet`
+fn cidr_subnet(params: HashMap<String, Value>) -> Result<Value, String> {
+	let cidr = Cidr::parse(param(&params, "prefix")?)?;
+	let newbits = integer(&params, "newbits")?;
+	let netnum = integer(&params, "netnum")?;
+
+	if newbits < 0 || newbits as u32 > cidr.host_bits() {
+		return Err(format!("Can't extend {cidr} by {newbits} bits"));
+	}
+
+	let newbits = newbits as u32;
+	if netnum < 0 || netnum as u128 > low_bits(newbits) {
+		return Err(format!("Network {netnum} doesn't fit in {newbits} bits"));
+	}
+
+	let prefix = cidr.prefix + newbits;
+	let value = cidr.network.value | ((netnum as u128) << (cidr.network.bits - prefix));
+	let subnet = Cidr {
+		network: Address {
+			value,
+			..cidr.network
+		},
+		prefix,
+	};
+
+	Ok(json!(subnet.to_string()))
+}
+
+/// Same semantics as Terraform's `cidrhost`, negative numbers count from the end
+fn cidr_host(params: HashMap<String, Value>) -> Result<Value, String> {
+	let cidr = Cidr::parse(param(&params, "prefix")?)?;
+	let hostnum = integer(&params, "hostnum")?;
+
+	let size = low_bits(cidr.host_bits());
+	let offset = if hostnum < 0 {
+		size.checked_sub(hostnum.unsigned_abs() - 1)
+	} else {
+		Some(hostnum as u128).filter(|offset| *offset <= size)
+	};
+
+	let host = offset
+		.and_then(|offset| cidr.network.offset(offset as i128))
+		.ok_or_else(|| format!("Host {hostnum} doesn't fit in {cidr}"))?;
+
+	Ok(json!(host.to_string()))
+}
+
+fn ip_add(params: HashMap<String, Value>) -> Result<Value, String> {
+	let ip = Address::parse(param(&params, "ip")?)?;
+	let amount = integer(&params, "amount")?;
+
+	let result = ip
+		.offset(amount)
+		.ok_or_else(|| format!("Adding {amount} to {ip} overflows the address space"))?;
+
+	Ok(json!(result.to_string()))
+}
+
+impl Generator for Net {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			(
+				"cidrSubnet",
+				function(&["prefix", "newbits", "netnum"], cidr_subnet),
+			),
+			("cidrHost", function(&["prefix", "hostnum"], cidr_host)),
+			("ipAdd", function(&["ip", "amount"], ip_add)),
+		];
+
+		Prop::group(Name::Net, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Net
+	}
+}
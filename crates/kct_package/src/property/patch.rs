@@ -0,0 +1,72 @@
+use super::function;
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use kct_helper::patch::{json_patch, merge_patch, strategic_merge};
+use serde_json::Value;
+
+pub struct Patch;
+
+fn value(params: &HashMap<String, Value>, name: &str) -> Value {
+	params.get(name).cloned().unwrap_or(Value::Null)
+}
+
+fn merge(params: HashMap<String, Value>) -> Result<Value, String> {
+	let mut target = value(&params, "target");
+
+	merge_patch(&mut target, &value(&params, "patch"));
+
+	Ok(target)
+}
+
+fn json(params: HashMap<String, Value>) -> Result<Value, String> {
+	let mut target = value(&params, "target");
+	let operations = match params.get("operations") {
+		Some(Value::Array(operations)) => operations,
+		_ => return Err(String::from("operations should be an array")),
+	};
+
+	json_patch(&mut target, operations).map_err(|err| err.to_string())?;
+
+	Ok(target)
+}
+
+fn strategic(params: HashMap<String, Value>) -> Result<Value, String> {
+	let mut target = value(&params, "target");
+	let keys = match params.get("mergeKeys") {
+		None | Some(Value::Null) => HashMap::new(),
+		Some(Value::Object(keys)) => keys
+			.iter()
+			.map(|(field, key)| match key {
+				Value::String(key) => Ok((field.clone(), key.clone())),
+				_ => Err(format!("merge key for {field} should be a string")),
+			})
+			.collect::<Result<_, _>>()?,
+		_ => return Err(String::from("mergeKeys should be an object")),
+	};
+
+	strategic_merge(&mut target, &value(&params, "patch"), &keys);
+
+	Ok(target)
+}
+
+impl Generator for Patch {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("merge", function(&["target", "patch"], merge)),
+			("json", function(&["target", "operations"], json)),
+			(
+				"strategic",
+				function(&["target", "patch", "mergeKeys"], strategic),
+			),
+		];
+
+		Prop::group(Name::Patch, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Patch
+	}
+}
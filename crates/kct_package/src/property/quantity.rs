@@ -0,0 +1,111 @@
+use super::function;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use kct_helper::quantity::Quantity as Amount;
+use serde_json::{json, Value};
+
+pub struct Quantity;
+
+/// Quantities can be strings like `1.5Gi` or plain numbers
+fn amount(params: &HashMap<String, Value>, name: &str) -> Result<Amount, String> {
+	let raw = match params.get(name) {
+		Some(Value::String(raw)) => raw.clone(),
+		Some(Value::Number(raw)) => number(raw),
+		_ => return Err(format!("{name} should be a quantity")),
+	};
+
+	raw.parse()
+		.map_err(|err: kct_helper::quantity::Error| err.to_string())
+}
+
+/// Writes numbers without exponents, which quantities don't accept for factors
+fn number(raw: &serde_json::Number) -> String {
+	let text = raw.to_string();
+
+	if text.contains(['e', 'E']) {
+		let fixed = format!("{:.18}", raw.as_f64().unwrap_or_default());
+
+		fixed
+			.trim_end_matches('0')
+			.trim_end_matches('.')
+			.to_string()
+	} else {
+		text
+	}
+}
+
+fn parse(params: HashMap<String, Value>) -> Result<Value, String> {
+	let amount = amount(&params, "quantity")?;
+
+	Ok(json!(amount.as_f64()))
+}
+
+fn format(params: HashMap<String, Value>) -> Result<Value, String> {
+	let amount = amount(&params, "quantity")?;
+
+	Ok(json!(amount.to_string()))
+}
+
+fn add(params: HashMap<String, Value>) -> Result<Value, String> {
+	let left = amount(&params, "a")?;
+	let right = amount(&params, "b")?;
+	let sum = left.add(&right).map_err(|err| err.to_string())?;
+
+	Ok(json!(sum.to_string()))
+}
+
+fn sub(params: HashMap<String, Value>) -> Result<Value, String> {
+	let left = amount(&params, "a")?;
+	let right = amount(&params, "b")?;
+	let diff = left.sub(&right).map_err(|err| err.to_string())?;
+
+	Ok(json!(diff.to_string()))
+}
+
+fn multiply(params: HashMap<String, Value>) -> Result<Value, String> {
+	let amount = amount(&params, "quantity")?;
+	let factor = match params.get("factor") {
+		Some(Value::Number(factor)) => number(factor),
+		_ => return Err(String::from("factor should be a number")),
+	};
+
+	let product = amount.multiply(&factor).map_err(|err| err.to_string())?;
+
+	Ok(json!(product.to_string()))
+}
+
+fn compare(params: HashMap<String, Value>) -> Result<Value, String> {
+	let left = amount(&params, "a")?;
+	let right = amount(&params, "b")?;
+
+	let ordering = match left.nanos().cmp(&right.nanos()) {
+		Ordering::Less => -1,
+		Ordering::Equal => 0,
+		Ordering::Greater => 1,
+	};
+
+	Ok(json!(ordering))
+}
+
+impl Generator for Quantity {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("parse", function(&["quantity"], parse)),
+			("format", function(&["quantity"], format)),
+			("add", function(&["a", "b"], add)),
+			("sub", function(&["a", "b"], sub)),
+			("multiply", function(&["quantity", "factor"], multiply)),
+			("compare", function(&["a", "b"], compare)),
+		];
+
+		Prop::group(Name::Quantity, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Quantity
+	}
+}
@@ -0,0 +1,176 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+const ALPHANUMERIC: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Longest value generated, well beyond any credential, so a typo can't
+/// exhaust memory
+const MAX_LENGTH: u64 = 4096;
+
+pub struct Random {
+	/// Whether the package declared the capability to use the release seed
+	pub declared: bool,
+}
+
+/// Source of bytes for a value, either derived from the release seed, and
+/// thus stable across renders of the same release, or random otherwise
+enum Source {
+	Derived {
+		seed: String,
+		scope: String,
+	},
+	Random,
+	/// There's a seed, but the package isn't allowed to use it
+	Undeclared,
+}
+
+impl Source {
+	fn bytes(&self, name: &str, amount: usize) -> Vec<u8> {
+		match self {
+			Source::Undeclared => unreachable!("values aren't generated without the capability"),
+			Source::Random => {
+				let mut bytes = vec![0; amount];
+				rand::thread_rng().fill_bytes(&mut bytes);
+
+				bytes
+			}
+			Source::Derived { seed, scope } => {
+				let mut bytes = Vec::with_capacity(amount);
+				let mut counter: u32 = 0;
+
+				while bytes.len() < amount {
+					let mut mac = Hmac::<Sha256>::new_from_slice(seed.as_bytes())
+						.expect("HMAC accepts keys of any size");
+					mac.update(scope.as_bytes());
+					mac.update(&[0]);
+					mac.update(name.as_bytes());
+					mac.update(&counter.to_be_bytes());
+
+					bytes.extend(mac.finalize().into_bytes());
+					counter += 1;
+				}
+
+				bytes.truncate(amount);
+				bytes
+			}
+		}
+	}
+
+	/// Picks characters by rejection sampling, so every one of them has the
+	/// same odds regardless of the charset size
+	fn string(&self, name: &str, length: usize, charset: &[char]) -> String {
+		let limit = 256 - (256 % charset.len());
+		let mut result: Vec<char> = Vec::with_capacity(length);
+		let mut round = 0;
+
+		while result.len() < length {
+			let bytes = self.bytes(&format!("{name}#{round}"), length * 2);
+			let missing = length - result.len();
+
+			result.extend(
+				bytes
+					.into_iter()
+					.filter(|byte| (*byte as usize) < limit)
+					.map(|byte| charset[byte as usize % charset.len()])
+					.take(missing),
+			);
+
+			round += 1;
+		}
+
+		result.into_iter().collect()
+	}
+}
+
+fn length(params: &HashMap<String, Value>) -> Result<usize, String> {
+	params
+		.get("length")
+		.and_then(Value::as_u64)
+		.filter(|length| *length > 0)
+		.ok_or_else(|| String::from("length should be a positive integer"))
+		.and_then(|length| match length {
+			length if length > MAX_LENGTH => Err(format!(
+				"length should be at most {MAX_LENGTH}, got {length}"
+			)),
+			length => Ok(length as usize),
+		})
+}
+
+fn generate(
+	source: &Source,
+	params: &HashMap<String, Value>,
+	charset: &str,
+) -> Result<Value, String> {
+	if let Source::Undeclared = source {
+		return Err(String::from(
+			"The package must declare the secrets capability to derive values from the release seed",
+		));
+	}
+
+	let name = param(params, "name")?;
+	let length = length(params)?;
+
+	let mut charset: Vec<char> = charset.chars().collect();
+	charset.sort_unstable();
+	charset.dedup();
+
+	if charset.is_empty() || charset.len() > 256 {
+		return Err(String::from(
+			"chars should have between 1 and 256 characters",
+		));
+	}
+
+	Ok(json!(source.string(name, length, &charset)))
+}
+
+impl Generator for Random {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let source = match runtime.context().release() {
+			Some(release) => match &release.seed {
+				Some(_) if !self.declared => Source::Undeclared,
+				Some(seed) => Source::Derived {
+					seed: seed.clone(),
+					scope: release.name.clone(),
+				},
+				None => Source::Random,
+			},
+			None => Source::Random,
+		};
+
+		let source = Arc::new(source);
+		let passwords = source.clone();
+		let strings = source;
+
+		let members = vec![
+			(
+				"password",
+				function(&["name", "length"], move |params| {
+					generate(&passwords, &params, ALPHANUMERIC)
+				}),
+			),
+			(
+				"string",
+				function(&["name", "length", "chars"], move |params| {
+					let chars = param(&params, "chars")?.to_string();
+
+					generate(&strings, &params, &chars)
+				}),
+			),
+		];
+
+		Prop::group(Name::Random, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Random
+	}
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use kct_compiler::property::{Callback, Function, Generator, Name, Prop};
+use kct_compiler::source::Sources;
+use kct_compiler::{Runtime, MAX_IMPORT_SIZE};
+use kct_helper::io;
+use serde_json::Value;
+
+pub struct ReadFile;
+
+pub struct ImportBin;
+
+struct Handler {
+	layers: Vec<PathBuf>,
+}
+
+impl Callback for Handler {
+	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String> {
+		let path = match params.get("path") {
+			Some(Value::String(path)) => path,
+			_ => return Err("path should be a string".into()),
+		};
+
+		let encoding = match params.get("encoding") {
+			None | Some(Value::Null) => "text",
+			Some(Value::String(encoding)) => encoding.as_str(),
+			_ => return Err("encoding should be a string".into()),
+		};
+
+		let file = self.locate(Path::new(path))?;
+		let size = fs::metadata(&file)
+			.map(|meta| meta.len())
+			.unwrap_or_default();
+		if size > MAX_IMPORT_SIZE {
+			return Err(format!(
+				"File {path} has {size} bytes, more than the {MAX_IMPORT_SIZE} templates may read"
+			));
+		}
+
+		let contents = fs::read(&file).map_err(|err| format!("Unable to read {path}: {err}"))?;
+		io::record(&file);
+
+		match encoding {
+			"text" => String::from_utf8(contents)
+				.map(Value::String)
+				.map_err(|_err| format!("File {path} isn't valid UTF-8, read it as base64")),
+			"base64" => Ok(Value::String(STANDARD.encode(contents))),
+			other => Err(format!("Unknown encoding {other}, use text or base64")),
+		}
+	}
+}
+
+impl Handler {
+	/// Finds the file on the first layer having it, refusing paths that escape
+	/// the package root through `..` or symlinks
+	fn locate(&self, path: &Path) -> Result<PathBuf, String> {
+		if path.is_absolute() {
+			return Err(format!(
+				"Path {} should be relative to the package",
+				path.display()
+			));
+		}
+
+		for layer in &self.layers {
+			let root = match io::canonical(layer) {
+				Ok(root) => root,
+				Err(_) => continue,
+			};
+
+			let file = match io::canonical(&root.join(path)) {
+				Ok(file) => file,
+				Err(_) => continue,
+			};
+
+			if !file.starts_with(&root) {
+				return Err(format!("Path {} is outside the package", path.display()));
+			}
+
+			if file.is_file() {
+				return Ok(file);
+			}
+		}
+
+		Err(format!("No file found at {}", path.display()))
+	}
+}
+
+impl Generator for ReadFile {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let layers = runtime.target().layers();
+
+		let params = vec![String::from("path"), String::from("encoding")];
+		let handler = Handler { layers };
+		let function = Function {
+			params,
+			handler: Box::new(handler),
+		};
+
+		Prop::callable(Name::ReadFile, function)
+	}
+
+	fn name(&self) -> Name {
+		Name::ReadFile
+	}
+}
+
+/// Reads binary files as imports do, resolving them from the main template
+struct Importer {
+	sources: Sources,
+}
+
+impl Callback for Importer {
+	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String> {
+		let path = match params.get("path") {
+			Some(Value::String(path)) => path,
+			_ => return Err("path should be a string".into()),
+		};
+
+		let contents = self.sources.read(Path::new(path))?;
+
+		Ok(Value::String(STANDARD.encode(contents)))
+	}
+}
+
+impl Generator for ImportBin {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let (context, target) = (runtime.context(), runtime.target());
+
+		let sources = Sources {
+			main: target.main().to_path_buf(),
+			layers: target.layers(),
+			lib: target.lib().to_path_buf(),
+			libraries: [context.libraries(), target.libraries()].concat(),
+			aliases: target.aliases().clone(),
+			vendor: context.vendor().to_path_buf(),
+			sdk: target.sdk(),
+		};
+
+		let params = vec![String::from("path")];
+		let function = Function {
+			params,
+			handler: Box::new(Importer { sources }),
+		};
+
+		Prop::callable(Name::ImportBin, function)
+	}
+
+	fn name(&self) -> Name {
+		Name::ImportBin
+	}
+}
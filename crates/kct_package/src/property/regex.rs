@@ -0,0 +1,59 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use regex::Regex as Pattern;
+use serde_json::{json, Value};
+
+pub struct Regex;
+
+fn pattern(params: &HashMap<String, Value>) -> Result<Pattern, String> {
+	let raw = param(params, "pattern")?;
+
+	Pattern::new(raw).map_err(|err| format!("Invalid pattern {raw}: {err}"))
+}
+
+fn is_match(params: HashMap<String, Value>) -> Result<Value, String> {
+	let pattern = pattern(&params)?;
+	let text = param(&params, "str")?;
+
+	Ok(json!(pattern.is_match(text)))
+}
+
+fn replace(params: HashMap<String, Value>) -> Result<Value, String> {
+	let pattern = pattern(&params)?;
+	let text = param(&params, "str")?;
+	let replacement = param(&params, "replacement")?;
+
+	Ok(json!(pattern.replace_all(text, replacement)))
+}
+
+fn find(params: HashMap<String, Value>) -> Result<Value, String> {
+	let pattern = pattern(&params)?;
+	let text = param(&params, "str")?;
+
+	let found: Vec<&str> = pattern.find_iter(text).map(|m| m.as_str()).collect();
+
+	Ok(json!(found))
+}
+
+impl Generator for Regex {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("match", function(&["pattern", "str"], is_match)),
+			(
+				"replace",
+				function(&["pattern", "str", "replacement"], replace),
+			),
+			("find", function(&["pattern", "str"], find)),
+		];
+
+		Prop::group(Name::Regex, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Regex
+	}
+}
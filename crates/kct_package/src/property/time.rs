@@ -0,0 +1,216 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use serde_json::{json, Value};
+
+pub struct Time;
+
+fn timestamp(params: &HashMap<String, Value>) -> Result<DateTime<Utc>, String> {
+	let raw = param(params, "timestamp")?;
+
+	DateTime::parse_from_rfc3339(raw)
+		.map(|time| time.with_timezone(&Utc))
+		.map_err(|err| format!("Invalid timestamp {raw}, it should be RFC 3339: {err}"))
+}
+
+fn format(params: HashMap<String, Value>) -> Result<Value, String> {
+	let time = timestamp(&params)?;
+	let format = param(&params, "format")?;
+
+	let items = StrftimeItems::new(format);
+	if items.clone().any(|item| item == Item::Error) {
+		return Err(format!("Invalid format {format}, it should be strftime"));
+	}
+
+	Ok(json!(time.format_with_items(items).to_string()))
+}
+
+fn unix(params: HashMap<String, Value>) -> Result<Value, String> {
+	let time = timestamp(&params)?;
+
+	Ok(json!(time.timestamp()))
+}
+
+impl Generator for Time {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let now = runtime.context().now().to_rfc3339();
+		let frozen = move |_params: HashMap<String, Value>| Ok(json!(now));
+
+		let members = vec![
+			("now", function(&[], frozen)),
+			("format", function(&["timestamp", "format"], format)),
+			("unix", function(&["timestamp"], unix)),
+		];
+
+		Prop::group(Name::Time, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Time
+	}
+}
+
+pub struct Duration;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Units accepted by Go's `time.ParseDuration`, plus days and weeks
+const UNITS: [(&str, i128); 10] = [
+	("ns", 1),
+	("us", 1_000),
+	("µs", 1_000),
+	("μs", 1_000),
+	("ms", 1_000_000),
+	("s", NANOS_PER_SECOND),
+	("m", 60 * NANOS_PER_SECOND),
+	("h", 3_600 * NANOS_PER_SECOND),
+	("d", 86_400 * NANOS_PER_SECOND),
+	("w", 604_800 * NANOS_PER_SECOND),
+];
+
+pub(crate) fn nanos(raw: &str) -> Result<i128, String> {
+	let invalid = || format!("Invalid duration {raw}, expected something like 1h30m");
+
+	let (negative, mut rest) = match raw.strip_prefix('-') {
+		Some(rest) => (true, rest),
+		None => (false, raw.strip_prefix('+').unwrap_or(raw)),
+	};
+
+	if rest == "0" {
+		return Ok(0);
+	}
+
+	if rest.is_empty() {
+		return Err(invalid());
+	}
+
+	let mut total: i128 = 0;
+
+	while !rest.is_empty() {
+		let digits = rest
+			.find(|c: char| !c.is_ascii_digit() && c != '.')
+			.ok_or_else(invalid)?;
+		let (number, tail) = rest.split_at(digits);
+		let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+
+		if whole.is_empty() && fraction.is_empty() {
+			return Err(invalid());
+		}
+
+		let (unit, scale) = UNITS
+			.iter()
+			.filter(|(unit, _)| tail.starts_with(unit))
+			.max_by_key(|(unit, _)| unit.len())
+			.ok_or_else(invalid)?;
+
+		let whole: i128 = match whole {
+			"" => 0,
+			whole => whole.parse().map_err(|_| invalid())?,
+		};
+
+		let mut amount = whole.checked_mul(*scale).ok_or_else(invalid)?;
+		let mut place = *scale;
+
+		for digit in fraction.chars() {
+			place /= 10;
+			amount += digit.to_digit(10).ok_or_else(invalid)? as i128 * place;
+		}
+
+		total = total.checked_add(amount).ok_or_else(invalid)?;
+		rest = &tail[unit.len()..];
+	}
+
+	Ok(if negative { -total } else { total })
+}
+
+/// Writes the value the way Go's `time.Duration` does, e.g. `1h30m0s`
+fn canonical(nanos: i128) -> String {
+	let sign = if nanos < 0 { "-" } else { "" };
+	let nanos = nanos.unsigned_abs();
+
+	if nanos == 0 {
+		return String::from("0s");
+	}
+
+	let decimal = |value: u128, scale: u128| {
+		let fraction = format!("{:0width$}", value % scale, width = scale.ilog10() as usize);
+		let fraction = fraction.trim_end_matches('0');
+
+		match fraction {
+			"" => (value / scale).to_string(),
+			fraction => format!("{}.{fraction}", value / scale),
+		}
+	};
+
+	if nanos < 1_000 {
+		return format!("{sign}{nanos}ns");
+	}
+
+	if nanos < 1_000_000 {
+		return format!("{sign}{}µs", decimal(nanos, 1_000));
+	}
+
+	if nanos < NANOS_PER_SECOND as u128 {
+		return format!("{sign}{}ms", decimal(nanos, 1_000_000));
+	}
+
+	let seconds = decimal(
+		nanos % (60 * NANOS_PER_SECOND as u128),
+		NANOS_PER_SECOND as u128,
+	);
+	let minutes = nanos / (60 * NANOS_PER_SECOND as u128);
+
+	match (minutes / 60, minutes % 60) {
+		(0, 0) => format!("{sign}{seconds}s"),
+		(0, minutes) => format!("{sign}{minutes}m{seconds}s"),
+		(hours, minutes) => format!("{sign}{hours}h{minutes}m{seconds}s"),
+	}
+}
+
+/// Durations can be strings like `1h30m` or plain numbers of seconds
+fn duration(params: &HashMap<String, Value>) -> Result<i128, String> {
+	match params.get("duration") {
+		Some(Value::String(raw)) => nanos(raw),
+		Some(Value::Number(seconds)) => {
+			let seconds = seconds.as_f64().unwrap_or_default();
+
+			Ok((seconds * NANOS_PER_SECOND as f64).round() as i128)
+		}
+		_ => Err(String::from("duration should be a string or a number")),
+	}
+}
+
+fn seconds(nanos: i128) -> Value {
+	match nanos % NANOS_PER_SECOND {
+		0 => json!((nanos / NANOS_PER_SECOND) as i64),
+		_ => json!(nanos as f64 / NANOS_PER_SECOND as f64),
+	}
+}
+
+fn parse(params: HashMap<String, Value>) -> Result<Value, String> {
+	Ok(seconds(duration(&params)?))
+}
+
+fn format_duration(params: HashMap<String, Value>) -> Result<Value, String> {
+	Ok(json!(canonical(duration(&params)?)))
+}
+
+impl Generator for Duration {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("parse", function(&["duration"], parse)),
+			("format", function(&["duration"], format_duration)),
+		];
+
+		Prop::group(Name::Duration, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Duration
+	}
+}
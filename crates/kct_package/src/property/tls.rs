@@ -0,0 +1,231 @@
+use super::{function, param};
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair};
+use rcgen::{
+	ExtendedKeyUsagePurpose, KeyUsagePurpose, SanType, PKCS_ECDSA_P256_SHA256, PKCS_ED25519,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+/// PKCS#8 header for an Ed25519 private key, followed by its 32 bytes seed
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+	0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+pub struct Tls;
+
+struct Options {
+	days: i64,
+	seed: Option<String>,
+}
+
+impl Options {
+	fn from(params: &HashMap<String, Value>) -> Result<Self, String> {
+		let days = match params.get("days") {
+			Some(Value::Number(days)) => days
+				.as_i64()
+				.filter(|days| *days > 0)
+				.ok_or_else(|| String::from("days should be a positive integer"))?,
+			_ => return Err(String::from("days should be a positive integer")),
+		};
+
+		let seed = match params.get("seed") {
+			None | Some(Value::Null) => None,
+			Some(Value::String(seed)) => Some(seed.clone()),
+			_ => return Err(String::from("seed should be a string")),
+		};
+
+		Ok(Self { days, seed })
+	}
+}
+
+/// Builds the parameters shared by every certificate. Seeded certificates use
+/// Ed25519 keys derived from the seed and the common name, which, along
+/// Ed25519 deterministic signatures, renders the same certificate every time
+fn params(
+	cn: &str,
+	sans: &[String],
+	options: &Options,
+	now: &DateTime<Utc>,
+) -> Result<CertificateParams, String> {
+	let mut params = CertificateParams::new(vec![]);
+
+	params.distinguished_name.remove(DnType::CommonName);
+	params.distinguished_name.push(DnType::CommonName, cn);
+	params.subject_alt_names = sans
+		.iter()
+		.map(|san| match san.parse::<IpAddr>() {
+			Ok(ip) => SanType::IpAddress(ip),
+			Err(_) => SanType::DnsName(san.clone()),
+		})
+		.collect();
+
+	let not_after = *now + Duration::days(options.days);
+	params.not_before = timestamp(now)?;
+	params.not_after = timestamp(&not_after)?;
+
+	match &options.seed {
+		None => params.alg = &PKCS_ECDSA_P256_SHA256,
+		Some(seed) => {
+			let secret = Sha256::new()
+				.chain_update(seed.as_bytes())
+				.chain_update([0])
+				.chain_update(cn.as_bytes())
+				.finalize();
+
+			let mut pkcs8 = ED25519_PKCS8_PREFIX.to_vec();
+			pkcs8.extend_from_slice(&secret);
+
+			let key = KeyPair::from_der(&pkcs8).map_err(|err| err.to_string())?;
+
+			params.alg = &PKCS_ED25519;
+			params.key_pair = Some(key);
+		}
+	}
+
+	Ok(params)
+}
+
+fn timestamp(time: &DateTime<Utc>) -> Result<OffsetDateTime, String> {
+	OffsetDateTime::from_unix_timestamp(time.timestamp()).map_err(|err| err.to_string())
+}
+
+fn leaf(params: &mut CertificateParams) {
+	params.key_usages = vec![
+		KeyUsagePurpose::DigitalSignature,
+		KeyUsagePurpose::KeyEncipherment,
+	];
+	params.extended_key_usages = vec![
+		ExtendedKeyUsagePurpose::ServerAuth,
+		ExtendedKeyUsagePurpose::ClientAuth,
+	];
+}
+
+fn sans(params: &HashMap<String, Value>) -> Result<Vec<String>, String> {
+	match params.get("sans") {
+		None | Some(Value::Null) => Ok(vec![]),
+		Some(Value::Array(sans)) => sans
+			.iter()
+			.map(|san| {
+				san.as_str()
+					.map(String::from)
+					.ok_or_else(|| String::from("sans should be a list of strings"))
+			})
+			.collect(),
+		_ => Err(String::from("sans should be a list of strings")),
+	}
+}
+
+fn pair(cert: &Certificate, pem: String) -> Value {
+	json!({
+		"cert": pem,
+		"key": cert.serialize_private_key_pem(),
+	})
+}
+
+fn ca(now: &DateTime<Utc>, params: HashMap<String, Value>) -> Result<Value, String> {
+	let cn = param(&params, "cn")?;
+	let options = Options::from(&params)?;
+
+	let mut params = self::params(cn, &[], &options, now)?;
+	params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+	params.key_usages = vec![
+		KeyUsagePurpose::KeyCertSign,
+		KeyUsagePurpose::CrlSign,
+		KeyUsagePurpose::DigitalSignature,
+	];
+
+	let cert = Certificate::from_params(params).map_err(|err| err.to_string())?;
+	let pem = cert.serialize_pem().map_err(|err| err.to_string())?;
+
+	Ok(pair(&cert, pem))
+}
+
+fn self_signed(now: &DateTime<Utc>, params: HashMap<String, Value>) -> Result<Value, String> {
+	let cn = param(&params, "cn")?;
+	let sans = sans(&params)?;
+	let options = Options::from(&params)?;
+
+	let mut params = self::params(cn, &sans, &options, now)?;
+	leaf(&mut params);
+
+	let cert = Certificate::from_params(params).map_err(|err| err.to_string())?;
+	let pem = cert.serialize_pem().map_err(|err| err.to_string())?;
+
+	Ok(pair(&cert, pem))
+}
+
+fn signed(now: &DateTime<Utc>, params: HashMap<String, Value>) -> Result<Value, String> {
+	let cn = param(&params, "cn")?;
+	let sans = sans(&params)?;
+	let options = Options::from(&params)?;
+
+	let authority = {
+		let ca = params
+			.get("ca")
+			.filter(|ca| ca.is_object())
+			.ok_or_else(|| String::from("ca should be an object with cert and key"))?;
+
+		let pem = ca["cert"]
+			.as_str()
+			.ok_or_else(|| String::from("ca should have a PEM cert"))?;
+		let key = ca["key"]
+			.as_str()
+			.ok_or_else(|| String::from("ca should have a PEM key"))?;
+
+		let key = KeyPair::from_pem(key).map_err(|err| format!("Invalid CA key: {err}"))?;
+		let params = CertificateParams::from_ca_cert_pem(pem, key)
+			.map_err(|err| format!("Invalid CA cert: {err}"))?;
+
+		Certificate::from_params(params).map_err(|err| err.to_string())?
+	};
+
+	let mut params = self::params(cn, &sans, &options, now)?;
+	leaf(&mut params);
+	params.use_authority_key_identifier_extension = true;
+
+	let cert = Certificate::from_params(params).map_err(|err| err.to_string())?;
+	let pem = cert
+		.serialize_pem_with_signer(&authority)
+		.map_err(|err| err.to_string())?;
+
+	Ok(pair(&cert, pem))
+}
+
+impl Generator for Tls {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let now = *runtime.context().now();
+
+		let members = vec![
+			(
+				"ca",
+				function(&["cn", "days", "seed"], move |params| ca(&now, params)),
+			),
+			(
+				"selfSignedCert",
+				function(&["cn", "sans", "days", "seed"], move |params| {
+					self_signed(&now, params)
+				}),
+			),
+			(
+				"signedCert",
+				function(&["cn", "sans", "ca", "days", "seed"], move |params| {
+					signed(&now, params)
+				}),
+			),
+		];
+
+		Prop::group(Name::Tls, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Tls
+	}
+}
@@ -0,0 +1,72 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::{function, param};
+
+use kct_compiler::property::{Generator, Name, Prop};
+use kct_compiler::Runtime;
+use semver::{BuildMetadata, Version, VersionReq};
+use serde_json::{json, Value};
+
+pub struct Semver;
+
+/// Parses versions leniently, accepting the `v` prefix used by Kubernetes
+fn version(raw: &str) -> Result<Version, String> {
+	let trimmed = raw.trim().trim_start_matches('v');
+
+	Version::parse(trimmed).map_err(|err| format!("Invalid version {raw}: {err}"))
+}
+
+fn compare(params: HashMap<String, Value>) -> Result<Value, String> {
+	let mut left = version(param(&params, "a")?)?;
+	let mut right = version(param(&params, "b")?)?;
+
+	// Build metadata doesn't take part in precedence
+	left.build = BuildMetadata::EMPTY;
+	right.build = BuildMetadata::EMPTY;
+
+	let ordering = match left.cmp(&right) {
+		Ordering::Less => -1,
+		Ordering::Equal => 0,
+		Ordering::Greater => 1,
+	};
+
+	Ok(json!(ordering))
+}
+
+fn satisfies(params: HashMap<String, Value>) -> Result<Value, String> {
+	let version = version(param(&params, "version")?)?;
+	let raw = param(&params, "constraint")?;
+	let constraint =
+		VersionReq::parse(raw).map_err(|err| format!("Invalid constraint {raw}: {err}"))?;
+
+	Ok(json!(constraint.matches(&version)))
+}
+
+fn parse(params: HashMap<String, Value>) -> Result<Value, String> {
+	let version = version(param(&params, "version")?)?;
+
+	Ok(json!({
+		"major": version.major,
+		"minor": version.minor,
+		"patch": version.patch,
+		"pre": version.pre.as_str(),
+		"build": version.build.as_str(),
+	}))
+}
+
+impl Generator for Semver {
+	fn generate(&self, _runtime: &Runtime) -> Prop {
+		let members = vec![
+			("compare", function(&["a", "b"], compare)),
+			("satisfies", function(&["version", "constraint"], satisfies)),
+			("parse", function(&["version"], parse)),
+		];
+
+		Prop::group(Name::Semver, members)
+	}
+
+	fn name(&self) -> Name {
+		Name::Semver
+	}
+}
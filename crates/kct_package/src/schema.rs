@@ -1,10 +1,16 @@
 use crate::error::Error;
+use crate::explain::explain;
+use crate::format;
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use anyhow::Result;
+use kct_compiler::property::{Name, Prop};
 use kct_compiler::Validator;
 use kct_helper::io;
 use serde_json::Value;
@@ -24,11 +30,193 @@ impl Clone for Schema {
 	}
 }
 
+impl Schema {
+	pub fn value(&self) -> &Value {
+		&self.schema
+	}
+
+	/// Layers this schema on top of the one from the extended package,
+	/// keeping what the base requires
+	pub fn extend(&self, base: &Schema) -> Result<Self, Error> {
+		let mut merged = base.schema.as_ref().clone();
+		layer(&mut merged, &self.schema);
+
+		Self::try_from(&merged)
+	}
+
+	/// Whether the schema is marked as `deprecated`, for inputs to move on
+	pub fn is_deprecated(&self) -> bool {
+		self.schema.get("deprecated") == Some(&Value::Bool(true))
+	}
+}
+
+/// Merges the schema over the base as inputs are, except for constraints
+/// taking lists, which would drop the ones of the base: `required` takes
+/// both, `enum` the values of both, and `items` layers each position
+fn layer(base: &mut Value, over: &Value) {
+	match (base, over) {
+		(Value::Object(base), Value::Object(over)) => {
+			for (key, over) in over {
+				let base = base.entry(key.clone()).or_insert(Value::Null);
+
+				match (key.as_str(), base, over) {
+					("required", Value::Array(base), Value::Array(over)) => {
+						let missing: Vec<Value> = over
+							.iter()
+							.filter(|name| !base.contains(name))
+							.cloned()
+							.collect();
+
+						base.extend(missing);
+					}
+					("enum", Value::Array(base), Value::Array(over)) => {
+						base.retain(|value| over.contains(value));
+					}
+					("items", Value::Array(base), Value::Array(over)) => {
+						for (position, over) in over.iter().enumerate() {
+							match base.get_mut(position) {
+								Some(base) => layer(base, over),
+								None => base.push(over.clone()),
+							}
+						}
+					}
+					(_, base, over) => layer(base, over),
+				}
+			}
+		}
+		(base, over) => *base = over.clone(),
+	}
+}
+
+/// Schemas of a package by the `apiVersion` of the inputs they validate, so
+/// inputs of older versions keep working while they're deprecated
+#[derive(Debug, Clone, Default)]
+pub struct Versions(BTreeMap<String, Schema>);
+
+impl Versions {
+	/// Schemas within the directory, each versioned by its file name, as
+	/// `v1.json`
+	pub fn load(dir: &Path) -> Result<Self, Error> {
+		let mut versions = BTreeMap::new();
+
+		let entries = fs::read_dir(dir).map_err(|_err| Error::InvalidSchema)?;
+		for entry in entries {
+			let path = entry.map_err(|_err| Error::InvalidSchema)?.path();
+			if path.extension() != Some(OsStr::new("json")) {
+				continue;
+			}
+
+			let version = path
+				.file_stem()
+				.and_then(OsStr::to_str)
+				.ok_or(Error::InvalidSchema)?
+				.to_string();
+
+			versions.insert(version, Schema::try_from(path)?);
+		}
+
+		Ok(Self(versions))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Versions from the least to the most preferred
+	pub fn names(&self) -> Vec<&str> {
+		let mut names: Vec<&str> = self.0.keys().map(String::as_str).collect();
+		names.sort_by_key(|name| rank(name));
+
+		names
+	}
+
+	/// Schema of the preferred version, as Kubernetes prefers them
+	pub fn latest(&self) -> Option<&Schema> {
+		self.names().last().and_then(|name| self.0.get(*name))
+	}
+
+	/// Layers each version on top of the same one from the extended package,
+	/// keeping the versions only either has
+	pub fn extend(&self, base: &Versions) -> Result<Self, Error> {
+		let mut versions = base.0.clone();
+		for (version, schema) in &self.0 {
+			let schema = match base.0.get(version) {
+				Some(inherited) => schema.extend(inherited)?,
+				None => schema.clone(),
+			};
+
+			versions.insert(version.clone(), schema);
+		}
+
+		Ok(Self(versions))
+	}
+
+	/// Layers each version on top of the unversioned schema of the extended
+	/// package
+	pub fn over(&self, base: &Schema) -> Result<Self, Error> {
+		let mut versions = BTreeMap::new();
+		for (version, schema) in &self.0 {
+			versions.insert(version.clone(), schema.extend(base)?);
+		}
+
+		Ok(Self(versions))
+	}
+
+	/// Version and schema the input declares through its `apiVersion`
+	pub fn select(&self, input: &Value) -> Result<(&str, &Schema), String> {
+		let known = self.names().join(", ");
+
+		let version = match input.get("apiVersion") {
+			Some(Value::String(version)) => version,
+			_ => {
+				return Err(format!(
+					"input should declare its apiVersion, one of {known}"
+				))
+			}
+		};
+
+		self.0
+			.get_key_value(version)
+			.map(|(version, schema)| (version.as_str(), schema))
+			.ok_or_else(|| format!("input apiVersion {version} is unknown, use one of {known}"))
+	}
+}
+
+/// Preference of the version as Kubernetes has it, stable ones over betas
+/// over alphas, then the higher major and minor, so `v2alpha1` before
+/// `v1beta1` before `v1` before `v2`, and the ones out of the convention
+/// first, alphabetically
+fn rank(version: &str) -> (u8, u64, u64, String) {
+	let unranked = (0, 0, 0, version.to_string());
+
+	let rest = match version.strip_prefix('v') {
+		Some(rest) => rest,
+		None => return unranked,
+	};
+
+	let digits = rest.chars().take_while(char::is_ascii_digit).count();
+	let major = match rest[..digits].parse::<u64>() {
+		Ok(major) => major,
+		Err(_) => return unranked,
+	};
+
+	let (stability, minor) = match &rest[digits..] {
+		"" => (3, 0),
+		pre => match (pre.strip_prefix("alpha"), pre.strip_prefix("beta")) {
+			(Some(minor), _) => (1, minor.parse().unwrap_or_default()),
+			(_, Some(minor)) => (2, minor.parse().unwrap_or_default()),
+			_ => return unranked,
+		},
+	};
+
+	(stability, major, minor, String::new())
+}
+
 impl TryFrom<&Value> for Schema {
 	type Error = Error;
 
 	fn try_from(schema: &Value) -> Result<Self, Error> {
-		let mut scope = Scope::new();
+		let mut scope = Scope::with_formats(format::kubernetes);
 		let id = scope
 			.compile(schema.clone(), false)
 			.map_err(|_err| Error::InvalidSchema)?;
@@ -56,6 +244,12 @@ impl TryFrom<PathBuf> for Schema {
 	}
 }
 
+impl From<&Schema> for Prop {
+	fn from(schema: &Schema) -> Self {
+		Prop::primitive(Name::Schema, schema.schema.as_ref().clone())
+	}
+}
+
 impl From<Schema> for Validator {
 	fn from(schema: Schema) -> Self {
 		let predicate = move |input: &Value| -> Result<(), String> {
@@ -63,11 +257,18 @@ impl From<Schema> for Validator {
 				return Err("input is not an object".to_string());
 			}
 
-			let schema = schema.scope.resolve(&schema.id).unwrap();
-			if schema.validate(input).is_strictly_valid() {
-				Ok(())
-			} else {
-				Err("input doesn't match your schema".to_string())
+			let resolved = schema.scope.resolve(&schema.id).unwrap();
+			if resolved.validate(input).is_strictly_valid() {
+				return Ok(());
+			}
+
+			let reasons = explain(&schema.scope, &schema.id, &schema.schema, input);
+			match reasons.is_empty() {
+				true => Err("input doesn't match your schema".to_string()),
+				false => Err(format!(
+					"input doesn't match your schema: {}",
+					reasons.join("; ")
+				)),
 			}
 		};
 
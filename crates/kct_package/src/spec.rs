@@ -1,17 +1,191 @@
 use crate::error::Error;
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use anyhow::Result;
+use kct_compiler::lint::Rule;
+use kct_compiler::{sdk, Dialect, Sdk};
 use kct_helper::io;
 use semver::Version;
 use serde_json::Value;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Kind {
+	/// Renders objects, the default
+	#[default]
+	Application,
+	/// Only ships libs and files to be vendored by other packages
+	Library,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Output {
+	/// Kubernetes objects found by walking the rendered tree, the default
+	#[default]
+	Kubernetes,
+	/// Arbitrary files, the rendered object mapping their paths to contents
+	Files,
+	/// Plain text, e.g. a script, the main template rendering a single string
+	Text,
+}
+
+impl Output {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Output::Kubernetes => "kubernetes",
+			Output::Files => "files",
+			Output::Text => "text",
+		}
+	}
+}
+
+/// Privileged natives a package needs, refused unless declared so consumers
+/// can audit what it does when rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+	/// Requests through `http`
+	Network,
+	/// Live objects through `lookup`
+	Lookup,
+	/// Values derived from the release seed through `random`
+	Secrets,
+}
+
+impl Capability {
+	pub fn as_str(&self) -> &str {
+		match self {
+			Capability::Network => "network",
+			Capability::Lookup => "lookup",
+			Capability::Secrets => "secrets",
+		}
+	}
+}
+
+impl TryFrom<&str> for Capability {
+	type Error = Error;
+
+	fn try_from(raw: &str) -> Result<Self, Self::Error> {
+		match raw {
+			"network" => Ok(Capability::Network),
+			"lookup" => Ok(Capability::Lookup),
+			"secrets" => Ok(Capability::Secrets),
+			_ => Err(Error::InvalidSpec),
+		}
+	}
+}
+
+/// Opening and closing delimiters of each kind of Tera tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delimiters {
+	pub variable: (String, String),
+	pub block: (String, String),
+	pub comment: (String, String),
+}
+
+impl Default for Delimiters {
+	fn default() -> Self {
+		let pair = |open: &str, close: &str| (String::from(open), String::from(close));
+
+		Self {
+			variable: pair("{{", "}}"),
+			block: pair("{%", "%}"),
+			comment: pair("{#", "#}"),
+		}
+	}
+}
+
+/// How the templates within `files` are rendered
+#[derive(Debug, Clone, Default)]
+pub struct Templating {
+	pub delimiters: Delimiters,
+	/// Globs of the files passed through as they are, without rendering
+	pub raw: Vec<String>,
+}
+
+impl TryFrom<&Value> for Templating {
+	type Error = Error;
+
+	fn try_from(json: &Value) -> Result<Self, Self::Error> {
+		let mut delimiters = Delimiters::default();
+
+		match json.get("delimiters") {
+			None => (),
+			Some(Value::Object(kinds)) => {
+				for (kind, pair) in kinds {
+					let slot = match kind.as_str() {
+						"variable" => &mut delimiters.variable,
+						"block" => &mut delimiters.block,
+						"comment" => &mut delimiters.comment,
+						_ => return Err(Error::InvalidSpec),
+					};
+
+					*slot = match pair.as_array().map(Vec::as_slice) {
+						Some([Value::String(open), Value::String(close)])
+							if !open.is_empty() && !close.is_empty() =>
+						{
+							(open.clone(), close.clone())
+						}
+						_ => return Err(Error::InvalidSpec),
+					};
+				}
+			}
+			Some(_) => return Err(Error::InvalidSpec),
+		}
+
+		// Tags are told apart by how they open
+		let openers = [
+			&delimiters.variable.0,
+			&delimiters.block.0,
+			&delimiters.comment.0,
+		];
+		let clashing = openers
+			.iter()
+			.enumerate()
+			.any(|(i, opener)| openers[i + 1..].iter().any(|other| other == opener));
+		if clashing {
+			return Err(Error::InvalidSpec);
+		}
+
+		let raw = match json.get("raw") {
+			None => vec![],
+			Some(Value::Array(globs)) => globs
+				.iter()
+				.map(|v| v.as_str().map(String::from).ok_or(Error::InvalidSpec))
+				.collect::<Result<_, _>>()?,
+			Some(_) => return Err(Error::InvalidSpec),
+		};
+
+		Ok(Self { delimiters, raw })
+	}
+}
+
 #[derive(Debug, Clone)]
 pub struct Spec {
 	pub name: String,
 	pub version: Version,
+	pub kind: Kind,
+	pub output: Output,
+	/// Path, relative to the package, of the package being extended
+	pub extends: Option<PathBuf>,
+	pub capabilities: Vec<Capability>,
+	/// Directories, relative to the package, whose kustomizations templates
+	/// build through `kustomize`
+	pub kustomize: Vec<PathBuf>,
+	/// Jsonnet lint rules not checked on the templates
+	pub disabled_lints: Vec<Rule>,
+	pub templating: Templating,
+	/// Directories, relative to the package, searched for libraries after
+	/// `lib`, the last one first as with `-J`
+	pub libraries: Vec<PathBuf>,
+	/// Files or directories, relative to the package, imported by the names
+	/// they're aliased to
+	pub aliases: BTreeMap<String, PathBuf>,
+	/// Parts of Jsonnet the templates may use, when the package restricts them
+	pub dialect: Option<Dialect>,
+	/// Version of the SDK the package pins, as `v1`
+	pub sdk: Option<&'static Sdk>,
 }
 
 impl TryFrom<PathBuf> for Spec {
@@ -34,9 +208,132 @@ impl TryFrom<PathBuf> for Spec {
 					.map(|v| Version::parse(v).map_err(|_err| Error::InvalidSpec))
 					.unwrap_or(Err(Error::InvalidSpec))?;
 
-				Ok(Spec { name, version })
+				let kind = match json.get("kind").map(|v| v.as_str()) {
+					None => Kind::default(),
+					Some(Some("application")) => Kind::Application,
+					Some(Some("library")) => Kind::Library,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let output = match json.get("output").map(|v| v.as_str()) {
+					None => Output::default(),
+					Some(Some("kubernetes")) => Output::Kubernetes,
+					Some(Some("files")) => Output::Files,
+					Some(Some("text")) => Output::Text,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let extends = match json.get("extends") {
+					None => None,
+					Some(Value::String(path)) => Some(PathBuf::from(path)),
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let capabilities = match json.get("capabilities") {
+					None => vec![],
+					Some(Value::Array(capabilities)) => capabilities
+						.iter()
+						.map(|v| v.as_str().ok_or(Error::InvalidSpec))
+						.map(|v| v.and_then(Capability::try_from))
+						.collect::<Result<_, _>>()?,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let kustomize = match json.get("kustomize") {
+					None => vec![],
+					Some(Value::Array(dirs)) => dirs
+						.iter()
+						.map(|v| v.as_str().ok_or(Error::InvalidSpec))
+						.map(|v| v.map(|dir| PathBuf::from(dir.trim_end_matches('/'))))
+						.collect::<Result<_, _>>()?,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let disabled_lints = match json.get("lint").map(|lint| lint.get("disable")) {
+					None | Some(None) => vec![],
+					Some(Some(Value::Array(rules))) => rules
+						.iter()
+						.map(|v| v.as_str().ok_or(Error::InvalidSpec))
+						.map(|v| v.and_then(|rule| rule.parse().map_err(|_err| Error::InvalidSpec)))
+						.collect::<Result<_, _>>()?,
+					Some(Some(_)) => return Err(Error::InvalidSpec),
+				};
+
+				let templating = match json.get("files") {
+					None => Templating::default(),
+					Some(files) => Templating::try_from(files)?,
+				};
+
+				let libraries = match json.get("libraries") {
+					None => vec![],
+					Some(Value::Array(dirs)) => dirs
+						.iter()
+						.map(|v| v.as_str().map(PathBuf::from).ok_or(Error::InvalidSpec))
+						.collect::<Result<_, _>>()?,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let aliases = match json.get("aliases") {
+					None => BTreeMap::new(),
+					Some(Value::Object(aliases)) => aliases
+						.iter()
+						.map(|(name, v)| match v.as_str() {
+							Some(target) => Ok((name.clone(), PathBuf::from(target))),
+							None => Err(Error::InvalidSpec),
+						})
+						.collect::<Result<_, _>>()?,
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				let dialect = match json.get("jsonnet") {
+					None => None,
+					Some(jsonnet) => Some(dialect(jsonnet)?),
+				};
+
+				let sdk = match json.get("sdk") {
+					None => None,
+					Some(Value::String(version)) => {
+						Some(sdk::find(version).ok_or(Error::InvalidSpec)?)
+					}
+					Some(_) => return Err(Error::InvalidSpec),
+				};
+
+				Ok(Spec {
+					name,
+					version,
+					kind,
+					output,
+					extends,
+					capabilities,
+					kustomize,
+					disabled_lints,
+					templating,
+					libraries,
+					aliases,
+					dialect,
+					sdk,
+				})
 			}
 			_ => Err(Error::InvalidSpec),
 		}
 	}
 }
+
+/// Dialect of the `jsonnet` section, allowing what it doesn't mention
+fn dialect(json: &Value) -> Result<Dialect, Error> {
+	let mut dialect = Dialect::default();
+
+	for (option, value) in json.as_object().ok_or(Error::InvalidSpec)? {
+		match (option.as_str(), value) {
+			("foreignExtVars", Value::Bool(allowed)) => dialect.foreign_ext_vars = *allowed,
+			("native", Value::Bool(allowed)) => dialect.native = *allowed,
+			("maxStringSize", Value::Number(size)) => {
+				let size = size.as_u64().filter(|size| *size > 0);
+				dialect.max_string_size = Some(size.ok_or(Error::InvalidSpec)? as usize);
+			}
+			_ => return Err(Error::InvalidSpec),
+		}
+	}
+
+	Ok(dialect)
+}
@@ -0,0 +1,74 @@
+use crate::archive::{self, Limits, Symlinks};
+use crate::error::Error;
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+/// Packages unpacked from archives by the digest of their contents, so the
+/// same package is unpacked once and reused, read-only, by every compile
+/// and project
+pub struct Store {
+	root: PathBuf,
+}
+
+impl Store {
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	/// Root of the package within the archive, unpacking it unless it's
+	/// stored already. Entries are checked every time, as what's refused
+	/// depends on the symlinks and limits given.
+	pub fn unpack(
+		&self,
+		archive: impl Read,
+		symlinks: Symlinks,
+		limits: Limits,
+	) -> Result<PathBuf, Error> {
+		let tarball = archive::decompress(archive, &limits)?;
+		archive::check(&tarball, symlinks, limits)?;
+
+		let dir = self.root.join(format!("{:x}", Sha256::digest(&tarball)));
+		if !dir.exists() {
+			self.store(&tarball, &dir)?;
+		}
+
+		archive::root(&dir)
+	}
+
+	/// Unpacks aside and moves it in at once, so others never see it halfway
+	fn store(&self, tarball: &[u8], dir: &Path) -> Result<(), Error> {
+		let unable = |err: std::io::Error| Error::Store(err.to_string());
+
+		fs::create_dir_all(&self.root).map_err(unable)?;
+		let staging = tempfile::tempdir_in(&self.root).map_err(unable)?;
+		archive::extract(tarball, staging.path())?;
+		seal(staging.path()).map_err(unable)?;
+
+		// Someone else storing it meanwhile is as good
+		match fs::rename(staging.path(), dir) {
+			Err(_) if dir.exists() => Ok(()),
+			renamed => renamed.map_err(unable),
+		}
+	}
+}
+
+/// Makes the files read-only, as they're shared by whoever unpacks the same
+fn seal(dir: &Path) -> std::io::Result<()> {
+	for entry in WalkDir::new(dir) {
+		let entry = entry?;
+		if !entry.file_type().is_file() {
+			continue;
+		}
+
+		let mut permissions = entry.metadata()?.permissions();
+		permissions.set_readonly(true);
+		fs::set_permissions(entry.path(), permissions)?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,607 @@
+use crate::error::Error;
+use crate::lock::{self, LOCK_FILE, VENDOR_DIR};
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use kct_helper::io;
+use semver::{Version, VersionReq};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tracing::debug;
+
+pub(crate) const MANIFEST_FILE: &str = "jsonnetfile.json";
+const ROOT: &str = "root";
+
+/// Dependency fetched from a git repository, declared in `jsonnetfile.json`
+/// as for Jsonnet Bundler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+	/// Any URL git understands, e.g. `git+ssh://git@github.com/org/repo.git`
+	pub remote: String,
+	/// Directory within the repository holding what's vendored
+	pub subdir: Option<String>,
+	/// Tag, branch, or commit to check out, or a semver range over the tags
+	/// like `^1.2`, the default branch when absent
+	pub version: Option<String>,
+}
+
+impl Dependency {
+	/// Directory within `vendor` where the dependency is placed
+	pub fn name(&self) -> PathBuf {
+		lock::name(&self.remote, self.subdir.as_deref())
+	}
+
+	fn try_from(value: &Value) -> Result<Option<Self>, Error> {
+		// Local dependencies are used in place, so there's nothing to fetch
+		let git = match value.pointer("/source/git") {
+			None => return Ok(None),
+			Some(git) => git,
+		};
+
+		let text = |value: Option<&Value>| match value {
+			None | Some(Value::Null) => Ok(None),
+			Some(Value::String(text)) => Ok(Some(text.clone())),
+			Some(_) => Err(Error::InvalidDependencies),
+		};
+
+		let remote = text(git.get("remote"))?.ok_or(Error::InvalidDependencies)?;
+		let subdir = text(git.get("subdir"))?.filter(|subdir| !subdir.is_empty());
+		let version = text(value.get("version"))?.filter(|version| !version.is_empty());
+
+		Ok(Some(Self {
+			remote,
+			subdir,
+			version,
+		}))
+	}
+
+	/// Range over the tags when the version is one, rather than a git ref
+	fn range(&self) -> Option<VersionReq> {
+		let version = self.version.as_deref()?;
+		let operators = ['^', '~', '=', '>', '<', '*'];
+
+		match version.starts_with(operators) || version.contains(',') {
+			true => VersionReq::parse(version).ok(),
+			false => None,
+		}
+	}
+}
+
+/// Dependency as vendored, pinned to the commit its version resolved to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vendored {
+	pub dependency: Dependency,
+	pub rev: String,
+	/// Digest of the vendored contents, as recorded in the lockfile
+	pub sum: String,
+	/// Key that signed the commit, when it was
+	pub signer: Option<String>,
+}
+
+/// Fetches the dependencies declared by the package at the root, along the
+/// ones they declare themselves, into its `vendor` directory, recording what
+/// they resolved to in the lockfile. Git runs without prompts, so SSH remotes
+/// rely on the agent and known hosts. Dependencies already in the lockfile
+/// are trusted on first use: their contents at the locked commit, and the
+/// key signing them, must stay the same.
+pub fn vendor(root: &Path) -> Result<Vec<Vendored>, Error> {
+	let contents =
+		io::from_file(&root.join(MANIFEST_FILE)).map_err(|_err| Error::NoDependencies)?;
+	let manifest: Value =
+		serde_json::from_str(&contents).map_err(|_err| Error::InvalidDependencies)?;
+
+	let locked: HashMap<PathBuf, Vendored> = locked(root)?
+		.into_iter()
+		.map(|locked| (locked.dependency.name(), locked))
+		.collect();
+
+	let mut resolver = Resolver::default();
+	let selected = resolver.resolve(dependencies(&manifest)?)?;
+
+	let vendor = root.join(VENDOR_DIR);
+	let mut vendored = vec![];
+	for (name, selection) in selected {
+		let repository = resolver.repository(&selection.dependency)?;
+		let display = name.display().to_string();
+		let failed = |reason: String| Error::Fetch(display.clone(), reason);
+
+		// Fetched aside, so nothing replaces what's vendored until it's trusted
+		fs::create_dir_all(&vendor).map_err(|err| failed(err.to_string()))?;
+		let staging = tempfile::tempdir_in(&vendor).map_err(|err| failed(err.to_string()))?;
+		let staged = staging.path().join("contents");
+
+		repository
+			.export(
+				&selection.rev,
+				selection.dependency.subdir.as_deref(),
+				&staged,
+			)
+			.map_err(failed)?;
+		let sum = lock::digest(&staged).map_err(|err| failed(err.to_string()))?;
+		let signer = repository
+			.signer(&selection.rev)
+			.map_err(|reason| Error::Tampered(display.clone(), reason))?;
+
+		if let Some(locked) = locked.get(&name) {
+			trust(&display, locked, &selection.rev, &sum, &signer)?;
+		}
+
+		let target = vendor.join(&name);
+		replace(&staged, &target).map_err(|err| failed(err.to_string()))?;
+
+		vendored.push(Vendored {
+			dependency: selection.dependency,
+			rev: selection.rev,
+			sum,
+			signer,
+		});
+	}
+
+	let locked: Vec<Value> = vendored
+		.iter()
+		.map(|vendored| {
+			let mut locked = json!({
+				"source": {
+					"git": {
+						"remote": vendored.dependency.remote,
+						"subdir": vendored.dependency.subdir.clone().unwrap_or_default(),
+					}
+				},
+				"version": vendored.rev,
+				"sum": vendored.sum,
+			});
+
+			if let Some(signer) = &vendored.signer {
+				locked["signer"] = Value::String(signer.clone());
+			}
+
+			locked
+		})
+		.collect();
+
+	let lock = json!({
+		"version": 1,
+		"dependencies": locked,
+		"legacyImports": manifest.get("legacyImports").cloned().unwrap_or(Value::Bool(false)),
+	});
+	let lock = serde_json::to_string_pretty(&lock).map_err(|_err| Error::InvalidLock)?;
+
+	io::write_contents(&root.join(LOCK_FILE), &format!("{lock}\n"))
+		.map_err(|_err| Error::InvalidLock)?;
+
+	Ok(vendored)
+}
+
+/// Refuses what was fetched when it differs from what the lockfile recorded:
+/// other contents at the locked commit, or a commit signed by another key
+fn trust(
+	name: &str,
+	locked: &Vendored,
+	rev: &str,
+	sum: &str,
+	signer: &Option<String>,
+) -> Result<(), Error> {
+	let tampered = |reason: String| Err(Error::Tampered(name.to_string(), reason));
+
+	if locked.rev == rev && !locked.sum.is_empty() && locked.sum != sum {
+		return tampered(format!("its contents at {rev} changed"));
+	}
+
+	match (&locked.signer, signer) {
+		(Some(locked), Some(signer)) if locked != signer => {
+			tampered(format!("it's signed by {signer} rather than {locked}"))
+		}
+		(Some(locked), None) => tampered(format!("it's no longer signed by {locked}")),
+		_ => Ok(()),
+	}
+}
+
+/// Moves the staged contents in place of the target
+fn replace(staged: &Path, target: &Path) -> std::io::Result<()> {
+	if target.exists() {
+		fs::remove_dir_all(target)?;
+	}
+
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	fs::rename(staged, target)
+}
+
+/// Dependencies recorded in the lockfile at the root, pinned to their commits
+pub(crate) fn locked(root: &Path) -> Result<Vec<Vendored>, Error> {
+	let path = root.join(LOCK_FILE);
+	if !path.exists() {
+		return Ok(vec![]);
+	}
+
+	let contents = io::from_file(&path).map_err(|_err| Error::InvalidLock)?;
+	let lock: Value = serde_json::from_str(&contents).map_err(|_err| Error::InvalidLock)?;
+
+	let recorded = match lock.get("dependencies") {
+		None => return Ok(vec![]),
+		Some(Value::Array(recorded)) => recorded,
+		Some(_) => return Err(Error::InvalidLock),
+	};
+
+	let text = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(String::from);
+
+	let mut locked = vec![];
+	for value in recorded {
+		let dependency = Dependency::try_from(value).map_err(|_err| Error::InvalidLock)?;
+		let (dependency, rev) = match (dependency, text(value, "version")) {
+			(Some(dependency), Some(rev)) => (dependency, rev),
+			_ => continue,
+		};
+
+		locked.push(Vendored {
+			dependency,
+			rev,
+			sum: text(value, "sum").unwrap_or_default(),
+			signer: text(value, "signer"),
+		});
+	}
+
+	Ok(locked)
+}
+
+pub(crate) fn dependencies(manifest: &Value) -> Result<Vec<Dependency>, Error> {
+	let declared = match manifest.get("dependencies") {
+		None => return Ok(vec![]),
+		Some(Value::Array(declared)) => declared,
+		Some(_) => return Err(Error::InvalidDependencies),
+	};
+
+	let mut dependencies = vec![];
+	for dependency in declared {
+		dependencies.extend(Dependency::try_from(dependency)?);
+	}
+
+	Ok(dependencies)
+}
+
+/// Version of a dependency asked by the root or another dependency
+struct Requirement {
+	dependency: Dependency,
+	/// Names from the root down to the one requiring it
+	chain: Vec<String>,
+	/// Dependency and commit requiring it, none for the root
+	by: Option<(PathBuf, String)>,
+}
+
+impl Requirement {
+	fn describe(&self) -> String {
+		let version = self
+			.dependency
+			.version
+			.as_deref()
+			.unwrap_or("the default branch");
+
+		format!("{version} by {}", self.chain.join(" → "))
+	}
+}
+
+struct Selection {
+	dependency: Dependency,
+	rev: String,
+}
+
+#[derive(Default)]
+struct Resolver {
+	repositories: HashMap<String, Repository>,
+	requirements: BTreeMap<PathBuf, Vec<Requirement>>,
+}
+
+impl Resolver {
+	/// Walks the graph from the root dependencies, selecting a commit for
+	/// each and reading what it declares until every requirement is settled
+	fn resolve(&mut self, roots: Vec<Dependency>) -> Result<BTreeMap<PathBuf, Selection>, Error> {
+		let mut selected: BTreeMap<PathBuf, Selection> = BTreeMap::new();
+		let mut pending: Vec<Requirement> = roots
+			.into_iter()
+			.map(|dependency| Requirement {
+				dependency,
+				chain: vec![String::from(ROOT)],
+				by: None,
+			})
+			.collect();
+		let mut changed = true;
+
+		while changed {
+			changed = false;
+
+			for requirement in pending.drain(..) {
+				let name = requirement.dependency.name();
+				let display = name.display().to_string();
+
+				if requirement.chain.contains(&display) {
+					let mut cycle = requirement.chain.clone();
+					cycle.push(display);
+
+					return Err(Error::Cycle(cycle[1..].join(" → ")));
+				}
+
+				self.requirements.entry(name).or_default().push(requirement);
+			}
+
+			for (name, requirements) in &self.requirements {
+				// Requirements only count while who asked is selected at that commit
+				let requirements: Vec<&Requirement> = requirements
+					.iter()
+					.filter(|requirement| match &requirement.by {
+						None => true,
+						Some((by, rev)) => selected.get(by).map(|s| &s.rev) == Some(rev),
+					})
+					.collect();
+
+				if requirements.is_empty() {
+					changed |= selected.remove(name).is_some();
+					continue;
+				}
+
+				let dependency = &requirements[0].dependency;
+				let repository = repository(&mut self.repositories, dependency)?;
+				let rev = select(repository, name, &requirements)?;
+
+				if selected.get(name).map(|s| &s.rev) == Some(&rev) {
+					continue;
+				}
+
+				debug!(name = %name.display(), rev, "dependency selected");
+				let declared = match repository.manifest(&rev, dependency.subdir.as_deref()) {
+					Some(manifest) => dependencies(&manifest)?,
+					None => vec![],
+				};
+
+				let mut chain = requirements[0].chain.clone();
+				chain.push(name.display().to_string());
+
+				pending.extend(declared.into_iter().map(|declared| Requirement {
+					dependency: declared,
+					chain: chain.clone(),
+					by: Some((name.clone(), rev.clone())),
+				}));
+
+				let selection = Selection {
+					dependency: dependency.clone(),
+					rev,
+				};
+				selected.insert(name.clone(), selection);
+				changed = true;
+			}
+		}
+
+		Ok(selected)
+	}
+
+	fn repository(&mut self, dependency: &Dependency) -> Result<&Repository, Error> {
+		repository(&mut self.repositories, dependency)
+	}
+}
+
+/// Clones each remote once, however many dependencies come from it
+fn repository<'a>(
+	repositories: &'a mut HashMap<String, Repository>,
+	dependency: &Dependency,
+) -> Result<&'a Repository, Error> {
+	if !repositories.contains_key(&dependency.remote) {
+		let name = dependency.name().display().to_string();
+		let repository =
+			Repository::clone(&dependency.remote).map_err(|reason| Error::Fetch(name, reason))?;
+
+		repositories.insert(dependency.remote.clone(), repository);
+	}
+
+	Ok(&repositories[&dependency.remote])
+}
+
+/// Settles the commit satisfying every requirement of a dependency. The root
+/// has the final word, otherwise refs must agree with each other and ranges,
+/// which pick the highest tag matching all of them.
+fn select(
+	repository: &Repository,
+	name: &Path,
+	requirements: &[&Requirement],
+) -> Result<String, Error> {
+	let display = name.display().to_string();
+	let failed = |reason: String| Error::Fetch(display.clone(), reason);
+
+	let conflict = || {
+		let asked: Vec<String> = requirements.iter().map(|r| r.describe()).collect();
+
+		Error::Conflict(display.clone(), asked.join(", and "))
+	};
+
+	let root = requirements
+		.iter()
+		.find(|requirement| requirement.by.is_none());
+	let considered: Vec<&Requirement> = match root {
+		Some(root) => vec![root],
+		None => requirements.to_vec(),
+	};
+
+	let mut refs: Vec<&str> = considered
+		.iter()
+		.filter(|requirement| requirement.dependency.range().is_none())
+		.filter_map(|requirement| requirement.dependency.version.as_deref())
+		.collect();
+	refs.sort();
+	refs.dedup();
+
+	let ranges: Vec<VersionReq> = considered
+		.iter()
+		.filter_map(|requirement| requirement.dependency.range())
+		.collect();
+	let satisfies = |version: &Version| ranges.iter().all(|range| range.matches(version));
+
+	let version = match refs.as_slice() {
+		[] if ranges.is_empty() => String::from("HEAD"),
+		[] => repository
+			.tags()
+			.map_err(failed)?
+			.into_iter()
+			.filter(|(_, version)| satisfies(version))
+			.max_by(|(_, a), (_, b)| a.cmp(b))
+			.map(|(tag, _)| tag)
+			.ok_or_else(conflict)?,
+		[only] => match parse_tag(only) {
+			Some(version) if satisfies(&version) => only.to_string(),
+			None if ranges.is_empty() => only.to_string(),
+			_ => return Err(conflict()),
+		},
+		_ => return Err(conflict()),
+	};
+
+	repository.rev(&version).map_err(failed)
+}
+
+pub(crate) fn parse_tag(tag: &str) -> Option<Version> {
+	Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// Local clone of a remote, from which any of its commits can be read
+pub(crate) struct Repository {
+	dir: TempDir,
+}
+
+impl Repository {
+	pub(crate) fn clone(remote: &str) -> Result<Self, String> {
+		let dir = tempfile::tempdir().map_err(|err| err.to_string())?;
+		let remote = remote.strip_prefix("git+").unwrap_or(remote);
+
+		debug!(remote, "cloning dependency");
+		git(
+			&["clone", "--quiet", "--no-checkout", remote, "."],
+			dir.path(),
+		)?;
+
+		Ok(Self { dir })
+	}
+
+	pub(crate) fn rev(&self, version: &str) -> Result<String, String> {
+		let commit = format!("{version}^{{commit}}");
+
+		git(
+			&["rev-parse", "--verify", "--quiet", &commit],
+			self.dir.path(),
+		)
+		.or_else(|_| {
+			// Branches only exist as remote ones in clones
+			let remote = format!("origin/{version}^{{commit}}");
+
+			git(
+				&["rev-parse", "--verify", "--quiet", &remote],
+				self.dir.path(),
+			)
+		})
+		.map_err(|_err| format!("there's no {version}"))
+	}
+
+	/// Tags that are semantic versions, with or without a `v` prefix
+	pub(crate) fn tags(&self) -> Result<Vec<(String, Version)>, String> {
+		let tags = git(&["tag", "--list"], self.dir.path())?;
+
+		Ok(tags
+			.lines()
+			.filter_map(|tag| parse_tag(tag).map(|version| (tag.to_string(), version)))
+			.collect())
+	}
+
+	/// Dependencies declared at the commit, next to what's vendored
+	fn manifest(&self, rev: &str, subdir: Option<&str>) -> Option<Value> {
+		let path = match subdir {
+			Some(subdir) => format!("{rev}:{}/{MANIFEST_FILE}", subdir.trim_matches('/')),
+			None => format!("{rev}:{MANIFEST_FILE}"),
+		};
+
+		let contents = git(&["show", &path], self.dir.path()).ok()?;
+
+		serde_json::from_str(&contents).ok()
+	}
+
+	/// Key signing the commit, none when it isn't signed. The key doesn't need
+	/// to be known for it to be told, but signatures known to be bad fail.
+	fn signer(&self, rev: &str) -> Result<Option<String>, String> {
+		let signature = git(&["show", "-s", "--format=%G?:%GK", rev], self.dir.path())?;
+
+		match signature.split_once(':') {
+			Some(("N", _)) | None => Ok(None),
+			Some(("B", _)) => Err(format!("the signature of {rev} is bad")),
+			Some((_, "")) => Err(format!("the key signing {rev} is unknown")),
+			Some((_, key)) => Ok(Some(key.to_string())),
+		}
+	}
+
+	/// Copies the contents of the subdirectory at the commit into the target
+	fn export(&self, rev: &str, subdir: Option<&str>, target: &Path) -> Result<(), String> {
+		let dir = self.dir.path();
+		git(&["checkout", "--quiet", "--force", rev], dir)?;
+
+		let source = match subdir {
+			Some(subdir) => dir.join(subdir.trim_matches('/')),
+			None => dir.to_path_buf(),
+		};
+		if !source.is_dir() {
+			let subdir = subdir.unwrap_or_default();
+
+			return Err(format!("there's no {subdir} at {rev}"));
+		}
+
+		copy(&source, target).map_err(|err| err.to_string())
+	}
+}
+
+/// Runs git without prompting, returning its trimmed output or why it failed
+fn git(args: &[&str], dir: &Path) -> Result<String, String> {
+	let mut command = Command::new("git");
+	command
+		.args(args)
+		.current_dir(dir)
+		.env("GIT_TERMINAL_PROMPT", "0");
+
+	// Keeps known hosts verified while failing instead of asking for passwords
+	if env::var_os("GIT_SSH_COMMAND").is_none() {
+		command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+	}
+
+	let output = command.output().map_err(|err| err.to_string())?;
+
+	if output.status.success() {
+		Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+	} else {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let reason: Vec<&str> = stderr
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.collect();
+
+		Err(reason.join(" "))
+	}
+}
+
+fn copy(source: &Path, target: &Path) -> std::io::Result<()> {
+	fs::create_dir_all(target)?;
+
+	for entry in fs::read_dir(source)? {
+		let entry = entry?;
+		let path = entry.path();
+
+		if entry.file_name() == ".git" {
+			continue;
+		}
+
+		if path.is_dir() {
+			copy(&path, &target.join(entry.file_name()))?;
+		} else {
+			fs::copy(&path, target.join(entry.file_name()))?;
+		}
+	}
+
+	Ok(())
+}
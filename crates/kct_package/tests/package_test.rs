@@ -2,8 +2,8 @@ use std::convert::TryFrom;
 use std::panic::panic_any;
 
 use assert_matches::assert_matches;
-use kct_compiler::Release;
-use kct_package::{Error, Package};
+use kct_compiler::{Error as CompilerError, Release, MAX_IMPORT_SIZE};
+use kct_package::{self as kcp, Capability, Delimiters, Error, Kind, Output, Package};
 use kct_testing::dir::TempDir;
 use kct_testing::{self as testing, Fixture};
 use serde_json::{json, Map, Value};
@@ -15,6 +15,25 @@ fn package(with: Vec<(&str, &str)>, without: Vec<&str>) -> (Result<Package, Erro
 	(package, dir)
 }
 
+/// Package extending the base, vendored within it as bases are only taken
+/// from within the package compiled
+fn extending(
+	base: &TempDir,
+	with: Vec<(&str, &str)>,
+	without: Vec<&str>,
+) -> (Result<Package, Error>, TempDir) {
+	let dir = Fixture::custom(with, without);
+	let vendored = dir.path().join("vendor/base");
+	testing::dir::mk(&vendored);
+	testing::dir::cp(base.path(), &vendored);
+
+	(Package::try_from(dir.path()), dir)
+}
+
+fn declaring(capabilities: &[&str]) -> String {
+	json!({ "name": "fixture", "version": "0.0.0", "capabilities": capabilities }).to_string()
+}
+
 fn compile_with_example(pkg: Package, rel: Option<Release>) -> Result<Value, Error> {
 	let input = pkg.example.clone().unwrap();
 
@@ -42,319 +61,4547 @@ mod try_from {
 	fn requests_example_for_schema() {
 		let (package, _dir) = package(vec![], vec!["example.json"]);
 
-		assert_matches!(package.unwrap_err(), Error::NoExample)
+		assert_matches!(package.unwrap_err(), Error::NoExample)
+	}
+
+	#[test]
+	fn request_schema_for_input() {
+		let (package, _dir) = package(vec![], vec!["schema.json"]);
+
+		assert_matches!(package.unwrap_err(), Error::NoSchema)
+	}
+
+	#[test]
+	fn needs_a_main_file() {
+		let (package, _dir) = package(vec![], vec!["templates/main.jsonnet"]);
+
+		assert_matches!(package.unwrap_err(), Error::NoMain);
+	}
+
+	#[test]
+	fn libraries_dont_need_main() {
+		let spec = r#"{ "name": "lib", "version": "0.0.0", "kind": "library" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec!["templates/main.jsonnet"]);
+		let package = package.unwrap();
+
+		assert_eq!(package.spec.kind, Kind::Library);
+		assert_eq!(package.main, None);
+	}
+
+	#[test]
+	fn needs_a_known_kind() {
+		let spec = r#"{ "name": "lib", "version": "0.0.0", "kind": "plugin" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn reads_output() {
+		let spec = r#"{ "name": "dashboards", "version": "0.0.0", "output": "files" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		assert_eq!(package.unwrap().spec.output, Output::Files);
+	}
+
+	#[test]
+	fn reads_text_output() {
+		let spec = r#"{ "name": "script", "version": "0.0.0", "output": "text" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		assert_eq!(package.unwrap().spec.output, Output::Text);
+	}
+
+	#[test]
+	fn needs_a_known_output() {
+		let spec = r#"{ "name": "dashboards", "version": "0.0.0", "output": "helm" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn reads_templating() {
+		let spec = json!({
+			"name": "dashboards",
+			"version": "0.0.0",
+			"files": { "delimiters": { "variable": ["[[", "]]"] }, "raw": ["grafana/**"] }
+		});
+		let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+		let templating = package.unwrap().spec.templating;
+
+		assert_eq!(
+			templating.delimiters.variable,
+			(String::from("[["), String::from("]]"))
+		);
+		assert_eq!(templating.delimiters.block, Delimiters::default().block);
+		assert_eq!(templating.raw, vec![String::from("grafana/**")]);
+	}
+
+	#[test]
+	fn needs_distinct_delimiters() {
+		let spec = json!({
+			"name": "dashboards",
+			"version": "0.0.0",
+			"files": { "delimiters": { "variable": ["[[", "]]"], "block": ["[[", "%]"] } }
+		});
+		let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn reads_capabilities() {
+		let spec = declaring(&["lookup", "network"]);
+		let (package, _dir) = package(vec![("kcp.json", &spec)], vec![]);
+
+		assert_eq!(
+			package.unwrap().spec.capabilities,
+			vec![Capability::Lookup, Capability::Network]
+		);
+	}
+
+	#[test]
+	fn needs_known_capabilities() {
+		let spec = declaring(&["filesystem"]);
+		let (package, _dir) = package(vec![("kcp.json", &spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn needs_libraries_to_be_paths() {
+		let spec = json!({ "name": "fixture", "version": "0.0.0", "libraries": [true] });
+		let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn needs_a_known_sdk() {
+		let spec = json!({ "name": "fixture", "version": "0.0.0", "sdk": "v0" });
+		let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn refuses_schemas_beside_versions() {
+		let (package, _dir) = package(vec![("schemas/v1.json", "{}")], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSchema);
+	}
+
+	#[test]
+	fn needs_known_jsonnet_options() {
+		for jsonnet in [
+			json!({ "eval": false }),
+			json!({ "native": "no" }),
+			json!({ "maxStringSize": 0 }),
+		] {
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "jsonnet": jsonnet });
+			let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+
+			assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+		}
+	}
+
+	#[test]
+	fn needs_aliases_to_be_paths() {
+		let spec =
+			json!({ "name": "fixture", "version": "0.0.0", "aliases": { "k.libsonnet": 1 } });
+		let (package, _dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+}
+
+mod extends {
+	use super::*;
+
+	fn child(
+		base: &TempDir,
+		with: Vec<(&str, &str)>,
+		without: Vec<&str>,
+	) -> (Result<Package, Error>, TempDir) {
+		let spec = json!({
+			"name": "child",
+			"version": "0.0.0",
+			"extends": "vendor/base",
+		})
+		.to_string();
+
+		let mut with = with;
+		with.push(("kcp.json", &spec));
+
+		extending(base, with, without)
+	}
+
+	#[test]
+	fn inherits_main_template() {
+		let (_base, dir) = package(vec![], vec![]);
+		let (package, child) = child(
+			&dir,
+			vec![],
+			vec!["templates/main.jsonnet", "schema.json", "example.json"],
+		);
+		let package = package.unwrap();
+
+		assert_eq!(
+			package.main,
+			Some(child.path().join("vendor/base/templates/main.jsonnet"))
+		);
+		assert!(package.schema.is_some());
+		assert!(package.example.is_some());
+	}
+
+	#[test]
+	fn shadows_templates() {
+		let (_base, dir) = package(
+			vec![
+				("templates/main.jsonnet", "import './name.jsonnet'"),
+				("templates/name.jsonnet", "'base'"),
+			],
+			vec![],
+		);
+		let (package, _child) = child(
+			&dir,
+			vec![("templates/name.jsonnet", "'child'")],
+			vec!["templates/main.jsonnet"],
+		);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert_eq!(rendered.unwrap(), json!("child"));
+	}
+
+	#[test]
+	fn shadows_files() {
+		let (_base, dir) = package(
+			vec![(
+				"templates/main.jsonnet",
+				"local _ = import 'kct.libsonnet'; [_.files('no-params.txt'), _.files('database.toml', _.input)]",
+			)],
+			vec![],
+		);
+		let (package, _child) = child(
+			&dir,
+			vec![("files/no-params.txt", "child")],
+			vec!["templates/main.jsonnet", "files/database.toml"],
+		);
+		let package = package.unwrap();
+		let input = package.example.clone().unwrap();
+		let database = testing::template(&Fixture::contents("files/database.toml"), &input);
+		let rendered = package.compile(Some(input), None);
+
+		assert_eq!(rendered.unwrap(), json!(["child", database]));
+	}
+
+	#[test]
+	fn shadows_partials() {
+		let (_base, dir) = package(
+			vec![
+				(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('page.txt')",
+				),
+				(
+					"files/page.txt",
+					"{% include \"partials/header.txt\" %}body",
+				),
+				("files/partials/header.txt", "base "),
+			],
+			vec![],
+		);
+		let (package, _child) = child(
+			&dir,
+			vec![("files/partials/header.txt", "child ")],
+			vec!["templates/main.jsonnet"],
+		);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert_eq!(rendered.unwrap(), json!("child body"));
+	}
+
+	#[test]
+	fn searches_libraries_of_bases() {
+		let spec = json!({ "name": "base", "version": "0.0.0", "libraries": ["shared"] });
+		let (_base, dir) = package(
+			vec![
+				("kcp.json", &spec.to_string()),
+				("shared/greeting.libsonnet", "'from base'"),
+			],
+			vec![],
+		);
+		let (package, _child) = child(
+			&dir,
+			vec![("templates/main.jsonnet", "import 'greeting.libsonnet'")],
+			vec![],
+		);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert_eq!(rendered.unwrap(), json!("from base"));
+	}
+
+	#[test]
+	fn inherits_the_dialect_of_bases() {
+		let spec = json!({ "name": "base", "version": "0.0.0", "jsonnet": { "native": false } });
+		let (_base, dir) = package(vec![("kcp.json", &spec.to_string())], vec![]);
+		let (package, _child) = child(
+			&dir,
+			vec![("templates/main.jsonnet", "std.native('kct')")],
+			vec![],
+		);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert!(rendered
+			.unwrap_err()
+			.to_string()
+			.contains("disallowed by the package"));
+	}
+
+	#[test]
+	fn overrides_aliases_of_bases() {
+		let spec = json!({
+			"name": "base",
+			"version": "0.0.0",
+			"aliases": { "k.libsonnet": "k/1.28.libsonnet" }
+		});
+		let (_base, dir) = package(
+			vec![
+				("kcp.json", &spec.to_string()),
+				("k/1.28.libsonnet", "'1.28'"),
+				("k/1.29.libsonnet", "'1.29'"),
+				("templates/main.jsonnet", "import 'k.libsonnet'"),
+			],
+			vec![],
+		);
+		let (package, child) = child(&dir, vec![], vec!["templates/main.jsonnet"]);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert_eq!(rendered.unwrap(), json!("1.28"));
+
+		let spec = json!({
+			"name": "child",
+			"version": "0.0.0",
+			"extends": "vendor/base",
+			"aliases": { "k.libsonnet": "vendor/base/k/1.29.libsonnet" }
+		});
+		std::fs::write(child.path().join("kcp.json"), spec.to_string()).unwrap();
+		let package = Package::try_from(child.path()).unwrap();
+		let rendered = compile_with_example(package, None);
+
+		assert_eq!(rendered.unwrap(), json!("1.29"));
+	}
+
+	#[test]
+	fn merges_schema() {
+		let (_base, dir) = package(vec![], vec![]);
+		let schema = json!({
+			"properties": {
+				"replicas": { "type": "integer" }
+			}
+		})
+		.to_string();
+		let (package, _child) = child(&dir, vec![("schema.json", &schema)], vec![]);
+		let package = package.unwrap();
+
+		let mut input = package.example.clone().unwrap();
+		input["replicas"] = json!("many");
+		let rendered = package.compile(Some(input), None);
+
+		assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+	}
+
+	#[test]
+	fn inherits_capabilities() {
+		let spec = declaring(&["network"]);
+		let (_base, dir) = package(vec![("kcp.json", &spec)], vec![]);
+		let (package, _child) = child(&dir, vec![], vec![]);
+
+		assert_eq!(package.unwrap().capabilities(), vec![Capability::Network]);
+	}
+
+	#[test]
+	fn needs_an_existing_base() {
+		let spec =
+			json!({ "name": "child", "version": "0.0.0", "extends": "../missing" }).to_string();
+		let (package, _dir) = package(vec![("kcp.json", &spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::NoBase);
+	}
+
+	#[test]
+	fn keeps_what_both_require() {
+		let (_base, dir) = package(vec![], vec![]);
+		let schema = json!({
+			"required": ["replicas"],
+			"properties": { "replicas": { "type": "integer" } }
+		})
+		.to_string();
+		let (package, _child) = child(&dir, vec![("schema.json", &schema)], vec![]);
+		let package = package.unwrap();
+		let schema = package.schema.as_ref().unwrap().value();
+
+		assert_eq!(schema["required"], json!(["database", "replicas"]));
+
+		let mut input = package.example.clone().unwrap();
+		input["replicas"] = json!(3);
+		input.as_object_mut().unwrap().remove("database");
+		let rendered = package.compile(Some(input), None);
+
+		assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+	}
+
+	#[test]
+	fn refuses_bases_outside_of_the_package() {
+		let (_base, dir) = package(vec![], vec![]);
+		let spec =
+			json!({ "name": "child", "version": "0.0.0", "extends": dir.path() }).to_string();
+		let (package, _child) = package(vec![("kcp.json", &spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::OutsideBase(_));
+	}
+
+	#[test]
+	fn cannot_extend_itself() {
+		let spec = json!({ "name": "child", "version": "0.0.0", "extends": "." }).to_string();
+		let (package, _dir) = package(vec![("kcp.json", &spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::CyclicBase);
+	}
+}
+
+mod docs {
+	use super::*;
+
+	#[test]
+	fn describes_parameters() {
+		let schema = json!({
+			"type": "object",
+			"required": ["replicas"],
+			"properties": {
+				"replicas": { "type": "integer", "default": 1, "description": "pods to run" },
+				"image": {
+					"type": "object",
+					"properties": { "tag": { "type": ["string", "null"] } }
+				}
+			}
+		})
+		.to_string();
+		let (package, _dir) = package(vec![("schema.json", &schema)], vec![]);
+		let docs = package.unwrap().docs();
+
+		assert!(docs.starts_with("# fixture\n"));
+		assert!(docs.contains("| `replicas` | integer | yes | `1` | pods to run |"));
+		assert!(docs.contains("| `image.tag` | string \\| null | no |  |  |"));
+	}
+
+	#[test]
+	fn catalogs_inputs() {
+		let schema = json!({
+			"type": "object",
+			"properties": {
+				"replicas": { "type": "integer", "default": 1, "description": "pods to run" },
+				"image": {
+					"type": "object",
+					"properties": { "tag": { "type": "string" } }
+				},
+				"debug": { "type": "boolean" }
+			}
+		})
+		.to_string();
+		let (package, _dir) = package(
+			vec![
+				("schema.json", &schema),
+				(
+					"templates/main.jsonnet",
+					"local _ = import 'kct.libsonnet'; local input = _.input; { replicas: input.replicas, image: (import 'image.libsonnet')(_.input.image) }",
+				),
+				("lib/image.libsonnet", "function(image) image.tag"),
+			],
+			vec![],
+		);
+		let catalog = package.unwrap().inputs().unwrap();
+
+		assert_eq!(catalog["name"], json!("fixture"));
+		assert_eq!(
+			catalog["inputs"],
+			json!([
+				{ "path": "debug", "type": "boolean", "required": false, "default": null, "description": "", "referenced": false },
+				{ "path": "image", "type": "object", "required": false, "default": null, "description": "", "referenced": true },
+				{ "path": "image.tag", "type": "string", "required": false, "default": null, "description": "", "referenced": true },
+				{ "path": "replicas", "type": "integer", "required": false, "default": 1, "description": "pods to run", "referenced": true }
+			])
+		);
+	}
+
+	#[test]
+	fn catalogs_inputs_read_by_bases() {
+		let schema = json!({
+			"type": "object",
+			"properties": { "replicas": { "type": "integer" } }
+		})
+		.to_string();
+		let main = "std.extVar('kct.io/input').replicas";
+		let (_base, dir) = package(
+			vec![("schema.json", &schema), ("templates/main.jsonnet", main)],
+			vec![],
+		);
+		let spec =
+			json!({ "name": "child", "version": "0.0.0", "extends": "vendor/base" }).to_string();
+		let (package, _child) = extending(
+			&dir,
+			vec![("kcp.json", &spec), ("templates/main.jsonnet", "{}")],
+			vec!["schema.json"],
+		);
+		let catalog = package.unwrap().inputs().unwrap();
+
+		assert_eq!(catalog["inputs"][0]["referenced"], json!(true));
+	}
+
+	#[test]
+	fn includes_example() {
+		let (package, _dir) = package(vec![], vec![]);
+		let docs = package.unwrap().docs();
+
+		let example =
+			serde_json::to_string_pretty(&testing::json(&Fixture::contents("example.json")))
+				.unwrap();
+		assert!(docs.contains(&format!("## Example\n\n```json\n{example}\n```")));
+	}
+
+	#[test]
+	fn lists_capabilities() {
+		let spec = declaring(&["secrets", "network"]);
+		let (package, _dir) = package(vec![("kcp.json", &spec)], vec![]);
+		let docs = package.unwrap().docs();
+
+		assert!(docs.contains("- Capabilities: `network`, `secrets`\n"));
+	}
+
+	#[test]
+	fn skips_parameters_without_schema() {
+		let (package, _dir) = package(vec![], vec!["schema.json", "example.json"]);
+		let docs = package.unwrap().docs();
+
+		assert!(!docs.contains("## Parameters"));
+		assert!(docs.contains("- `main.jsonnet`"));
+	}
+}
+
+mod lint {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	use kct_compiler::lint::Rule;
+
+	fn findings(source: &str, spec: Option<&str>) -> Vec<(Rule, usize, usize)> {
+		let mut with = vec![("templates/extra.jsonnet", source)];
+		with.extend(spec.map(|spec| ("kcp.json", spec)));
+		let (package, _dir) = package(with, vec![]);
+
+		package
+			.unwrap()
+			.lint()
+			.unwrap()
+			.into_iter()
+			.map(|finding| (finding.rule, finding.line, finding.column))
+			.collect()
+	}
+
+	#[test]
+	fn passes_the_fixture() {
+		let (package, _dir) = package(vec![], vec![]);
+
+		assert_eq!(package.unwrap().lint().unwrap(), vec![]);
+	}
+
+	#[test]
+	fn finds_unused_locals() {
+		let source = "local a = 1;\nlocal b = 2;\nlocal f(a) = a;\n{ local c = 3, x: f(b) }";
+
+		assert_eq!(
+			findings(source, None),
+			vec![(Rule::UnusedVariable, 1, 11), (Rule::UnusedVariable, 4, 13)]
+		);
+	}
+
+	#[test]
+	fn finds_superseded_std_calls() {
+		let source = "{ a: std.mod(3, 2), b: std.objectHas({}, 'a') }";
+
+		assert_eq!(findings(source, None), vec![(Rule::DeprecatedStd, 1, 6)]);
+	}
+
+	#[test]
+	fn finds_plus_merging_into_nothing() {
+		let source = "{ a+: 1 } + { spec: { replicas+: 1 }, meta+: { labels+: {} } }";
+
+		assert_eq!(
+			findings(source, None),
+			vec![(Rule::SuspiciousPlus, 1, 1), (Rule::SuspiciousPlus, 1, 21)]
+		);
+	}
+
+	#[test]
+	fn skips_disabled_rules() {
+		let spec = json!({
+			"name": "fixture",
+			"version": "0.0.0",
+			"lint": { "disable": ["unused-variable"] },
+		})
+		.to_string();
+
+		assert_eq!(
+			findings("local a = std.mod(1, 2); {}", Some(&spec)),
+			vec![(Rule::DeprecatedStd, 1, 11)]
+		);
+	}
+
+	#[test]
+	fn refuses_unknown_rules() {
+		let spec = r#"{ "name": "fixture", "version": "0.0.0", "lint": { "disable": ["tabs"] } }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidSpec);
+	}
+
+	#[test]
+	fn refuses_invalid_jsonnet() {
+		let (package, _dir) = package(vec![("lib/broken.libsonnet", "{ a: }")], vec![]);
+
+		assert_matches!(package.unwrap().lint(), Err(Error::Compilation(_)));
+	}
+
+	fn input_findings(main: &str, spec: Option<&str>) -> Vec<(Rule, PathBuf, usize, usize)> {
+		let mut with = vec![("templates/main.jsonnet", main)];
+		with.extend(spec.map(|spec| ("kcp.json", spec)));
+		let (package, _dir) = package(with, vec![]);
+
+		package
+			.unwrap()
+			.lint_inputs()
+			.unwrap()
+			.into_iter()
+			.map(|finding| (finding.rule, finding.path, finding.line, finding.column))
+			.collect()
+	}
+
+	#[test]
+	fn inputs_pass_the_fixture() {
+		let (package, _dir) = package(vec![], vec![]);
+
+		assert_eq!(package.unwrap().lint_inputs().unwrap(), vec![]);
+	}
+
+	#[test]
+	fn finds_unused_inputs() {
+		let main = "local _ = import 'kct.libsonnet';\nlocal db = _.input.database;\n{ host: db.host, user: db.credentials.user }";
+
+		assert_eq!(
+			input_findings(main, None),
+			vec![
+				(Rule::UnusedInput, PathBuf::from("schema.json"), 18, 7),
+				(Rule::UnusedInput, PathBuf::from("schema.json"), 12, 5)
+			]
+		);
+	}
+
+	#[test]
+	fn finds_only_the_outermost_unused_input() {
+		let main = "local _ = import 'kct.libsonnet';\n{ host: _.input.database.host, port: _.input.database.port }";
+
+		assert_eq!(
+			input_findings(main, None),
+			vec![(Rule::UnusedInput, PathBuf::from("schema.json"), 13, 5)]
+		);
+	}
+
+	#[test]
+	fn finds_undefined_inputs() {
+		let main = "local _ = import 'kct.libsonnet';\nlocal input = _.input;\n{ all: input, host: input.database.hots, debug: std.extVar('kct.io/input').debug }";
+
+		assert_eq!(
+			input_findings(main, None),
+			vec![
+				(
+					Rule::UndefinedInput,
+					PathBuf::from("templates/main.jsonnet"),
+					3,
+					21
+				),
+				(
+					Rule::UndefinedInput,
+					PathBuf::from("templates/main.jsonnet"),
+					3,
+					49
+				)
+			]
+		);
+	}
+
+	#[test]
+	fn counts_inputs_read_by_bases() {
+		let (_base, dir) = package(vec![], vec![]);
+		let spec =
+			json!({ "name": "child", "version": "0.0.0", "extends": "vendor/base" }).to_string();
+		let (package, _child) = extending(
+			&dir,
+			vec![("kcp.json", &spec), ("templates/main.jsonnet", "{}")],
+			vec!["schema.json"],
+		);
+		let findings = package.unwrap().lint_inputs().unwrap();
+
+		assert_eq!(findings, vec![]);
+	}
+
+	#[test]
+	fn skips_disabled_input_rules() {
+		let spec = json!({
+			"name": "fixture",
+			"version": "0.0.0",
+			"lint": { "disable": ["unused-input"] },
+		})
+		.to_string();
+		let main = "local _ = import 'kct.libsonnet';\n_.input.typo";
+
+		assert_eq!(
+			input_findings(main, Some(&spec)),
+			vec![(
+				Rule::UndefinedInput,
+				PathBuf::from("templates/main.jsonnet"),
+				2,
+				1
+			)]
+		);
+	}
+
+	fn dead_files(with: Vec<(&str, &str)>) -> Vec<PathBuf> {
+		let (package, _dir) = package(with, vec![]);
+
+		package
+			.unwrap()
+			.lint_files()
+			.unwrap()
+			.into_iter()
+			.map(|finding| {
+				assert_eq!(finding.rule, Rule::DeadFile);
+
+				finding.path
+			})
+			.collect()
+	}
+
+	#[test]
+	fn finds_files_no_glob_matches() {
+		assert_eq!(dead_files(vec![]), vec![PathBuf::from("files/invalid.ini")]);
+	}
+
+	#[test]
+	fn counts_files_brought_in_by_others() {
+		let main = "local _ = import 'kct.libsonnet'; { page: _.files('page.txt'), raw: _.readFile('files/raw/') }";
+
+		assert_eq!(
+			dead_files(vec![
+				("templates/main.jsonnet", main),
+				("files/page.txt", "{% include \"partials/header.txt\" %}"),
+				("files/partials/header.txt", "{%- extends 'base.txt' -%}"),
+				("files/base.txt", "base"),
+				("files/raw/data.bin", "raw"),
+			]),
+			vec![
+				PathBuf::from("files/database.toml"),
+				PathBuf::from("files/events/settings.toml"),
+				PathBuf::from("files/invalid.ini"),
+				PathBuf::from("files/no-params.txt"),
+			]
+		);
+	}
+
+	#[test]
+	fn finds_libs_never_imported() {
+		let main = "local _ = import 'kct.libsonnet'; local h = import 'helpers.libsonnet'; { a: h.a, files: _.files('**/*') }";
+
+		assert_eq!(
+			dead_files(vec![
+				("templates/main.jsonnet", main),
+				("lib/helpers.libsonnet", "{ a: importstr 'data/a.txt' }"),
+				("lib/data/a.txt", "a"),
+				("lib/unused.libsonnet", "{}"),
+			]),
+			vec![PathBuf::from("lib/unused.libsonnet")]
+		);
+	}
+
+	#[test]
+	fn spares_the_libs_of_libraries() {
+		let spec = json!({ "name": "fixture", "version": "0.0.0", "kind": "library" }).to_string();
+
+		assert_eq!(
+			dead_files(vec![("kcp.json", &spec), ("lib/unused.libsonnet", "{}")]),
+			vec![PathBuf::from("files/invalid.ini")]
+		);
+	}
+}
+
+mod sources {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	const MAIN: &str = "local svc = import 'svc.jsonnet';
+local cm(name) = { kind: 'ConfigMap', name: name };
+{
+	app: { service: svc, config: cm('a') },
+} + {
+	app+: { deploy: { kind: 'Deployment' } },
+}
+";
+
+	fn locate(fields: &[&str]) -> Option<(PathBuf, usize, usize, String)> {
+		let (package, dir) = package(
+			vec![
+				("templates/main.jsonnet", MAIN),
+				("templates/svc.jsonnet", "{ kind: 'Service' }"),
+			],
+			vec![],
+		);
+		let sources = package.unwrap().sources().unwrap();
+
+		sources.locate(fields).map(|source| {
+			let file = source.file.canonicalize().unwrap();
+			let root = dir.path().canonicalize().unwrap();
+
+			(
+				file.strip_prefix(root).unwrap().to_path_buf(),
+				source.line,
+				source.column,
+				source.field,
+			)
+		})
+	}
+
+	#[test]
+	fn follows_imports() {
+		assert_eq!(
+			locate(&["app", "service"]),
+			Some((PathBuf::from("templates/svc.jsonnet"), 1, 1, String::new()))
+		);
+	}
+
+	#[test]
+	fn stops_at_what_computes_the_value() {
+		assert_eq!(
+			locate(&["app", "config"]),
+			Some((
+				PathBuf::from("templates/main.jsonnet"),
+				4,
+				31,
+				String::from("app.config")
+			))
+		);
+	}
+
+	#[test]
+	fn looks_into_every_object_added() {
+		assert_eq!(
+			locate(&["app", "deploy"]),
+			Some((
+				PathBuf::from("templates/main.jsonnet"),
+				6,
+				18,
+				String::from("app.deploy")
+			))
+		);
+	}
+
+	#[test]
+	fn misses_what_isnt_defined() {
+		assert_eq!(locate(&["db"]), None);
+	}
+}
+
+mod verify {
+	use super::*;
+
+	use base64::engine::general_purpose::STANDARD;
+	use base64::Engine;
+	use kct_package::Integrity;
+	use sha2::{Digest, Sha256};
+
+	const LIB: &str = "vendor/github.com/acme/jsonnet/lib/main.libsonnet";
+
+	fn locking(sum: &str) -> String {
+		json!({
+			"version": 1,
+			"dependencies": [{
+				"source": { "git": { "remote": "https://github.com/acme/jsonnet.git", "subdir": "lib" } },
+				"version": "main",
+				"sum": sum
+			}],
+			"legacyImports": false
+		})
+		.to_string()
+	}
+
+	fn sum(contents: &str) -> String {
+		STANDARD.encode(Sha256::digest(contents.as_bytes()))
+	}
+
+	#[test]
+	fn accepts_matching_dependencies() {
+		let lock = locking(&sum("{}"));
+		let (package, _dir) = package(vec![(LIB, "{}"), ("jsonnetfile.lock.json", &lock)], vec![]);
+
+		assert_matches!(package.unwrap().verify(Integrity::Verify), Ok(()));
+	}
+
+	#[test]
+	fn refuses_drifts() {
+		let lock = locking(&sum("{}"));
+		let (package, _dir) = package(
+			vec![(LIB, "{ changed: true }"), ("jsonnetfile.lock.json", &lock)],
+			vec![],
+		);
+
+		let verified = package.unwrap().verify(Integrity::Verify);
+
+		assert_matches!(verified, Err(Error::Drift(name)) if name == "github.com/acme/jsonnet/lib");
+	}
+
+	#[test]
+	fn records_drifts_when_updating() {
+		let lock = locking(&sum("{}"));
+		let (package, dir) = package(
+			vec![(LIB, "{ changed: true }"), ("jsonnetfile.lock.json", &lock)],
+			vec![],
+		);
+		let package = package.unwrap();
+
+		package.verify(Integrity::Update).unwrap();
+		let updated = std::fs::read_to_string(dir.path().join("jsonnetfile.lock.json")).unwrap();
+
+		assert!(updated.contains(&sum("{ changed: true }")));
+		assert_matches!(package.verify(Integrity::Verify), Ok(()));
+	}
+
+	#[test]
+	fn skips_missing_lockfiles() {
+		let (package, _dir) = package(vec![(LIB, "{}")], vec![]);
+
+		assert_matches!(package.unwrap().verify(Integrity::Verify), Ok(()));
+	}
+
+	#[test]
+	fn needs_lockfile_when_frozen() {
+		let (package, _dir) = package(vec![(LIB, "{}")], vec![]);
+
+		let verified = package.unwrap().verify(Integrity::Frozen);
+
+		assert_matches!(verified, Err(Error::NoLock));
+	}
+
+	#[test]
+	fn needs_locked_dependencies_when_frozen() {
+		let lock = locking(&sum("{}"));
+		let (package, _dir) = package(vec![("jsonnetfile.lock.json", &lock)], vec![]);
+		let package = package.unwrap();
+
+		assert_matches!(package.verify(Integrity::Verify), Ok(()));
+		assert_matches!(
+			package.verify(Integrity::Frozen),
+			Err(Error::NotVendored(_))
+		);
+	}
+}
+
+mod vendor {
+	use super::*;
+
+	use std::fs;
+	#[cfg(unix)]
+	use std::os::unix::fs::PermissionsExt;
+	use std::path::Path;
+	use std::process::Command;
+
+	use kct_package::{Change, Dependency, Integrity};
+	use kct_testing::dir;
+
+	fn git(args: &[&str], dir: &Path) {
+		let status = Command::new("git")
+			.args(["-c", "user.name=kct", "-c", "user.email=kct@localhost"])
+			.args(args)
+			.current_dir(dir)
+			.status()
+			.unwrap();
+
+		assert!(status.success(), "git {args:?} failed");
+	}
+
+	fn init() -> TempDir {
+		let repo = dir::tmp();
+		git(&["init", "-q"], repo.path());
+
+		repo
+	}
+
+	/// Commits `lib/main.libsonnet` along the dependencies of `lib`, tagging it
+	fn commit(repo: &Path, tag: &str, contents: &str, dependencies: &[(&Path, &str)]) {
+		fs::create_dir_all(repo.join("lib")).unwrap();
+		fs::write(repo.join("lib/main.libsonnet"), contents).unwrap();
+		fs::write(repo.join("lib/jsonnetfile.json"), depending(dependencies)).unwrap();
+
+		git(&["add", "-A"], repo);
+		git(&["commit", "-qm", contents], repo);
+		git(&["tag", tag], repo);
+	}
+
+	/// Repository with `lib` at `v1` and a later commit changing it
+	fn repository() -> TempDir {
+		let repo = init();
+		commit(repo.path(), "v1", "{ version: 1 }", &[]);
+		commit(repo.path(), "v2", "{ version: 2 }", &[]);
+
+		repo
+	}
+
+	/// GnuPG home with a fresh signing key, along its ID
+	#[cfg(unix)]
+	fn keyring() -> (TempDir, String) {
+		let home = dir::tmp();
+		let gpg = |args: &[&str]| {
+			Command::new("gpg")
+				.args(["--batch", "--quiet", "--homedir"])
+				.arg(home.path())
+				.args(args)
+				.output()
+				.unwrap()
+		};
+
+		let generated = gpg(&["--passphrase", "", "--quick-gen-key", "kct", "ed25519"]);
+		assert!(generated.status.success(), "unable to generate a key");
+
+		let keys = gpg(&["--list-keys", "--with-colons"]);
+		let key = String::from_utf8_lossy(&keys.stdout)
+			.lines()
+			.find_map(|line| line.strip_prefix("fpr:::::::::"))
+			.map(|fingerprint| fingerprint.trim_end_matches(':').to_string())
+			.unwrap();
+
+		(home, key)
+	}
+
+	/// Signs the later commits of the repository with the key
+	#[cfg(unix)]
+	fn sign(repo: &Path, (home, key): &(TempDir, String)) {
+		let program = home.path().join("gpg.sh");
+		let script = format!(
+			"#!/bin/sh\nexec gpg --homedir {} \"$@\"\n",
+			home.path().display()
+		);
+		fs::write(&program, script).unwrap();
+		fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+		git(
+			&["config", "gpg.program", &program.display().to_string()],
+			repo,
+		);
+		git(&["config", "user.signingkey", key], repo);
+		git(&["config", "commit.gpgsign", "true"], repo);
+	}
+
+	fn depending(dependencies: &[(&Path, &str)]) -> String {
+		let dependencies: Vec<Value> = dependencies
+			.iter()
+			.map(|(repo, version)| {
+				let remote = format!("file://{}", repo.display());
+				let source = json!({ "git": { "remote": remote, "subdir": "lib" } });
+
+				json!({ "source": source, "version": version })
+			})
+			.collect();
+
+		json!({ "version": 1, "dependencies": dependencies }).to_string()
+	}
+
+	fn vendored(root: &Path, repo: &Path) -> String {
+		let dependency = Dependency {
+			remote: format!("file://{}", repo.display()),
+			subdir: Some(String::from("lib")),
+			version: None,
+		};
+		let lib = root
+			.join("vendor")
+			.join(dependency.name())
+			.join("main.libsonnet");
+
+		fs::read_to_string(lib).unwrap()
+	}
+
+	#[test]
+	fn fetches_pinned_versions() {
+		let repo = repository();
+		let spec = depending(&[(repo.path(), "v1")]);
+		let (package, dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let package = package.unwrap();
+
+		let fetched = kcp::vendor(&package.root).unwrap();
+
+		assert_eq!(fetched.len(), 1);
+		assert_eq!(vendored(dir.path(), repo.path()), "{ version: 1 }");
+		assert_matches!(package.verify(Integrity::Frozen), Ok(()));
+	}
+
+	#[test]
+	fn resolves_transitive_dependencies() {
+		let base = init();
+		commit(base.path(), "v1.0.0", "{ base: '1.0' }", &[]);
+		commit(base.path(), "v1.1.0", "{ base: '1.1' }", &[]);
+		commit(base.path(), "v2.0.0", "{ base: '2.0' }", &[]);
+		let app = init();
+		commit(app.path(), "v1", "{ app: 1 }", &[(base.path(), "^1.0")]);
+
+		let spec = depending(&[(app.path(), "v1")]);
+		let (package, dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		let fetched = kcp::vendor(&package.unwrap().root).unwrap();
+
+		assert_eq!(fetched.len(), 2);
+		assert_eq!(vendored(dir.path(), base.path()), "{ base: '1.1' }");
+	}
+
+	#[test]
+	fn lets_the_root_override_versions() {
+		let base = init();
+		commit(base.path(), "v1.0.0", "{ base: 1 }", &[]);
+		commit(base.path(), "v2.0.0", "{ base: 2 }", &[]);
+		let app = init();
+		commit(app.path(), "v1", "{ app: 1 }", &[(base.path(), "^1.0")]);
+
+		let spec = depending(&[(app.path(), "v1"), (base.path(), "v2.0.0")]);
+		let (package, dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		kcp::vendor(&package.unwrap().root).unwrap();
+
+		assert_eq!(vendored(dir.path(), base.path()), "{ base: 2 }");
+	}
+
+	#[test]
+	fn refuses_conflicting_versions() {
+		let base = init();
+		commit(base.path(), "v1.0.0", "{ base: 1 }", &[]);
+		commit(base.path(), "v2.0.0", "{ base: 2 }", &[]);
+		let app = init();
+		commit(app.path(), "v1", "{ app: 1 }", &[(base.path(), "^1.0")]);
+		let tool = init();
+		commit(tool.path(), "v1", "{ tool: 1 }", &[(base.path(), "^2.0")]);
+
+		let spec = depending(&[(app.path(), "v1"), (tool.path(), "v1")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		let fetched = kcp::vendor(&package.unwrap().root);
+
+		assert_matches!(fetched.unwrap_err(), Error::Conflict(_, asked) if asked.contains("^1.0 by root") && asked.contains("^2.0 by root"));
+	}
+
+	#[test]
+	fn refuses_cycles() {
+		let (first, second) = (init(), init());
+		commit(first.path(), "v1", "{ first: 1 }", &[(second.path(), "v1")]);
+		commit(
+			second.path(),
+			"v1",
+			"{ second: 1 }",
+			&[(first.path(), "v1")],
+		);
+
+		let spec = depending(&[(first.path(), "v1")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		let fetched = kcp::vendor(&package.unwrap().root);
+
+		assert_matches!(fetched.unwrap_err(), Error::Cycle(_));
+	}
+
+	#[test]
+	fn needs_existing_versions() {
+		let repo = repository();
+		let spec = depending(&[(repo.path(), "v3")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		let vendored = kcp::vendor(&package.unwrap().root);
+
+		assert_matches!(vendored.unwrap_err(), Error::Fetch(..));
+	}
+
+	#[test]
+	fn refuses_tampered_contents() {
+		let repo = repository();
+		let spec = depending(&[(repo.path(), "v1")]);
+		let (package, dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let root = package.unwrap().root;
+		kcp::vendor(&root).unwrap();
+
+		let lock = dir.path().join("jsonnetfile.lock.json");
+		let mut locked: Value = serde_json::from_str(&fs::read_to_string(&lock).unwrap()).unwrap();
+		locked["dependencies"][0]["sum"] = json!("AAAA");
+		fs::write(&lock, locked.to_string()).unwrap();
+
+		let refetched = kcp::vendor(&root);
+
+		assert_matches!(refetched.unwrap_err(), Error::Tampered(..));
+		assert_eq!(vendored(dir.path(), repo.path()), "{ version: 1 }");
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn records_signers() {
+		let repo = init();
+		let trusted = keyring();
+		sign(repo.path(), &trusted);
+		commit(repo.path(), "v1", "{ version: 1 }", &[]);
+		commit(repo.path(), "v2", "{ version: 2 }", &[]);
+		let spec = depending(&[(repo.path(), "v1")]);
+		let (package, dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let root = package.unwrap().root;
+
+		let fetched = kcp::vendor(&root).unwrap();
+		let signer = fetched[0].signer.clone().unwrap();
+
+		assert!(trusted.1.ends_with(&signer));
+
+		fs::write(
+			root.join("jsonnetfile.json"),
+			depending(&[(repo.path(), "v2")]),
+		)
+		.unwrap();
+		let fetched = kcp::vendor(&root).unwrap();
+
+		assert_eq!(fetched[0].signer, Some(signer));
+		assert_eq!(vendored(dir.path(), repo.path()), "{ version: 2 }");
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn refuses_other_signers() {
+		let repo = init();
+		let trusted = keyring();
+		sign(repo.path(), &trusted);
+		commit(repo.path(), "v1", "{ version: 1 }", &[]);
+		let spec = depending(&[(repo.path(), "v1")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let root = package.unwrap().root;
+		kcp::vendor(&root).unwrap();
+
+		let other = keyring();
+		sign(repo.path(), &other);
+		commit(repo.path(), "v2", "{ version: 2 }", &[]);
+		fs::write(
+			root.join("jsonnetfile.json"),
+			depending(&[(repo.path(), "v2")]),
+		)
+		.unwrap();
+
+		let vendored = kcp::vendor(&root);
+
+		assert_matches!(vendored.unwrap_err(), Error::Tampered(..));
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn refuses_unsigned_upgrades() {
+		let repo = init();
+		let trusted = keyring();
+		sign(repo.path(), &trusted);
+		commit(repo.path(), "v1", "{ version: 1 }", &[]);
+		let spec = depending(&[(repo.path(), "v1")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let root = package.unwrap().root;
+		kcp::vendor(&root).unwrap();
+
+		git(&["config", "commit.gpgsign", "false"], repo.path());
+		commit(repo.path(), "v2", "{ version: 2 }", &[]);
+		fs::write(
+			root.join("jsonnetfile.json"),
+			depending(&[(repo.path(), "v2")]),
+		)
+		.unwrap();
+
+		let vendored = kcp::vendor(&root);
+
+		assert_matches!(vendored.unwrap_err(), Error::Tampered(..));
+	}
+
+	#[test]
+	fn needs_dependencies() {
+		let (package, _dir) = package(vec![], vec!["jsonnetfile.json"]);
+
+		let vendored = kcp::vendor(&package.unwrap().root);
+
+		assert_matches!(vendored.unwrap_err(), Error::NoDependencies);
+	}
+
+	#[test]
+	fn names_ssh_remotes() {
+		let dependency = |remote: &str| Dependency {
+			remote: remote.to_string(),
+			subdir: Some(String::from("lib")),
+			version: None,
+		};
+
+		assert_eq!(
+			dependency("git+ssh://git@github.com/acme/jsonnet.git").name(),
+			Path::new("github.com/acme/jsonnet/lib")
+		);
+		assert_eq!(
+			dependency("git@github.com:acme/jsonnet.git").name(),
+			Path::new("github.com/acme/jsonnet/lib")
+		);
+	}
+
+	#[test]
+	fn lists_outdated_dependencies() {
+		let repo = init();
+		commit(repo.path(), "v1.0.0", "{ version: '1.0.0' }", &[]);
+		commit(repo.path(), "v1.0.1", "{ version: '1.0.1' }", &[]);
+		commit(repo.path(), "v1.1.0", "{ version: '1.1.0' }", &[]);
+		commit(repo.path(), "v2.0.0", "{ version: '2.0.0' }", &[]);
+		commit(repo.path(), "v3.0.0-rc.1", "{ version: '3.0.0-rc.1' }", &[]);
+
+		let change = |version: &str| {
+			let spec = depending(&[(repo.path(), version)]);
+			let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+			let upgrades = kcp::outdated(&package.unwrap().root).unwrap();
+
+			upgrades
+				.into_iter()
+				.map(|upgrade| (upgrade.current, upgrade.latest, upgrade.change))
+				.next()
+		};
+
+		let upgrade =
+			|current: &str, change| Some((current.to_string(), String::from("v2.0.0"), change));
+
+		assert_eq!(change("v1.0.0"), upgrade("v1.0.0", Change::Major));
+		assert_eq!(change("v1.1.0"), upgrade("v1.1.0", Change::Major));
+		assert_eq!(change("v2.0.0"), None);
+		assert_eq!(change("main"), None);
+	}
+
+	#[test]
+	fn compares_locked_versions() {
+		let repo = init();
+		commit(repo.path(), "v1.0.0", "{ version: '1.0.0' }", &[]);
+		commit(repo.path(), "v1.0.1", "{ version: '1.0.1' }", &[]);
+		commit(repo.path(), "v1.1.0", "{ version: '1.1.0' }", &[]);
+		git(&["tag", "v1.1.1"], repo.path());
+		let spec = depending(&[(repo.path(), "~1.0")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+		let root = package.unwrap().root;
+
+		assert_eq!(kcp::outdated(&root).unwrap(), vec![]);
+
+		kcp::vendor(&root).unwrap();
+		let upgrades = kcp::outdated(&root).unwrap();
+
+		assert_eq!(upgrades.len(), 1);
+		assert_eq!(upgrades[0].current, "v1.0.1");
+		assert_eq!(upgrades[0].latest, "v1.1.1");
+		assert_eq!(upgrades[0].change, Change::Minor);
+	}
+
+	#[test]
+	fn classifies_patches() {
+		let repo = init();
+		commit(repo.path(), "v1.0.0", "{ version: '1.0.0' }", &[]);
+		commit(repo.path(), "v1.0.1", "{ version: '1.0.1' }", &[]);
+		let spec = depending(&[(repo.path(), "v1.0.0")]);
+		let (package, _dir) = package(vec![("jsonnetfile.json", &spec)], vec![]);
+
+		let upgrades = kcp::outdated(&package.unwrap().root).unwrap();
+
+		assert_eq!(upgrades[0].change, Change::Patch);
+	}
+}
+
+mod archive {
+	use super::*;
+
+	use std::fs;
+	#[cfg(unix)]
+	use std::os::unix::fs::symlink;
+	use std::path::{Path, PathBuf};
+
+	use flate2::write::GzEncoder;
+	use flate2::Compression;
+	use kct_package::{Limits, Symlinks};
+	use kct_testing::dir;
+
+	fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+		linking(files, &[])
+	}
+
+	/// Tarball with the files and symbolic links to their targets
+	fn linking(files: &[(&str, &str)], links: &[(&str, &str)]) -> Vec<u8> {
+		let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+
+		for (path, contents) in files {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(contents.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+
+			builder
+				.append_data(&mut header, path, contents.as_bytes())
+				.unwrap();
+		}
+
+		for (path, target) in links {
+			let mut header = tar::Header::new_gnu();
+			header.set_entry_type(tar::EntryType::Symlink);
+			header.set_size(0);
+			header.set_mode(0o777);
+
+			builder.append_link(&mut header, path, target).unwrap();
+		}
+
+		builder.into_inner().unwrap().finish().unwrap()
+	}
+
+	const SPEC: &str = r#"{ "name": "archived", "version": "0.0.0" }"#;
+
+	#[test]
+	fn unpacks_packages() {
+		let archive = tarball(&[("kcp.json", SPEC), ("templates/main.jsonnet", "{}")]);
+		let into = dir::tmp();
+
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		)
+		.unwrap();
+		let package = Package::try_from(root.as_path()).unwrap();
+
+		assert_eq!(root, into.path());
+		assert_eq!(package.spec.name, "archived");
+	}
+
+	#[test]
+	fn unpacks_packages_within_a_directory() {
+		let archive = tarball(&[
+			("archived/kcp.json", SPEC),
+			("archived/templates/main.jsonnet", "{}"),
+		]);
+		let into = dir::tmp();
+
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		)
+		.unwrap();
+
+		assert_eq!(root, into.path().join("archived"));
+	}
+
+	#[test]
+	fn needs_a_package() {
+		let archive = tarball(&[("templates/main.jsonnet", "{}")]);
+		let into = dir::tmp();
+
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		);
+
+		assert_matches!(root.unwrap_err(), Error::NoSpec);
+	}
+
+	#[test]
+	fn needs_a_tarball() {
+		let into = dir::tmp();
+
+		let root = kcp::unpack(
+			"{}".as_bytes(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		);
+
+		assert_matches!(root.unwrap_err(), Error::InvalidArchive(_));
+	}
+
+	#[test]
+	fn reads_packages_in_memory() {
+		let archive = tarball(&[
+			("archived/kcp.json", SPEC),
+			("archived/templates/main.jsonnet", "{}"),
+		]);
+
+		let files = kcp::read(archive.as_slice(), Symlinks::default(), Limits::default()).unwrap();
+		let paths: Vec<&Path> = files.keys().map(PathBuf::as_path).collect();
+
+		assert_eq!(
+			paths,
+			[Path::new("kcp.json"), Path::new("templates/main.jsonnet")]
+		);
+		assert_eq!(files[Path::new("kcp.json")], SPEC.as_bytes());
+	}
+
+	#[test]
+	fn reads_only_packages() {
+		let archive = tarball(&[("templates/main.jsonnet", "{}")]);
+
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::NoSpec
+		);
+		assert_matches!(
+			kcp::read("{}".as_bytes(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(_)
+		);
+	}
+
+	#[test]
+	fn refuses_names_windows_cannot_write() {
+		let archive = tarball(&[("kcp.json", SPEC), ("files/aux.yaml", "{}")]);
+		let into = dir::tmp();
+
+		assert_matches!(
+			kcp::unpack(archive.as_slice(), into.path(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("aux.yaml")
+		);
+		assert!(!into.path().join("kcp.json").exists());
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(_)
+		);
+	}
+
+	#[test]
+	fn packs_only_names_windows_can_write() {
+		let (package, _dir) = package(vec![("files/nul.txt", "")], vec![]);
+		let package = package.unwrap();
+
+		let packed = kcp::pack(&package.root, "packed", Symlinks::default(), &mut vec![]);
+
+		assert_matches!(packed.unwrap_err(), Error::InvalidArchive(reason) if reason.contains("nul.txt"));
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn unpacks_links_within_packages() {
+		let archive = linking(
+			&[("kcp.json", SPEC), ("files/page.txt", "hi")],
+			&[("files/alias.txt", "page.txt")],
+		);
+		let into = dir::tmp();
+
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		)
+		.unwrap();
+		let alias = root.join("files/alias.txt");
+
+		assert!(fs::symlink_metadata(&alias).unwrap().is_symlink());
+		assert_eq!(fs::read_to_string(alias).unwrap(), "hi");
+	}
+
+	#[test]
+	fn refuses_links_escaping_packages() {
+		for target in ["../../etc/passwd", "/etc/passwd"] {
+			let archive = linking(&[("kcp.json", SPEC)], &[("files/passwd", target)]);
+			let into = dir::tmp();
+
+			assert_matches!(
+				kcp::unpack(archive.as_slice(), into.path(), Symlinks::default(), Limits::default()).unwrap_err(),
+				Error::InvalidArchive(reason) if reason.contains("outside of the package")
+			);
+			assert!(!into.path().join("kcp.json").exists());
+			assert_matches!(
+				kcp::read(archive.as_slice(), Symlinks::Preserve, Limits::default()).unwrap_err(),
+				Error::InvalidArchive(_)
+			);
+		}
+	}
+
+	#[test]
+	fn refuses_entries_through_links() {
+		let link = "a/b/c/d/e/f/g/h/i/j";
+		let within = format!("{link}/s");
+		let chained = linking(
+			&[("kcp.json", SPEC)],
+			&[
+				(link, "../../../../../../../../.."),
+				(&within, "../../../../../../../../../.."),
+			],
+		);
+		let into = dir::tmp();
+
+		assert_matches!(
+			kcp::unpack(chained.as_slice(), into.path(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("through the link")
+		);
+		assert!(!into.path().join("kcp.json").exists());
+		assert_matches!(
+			kcp::read(chained.as_slice(), Symlinks::Preserve, Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("through the link")
+		);
+	}
+
+	#[test]
+	fn resolves_links_through_the_ones_before() {
+		let archive = linking(
+			&[("kcp.json", SPEC)],
+			&[("files/up", ".."), ("escape", "files/up/..")],
+		);
+		let into = dir::tmp();
+
+		assert_matches!(
+			kcp::unpack(archive.as_slice(), into.path(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("outside of the package")
+		);
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::Preserve, Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("outside of the package")
+		);
+	}
+
+	#[test]
+	fn refuses_links_when_rejecting_them() {
+		let archive = linking(
+			&[("kcp.json", SPEC), ("files/page.txt", "hi")],
+			&[("files/alias.txt", "page.txt")],
+		);
+		let into = dir::tmp();
+
+		assert_matches!(
+			kcp::unpack(archive.as_slice(), into.path(), Symlinks::Reject, Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("alias.txt")
+		);
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::Reject, Limits::default()).unwrap_err(),
+			Error::InvalidArchive(_)
+		);
+	}
+
+	#[test]
+	fn reads_links_as_what_they_point_to() {
+		let archive = linking(
+			&[
+				("archived/kcp.json", SPEC),
+				("archived/files/pages/index.txt", "hi"),
+			],
+			&[
+				("archived/files/alias", "pages"),
+				("archived/files/index.txt", "alias/index.txt"),
+			],
+		);
+
+		let files = kcp::read(archive.as_slice(), Symlinks::default(), Limits::default()).unwrap();
+
+		assert_eq!(files[Path::new("files/alias/index.txt")], b"hi");
+		assert_eq!(files[Path::new("files/index.txt")], b"hi");
+	}
+
+	#[test]
+	fn refuses_dangling_links_in_memory() {
+		let archive = linking(&[("kcp.json", SPEC)], &[("files/alias.txt", "page.txt")]);
+
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::default(), Limits::default()).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("doesn't have")
+		);
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn packs_what_links_point_to() {
+		let (package, _dir) = package(vec![("files/page.txt", "hi")], vec![]);
+		let package = package.unwrap();
+		let outside = dir::tmp();
+		symlink("page.txt", package.root.join("files/alias.txt")).unwrap();
+		symlink(outside.path(), package.root.join("vendor/local")).unwrap();
+		fs::write(outside.path().join("lib.libsonnet"), "{}").unwrap();
+
+		let mut archive = vec![];
+		kcp::pack(&package.root, "packed", Symlinks::Follow, &mut archive).unwrap();
+		let files = kcp::read(archive.as_slice(), Symlinks::Reject, Limits::default()).unwrap();
+
+		assert_eq!(files[Path::new("files/alias.txt")], b"hi");
+		assert_eq!(files[Path::new("vendor/local/lib.libsonnet")], b"{}");
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn refuses_packing_links_escaping_packages() {
+		let (package, _dir) = package(vec![], vec![]);
+		let package = package.unwrap();
+		let outside = dir::tmp();
+		symlink(outside.path(), package.root.join("files/outside")).unwrap();
+
+		for symlinks in [Symlinks::Follow, Symlinks::Preserve, Symlinks::Reject] {
+			let packed = kcp::pack(&package.root, "packed", symlinks, &mut vec![]);
+
+			assert_matches!(packed.unwrap_err(), Error::InvalidArchive(reason) if reason.contains("outside"));
+		}
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn refuses_packing_links_to_their_parents() {
+		let (package, _dir) = package(vec![], vec![]);
+		let package = package.unwrap();
+		symlink("..", package.root.join("files/loop")).unwrap();
+
+		let packed = kcp::pack(&package.root, "packed", Symlinks::Follow, &mut vec![]);
+
+		assert_matches!(packed.unwrap_err(), Error::InvalidArchive(reason) if reason.contains("holding it"));
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn preserves_links() {
+		let (package, _dir) = package(vec![("files/page.txt", "hi")], vec![]);
+		let package = package.unwrap();
+		symlink("page.txt", package.root.join("files/alias.txt")).unwrap();
+
+		let mut archive = vec![];
+		kcp::pack(&package.root, "packed", Symlinks::Preserve, &mut archive).unwrap();
+		let into = dir::tmp();
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::Preserve,
+			Limits::default(),
+		)
+		.unwrap();
+
+		let alias = root.join("files/alias.txt");
+		assert!(fs::symlink_metadata(&alias).unwrap().is_symlink());
+		assert_eq!(fs::read_link(alias).unwrap(), Path::new("page.txt"));
+		assert_matches!(
+			kcp::pack(&package.root, "packed", Symlinks::Reject, &mut vec![]).unwrap_err(),
+			Error::InvalidArchive(reason) if reason.contains("symbolic link")
+		);
+	}
+
+	#[test]
+	fn refuses_archives_beyond_the_limits() {
+		let archive = tarball(&[
+			("kcp.json", SPEC),
+			("files/a.txt", "1234"),
+			("files/b.txt", &"8".repeat(64)),
+		]);
+		let limited = [
+			(
+				Limits {
+					entries: 2,
+					..Limits::default()
+				},
+				"entries",
+			),
+			(
+				Limits {
+					file: 50,
+					..Limits::default()
+				},
+				"b.txt",
+			),
+			(
+				Limits {
+					total: 50,
+					..Limits::default()
+				},
+				"more than 50 bytes",
+			),
+		];
+
+		for (limits, reason) in limited {
+			let into = dir::tmp();
+
+			assert_matches!(
+				kcp::unpack(archive.as_slice(), into.path(), Symlinks::default(), limits).unwrap_err(),
+				Error::ArchiveTooLarge(why) if why.contains(reason)
+			);
+			assert!(!into.path().join("kcp.json").exists());
+			assert_matches!(
+				kcp::read(archive.as_slice(), Symlinks::default(), limits).unwrap_err(),
+				Error::ArchiveTooLarge(why) if why.contains(reason)
+			);
+		}
+	}
+
+	#[test]
+	fn refuses_decompression_bombs() {
+		let zeros = "0".repeat(1024 * 1024);
+		let archive = tarball(&[("kcp.json", SPEC), ("files/zeros.txt", &zeros)]);
+		let limits = Limits {
+			entries: 2,
+			file: 1024,
+			total: 1024,
+		};
+
+		assert!(archive.len() < 4096);
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::default(), limits).unwrap_err(),
+			Error::ArchiveTooLarge(why) if why.contains("decompresses")
+		);
+	}
+
+	#[test]
+	fn counts_what_links_copy_in_memory() {
+		let archive = linking(
+			&[("kcp.json", SPEC), ("files/pages/index.txt", "1234567890")],
+			&[
+				("files/a", "pages"),
+				("files/b", "pages"),
+				("files/c", "pages"),
+			],
+		);
+		let limits = Limits {
+			total: 64,
+			..Limits::default()
+		};
+
+		assert_matches!(
+			kcp::read(archive.as_slice(), Symlinks::default(), limits).unwrap_err(),
+			Error::ArchiveTooLarge(why) if why.contains("links are followed")
+		);
+	}
+
+	#[test]
+	fn stores_packages_once() {
+		let archive = tarball(&[("kcp.json", SPEC), ("templates/main.jsonnet", "{}")]);
+		let dir = dir::tmp();
+		let store = kcp::Store::new(dir.path());
+
+		let root = store
+			.unpack(archive.as_slice(), Symlinks::default(), Limits::default())
+			.unwrap();
+		let again = store
+			.unpack(archive.as_slice(), Symlinks::default(), Limits::default())
+			.unwrap();
+		let package = Package::try_from(root.as_path()).unwrap();
+
+		assert_eq!(root, again);
+		assert_eq!(package.spec.name, "archived");
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+		assert!(fs::metadata(root.join("kcp.json"))
+			.unwrap()
+			.permissions()
+			.readonly());
+	}
+
+	#[test]
+	fn stores_packages_by_their_contents() {
+		let dir = dir::tmp();
+		let store = kcp::Store::new(dir.path());
+		let unpack = |files: &[(&str, &str)]| {
+			store
+				.unpack(
+					tarball(files).as_slice(),
+					Symlinks::default(),
+					Limits::default(),
+				)
+				.unwrap()
+		};
+
+		let root = unpack(&[("kcp.json", SPEC), ("templates/main.jsonnet", "{}")]);
+		let other = unpack(&[("kcp.json", SPEC), ("templates/main.jsonnet", "[]")]);
+
+		assert_ne!(root, other);
+	}
+
+	#[test]
+	fn checks_stored_packages_again() {
+		let archive = linking(
+			&[("kcp.json", SPEC), ("files/page.txt", "hi")],
+			&[("files/alias.txt", "page.txt")],
+		);
+		let dir = dir::tmp();
+		let store = kcp::Store::new(dir.path());
+
+		store
+			.unpack(archive.as_slice(), Symlinks::default(), Limits::default())
+			.unwrap();
+		let rejected = store.unpack(archive.as_slice(), Symlinks::Reject, Limits::default());
+
+		assert_matches!(rejected.unwrap_err(), Error::InvalidArchive(_));
+	}
+
+	#[test]
+	fn packs_packages() {
+		let (package, _dir) = package(vec![], vec![]);
+		let package = package.unwrap();
+		let (mut archive, mut again) = (vec![], vec![]);
+
+		kcp::pack(&package.root, "packed", Symlinks::default(), &mut archive).unwrap();
+		kcp::pack(&package.root, "packed", Symlinks::default(), &mut again).unwrap();
+
+		let into = dir::tmp();
+		let root = kcp::unpack(
+			archive.as_slice(),
+			into.path(),
+			Symlinks::default(),
+			Limits::default(),
+		)
+		.unwrap();
+		let unpacked = Package::try_from(root.as_path()).unwrap();
+
+		assert_eq!(archive, again);
+		assert_eq!(root, into.path().join("packed"));
+		assert_eq!(unpacked.digest().unwrap(), package.digest().unwrap());
+	}
+}
+
+mod artifacts {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	#[test]
+	fn serializes_by_extension() {
+		let value = json!({
+			"README.md": "# Dashboards\n",
+			"dashboards/api.json": { "title": "API" },
+			"ci/pipeline.yaml": { "steps": ["build"] },
+			"skipped.json": null,
+		});
+
+		let files = kcp::artifacts(&value).unwrap();
+
+		assert_eq!(
+			files,
+			vec![
+				(PathBuf::from("/README.md"), String::from("# Dashboards\n")),
+				(
+					PathBuf::from("/ci/pipeline.yaml"),
+					String::from("---\nsteps:\n  - build\n")
+				),
+				(
+					PathBuf::from("/dashboards/api.json"),
+					String::from("{\n  \"title\": \"API\"\n}\n")
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn needs_an_object() {
+		let files = kcp::artifacts(&json!(["a.json"]));
+
+		assert_matches!(files.unwrap_err(), Error::InvalidArtifact(_, _));
+	}
+
+	#[test]
+	fn keeps_files_within_the_output() {
+		for path in ["/etc/passwd", "../outside.json", "a/../../b", ""] {
+			let files = kcp::artifacts(&json!({ path: "contents" }));
+
+			assert_matches!(files.unwrap_err(), Error::InvalidArtifact(name, _) if name == path);
+		}
+	}
+}
+
+mod oci {
+	use super::*;
+
+	use std::collections::{BTreeMap, HashMap};
+	use std::io::{BufRead, BufReader, Read, Write};
+	use std::net::TcpListener;
+	use std::sync::{Arc, Mutex};
+	use std::thread;
+
+	use kcp::{Reference, Registry};
+	use sha2::{Digest, Sha256};
+
+	/// Blobs by digest and manifests by tag the registry received
+	#[derive(Default)]
+	struct Received {
+		blobs: HashMap<String, Vec<u8>>,
+		manifests: HashMap<String, Vec<u8>>,
+	}
+
+	/// Registry accepting pushes, refusing requests without the authorization
+	/// when there's one
+	fn registry(authorization: Option<&'static str>) -> (String, Arc<Mutex<Received>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap().to_string();
+		let received = Arc::new(Mutex::new(Received::default()));
+		let store = received.clone();
+
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let mut stream = stream.unwrap();
+				let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				let mut parts = line.split_whitespace();
+				let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+
+				let mut headers = HashMap::new();
+				loop {
+					let mut header = String::new();
+					reader.read_line(&mut header).unwrap();
+
+					match header.trim().split_once(": ") {
+						Some((name, value)) => {
+							headers.insert(name.to_lowercase(), value.to_string());
+						}
+						None => break,
+					}
+				}
+
+				let length = headers
+					.get("content-length")
+					.map_or(0, |length| length.parse().unwrap());
+				let mut body = vec![0; length];
+				reader.read_exact(&mut body).unwrap();
+
+				let authorized = authorization.is_none()
+					|| headers.get("authorization").map(String::as_str) == authorization;
+				let mut store = store.lock().unwrap();
+
+				let (status, extra) = match (method, path) {
+					_ if !authorized => (
+						"401 Unauthorized",
+						"WWW-Authenticate: Basic realm=\"fake\"\r\n",
+					),
+					("POST", path) if path.ends_with("/blobs/uploads/") => {
+						("202 Accepted", "Location: /upload?session=1\r\n")
+					}
+					("PUT", path) if path.starts_with("/upload?") => {
+						let digest = path.split("digest=").nth(1).unwrap();
+						store.blobs.insert(digest.to_string(), body);
+
+						("201 Created", "")
+					}
+					("PUT", path) if path.contains("/manifests/") => {
+						let tag = path.rsplit('/').next().unwrap();
+						store.manifests.insert(tag.to_string(), body);
+
+						("201 Created", "")
+					}
+					_ => ("404 Not Found", ""),
+				};
+
+				let response = format!(
+					"HTTP/1.1 {status}\r\n{extra}Content-Length: 0\r\nConnection: close\r\n\r\n"
+				);
+				stream.write_all(response.as_bytes()).unwrap();
+			}
+		});
+
+		(address, received)
+	}
+
+	fn local() -> Registry {
+		Registry {
+			insecure: true,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn pushes_flux_artifacts() {
+		let (address, received) = registry(None);
+		let reference: Reference = format!("oci://{address}/team/app:1.0").parse().unwrap();
+		let annotations = BTreeMap::from([(
+			String::from("org.opencontainers.image.revision"),
+			String::from("main@sha1:abc"),
+		)]);
+
+		let digest = kcp::push(&reference, &local(), b"layer", &annotations).unwrap();
+
+		let received = received.lock().unwrap();
+		let manifest = &received.manifests["1.0"];
+		let layer = format!("sha256:{:x}", Sha256::digest(b"layer"));
+		let parsed: Value = serde_json::from_slice(manifest).unwrap();
+
+		assert_eq!(digest, format!("sha256:{:x}", Sha256::digest(manifest)));
+		assert_eq!(received.blobs[&layer], b"layer");
+		assert_eq!(
+			parsed["config"]["mediaType"],
+			"application/vnd.cncf.flux.config.v1+json"
+		);
+		assert_eq!(
+			parsed["layers"],
+			json!([{
+				"mediaType": "application/vnd.cncf.flux.content.v1.tar+gzip",
+				"digest": layer,
+				"size": 5,
+			}])
+		);
+		assert_eq!(
+			parsed["annotations"]["org.opencontainers.image.revision"],
+			"main@sha1:abc"
+		);
+	}
+
+	#[test]
+	fn sends_credentials_when_asked() {
+		let (address, received) = registry(Some("Basic dXNlcjpwYXNz"));
+		let reference: Reference = format!("{address}/app:1.0").parse().unwrap();
+		let registry = Registry {
+			credentials: Some((String::from("user"), String::from("pass"))),
+			..local()
+		};
+
+		kcp::push(&reference, &registry, b"layer", &BTreeMap::new()).unwrap();
+
+		assert!(received.lock().unwrap().manifests.contains_key("1.0"));
+	}
+
+	#[test]
+	fn fails_without_credentials() {
+		let (address, _received) = registry(Some("Basic dXNlcjpwYXNz"));
+		let reference: Reference = format!("{address}/app:1.0").parse().unwrap();
+
+		let pushed = kcp::push(&reference, &local(), b"layer", &BTreeMap::new());
+
+		assert_matches!(pushed.unwrap_err(), Error::Push(..));
+	}
+
+	#[test]
+	fn parses_references() {
+		let reference: Reference = "oci://localhost:5000/team/app:1.0".parse().unwrap();
+		let untagged: Reference = "ghcr.io/app".parse().unwrap();
+
+		assert_eq!(reference.registry, "localhost:5000");
+		assert_eq!(reference.repository, "team/app");
+		assert_eq!(reference.tag, "1.0");
+		assert_eq!(untagged.tag, "latest");
+		assert_matches!(
+			"app".parse::<Reference>().unwrap_err(),
+			Error::InvalidReference(_)
+		);
+	}
+}
+
+mod compile {
+	use super::*;
+
+	/// Compiles the expression as the only template, with `_` being the SDK
+	fn render(expression: &str) -> Result<Value, Error> {
+		render_in(expression, None)
+	}
+
+	fn render_in(expression: &str, release: Option<Release>) -> Result<Value, Error> {
+		let main = format!("local _ = import 'kct.libsonnet'; {expression}");
+		let (package, _dir) = package(vec![("templates/main.jsonnet", &main)], vec![]);
+
+		compile_with_example(package.unwrap(), release)
+	}
+
+	#[test]
+	fn refuses_libraries() {
+		let spec = r#"{ "name": "lib", "version": "0.0.0", "kind": "library" }"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+		let rendered = compile_with_example(package.unwrap(), None);
+
+		assert_matches!(rendered.unwrap_err(), Error::Library(name) if name == "lib");
+	}
+
+	#[test]
+	fn records_the_files_read() {
+		let template = "local _ = import 'kct.libsonnet'; { files: _.files('database.toml', _.input), raw: _.readFile('files/no-params.txt') }";
+		let (package, dir) = package(
+			vec![
+				("templates/main.jsonnet", template),
+				("templates/unused.libsonnet", "{}"),
+			],
+			vec![],
+		);
+		let _rendered = compile_with_example(package.unwrap(), None).unwrap();
+
+		let reads = kct_helper::io::reads();
+		let root = dir.path().canonicalize().unwrap();
+		for read in [
+			"kcp.json",
+			"schema.json",
+			"templates/main.jsonnet",
+			"lib/kct.libsonnet",
+			"files/database.toml",
+			"files/no-params.txt",
+		] {
+			assert!(
+				reads.contains(&root.join(read)),
+				"{read} should be recorded"
+			);
+		}
+		assert!(!reads.contains(&root.join("templates/unused.libsonnet")));
+	}
+
+	mod input {
+		use super::*;
+
+		#[test]
+		fn renders_with_null() {
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
+				vec!["example.json", "schema.json"],
+			);
+			let package = package.unwrap();
+
+			let rendered = package.compile(None, None);
+
+			assert_eq!(rendered.unwrap(), Value::Null);
+		}
+
+		#[test]
+		fn doesnt_merge_input_with_defaults() {
+			let input: Value = testing::json(
+				r#"{ "database": { "port": 5432, "host": "localhost", "credentials": { "user": "admin", "pass": "admin" } } }"#,
+			);
+
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = package.compile(Some(input.clone()), None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		#[should_panic(expected = "input provided is invalid")]
+		fn validate_input() {
+			let input: Value = testing::json(r#"{ "database": null }"#);
+
+			let (root, _dir) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
+				vec![],
+			);
+			let package = root.unwrap();
+
+			let rendered = package.compile(Some(input), None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a validation issue!"),
+			}
+		}
+	}
+
+	mod jsonnet {
+		use super::*;
+
+		const TEXT: &str = r#"{ "name": "script", "version": "0.0.0", "output": "text" }"#;
+
+		#[test]
+		fn renders_plain_text() {
+			let main = r#"|||
+				#!/bin/sh
+				echo "%s"
+			||| % (import 'kct.libsonnet').input.database.host"#;
+			let (package, _dir) = package(
+				vec![("kcp.json", TEXT), ("templates/main.jsonnet", main)],
+				vec![],
+			);
+
+			let rendered = compile_with_example(package.unwrap(), None).unwrap();
+
+			assert_eq!(rendered, json!("#!/bin/sh\necho \"postgres\"\n"));
+		}
+
+		#[test]
+		fn needs_text_to_be_a_string() {
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", TEXT),
+					("templates/main.jsonnet", "{ script: true }"),
+				],
+				vec![],
+			);
+
+			let rendered = compile_with_example(package.unwrap(), None);
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+
+		#[test]
+		#[should_panic(expected = "manifest function")]
+		fn disallows_top_level_functions() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"function(input = null, files = null) { input: input }",
+				)],
+				vec![],
+			);
+
+			let package = package.unwrap();
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		fn renders_imports() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"local valid = import './input/entry.jsonnet'; valid",
+					),
+					("templates/input/entry.jsonnet", "import '../input.jsonnet'"),
+					("templates/input.jsonnet", "(import 'kct.libsonnet').input"),
+				],
+				vec![],
+			);
+
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		#[should_panic(expected = "can't resolve input.jsonnet")]
+		fn doesnt_include_templates_on_imports() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"local valid = import './input/entry.jsonnet'; valid",
+					),
+					("templates/input/entry.jsonnet", "import 'input.jsonnet'"),
+					("templates/input.jsonnet", "(import 'kct.libsonnet').input"),
+				],
+				vec![],
+			);
+
+			let package = package.unwrap();
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		fn includes_vendor_for_imports() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"local valid = import 'ksonnet/ksonnet.beta.4/k8s.libjsonnet'; valid",
+					),
+					(
+						"vendor/ksonnet/ksonnet.beta.4/k8s.libjsonnet",
+						"(import 'kct.libsonnet').input",
+					),
+				],
+				vec![],
+			);
+
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		fn includes_lib_for_aliasing() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"local valid = import 'k.libjsonnet'; valid",
+					),
+					(
+						"vendor/ksonnet/ksonnet.beta.4/k8s.libjsonnet",
+						"(import 'kct.libsonnet').input",
+					),
+					(
+						"lib/k.libjsonnet",
+						"import 'ksonnet/ksonnet.beta.4/k8s.libjsonnet'",
+					),
+				],
+				vec![],
+			);
+
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		fn imports_strings_through_every_resolver() {
+			let spec = json!({
+				"name": "fixture",
+				"version": "0.0.0",
+				"aliases": { "aliased.txt": "vendor/other/aliased.txt" }
+			});
+			let main = "[importstr 'relative.txt', importstr 'lib.txt', importstr 'vendored/vendor.txt', importstr 'aliased.txt']";
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", &spec.to_string()),
+					("templates/main.jsonnet", main),
+					("templates/relative.txt", "relative"),
+					("lib/lib.txt", "lib"),
+					("vendor/vendored/vendor.txt", "vendor"),
+					("vendor/other/aliased.txt", "aliased"),
+				],
+				vec![],
+			);
+			let rendered = compile_with_example(package.unwrap(), None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["relative", "lib", "vendor", "aliased"])
+			);
+		}
+
+		#[test]
+		#[should_panic(expected = "more than the 16777216 imports may load")]
+		fn refuses_imports_over_the_limit() {
+			let big = "x".repeat(MAX_IMPORT_SIZE as usize + 1);
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "importstr 'big.txt'"),
+					("lib/big.txt", &big),
+				],
+				vec![],
+			);
+			let rendered = compile_with_example(package.unwrap(), None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+	}
+
+	mod versioned_sdk {
+		use super::*;
+
+		#[test]
+		fn imports_a_version() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"local _ = import 'kct.io/v1.libsonnet'; { input: _.input, sdk: std.objectFields(_.sdk) }",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!({ "input": input, "sdk": ["chunk", "dependsOn", "inOrder"] })
+			);
+		}
+
+		#[test]
+		fn stands_for_the_one_pinned() {
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "sdk": "v1" });
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", &spec.to_string()),
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+				],
+				vec!["lib/kct.libsonnet"],
+			);
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		fn comes_after_the_vendored() {
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "import 'kct.libsonnet'"),
+					("lib/kct.libsonnet", "'vendored'"),
+				],
+				vec![],
+			);
+			let rendered = compile_with_example(package.unwrap(), None);
+
+			assert_eq!(rendered.unwrap(), json!("vendored"));
+		}
+	}
+
+	mod k8s {
+		use super::*;
+
+		fn render(object: &str) -> Value {
+			let main = format!("local k8s = import 'kct.io/v1/k8s.libsonnet'; {object}");
+			let (package, _dir) = package(vec![("templates/main.jsonnet", &main)], vec![]);
+			let release = Release {
+				name: String::from("prod"),
+				..Default::default()
+			};
+
+			compile_with_example(package.unwrap(), Some(release)).unwrap()
+		}
+
+		#[test]
+		fn labels_with_the_release() {
+			let rendered = render("k8s.labels('api', 'backend')");
+
+			assert_eq!(
+				rendered,
+				json!({
+					"app.kubernetes.io/name": "api",
+					"app.kubernetes.io/instance": "prod-fixture",
+					"app.kubernetes.io/version": "0.0.0",
+					"app.kubernetes.io/managed-by": "kct",
+					"app.kubernetes.io/component": "backend"
+				})
+			);
+		}
+
+		#[test]
+		fn builds_deployments_selecting_their_pods() {
+			let rendered = render(
+				"k8s.deployment('api', k8s.container('api', 'api:1.0', ports = { http: 8080 }))",
+			);
+
+			assert_eq!(rendered["kind"], json!("Deployment"));
+			assert_eq!(
+				rendered["spec"]["selector"]["matchLabels"],
+				json!({
+					"app.kubernetes.io/name": "api",
+					"app.kubernetes.io/instance": "prod-fixture"
+				})
+			);
+			assert_eq!(
+				rendered["spec"]["template"]["spec"]["containers"],
+				json!([{
+					"name": "api",
+					"image": "api:1.0",
+					"ports": [{ "name": "http", "containerPort": 8080 }]
+				}])
+			);
+		}
+
+		#[test]
+		fn builds_services_targeting_named_ports() {
+			let rendered = render("k8s.service('api', { http: 80 })");
+
+			assert_eq!(
+				rendered["spec"]["ports"],
+				json!([{ "name": "http", "port": 80, "targetPort": "http", "protocol": "TCP" }])
+			);
+			assert_eq!(
+				rendered["spec"]["selector"],
+				json!({
+					"app.kubernetes.io/name": "api",
+					"app.kubernetes.io/instance": "prod-fixture"
+				})
+			);
+		}
+
+		#[test]
+		fn binds_roles_to_service_accounts() {
+			let rendered = render("k8s.roleBinding('api', namespace = 'apps')");
+
+			assert_eq!(
+				rendered["roleRef"],
+				json!({ "apiGroup": "rbac.authorization.k8s.io", "kind": "Role", "name": "api" })
+			);
+			assert_eq!(
+				rendered["subjects"],
+				json!([{ "kind": "ServiceAccount", "name": "api", "namespace": "apps" }])
+			);
+		}
+	}
+
+	mod dialect {
+		use super::*;
+
+		fn restricting(jsonnet: Value, main: &str) -> Result<Value, Error> {
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "jsonnet": jsonnet });
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", &spec.to_string()),
+					("templates/main.jsonnet", main),
+				],
+				vec![],
+			);
+
+			compile_with_example(package.unwrap(), None)
+		}
+
+		#[test]
+		#[should_panic(expected = "only kct.io/ variables can be read")]
+		fn refuses_foreign_ext_vars() {
+			let rendered = restricting(
+				json!({ "foreignExtVars": false }),
+				"std.extVar('CI_COMMIT_SHA')",
+			);
+
+			match rendered.unwrap_err() {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		fn reads_the_ones_of_kct() {
+			let rendered = restricting(
+				json!({ "foreignExtVars": false, "native": false }),
+				"(import 'kct.libsonnet').input",
+			);
+
+			assert!(rendered.unwrap().is_object());
+		}
+
+		#[test]
+		#[should_panic(expected = "std.native(\"kct\") is disallowed by the package")]
+		fn refuses_native_through_aliases() {
+			let rendered =
+				restricting(json!({ "native": false }), "local s = std; s.native('kct')");
+
+			match rendered.unwrap_err() {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		#[should_panic(expected = "String at $.data[1] has 5 bytes, more than the 4 allowed")]
+		fn refuses_strings_too_large() {
+			let rendered = restricting(json!({ "maxStringSize": 4 }), "{ data: ['kct', 'kctio'] }");
+
+			match rendered.unwrap_err() {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+	}
+
+	mod libraries {
+		use super::*;
+
+		use kct_compiler::Context;
+
+		const MAIN: &str = "import 'greeting.libsonnet'";
+
+		fn declaring(libraries: &[&str], with: Vec<(&str, &str)>) -> (Package, TempDir) {
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "libraries": libraries });
+			let spec = spec.to_string();
+
+			let mut with = with;
+			with.extend([
+				("kcp.json", spec.as_str()),
+				("templates/main.jsonnet", MAIN),
+			]);
+			let (package, dir) = package(with, vec![]);
+
+			(package.unwrap(), dir)
+		}
+
+		#[test]
+		fn search_the_ones_declared() {
+			let (package, _dir) =
+				declaring(&["shared"], vec![("shared/greeting.libsonnet", "'shared'")]);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("shared"));
+		}
+
+		#[test]
+		fn prefer_the_last_declared() {
+			let (package, _dir) = declaring(
+				&["first", "last"],
+				vec![
+					("first/greeting.libsonnet", "'first'"),
+					("last/greeting.libsonnet", "'last'"),
+				],
+			);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("last"));
+		}
+
+		#[test]
+		fn come_after_lib() {
+			let (package, _dir) = declaring(
+				&["shared"],
+				vec![
+					("lib/greeting.libsonnet", "'lib'"),
+					("shared/greeting.libsonnet", "'shared'"),
+				],
+			);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("lib"));
+		}
+
+		#[test]
+		fn come_after_the_ones_given() {
+			let (package, dir) = declaring(
+				&["shared"],
+				vec![
+					("given/greeting.libsonnet", "'given'"),
+					("shared/greeting.libsonnet", "'shared'"),
+				],
+			);
+
+			let context = Context::builder()
+				.root(package.root.clone())
+				.libraries(vec![dir.path().join("given")])
+				.build()
+				.unwrap();
+			let input = package.example.clone();
+			let rendered = package.compile_in(&context, input);
+
+			assert_eq!(rendered.unwrap(), json!("given"));
+		}
+	}
+
+	mod aliases {
+		use super::*;
+
+		fn aliasing(main: &str, with: Vec<(&str, &str)>) -> (Package, TempDir) {
+			let spec = json!({
+				"name": "fixture",
+				"version": "0.0.0",
+				"aliases": {
+					"k.libsonnet": "vendor/github.com/jsonnet-libs/k8s-libsonnet/1.29/main.libsonnet",
+					"grafana": "vendor/github.com/grafana/grafonnet/gen/grafonnet-v10.0.0"
+				}
+			});
+			let spec = spec.to_string();
+
+			let mut with = with;
+			with.extend([
+				("kcp.json", spec.as_str()),
+				("templates/main.jsonnet", main),
+			]);
+			let (package, dir) = package(with, vec![]);
+
+			(package.unwrap(), dir)
+		}
+
+		#[test]
+		fn resolve_the_whole_import() {
+			let (package, _dir) = aliasing(
+				"import 'k.libsonnet'",
+				vec![(
+					"vendor/github.com/jsonnet-libs/k8s-libsonnet/1.29/main.libsonnet",
+					"'1.29'",
+				)],
+			);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("1.29"));
+		}
+
+		#[test]
+		fn resolve_the_leading_directories() {
+			let (package, _dir) =
+				aliasing(
+					"import 'grafana/main.libsonnet'",
+					vec![(
+					"vendor/github.com/grafana/grafonnet/gen/grafonnet-v10.0.0/main.libsonnet",
+					"import 'panel.libsonnet'",
+				), (
+					"vendor/github.com/grafana/grafonnet/gen/grafonnet-v10.0.0/panel.libsonnet",
+					"'panel'",
+				)],
+				);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("panel"));
+		}
+
+		#[test]
+		fn come_before_lib() {
+			let (package, _dir) = aliasing(
+				"import 'k.libsonnet'",
+				vec![
+					("lib/k.libsonnet", "'lib'"),
+					(
+						"vendor/github.com/jsonnet-libs/k8s-libsonnet/1.29/main.libsonnet",
+						"'aliased'",
+					),
+				],
+			);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("aliased"));
+		}
+
+		#[test]
+		fn fail_when_the_target_is_missing() {
+			let (package, _dir) =
+				aliasing("import 'k.libsonnet'", vec![("lib/k.libsonnet", "'lib'")]);
+
+			let rendered = compile_with_example(package, None);
+
+			assert!(rendered.is_err());
+		}
+	}
+
+	mod file_templates {
+		use super::*;
+
+		#[test]
+		fn renders_templates() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('database.toml')",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+			let template = testing::template(&Fixture::contents("files/database.toml"), &input);
+			let rendered = package.compile(Some(input), None);
+
+			assert_eq!(rendered.unwrap(), Value::String(template));
+		}
+
+		#[test]
+		fn renders_multiple_templates() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('**/*.toml')",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+
+			let db_template = testing::template(&Fixture::contents("files/database.toml"), &input);
+			let evt_template =
+				testing::template(&Fixture::contents("files/events/settings.toml"), &input);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				Value::Array(vec![
+					Value::String(db_template),
+					Value::String(evt_template),
+				])
+			);
+		}
+
+		#[test]
+		fn renders_each_call_with_its_input() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"local _ = import 'kct.libsonnet'; [_.files('greet.txt', {name: 'a'}), _.files('greet.txt', {name: '<b>'})]",
+					),
+					("files/greet.txt", "hi {{ name }}"),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!(["hi a", "hi &lt;b&gt;"]));
+		}
+
+		#[test]
+		fn globs_the_same_folder_many_times() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"local _ = import 'kct.libsonnet'; [_.files('events/*.toml', _.input), _.files('*.toml', _.input)]",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+			let input = package.example.clone().unwrap();
+
+			let db_template = testing::template(&Fixture::contents("files/database.toml"), &input);
+			let evt_template =
+				testing::template(&Fixture::contents("files/events/settings.toml"), &input);
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!([evt_template, [db_template, evt_template]])
+			);
+		}
+
+		#[test]
+		fn include_other_templates() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"(import 'kct.libsonnet').files('page.txt', {name: 'kct'})",
+					),
+					(
+						"files/page.txt",
+						"{% include \"partials/header.txt\" %}body",
+					),
+					("files/partials/header.txt", "hi {{ name }}\n"),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("hi kct\nbody"));
+		}
+
+		#[test]
+		fn renders_lines_ending_with_crlf_as_lf() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"(import 'kct.libsonnet').files('page.txt', {name: 'kct'})",
+					),
+					("files/page.txt", "hi {{ name }}\r\nbye\r\n"),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("hi kct\nbye\n"));
+		}
+
+		#[test]
+		fn extend_other_templates() {
+			let (package, _dir) = package(
+				vec![
+					(
+						"templates/main.jsonnet",
+						"(import 'kct.libsonnet').files('child.txt')",
+					),
+					(
+						"files/base.txt",
+						"[{% block body %}base{% endblock body %}]",
+					),
+					(
+						"files/child.txt",
+						"{% extends \"base.txt\" %}{% block body %}child{% endblock body %}",
+					),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!("[child]"));
+		}
+
+		fn templating(settings: Value, file: &str, input: Value) -> Result<Value, Error> {
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "files": settings });
+			let spec = spec.to_string();
+			let main = format!("(import 'kct.libsonnet').files('rules.yaml', {input})");
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", &spec),
+					("templates/main.jsonnet", &main),
+					("files/rules.yaml", file),
+				],
+				vec![],
+			);
+
+			compile_with_example(package.unwrap(), None)
+		}
+
+		#[test]
+		fn use_the_delimiters_given() {
+			let settings =
+				json!({ "delimiters": { "variable": ["[[", "]]"], "block": ["[%", "%]"] } });
+			let file =
+				"[% if alert %]summary: {{ $labels.instance }} {% of %} [[ alert ]][% endif %]";
+
+			let rendered = templating(settings, file, json!({"alert": "down"}));
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!("summary: {{ $labels.instance }} {% of %} down")
+			);
+		}
+
+		#[test]
+		fn keep_raw_sections_with_the_delimiters_given() {
+			let settings = json!({ "delimiters": { "block": ["[%", "%]"] } });
+			let file = "[% raw %][[ kept ]] [% if %] {{ too }}[% endraw %] {{ name }}";
+
+			let rendered = templating(settings, file, json!({"name": "api"}));
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!("[[ kept ]] [% if %] {{ too }} api")
+			);
+		}
+
+		#[test]
+		fn pass_through_the_raw_ones() {
+			let settings = json!({ "raw": ["*.yaml"] });
+			let file = "summary: {{ $labels.instance }} {% broken";
+
+			let rendered = templating(settings, file, json!({}));
+
+			assert_eq!(rendered.unwrap(), json!(file));
+		}
+
+		fn vendored(dir: &TempDir, files: Vec<(&str, &str)>) {
+			let root = dir.path().join("vendor/grafana-lib");
+			let spec = json!({"name": "grafana-lib", "version": "0.0.0", "kind": "library"});
+			testing::dir::mk(&root.join("files/dashboards"));
+			std::fs::write(root.join("kcp.json"), spec.to_string()).unwrap();
+
+			for (path, contents) in files {
+				std::fs::write(root.join(path), contents).unwrap();
+			}
+		}
+
+		#[test]
+		fn render_the_ones_vendored() {
+			let (package, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('vendor:grafana-lib/dashboards/*.json', {title: 'api'})",
+				)],
+				vec![],
+			);
+			vendored(
+				&dir,
+				vec![
+					("files/dashboards/a.json", "{% include \"title.txt\" %}a"),
+					("files/dashboards/b.json", "{% include \"title.txt\" %}b"),
+					("files/title.txt", "{{ title }}-"),
+				],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json!(["api-a", "api-b"]));
+		}
+
+		#[test]
+		#[should_panic(expected = "Vendored files should be globbed as vendor:<package>/<glob>")]
+		fn fails_on_vendored_globs_escaping_the_vendor() {
+			let (package, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('vendor:../files/*.toml')",
+				)],
+				vec![],
+			);
+			vendored(&dir, vec![]);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		#[should_panic(expected = "Unable to load vendored package missing")]
+		fn fails_on_missing_vendored_packages() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('vendor:missing/*.json')",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		#[should_panic(expected = "Unable to compile templates")]
+		fn fails_on_invalid_templates() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('invalid.ini')",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		fn locate_the_invalid_ones() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('invalid.ini')",
+				)],
+				vec![],
+			);
+
+			let rendered = compile_with_example(package.unwrap(), None).unwrap_err();
+
+			let err = match rendered {
+				Error::Compilation(CompilerError::Template(err)) => err,
+				err => panic!("It should be a template issue, not {err}!"),
+			};
+			assert!(err.path.ends_with("files/invalid.ini"));
+			assert!(err.line.is_some());
+			assert!(err.column.is_some());
+			assert!(err.snippet.unwrap().contains("setup = {{ secret"));
+		}
+
+		#[test]
+		fn locate_the_variables_missing() {
+			let file = "name = {{ name }}\nsecret = {{ missing }}\n";
+
+			let rendered = templating(json!({}), file, json!({"name": "api"})).unwrap_err();
+
+			let err = match rendered {
+				Error::Compilation(CompilerError::Template(err)) => err,
+				err => panic!("It should be a template issue, not {err}!"),
+			};
+			assert!(err.path.ends_with("files/rules.yaml"));
+			assert!(err.reason.contains("Variable `missing` not found"));
+			assert_eq!(err.line, Some(2));
+			assert!(err.snippet.unwrap().contains("2 | secret = {{ missing }}"));
+		}
+
+		#[test]
+		fn compiles_templates_with_empty_input() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('no-params.txt')",
+				)],
+				vec!["example.json", "schema.json"],
+			);
+			let package = package.unwrap();
+
+			let template = testing::template(
+				&Fixture::contents("files/no-params.txt"),
+				&Value::Object(Map::new()),
+			);
+
+			let rendered = package.compile(None, None);
+
+			assert_eq!(rendered.unwrap(), Value::String(template));
+		}
+
+		#[test]
+		#[should_panic(expected = "No files folder to search for templates")]
+		fn fails_on_empty_templates_folder() {
+			let (package, _dir) = package(vec![], vec!["files"]);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		#[should_panic(expected = "No template found for glob")]
+		fn fails_on_not_found_template() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').files('*.json')",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, None).unwrap_err();
+
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+	}
+
+	mod files_digest {
+		use super::*;
+
+		fn digest(with: Vec<(&str, &str)>, input: Value) -> Value {
+			let mut with = with;
+			with.push((
+				"templates/main.jsonnet",
+				"(import 'kct.libsonnet').filesDigest('**/*.toml')",
+			));
+
+			let (package, _dir) = package(with, vec![]);
+
+			package.unwrap().compile(Some(input), None).unwrap()
+		}
+
+		#[test]
+		fn is_stable() {
+			let input = testing::json(&Fixture::contents("example.json"));
+
+			let first = digest(vec![], input.clone());
+			let second = digest(vec![], input);
+
+			assert_eq!(first, second);
+			assert_eq!(first.as_str().unwrap().len(), 64);
+		}
+
+		#[test]
+		fn changes_with_rendered_contents() {
+			let input = testing::json(&Fixture::contents("example.json"));
+			let mut changed = input.clone();
+			changed["database"]["host"] = json!("mysql");
+
+			assert_ne!(digest(vec![], input), digest(vec![], changed));
+		}
+
+		#[test]
+		fn changes_with_matched_files() {
+			let input = testing::json(&Fixture::contents("example.json"));
+			let extra = vec![("files/extra.toml", "extra = true")];
+
+			assert_ne!(digest(vec![], input.clone()), digest(extra, input));
+		}
+	}
+
+	mod sdk {
+		use super::*;
+
+		fn chunk(call: &str) -> Result<Value, Error> {
+			let main = format!("local _ = import 'kct.libsonnet'; _.sdk.chunk{call}");
+			let (package, _dir) = package(vec![("templates/main.jsonnet", &main)], vec![]);
+
+			compile_with_example(package.unwrap(), None)
+		}
+
+		#[test]
+		fn keeps_small_files_together() {
+			let rendered = chunk("('conf', {a: 'aa', b: 'bb'})").unwrap();
+
+			assert_eq!(
+				rendered["objects"]["conf-0"]["data"],
+				json!({"a": "aa", "b": "bb"})
+			);
+			assert_eq!(rendered["manifest"], json!({"a": "conf-0", "b": "conf-0"}));
+		}
+
+		#[test]
+		fn splits_on_size_limit() {
+			let rendered =
+				chunk("('conf', {a: 'aa', b: 'bb', c: 'cc'}, 'Secret', limit = 6)").unwrap();
+
+			let objects = rendered["objects"].as_object().unwrap();
+			assert_eq!(objects.len(), 2);
+			assert_eq!(
+				rendered["objects"]["conf-1"]["stringData"],
+				json!({"c": "cc"})
+			);
+			assert_eq!(rendered["manifest"]["c"], json!("conf-1"));
+		}
+
+		#[test]
+		fn splits_on_key_count() {
+			let rendered = chunk("('conf', {a: 'a', b: 'b', c: 'c'}, maxKeys = 1)").unwrap();
+
+			let objects = rendered["objects"].as_object().unwrap();
+			assert_eq!(objects.len(), 3);
+		}
+
+		#[test]
+		fn fails_on_oversized_files() {
+			let rendered = chunk("('conf', {a: 'aaaa'}, limit = 2)");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod semver {
+		use super::*;
+
+		#[test]
+		fn compares_versions() {
+			let rendered = render(
+				"[_.semver.compare('1.2.0', '1.10.0'), _.semver.compare('v1.2.0', '1.2.0'), _.semver.compare('2.0.0', '2.0.0-rc.1')]",
+			)
+			.unwrap();
+
+			assert_eq!(rendered, json!([-1, 0, 1]));
+		}
+
+		#[test]
+		fn checks_constraints() {
+			let rendered = render(
+				"[_.semver.satisfies('v1.25.3', '>=1.24, <1.26'), _.semver.satisfies(_.package.version, '^1')]",
+			)
+			.unwrap();
+
+			assert_eq!(rendered, json!([true, false]));
+		}
+
+		#[test]
+		fn parses_versions() {
+			let rendered = render("_.semver.parse('v1.25.3-gke.100')").unwrap();
+
+			assert_eq!(
+				rendered,
+				json!({"major": 1, "minor": 25, "patch": 3, "pre": "gke.100", "build": ""})
+			);
+		}
+	}
+
+	mod regex {
+		use super::*;
+
+		#[test]
+		fn matches_patterns() {
+			let rendered = render(
+				"[_.regex.match('^[a-z0-9-]+$', 'my-app'), _.regex.match('^[a-z0-9-]+$', 'My_App')]",
+			);
+
+			assert_eq!(rendered.unwrap(), json!([true, false]));
+		}
+
+		#[test]
+		fn replaces_all_occurrences() {
+			let rendered = render("_.regex.replace('[^a-z0-9]+', 'my_app.v2', '-')");
+
+			assert_eq!(rendered.unwrap(), json!("my-app-v2"));
+		}
+
+		#[test]
+		fn replaces_with_groups() {
+			let rendered = render("_.regex.replace(@'(\\w+)@(\\w+)', 'user@host', '$2.$1')");
+
+			assert_eq!(rendered.unwrap(), json!("host.user"));
+		}
+
+		#[test]
+		fn finds_matches() {
+			let rendered = render("_.regex.find('[0-9]+', 'v1.25.3')");
+
+			assert_eq!(rendered.unwrap(), json!(["1", "25", "3"]));
+		}
+
+		#[test]
+		fn fails_on_invalid_patterns() {
+			let rendered = render("_.regex.match('(', 'text')");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod uuid {
+		use super::*;
+
+		#[test]
+		fn generates_random_ids() {
+			let rendered = render("[_.uuid(), _.uuid()]").unwrap();
+
+			let ids: Vec<&str> = rendered
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|v| v.as_str().unwrap())
+				.collect();
+			assert_eq!(ids[0].len(), 36);
+			assert_ne!(ids[0], ids[1]);
+		}
+
+		#[test]
+		fn generates_deterministic_ids() {
+			let rendered =
+				render("[_.uuidFrom('dns', 'example.com'), _.uuidFrom('dns', 'example.com')]")
+					.unwrap();
+
+			let expected = "cfbff0d1-9375-5685-968c-48ce8b15ae17";
+			assert_eq!(rendered, json!([expected, expected]));
+		}
+
+		#[test]
+		fn accepts_custom_namespaces() {
+			let rendered =
+				render("[_.uuidFrom('my-app', 'db'), _.uuidFrom('my-app', 'cache')]").unwrap();
+
+			assert_ne!(rendered[0], rendered[1]);
+		}
+	}
+
+	mod time {
+		use super::*;
+
+		use chrono::{TimeZone, Utc};
+		use kct_compiler::Context;
+
+		#[test]
+		fn freezes_now_within_compilation() {
+			let rendered = render("[_.now(), _.time.now()]").unwrap();
+
+			assert_eq!(rendered[0], rendered[1]);
+		}
+
+		#[test]
+		fn uses_release_timestamp() {
+			let release = Release {
+				name: String::from("rel"),
+				timestamp: Some(Utc.with_ymd_and_hms(2023, 2, 5, 10, 30, 0).unwrap()),
+				..Default::default()
+			};
+			let rendered = render_in(
+				"[_.now(), _.time.format(_.now(), '%Y%m%d'), _.time.unix(_.now()), _.release.timestamp]",
+				Some(release),
+			)
+			.unwrap();
+
+			assert_eq!(
+				rendered,
+				json!([
+					"2023-02-05T10:30:00+00:00",
+					"20230205",
+					1675593000,
+					"2023-02-05T10:30:00+00:00"
+				])
+			);
+		}
+
+		#[test]
+		fn fails_on_invalid_format() {
+			let rendered = render("_.time.format(_.now(), '%Q')");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+
+		#[test]
+		fn uses_given_time_without_release() {
+			let main = "local _ = import 'kct.libsonnet'; _.now()";
+			let (package, _dir) = package(vec![("templates/main.jsonnet", main)], vec![]);
+			let package = package.unwrap();
+
+			let context = Context::builder()
+				.root(package.root.clone())
+				.now(Utc.with_ymd_and_hms(2023, 2, 5, 10, 30, 0).unwrap())
+				.build()
+				.unwrap();
+
+			let input = package.example.clone();
+			let rendered = package.compile_in(&context, input).unwrap();
+
+			assert_eq!(rendered, json!("2023-02-05T10:30:00+00:00"));
+		}
+	}
+
+	mod encoding {
+		use super::*;
+
+		#[test]
+		fn encodes_base64() {
+			let rendered = render("[_.base64.encode('kct ✓'), _.base64.decode('a2N0IOKckw==')]");
+
+			assert_eq!(rendered.unwrap(), json!(["a2N0IOKckw==", "kct ✓"]));
+		}
+
+		#[test]
+		fn encodes_url_components() {
+			let rendered = render("[_.url.encode('a b&c=d/é~'), _.url.decode('a%20b%26c')]");
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["a%20b%26c%3Dd%2F%C3%A9~", "a b&c"])
+			);
+		}
+
+		#[test]
+		fn wraps_pem() {
+			let rendered =
+				render("_.pem.wrap('CERTIFICATE', _.base64.encode(std.repeat('k', 60)))");
+
+			let data = "a2tr".repeat(20);
+			let expected = format!(
+				"-----BEGIN CERTIFICATE-----\n{}\n{}\n-----END CERTIFICATE-----\n",
+				&data[..64],
+				&data[64..]
+			);
+			assert_eq!(rendered.unwrap(), json!(expected));
+		}
+
+		#[test]
+		fn unwraps_pem_chains() {
+			let rendered = render(
+				"local chain = _.pem.wrap('CERTIFICATE', 'a2N0') + _.pem.wrap('PRIVATE KEY', 'a2V5'); _.pem.unwrap(chain)",
+			);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!([
+					{"label": "CERTIFICATE", "data": "a2N0"},
+					{"label": "PRIVATE KEY", "data": "a2V5"}
+				])
+			);
+		}
+
+		#[test]
+		fn fails_on_unclosed_pem() {
+			let rendered = render("_.pem.unwrap('-----BEGIN CERTIFICATE-----\\na2N0')");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod tls {
+		use super::*;
+
+		use chrono::{TimeZone, Utc};
+		use x509_parser::pem::parse_x509_pem;
+
+		fn render(expression: &str) -> Value {
+			let release = Release {
+				name: String::from("rel"),
+				timestamp: Some(Utc.with_ymd_and_hms(2023, 2, 5, 10, 30, 0).unwrap()),
+				..Default::default()
+			};
+
+			render_in(expression, Some(release)).unwrap()
+		}
+
+		fn subject_and_issuer(pem: &Value) -> (String, String) {
+			let (_, pem) = parse_x509_pem(pem.as_str().unwrap().as_bytes()).unwrap();
+			let cert = pem.parse_x509().unwrap();
+
+			(cert.subject().to_string(), cert.issuer().to_string())
+		}
+
+		#[test]
+		fn generates_self_signed_certs() {
+			let rendered =
+				render("_.tls.selfSignedCert('webhook', ['webhook.default.svc', '10.0.0.1'])");
+
+			let (subject, issuer) = subject_and_issuer(&rendered["cert"]);
+			assert_eq!(subject, "CN=webhook");
+			assert_eq!(issuer, "CN=webhook");
+			assert!(rendered["key"].as_str().unwrap().contains("PRIVATE KEY"));
+		}
+
+		#[test]
+		fn signs_certs_with_ca() {
+			let rendered = render(
+				"local ca = _.tls.ca('root'); { ca: ca, cert: _.tls.signedCert('webhook', ['webhook.default.svc'], ca) }",
+			);
+
+			let (subject, issuer) = subject_and_issuer(&rendered["cert"]["cert"]);
+			assert_eq!(subject, "CN=webhook");
+			assert_eq!(issuer, "CN=root");
+		}
+
+		#[test]
+		fn is_deterministic_with_seed() {
+			let expression = "local ca = _.tls.ca('root', seed = 'abc'); [ca, _.tls.signedCert('webhook', [], ca, seed = 'abc')]";
+
+			assert_eq!(render(expression), render(expression));
+		}
+
+		#[test]
+		fn is_random_without_seed() {
+			let rendered = render("[_.tls.ca('root'), _.tls.ca('root')]");
+
+			assert_ne!(rendered[0]["key"], rendered[1]["key"]);
+		}
+	}
+
+	mod random {
+		use super::*;
+
+		fn compile(expression: &str, seed: Option<&str>, spec: &str) -> Result<Value, Error> {
+			let main = format!("local _ = import 'kct.libsonnet'; {expression}");
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", &main), ("kcp.json", spec)],
+				vec![],
+			);
+			let release = Release {
+				name: String::from("rel"),
+				seed: seed.map(String::from),
+				..Default::default()
+			};
+
+			compile_with_example(package.unwrap(), Some(release))
+		}
+
+		fn render(expression: &str, seed: Option<&str>) -> Value {
+			compile(expression, seed, &declaring(&["secrets"])).unwrap()
+		}
+
+		#[test]
+		fn refuses_seed_without_capability() {
+			let expression = "_.random.password('db')";
+			let undeclared = declaring(&[]);
+
+			let seeded = compile(expression, Some("secret"), &undeclared);
+			let unseeded = compile(expression, None, &undeclared);
+
+			assert_matches!(seeded.unwrap_err(), Error::Compilation(_));
+			assert!(unseeded.is_ok());
+		}
+
+		#[test]
+		fn generates_passwords() {
+			let rendered = render("_.random.password('db')", None);
+
+			let password = rendered.as_str().unwrap();
+			assert_eq!(password.len(), 32);
+			assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+		}
+
+		#[test]
+		fn is_stable_with_seed() {
+			let expression = "[_.random.password('db', 16), _.random.password('cache', 16)]";
+
+			let first = render(expression, Some("secret"));
+			let second = render(expression, Some("secret"));
+
+			assert_eq!(first, second);
+			assert_ne!(first[0], first[1]);
+			assert_ne!(first, render(expression, Some("other")));
+		}
+
+		#[test]
+		fn rotates_without_seed() {
+			let expression = "_.random.password('db')";
+
+			assert_ne!(render(expression, None), render(expression, None));
+		}
+
+		#[test]
+		fn uses_custom_chars() {
+			let rendered = render("_.random.string('pin', 6, '0123456789')", Some("secret"));
+
+			let pin = rendered.as_str().unwrap();
+			assert_eq!(pin.len(), 6);
+			assert!(pin.chars().all(|c| c.is_ascii_digit()));
+		}
+
+		#[test]
+		fn fails_on_excessive_length() {
+			let spec = declaring(&["secrets"]);
+
+			let longest = compile("_.random.password('db', 4096)", Some("secret"), &spec);
+			let password = compile("_.random.password('db', 4097)", Some("secret"), &spec);
+			let string = compile("_.random.string('pin', 1000000, '0123456789')", None, &spec);
+
+			assert_eq!(longest.unwrap().as_str().unwrap().len(), 4096);
+			assert_matches!(password.unwrap_err(), Error::Compilation(_));
+			assert_matches!(string.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod net {
+		use super::*;
+
+		#[test]
+		fn computes_subnets() {
+			let rendered = render(
+				"[_.net.cidrSubnet('10.0.0.0/16', 8, 2), _.net.cidrSubnet('10.1.2.3/16', 4, 15), _.net.cidrSubnet('fd00::/48', 16, 1)]",
+			);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["10.0.2.0/24", "10.1.240.0/20", "fd00:0:0:1::/64"])
+			);
+		}
+
+		#[test]
+		fn computes_hosts() {
+			let rendered = render(
+				"[_.net.cidrHost('10.0.2.0/24', 5), _.net.cidrHost('10.0.2.0/24', -2), _.net.cidrHost('fd00::/64', 10)]",
+			);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["10.0.2.5", "10.0.2.254", "fd00::a"])
+			);
+		}
+
+		#[test]
+		fn adds_to_ips() {
+			let rendered = render("[_.net.ipAdd('10.0.0.255', 1), _.net.ipAdd('10.0.1.0', -1)]");
+
+			assert_eq!(rendered.unwrap(), json!(["10.0.1.0", "10.0.0.255"]));
+		}
+
+		#[test]
+		fn fails_out_of_range() {
+			let subnet = render("_.net.cidrSubnet('10.0.0.0/24', 4, 16)");
+			let host = render("_.net.cidrHost('10.0.0.0/30', 4)");
+			let ip = render("_.net.ipAdd('255.255.255.255', 1)");
+
+			assert_matches!(subnet.unwrap_err(), Error::Compilation(_));
+			assert_matches!(host.unwrap_err(), Error::Compilation(_));
+			assert_matches!(ip.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod duration {
+		use super::*;
+
+		#[test]
+		fn parses_into_seconds() {
+			let rendered = render(
+				"local d = _.duration; [d.parse('90s'), d.parse('1h30m'), d.parse('1.5m'), d.parse('250ms'), d.parse('-2d'), d.parse(10)]",
+			);
+
+			assert_eq!(rendered.unwrap(), json!([90, 5400, 90, 0.25, -172800, 10]));
+		}
+
+		#[test]
+		fn formats_like_go() {
+			let rendered = render(
+				"local d = _.duration; [d.format(90), d.format('2h'), d.format(0.5), d.format('1.5s'), d.format(0), d.format('1w')]",
+			);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["1m30s", "2h0m0s", "500ms", "1.5s", "0s", "168h0m0s"])
+			);
+		}
+
+		#[test]
+		fn fails_on_invalid_durations() {
+			for invalid in ["''", "'10'", "'1x'", "'h'", "true"] {
+				let rendered = render(&format!("_.duration.parse({invalid})"));
+
+				assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+			}
+		}
+	}
+
+	mod http {
+		use super::*;
+
+		use std::io::{Read, Write};
+		use std::net::TcpListener;
+		use std::sync::atomic::{AtomicUsize, Ordering};
+		use std::sync::Arc;
+		use std::thread;
+		use std::time::Duration;
+
+		use kct_compiler::{Context, Http};
+
+		/// Answers every request with the body, counting how many came
+		fn serve(body: &'static str) -> (String, Arc<AtomicUsize>) {
+			answer(format!(
+				"HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+				body.len()
+			))
+		}
+
+		/// Redirects every request to the location, counting how many came
+		fn redirect(location: &str) -> (String, Arc<AtomicUsize>) {
+			answer(format!(
+				"HTTP/1.1 302 Found\r\nLocation: {location}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+			))
+		}
+
+		fn answer(response: String) -> (String, Arc<AtomicUsize>) {
+			let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+			let address = format!("http://{}", listener.local_addr().unwrap());
+			let hits = Arc::new(AtomicUsize::new(0));
+			let counter = hits.clone();
+
+			thread::spawn(move || {
+				for stream in listener.incoming() {
+					let mut stream = stream.unwrap();
+					let mut request = [0; 1024];
+					let _ = stream.read(&mut request);
+
+					counter.fetch_add(1, Ordering::SeqCst);
+
+					stream.write_all(response.as_bytes()).unwrap();
+				}
+			});
+
+			(address, hits)
+		}
+
+		fn render(url: &str, http: Http) -> Result<Value, Error> {
+			render_declaring(url, http, &["network"], false)
+		}
+
+		fn render_declaring(
+			url: &str,
+			http: Http,
+			capabilities: &[&str],
+			offline: bool,
+		) -> Result<Value, Error> {
+			let main = format!("local _ = import 'kct.libsonnet'; _.http.get('{url}')");
+			let spec = declaring(capabilities);
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", &main), ("kcp.json", &spec)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let context = Context::builder()
+				.root(package.root.clone())
+				.http(http)
+				.offline(offline)
+				.build()
+				.unwrap();
+
+			let input = package.example.clone();
+			package.compile_in(&context, input)
+		}
+
+		#[test]
+		fn is_disabled_by_default() {
+			let (address, hits) = serve("{}");
+
+			let rendered = render(&address, Http::default());
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+			assert_eq!(hits.load(Ordering::SeqCst), 0);
+		}
+
+		#[test]
+		fn needs_the_capability() {
+			let (address, hits) = serve("{}");
+			let http = Http {
+				allow: vec![address.clone()],
+				..Default::default()
+			};
+
+			let rendered = render_declaring(&address, http, &[], false);
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+			assert_eq!(hits.load(Ordering::SeqCst), 0);
+		}
+
+		#[test]
+		fn requests_only_allowed_urls() {
+			let (address, hits) = serve("keys");
+			let http = Http {
+				allow: vec![format!("{address}/public/")],
+				..Default::default()
+			};
+
+			let allowed = render(&format!("{address}/public/jwks"), http.clone());
+			let denied = render(&format!("{address}/private/jwks"), http);
+
+			assert_eq!(allowed.unwrap(), json!("keys"));
+			assert_matches!(denied.unwrap_err(), Error::Compilation(_));
+			assert_eq!(hits.load(Ordering::SeqCst), 1);
+		}
+
+		#[test]
+		fn allows_paths_by_whole_segments() {
+			let (address, hits) = serve("keys");
+			let http = Http {
+				allow: vec![format!("{address}/api")],
+				..Default::default()
+			};
+
+			let allowed = render(&format!("{address}/api/jwks"), http.clone());
+			let denied = render(&format!("{address}/apiary"), http);
+
+			assert_eq!(allowed.unwrap(), json!("keys"));
+			assert_matches!(denied.unwrap_err(), Error::Compilation(_));
+			assert_eq!(hits.load(Ordering::SeqCst), 1);
+		}
+
+		#[test]
+		fn follows_only_allowed_redirects() {
+			let (target, reached) = serve("keys");
+			let (redirecting, _) = redirect(&format!("{target}/jwks"));
+			let allowed = Http {
+				allow: vec![redirecting.clone(), target.clone()],
+				..Default::default()
+			};
+			let denied = Http {
+				allow: vec![redirecting.clone()],
+				..Default::default()
+			};
+
+			let followed = render(&redirecting, allowed);
+			let refused = render(&redirecting, denied);
+
+			assert_eq!(followed.unwrap(), json!("keys"));
+			assert_matches!(refused.unwrap_err(), Error::Compilation(_));
+			assert_eq!(reached.load(Ordering::SeqCst), 1);
+		}
+
+		#[test]
+		fn reuses_cached_responses() {
+			let (address, hits) = serve("keys");
+			let cache = testing::dir::tmp();
+			let http = |ttl| Http {
+				allow: vec![address.clone()],
+				cache: Some(cache.path().to_path_buf()),
+				ttl: Duration::from_secs(ttl),
+			};
+
+			render(&address, http(60)).unwrap();
+			render(&address, http(60)).unwrap();
+			assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+			render(&address, http(0)).unwrap();
+			assert_eq!(hits.load(Ordering::SeqCst), 2);
+		}
+
+		#[test]
+		fn uses_only_cached_responses_offline() {
+			let (address, hits) = serve("keys");
+			let cache = testing::dir::tmp();
+			let http = Http {
+				allow: vec![address.clone()],
+				cache: Some(cache.path().to_path_buf()),
+				ttl: Duration::from_secs(0),
+			};
+
+			render(&format!("{address}/cached"), http.clone()).unwrap();
+			let cached = render_declaring(
+				&format!("{address}/cached"),
+				http.clone(),
+				&["network"],
+				true,
+			);
+			let uncached =
+				render_declaring(&format!("{address}/uncached"), http, &["network"], true);
+
+			assert_eq!(cached.unwrap(), json!("keys"));
+			assert_matches!(uncached.unwrap_err(), Error::Compilation(_));
+			assert_eq!(hits.load(Ordering::SeqCst), 1);
+		}
+	}
+
+	mod lookup {
+		use super::*;
+
+		use std::sync::{Arc, Mutex};
+
+		use kct_compiler::Context;
+		use kct_helper::lookup::{Finder, Query};
+
+		#[derive(Default)]
+		struct Fake(Mutex<Vec<Query>>);
+
+		impl Finder for Fake {
+			fn find(&self, query: &Query) -> anyhow::Result<Option<Value>> {
+				self.0.lock().unwrap().push(query.clone());
+
+				match query.name.as_deref() {
+					Some("broken") => Err(anyhow::anyhow!("forbidden")),
+					Some("missing") => Ok(None),
+					Some(name) => Ok(Some(
+						json!({"kind": query.kind, "metadata": {"name": name}}),
+					)),
+					None => Ok(Some(json!([]))),
+				}
+			}
+		}
+
+		fn render(expression: &str, fake: Option<Arc<Fake>>) -> Result<Value, Error> {
+			render_declaring(expression, fake, &["lookup"], false)
+		}
+
+		fn render_declaring(
+			expression: &str,
+			fake: Option<Arc<Fake>>,
+			capabilities: &[&str],
+			offline: bool,
+		) -> Result<Value, Error> {
+			let main = format!("local _ = import 'kct.libsonnet'; {expression}");
+			let spec = declaring(capabilities);
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", &main), ("kcp.json", &spec)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let context = Context::builder()
+				.root(package.root.clone())
+				.offline(offline);
+			let context = match fake {
+				Some(fake) => context.lookup(fake),
+				None => context,
+			};
+
+			let input = package.example.clone();
+			package.compile_in(&context.build().unwrap(), input)
+		}
+
+		#[test]
+		fn is_null_without_cluster() {
+			let rendered = render("_.lookup('v1', 'Secret', 'default', 'ca')", None);
+
+			assert_eq!(rendered.unwrap(), Value::Null);
+		}
+
+		#[test]
+		fn finds_live_objects() {
+			let fake = Arc::new(Fake::default());
+
+			let rendered = render(
+				"[_.lookup('v1', 'Secret', 'default', 'ca').metadata.name, _.lookup('v1', 'Secret', 'default', 'missing'), _.lookup('apps/v1', 'Deployment', '')]",
+				Some(fake.clone()),
+			);
+
+			assert_eq!(rendered.unwrap(), json!(["ca", null, []]));
+
+			let queries = fake.0.lock().unwrap().clone();
+			assert_eq!(
+				queries[2],
+				Query {
+					api_version: String::from("apps/v1"),
+					kind: String::from("Deployment"),
+					namespace: None,
+					name: None,
+				}
+			);
+		}
+
+		#[test]
+		fn needs_the_capability() {
+			let fake = Arc::new(Fake::default());
+
+			let rendered = render_declaring(
+				"_.lookup('v1', 'Secret', 'default', 'ca')",
+				Some(fake.clone()),
+				&[],
+				false,
+			);
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+			assert!(fake.0.lock().unwrap().is_empty());
+		}
+
+		#[test]
+		fn fails_when_the_cluster_does() {
+			let fake = Arc::new(Fake::default());
+
+			let rendered = render("_.lookup('v1', 'Secret', 'default', 'broken')", Some(fake));
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+
+		#[test]
+		fn fails_offline() {
+			let expression = "_.lookup('v1', 'Secret', 'default', 'ca')";
+
+			let rendered = render_declaring(expression, None, &["lookup"], true);
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod jsonpath {
+		use super::*;
+
+		#[test]
+		fn queries_values() {
+			let rendered = render(
+				"local crd = { spec: { versions: [{ name: 'v1', served: true }, { name: 'v2', served: false }] } }; [_.jsonpath(crd, '$.spec.versions[?@.served == true].name'), _.jsonpath(crd, '$..name'), _.jsonpath(crd, '$.missing')]",
+			);
+
+			assert_eq!(rendered.unwrap(), json!([["v1"], ["v1", "v2"], []]));
+		}
+
+		#[test]
+		fn fails_on_invalid_paths() {
+			let rendered = render("_.jsonpath({}, 'spec[')");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
+
+	mod patch {
+		use super::*;
+
+		#[test]
+		fn merges() {
+			let rendered = render("_.merge({ a: { b: 1, c: 2 } }, { a: { c: null, d: 3 } })");
+
+			assert_eq!(rendered.unwrap(), json!({"a": {"b": 1, "d": 3}}));
+		}
+
+		#[test]
+		fn patches() {
+			let rendered = render(
+				"_.patch({ list: [1] }, [{ op: 'add', path: '/list/-', value: 2 }, { op: 'add', path: '/a', value: true }])",
+			);
+
+			assert_eq!(rendered.unwrap(), json!({"list": [1, 2], "a": true}));
+		}
+
+		#[test]
+		fn fails_on_invalid_patches() {
+			let rendered = render("_.patch({}, [{ op: 'remove', path: '/a' }])");
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+
+		#[test]
+		fn merges_strategically() {
+			let rendered = render(
+				"_.strategicMerge({ containers: [{ name: 'app', image: 'a' }], rules: [{ host: 'a' }] }, { containers: [{ name: 'app', args: ['x'] }], rules: [{ host: 'a', tls: true }] }, { rules: 'host' })",
+			);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!({
+					"containers": [{"name": "app", "image": "a", "args": ["x"]}],
+					"rules": [{"host": "a", "tls": true}],
+				})
+			);
+		}
 	}
 
-	#[test]
-	fn request_schema_for_input() {
-		let (package, _dir) = package(vec![], vec!["schema.json"]);
+	mod names {
+		use super::*;
 
-		assert_matches!(package.unwrap_err(), Error::NoSchema)
-	}
+		#[test]
+		fn sanitizes_names() {
+			let rendered = render(
+				"local n = _.names; [n.label('My_App v1.2!'), n.dns1035('1st-Service'), n.subdomain('-Api..Example.COM-'), n.labelValue('Hello World_1.0.')]",
+			);
 
-	#[test]
-	fn needs_a_main_file() {
-		let (package, _dir) = package(vec![], vec!["templates/main.jsonnet"]);
+			assert_eq!(
+				rendered.unwrap(),
+				json!([
+					"my-app-v1-2",
+					"st-service",
+					"api.example.com",
+					"Hello-World_1.0"
+				])
+			);
+		}
 
-		assert_matches!(package.unwrap_err(), Error::NoMain);
-	}
-}
+		#[test]
+		fn truncates_with_a_stable_suffix() {
+			let long = "a".repeat(70);
+			let rendered = render(&format!(
+				"local n = _.names; [n.label('{long}'), n.label('{long}'), n.label('{long}b'), n.label('release-package', 12)]"
+			));
 
-mod compile {
-	use super::*;
+			let rendered = rendered.unwrap();
+			let names: Vec<&str> = rendered
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|name| name.as_str().unwrap())
+				.collect();
 
-	mod input {
-		use super::*;
+			assert_eq!(names[0].len(), 63);
+			assert!(names[0].starts_with(&"a".repeat(54)));
+			assert_eq!(names[0], names[1]);
+			assert_ne!(names[0], names[2]);
+			assert_eq!(names[3].len(), 12);
+			assert!(names[3].starts_with("rel-"));
+		}
 
 		#[test]
-		fn renders_with_null() {
-			let (package, _dir) = package(
-				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
-				vec!["example.json", "schema.json"],
-			);
-			let package = package.unwrap();
+		fn allows_empty_label_values() {
+			let rendered = render("_.names.labelValue('!!!')");
 
-			let rendered = package.compile(None, None);
+			assert_eq!(rendered.unwrap(), json!(""));
+		}
 
-			assert_eq!(rendered.unwrap(), Value::Null);
+		#[test]
+		fn fails_without_valid_characters() {
+			for invalid in [
+				"label('!!!')",
+				"dns1035('123')",
+				"subdomain('...')",
+				"label('long-name', 5)",
+			] {
+				let rendered = render(&format!("_.names.{invalid}"));
+
+				assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+			}
 		}
+	}
+
+	mod quantity {
+		use super::*;
 
 		#[test]
-		fn doesnt_merge_input_with_defaults() {
-			let input: Value = testing::json(
-				r#"{ "database": { "port": 5432, "host": "localhost", "credentials": { "user": "admin", "pass": "admin" } } }"#,
+		fn computes_aggregates() {
+			let rendered = render(
+				"local q = _.quantity; [q.add('1Gi', '512Mi'), q.sub('1', '250m'), q.multiply('500m', 3), q.multiply('1Gi', 1.5)]",
 			);
 
-			let (package, _dir) = package(
-				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
-				vec![],
+			assert_eq!(
+				rendered.unwrap(),
+				json!(["1536Mi", "750m", "1500m", "1536Mi"])
 			);
-			let package = package.unwrap();
+		}
 
-			let rendered = package.compile(Some(input.clone()), None);
+		#[test]
+		fn parses_and_formats() {
+			let rendered = render(
+				"local q = _.quantity; [q.parse('1500m'), q.parse('1Ki'), q.format('2000m'), q.format(0.25)]",
+			);
 
-			assert_eq!(rendered.unwrap(), input);
+			assert_eq!(rendered.unwrap(), json!([1.5, 1024, "2", "250m"]));
 		}
 
 		#[test]
-		#[should_panic(expected = "input provided is invalid")]
-		fn validate_input() {
-			let input: Value = testing::json(r#"{ "database": null }"#);
+		fn compares() {
+			let rendered =
+				render("[_.quantity.compare('1Gi', '1G'), _.quantity.compare('1', '1000m')]");
 
-			let (root, _dir) = package(
-				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
-				vec![],
-			);
-			let package = root.unwrap();
+			assert_eq!(rendered.unwrap(), json!([1, 0]));
+		}
 
-			let rendered = package.compile(Some(input), None).unwrap_err();
+		#[test]
+		fn fails_on_invalid_quantities() {
+			let rendered = render("_.quantity.parse('1.5Xi')");
 
-			match rendered {
-				Error::Compilation(err) => panic_any(err.to_string()),
-				_ => panic!("It should be a validation issue!"),
-			}
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
 		}
 	}
 
-	mod jsonnet {
+	mod read_file {
 		use super::*;
 
 		#[test]
-		#[should_panic(expected = "manifest function")]
-		fn disallows_top_level_functions() {
+		fn reads_raw_contents() {
 			let (package, _dir) = package(
 				vec![(
 					"templates/main.jsonnet",
-					"function(input = null, files = null) { input: input }",
+					"(import 'kct.libsonnet').readFile('files/database.toml')",
 				)],
 				vec![],
 			);
+			let rendered = compile_with_example(package.unwrap(), None);
 
-			let package = package.unwrap();
-			let rendered = compile_with_example(package, None).unwrap_err();
-
-			match rendered {
-				Error::Compilation(err) => panic_any(err.to_string()),
-				_ => panic!("It should be a compilation issue!"),
-			}
+			let contents = Fixture::contents("files/database.toml");
+			assert_eq!(rendered.unwrap(), Value::String(contents));
 		}
 
 		#[test]
-		fn renders_imports() {
+		fn encodes_as_base64() {
 			let (package, _dir) = package(
 				vec![
 					(
 						"templates/main.jsonnet",
-						"local valid = import './input/entry.jsonnet'; valid",
+						"(import 'kct.libsonnet').readFile('files/raw.bin', 'base64')",
 					),
-					("templates/input/entry.jsonnet", "import '../input.jsonnet'"),
-					("templates/input.jsonnet", "(import 'kct.libsonnet').input"),
+					("files/raw.bin", "kct"),
 				],
 				vec![],
 			);
+			let rendered = compile_with_example(package.unwrap(), None);
 
-			let package = package.unwrap();
-			let input = package.example.clone().unwrap();
-			let rendered = compile_with_example(package, None);
-
-			assert_eq!(rendered.unwrap(), input);
+			assert_eq!(rendered.unwrap(), json!("a2N0"));
 		}
 
 		#[test]
-		#[should_panic(expected = "can't resolve input.jsonnet")]
-		fn doesnt_include_templates_on_imports() {
+		#[should_panic(expected = "is outside the package")]
+		fn refuses_paths_outside_package() {
 			let (package, _dir) = package(
-				vec![
-					(
-						"templates/main.jsonnet",
-						"local valid = import './input/entry.jsonnet'; valid",
-					),
-					("templates/input/entry.jsonnet", "import 'input.jsonnet'"),
-					("templates/input.jsonnet", "(import 'kct.libsonnet').input"),
-				],
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').readFile('../')",
+				)],
 				vec![],
 			);
-
-			let package = package.unwrap();
-			let rendered = compile_with_example(package, None).unwrap_err();
+			let rendered = compile_with_example(package.unwrap(), None).unwrap_err();
 
 			match rendered {
 				Error::Compilation(err) => panic_any(err.to_string()),
 				_ => panic!("It should be a compilation issue!"),
 			}
 		}
+	}
+
+	mod import_bin {
+		use super::*;
 
 		#[test]
-		fn includes_vendor_for_imports() {
+		fn encodes_as_base64() {
 			let (package, _dir) = package(
 				vec![
 					(
 						"templates/main.jsonnet",
-						"local valid = import 'ksonnet/ksonnet.beta.4/k8s.libjsonnet'; valid",
-					),
-					(
-						"vendor/ksonnet/ksonnet.beta.4/k8s.libjsonnet",
-						"(import 'kct.libsonnet').input",
+						"(import 'kct.libsonnet').importBin('logo.png')",
 					),
+					("lib/logo.png", "kct"),
 				],
 				vec![],
 			);
+			let rendered = compile_with_example(package.unwrap(), None);
 
-			let package = package.unwrap();
-			let input = package.example.clone().unwrap();
-			let rendered = compile_with_example(package, None);
-
-			assert_eq!(rendered.unwrap(), input);
+			assert_eq!(rendered.unwrap(), json!("a2N0"));
 		}
 
 		#[test]
-		fn includes_lib_for_aliasing() {
+		fn resolves_as_imports() {
+			let spec = json!({
+				"name": "fixture",
+				"version": "0.0.0",
+				"aliases": { "assets": "vendor/github.com/org/assets" }
+			});
 			let (package, _dir) = package(
 				vec![
+					("kcp.json", &spec.to_string()),
 					(
 						"templates/main.jsonnet",
-						"local valid = import 'k.libjsonnet'; valid",
-					),
-					(
-						"vendor/ksonnet/ksonnet.beta.4/k8s.libjsonnet",
-						"(import 'kct.libsonnet').input",
-					),
-					(
-						"lib/k.libjsonnet",
-						"import 'ksonnet/ksonnet.beta.4/k8s.libjsonnet'",
+						"local _ = import 'kct.libsonnet'; [_.importBin('icon.ico'), _.importBin('assets/logo.png')]",
 					),
+					("templates/icon.ico", "ico"),
+					("vendor/github.com/org/assets/logo.png", "png"),
 				],
 				vec![],
 			);
+			let rendered = compile_with_example(package.unwrap(), None);
 
-			let package = package.unwrap();
-			let input = package.example.clone().unwrap();
-			let rendered = compile_with_example(package, None);
-
-			assert_eq!(rendered.unwrap(), input);
+			assert_eq!(rendered.unwrap(), json!(["aWNv", "cG5n"]));
 		}
-	}
-
-	mod file_templates {
-		use super::*;
 
 		#[test]
-		fn renders_templates() {
+		#[should_panic(expected = "can't resolve missing.png")]
+		fn fails_when_not_found() {
 			let (package, _dir) = package(
 				vec![(
 					"templates/main.jsonnet",
-					"(import 'kct.libsonnet').files('database.toml')",
+					"(import 'kct.libsonnet').importBin('missing.png')",
 				)],
 				vec![],
 			);
-			let package = package.unwrap();
-			let input = package.example.clone().unwrap();
-			let template = testing::template(&Fixture::contents("files/database.toml"), &input);
-			let rendered = package.compile(Some(input), None);
+			let rendered = compile_with_example(package.unwrap(), None).unwrap_err();
 
-			assert_eq!(rendered.unwrap(), Value::String(template));
+			match rendered {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
 		}
+	}
 
-		#[test]
-		fn renders_multiple_templates() {
+	// Faked with shell scripts
+	#[cfg(unix)]
+	mod kustomize {
+		use super::*;
+
+		use std::env;
+		use std::fs;
+		use std::os::unix::fs::PermissionsExt;
+		use std::sync::OnceLock;
+
+		const OBJECTS: &str = "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: a\n---\napiVersion: v1\nkind: Secret\nmetadata:\n  name: b\n";
+
+		/// Points kustomize to a script printing the objects.yaml within the
+		/// directory, shared by every test as the variable is global
+		fn fake() {
+			static FAKE: OnceLock<TempDir> = OnceLock::new();
+
+			FAKE.get_or_init(|| {
+				let dir = kct_testing::dir::tmp();
+				let script = dir.path().join("kustomize");
+				fs::write(&script, "#!/bin/sh\ncat \"$1/objects.yaml\" || exit 1\n").unwrap();
+				fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+				env::set_var("KCT_KUSTOMIZE", &script);
+
+				dir
+			});
+		}
+
+		fn render(expression: &str, declared: &[&str], objects: &str) -> Result<Value, Error> {
+			fake();
+
+			let main = format!("local _ = import 'kct.libsonnet'; {expression}");
+			let spec = json!({ "name": "fixture", "version": "0.0.0", "kustomize": declared });
+			let spec = spec.to_string();
 			let (package, _dir) = package(
-				vec![(
-					"templates/main.jsonnet",
-					"(import 'kct.libsonnet').files('**/*.toml')",
-				)],
+				vec![
+					("templates/main.jsonnet", &main),
+					("kcp.json", &spec),
+					("legacy/objects.yaml", objects),
+					("legacy/kustomization.yaml", "resources: []"),
+				],
 				vec![],
 			);
-			let package = package.unwrap();
-			let input = package.example.clone().unwrap();
 
-			let db_template = testing::template(&Fixture::contents("files/database.toml"), &input);
-			let evt_template =
-				testing::template(&Fixture::contents("files/events/settings.toml"), &input);
+			compile_with_example(package.unwrap(), None)
+		}
 
-			let rendered = compile_with_example(package, None);
+		#[test]
+		fn builds_declared_kustomizations() {
+			let rendered = render("_.kustomize('legacy')", &["legacy"], OBJECTS);
 
 			assert_eq!(
 				rendered.unwrap(),
-				Value::Array(vec![
-					Value::String(db_template),
-					Value::String(evt_template),
+				json!([
+					{ "apiVersion": "v1", "kind": "ConfigMap", "metadata": { "name": "a" } },
+					{ "apiVersion": "v1", "kind": "Secret", "metadata": { "name": "b" } },
 				])
 			);
 		}
 
 		#[test]
-		#[should_panic(expected = "Unable to compile templates")]
-		fn fails_on_invalid_templates() {
-			let (package, _dir) = package(
-				vec![(
-					"templates/main.jsonnet",
-					"(import 'kct.libsonnet').files('invalid.ini')",
-				)],
-				vec![],
-			);
-			let package = package.unwrap();
+		fn needs_the_declaration() {
+			let rendered = render("_.kustomize('legacy')", &[], OBJECTS);
 
-			let rendered = compile_with_example(package, None).unwrap_err();
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
 
-			match rendered {
-				Error::Compilation(err) => panic_any(err.to_string()),
-				_ => panic!("It should be a compilation issue!"),
-			}
+		#[test]
+		fn stays_within_the_package() {
+			let rendered = render("_.kustomize('../legacy')", &["../legacy"], OBJECTS);
+
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
 		}
 
 		#[test]
-		fn compiles_templates_with_empty_input() {
-			let (package, _dir) = package(
-				vec![(
-					"templates/main.jsonnet",
-					"(import 'kct.libsonnet').files('no-params.txt')",
-				)],
-				vec!["example.json", "schema.json"],
-			);
-			let package = package.unwrap();
+		fn fails_along_the_build() {
+			let rendered = render("_.kustomize('templates')", &["templates"], OBJECTS);
 
-			let template = testing::template(
-				&Fixture::contents("files/no-params.txt"),
-				&Value::Object(Map::new()),
-			);
+			assert_matches!(rendered.unwrap_err(), Error::Compilation(_));
+		}
+	}
 
-			let rendered = package.compile(None, None);
+	// Faked with shell scripts
+	#[cfg(unix)]
+	mod cue {
+		use super::*;
 
-			assert_eq!(rendered.unwrap(), Value::String(template));
+		use std::env;
+		use std::fs;
+		use std::os::unix::fs::PermissionsExt;
+		use std::sync::OnceLock;
+
+		use kct_compiler::Error as CompilerError;
+
+		/// Points cue to a script requiring every line of the files to be
+		/// within the input read from STDIN, shared by every test as the
+		/// variable is global
+		fn fake() {
+			static FAKE: OnceLock<TempDir> = OnceLock::new();
+
+			FAKE.get_or_init(|| {
+				let dir = kct_testing::dir::tmp();
+				let script = dir.path().join("cue");
+				let contents = r#"#!/bin/sh
+shift 2
+input=$(cat)
+for file; do
+	[ "$file" = "json:" ] && break
+	while read -r line || [ -n "$line" ]; do
+		printf '%s' "$input" | grep -qF "$line" || { echo "missing $line" >&2; exit 1; }
+	done < "$file"
+done
+"#;
+				fs::write(&script, contents).unwrap();
+				fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+				env::set_var("KCT_CUE", &script);
+
+				dir
+			});
 		}
 
 		#[test]
-		#[should_panic(expected = "No files folder to search for templates")]
-		fn fails_on_empty_templates_folder() {
-			let (package, _dir) = package(vec![], vec!["files"]);
+		fn vets_inputs() {
+			fake();
+
+			let (package, _dir) = package(
+				vec![("schema.cue", r#""host":"postgres""#)],
+				vec!["schema.json"],
+			);
 			let package = package.unwrap();
 
-			let rendered = compile_with_example(package, None).unwrap_err();
+			assert!(package.schema.is_none());
+			assert!(compile_with_example(package, None).is_ok());
+		}
 
-			match rendered {
-				Error::Compilation(err) => panic_any(err.to_string()),
-				_ => panic!("It should be a compilation issue!"),
-			}
+		#[test]
+		fn refuses_what_it_rejects() {
+			fake();
+
+			let (package, _dir) = package(vec![("schema.cue", r#""host":"mysql""#)], vec![]);
+			let compiled = compile_with_example(package.unwrap(), None);
+
+			assert_matches!(
+				compiled.unwrap_err(),
+				Error::Compilation(CompilerError::InvalidInput(reason)) if reason.contains("missing")
+			);
 		}
 
 		#[test]
-		#[should_panic(expected = "No template found for glob")]
-		fn fails_on_not_found_template() {
-			let (package, _dir) = package(
-				vec![(
-					"templates/main.jsonnet",
-					"(import 'kct.libsonnet').files('*.json')",
-				)],
-				vec![],
+		fn unifies_with_the_base() {
+			fake();
+
+			let (_base, base) = package(vec![("schema.cue", r#""host":"mysql""#)], vec![]);
+			let spec = json!({ "name": "child", "version": "0.0.0", "extends": "vendor/base" });
+			let spec = spec.to_string();
+			let (package, _dir) = extending(
+				&base,
+				vec![("kcp.json", &spec), ("schema.cue", r#""port":5432"#)],
+				vec!["schema.json"],
 			);
 			let package = package.unwrap();
 
-			let rendered = compile_with_example(package, None).unwrap_err();
+			assert_eq!(package.constraints().len(), 2);
+			assert_matches!(
+				compile_with_example(package, None).unwrap_err(),
+				Error::Compilation(CompilerError::InvalidInput(_))
+			);
+		}
 
-			match rendered {
-				Error::Compilation(err) => panic_any(err.to_string()),
-				_ => panic!("It should be a compilation issue!"),
-			}
+		#[test]
+		fn needs_an_example() {
+			let (package, _dir) = package(
+				vec![("schema.cue", "")],
+				vec!["schema.json", "example.json"],
+			);
+
+			assert_matches!(package.unwrap_err(), Error::NoExample);
 		}
 	}
 
@@ -365,6 +4612,7 @@ mod compile {
 		fn prefixes_installation_name() {
 			let release = Release {
 				name: String::from("rc"),
+				..Default::default()
 			};
 			let (package, _dir) = package(
 				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').name")],
@@ -382,6 +4630,7 @@ mod compile {
 		fn is_injected_on_global() {
 			let release = Release {
 				name: String::from("rc"),
+				..Default::default()
 			};
 			let (package, _dir) = package(
 				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').release")],
@@ -394,6 +4643,27 @@ mod compile {
 
 			assert_eq!(rendered.unwrap(), json);
 		}
+
+		#[test]
+		fn exposes_target() {
+			let release = Release {
+				name: String::from("rc"),
+				target: Some(String::from("east")),
+				..Default::default()
+			};
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').release.target",
+				)],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = compile_with_example(package, Some(release));
+
+			assert_eq!(rendered.unwrap(), json!("east"));
+		}
 	}
 
 	mod package {
@@ -431,9 +4701,387 @@ mod compile {
 		}
 	}
 
+	mod schema {
+		use super::*;
+
+		#[test]
+		fn is_injected_on_global() {
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').schema")],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let json = testing::json(&Fixture::contents("schema.json"));
+			let rendered = compile_with_example(package, None);
+
+			assert_eq!(rendered.unwrap(), json);
+		}
+
+		#[test]
+		fn is_null_without_schema() {
+			let (package, _dir) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').schema")],
+				vec!["schema.json", "example.json"],
+			);
+			let rendered = package.unwrap().compile(None, None);
+
+			assert_eq!(rendered.unwrap(), Value::Null);
+		}
+
+		fn refusal(schema: Value, input: Value) -> String {
+			let schema = schema.to_string();
+			let (package, _dir) = package(vec![("schema.json", &schema)], vec![]);
+
+			package
+				.unwrap()
+				.compile(Some(input), None)
+				.unwrap_err()
+				.to_string()
+		}
+
+		#[test]
+		fn explains_missing_fields() {
+			let schema = testing::json(&Fixture::contents("schema.json"));
+			let input = json!({ "database": { "host": "mysql", "port": "3306" } });
+
+			assert_eq!(
+				refusal(schema, input),
+				"The input provided is invalid: input doesn't match your schema: input.database.credentials is required; input.database.port: The value must be integer"
+			);
+		}
+
+		#[test]
+		fn explains_conditions() {
+			let schema = json!({
+				"type": "object",
+				"properties": { "tls": { "type": "boolean" }, "cert": { "type": "string" } },
+				"if": { "properties": { "tls": { "const": true } }, "required": ["tls"] },
+				"then": { "required": ["cert"] },
+				"else": { "not": { "required": ["cert"] } }
+			});
+
+			assert!(refusal(schema.clone(), json!({ "tls": true })).ends_with(
+				"input matches the if condition (tls is true), so its then applies: input.cert is required"
+			));
+			assert!(refusal(schema, json!({ "cert": "pem" })).ends_with(
+				"input doesn't match the if condition (tls is true), so its else applies: input: Not condition is not met"
+			));
+		}
+
+		fn storage() -> Value {
+			json!({
+				"type": "object",
+				"properties": {
+					"storage": {
+						"oneOf": [
+							{
+								"title": "S3",
+								"properties": { "kind": { "const": "s3" }, "bucket": { "type": "string" } },
+								"required": ["kind", "bucket"]
+							},
+							{
+								"title": "GCS",
+								"properties": { "kind": { "const": "gcs" }, "project": { "type": "string" } },
+								"required": ["kind", "project"]
+							}
+						]
+					}
+				}
+			})
+		}
+
+		#[test]
+		fn explains_the_branch_picked_by_discriminators() {
+			let input = json!({ "storage": { "kind": "s3", "project": "kct" } });
+
+			assert!(refusal(storage(), input).ends_with(
+				"input.storage is oneOf[0] (S3) by its kind, but input.storage.bucket is required"
+			));
+		}
+
+		#[test]
+		fn explains_unknown_discriminators() {
+			let input = json!({ "storage": { "kind": "azure" } });
+
+			assert!(refusal(storage(), input).ends_with(
+				r#"input.storage.kind is "azure", but the branches of the oneOf of input.storage take "s3", "gcs""#
+			));
+		}
+
+		#[test]
+		fn explains_branches_matching_at_once() {
+			let schema = json!({
+				"type": "object",
+				"properties": {
+					"port": { "oneOf": [{ "type": "integer" }, { "minimum": 1024 }] }
+				}
+			});
+
+			assert!(refusal(schema, json!({ "port": 8080 })).ends_with(
+				"input.port matches oneOf[0] and oneOf[1], but should match only one of its oneOf"
+			));
+		}
+
+		#[test]
+		fn explains_every_branch_failing() {
+			let schema = json!({
+				"type": "object",
+				"properties": {
+					"replicas": { "anyOf": [{ "type": "integer" }, { "type": "string", "pattern": "^[0-9]+$" }] }
+				}
+			});
+
+			assert!(refusal(schema, json!({ "replicas": "many" })).ends_with(
+				"input.replicas matches none of its anyOf: anyOf[0] fails as input.replicas: The value must be integer; anyOf[1] fails as input.replicas: Pattern condition is not met"
+			));
+		}
+
+		fn formats() -> Value {
+			json!({
+				"type": "object",
+				"properties": {
+					"name": { "type": "string", "format": "dns-1123-label" },
+					"memory": { "type": "string", "format": "quantity" },
+					"timeout": { "type": "string", "format": "duration" },
+					"image": { "type": "string", "format": "image-reference" },
+					"schedule": { "type": "string", "format": "cron" }
+				}
+			})
+		}
+
+		#[test]
+		fn accepts_kubernetes_formats() {
+			let schema = formats().to_string();
+			let (package, _dir) = package(
+				vec![
+					("schema.json", &schema),
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+				],
+				vec![],
+			);
+			let input = json!({
+				"name": "my-app",
+				"memory": "1.5Gi",
+				"timeout": "1h30m",
+				"image": "registry.example.com:5000/team/app:1.2.3@sha256:0123456789abcdef0123456789abcdef",
+				"schedule": "*/15 0-6 1,15 jan-jun mon-fri"
+			});
+
+			let rendered = package.unwrap().compile(Some(input.clone()), None);
+
+			assert_eq!(rendered.unwrap(), input);
+		}
+
+		#[test]
+		fn refuses_invalid_labels() {
+			assert!(refusal(formats(), json!({ "name": "My_App" })).ends_with(
+				"input.name: My_App should be lowercase letters, digits, and dashes, starting and ending with a letter or a digit"
+			));
+			assert!(refusal(formats(), json!({ "name": "a".repeat(64) }))
+				.ends_with("is longer than 63 characters"));
+		}
+
+		#[test]
+		fn refuses_invalid_quantities() {
+			assert!(refusal(formats(), json!({ "memory": "lots" }))
+				.ends_with("input.memory: lots isn't a quantity, as 500m or 1.5Gi"));
+		}
+
+		#[test]
+		fn refuses_invalid_durations() {
+			assert!(refusal(formats(), json!({ "timeout": "90 minutes" }))
+				.ends_with("input.timeout: 90 minutes isn't a duration, as 1h30m"));
+		}
+
+		#[test]
+		fn refuses_invalid_images() {
+			for image in ["Nginx:latest", "nginx:", "nginx@sha256:short", "-nginx"] {
+				assert!(
+					refusal(formats(), json!({ "image": image })).ends_with(&format!(
+						"input.image: {image} should be as [registry/]repository[:tag][@digest]"
+					))
+				);
+			}
+		}
+
+		#[test]
+		fn refuses_invalid_schedules() {
+			assert!(refusal(formats(), json!({ "schedule": "* * *" })).ends_with(
+				"input.schedule: * * * should have 5 fields, minute, hour, day of month, month, and day of week"
+			));
+			assert!(refusal(formats(), json!({ "schedule": "0 24 * * *" }))
+				.ends_with("input.schedule: 24 isn't a valid hour of 0 24 * * *"));
+			assert!(refusal(formats(), json!({ "schedule": "0 0 * * sun/0" }))
+				.ends_with("input.schedule: sun/0 isn't a valid day of week of 0 0 * * sun/0"));
+			assert!(refusal(formats(), json!({ "schedule": "@every soon" }))
+				.ends_with("input.schedule: soon of @every soon isn't a duration, as 1h30m"));
+		}
+
+		#[test]
+		fn accepts_schedule_shorthands() {
+			let schema = formats().to_string();
+
+			for schedule in ["@daily", "@every 1h", "0 0 ? * 0"] {
+				let (package, _dir) = package(
+					vec![
+						("schema.json", &schema),
+						("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					],
+					vec![],
+				);
+				let input = json!({ "schedule": schedule });
+				let rendered = package.unwrap().compile(Some(input), None);
+
+				assert!(rendered.is_ok(), "{schedule} should be accepted");
+			}
+		}
+	}
+
+	mod schema_versions {
+		use super::*;
+
+		const MAIN: &str = "local _ = import 'kct.libsonnet'; { version: _.schemaVersion, schema: _.schema.title }";
+
+		fn versioned(with: Vec<(&str, &str)>) -> (Result<Package, Error>, TempDir) {
+			let v1 = json!({
+				"title": "v1",
+				"deprecated": true,
+				"type": "object",
+				"required": ["host"],
+				"properties": { "apiVersion": { "const": "v1" }, "host": { "type": "string" } }
+			})
+			.to_string();
+			let v2 = json!({
+				"title": "v2",
+				"type": "object",
+				"required": ["database"],
+				"properties": {
+					"apiVersion": { "const": "v2" },
+					"database": { "type": "object", "properties": { "host": { "type": "string" } } }
+				}
+			})
+			.to_string();
+
+			let mut files = vec![
+				("schemas/v1.json", v1.as_str()),
+				("schemas/v2.json", v2.as_str()),
+				("templates/main.jsonnet", MAIN),
+			];
+			files.extend(with);
+
+			package(files, vec!["schema.json"])
+		}
+
+		#[test]
+		fn validates_against_the_version_of_the_input() {
+			let (package, _dir) = versioned(vec![]);
+			let package = package.unwrap();
+
+			let v1 = json!({ "apiVersion": "v1", "host": "mysql" });
+			let v2 = json!({ "apiVersion": "v2", "database": { "host": "mysql" } });
+
+			assert_eq!(
+				package.clone().compile(Some(v1), None).unwrap(),
+				json!({ "version": "v1", "schema": "v1" })
+			);
+			assert_eq!(
+				package.compile(Some(v2), None).unwrap(),
+				json!({ "version": "v2", "schema": "v2" })
+			);
+		}
+
+		#[test]
+		fn refuses_inputs_of_another_version() {
+			let (package, _dir) = versioned(vec![]);
+			let input = json!({ "apiVersion": "v2", "host": "mysql" });
+
+			assert_matches!(
+				package.unwrap().compile(Some(input), None).unwrap_err(),
+				Error::Compilation(CompilerError::InvalidInput(_))
+			);
+		}
+
+		#[test]
+		#[should_panic(expected = "input should declare its apiVersion, one of v1, v2")]
+		fn needs_the_version_of_the_input() {
+			let (package, _dir) = versioned(vec![]);
+			let input = json!({ "host": "mysql" });
+
+			let err = package.unwrap().compile(Some(input), None).unwrap_err();
+			panic_any(err.to_string());
+		}
+
+		#[test]
+		#[should_panic(expected = "input apiVersion v3 is unknown, use one of v1, v2")]
+		fn refuses_unknown_versions() {
+			let (package, _dir) = versioned(vec![]);
+			let input = json!({ "apiVersion": "v3" });
+
+			let err = package.unwrap().compile(Some(input), None).unwrap_err();
+			panic_any(err.to_string());
+		}
+
+		#[test]
+		fn is_null_without_versions() {
+			let (package, _dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').schemaVersion",
+				)],
+				vec![],
+			);
+			let rendered = compile_with_example(package.unwrap(), None);
+
+			assert_eq!(rendered.unwrap(), Value::Null);
+		}
+
+		#[test]
+		fn prefers_stable_versions() {
+			let schema = |title: &str| json!({ "title": title, "type": "object" }).to_string();
+			let (v2alpha1, v1, v1beta1) = (schema("v2alpha1"), schema("v1"), schema("v1beta1"));
+			let (package, _dir) = versioned(vec![
+				("schemas/v2alpha1.json", &v2alpha1),
+				("schemas/v1beta1.json", &v1beta1),
+				("schemas/v1.json", &v1),
+			]);
+			let package = package.unwrap();
+
+			assert_eq!(
+				package.versions.names(),
+				vec!["v2alpha1", "v1beta1", "v1", "v2"]
+			);
+			assert_eq!(
+				package.latest_schema().unwrap().value()["title"],
+				json!("v2")
+			);
+		}
+
+		#[test]
+		fn layers_versions_over_the_base() {
+			let (_base, base) = package(vec![], vec![]);
+			let spec = json!({ "name": "child", "version": "0.0.0", "extends": "vendor/base" })
+				.to_string();
+			let v1 = json!({ "properties": { "apiVersion": { "const": "v1" } } }).to_string();
+			let (package, _child) = extending(
+				&base,
+				vec![("kcp.json", &spec), ("schemas/v1.json", &v1)],
+				vec!["schema.json", "templates/main.jsonnet"],
+			);
+			let package = package.unwrap();
+			let schema = package.versions.latest().unwrap().value();
+
+			assert!(package.schema.is_none());
+			assert_eq!(schema["required"], json!(["database"]));
+			assert_eq!(schema["properties"]["apiVersion"], json!({ "const": "v1" }));
+		}
+	}
+
 	mod subpackage {
 		use super::*;
 
+		use std::fs;
+
 		use kct_helper::json::get_in;
 
 		fn subpackage(dir: &TempDir, name: &str, with: Vec<(&str, &str)>, without: Vec<&str>) {
@@ -576,6 +5224,7 @@ mod compile {
 			let name = "rc";
 			let release = Release {
 				name: String::from(name),
+				..Default::default()
 			};
 			let (root, dir) = package(
 				vec![(
@@ -630,5 +5279,196 @@ mod compile {
 
 			assert_eq!(rendered.unwrap(), result);
 		}
+
+		#[test]
+		fn share_the_libraries_they_import() {
+			let (root, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"local kct = import 'kct.libsonnet'; [kct.include('a', null), kct.include('b', null)]",
+				)],
+				vec![],
+			);
+			let shared = "{ named(name):: { name: name } }";
+			fs::write(dir.path().join("vendor/shared.libsonnet"), shared).unwrap();
+			for name in ["a", "b"] {
+				let main = format!("(import 'shared.libsonnet').named('{name}')");
+				subpackage(
+					&dir,
+					name,
+					vec![("templates/main.jsonnet", &main)],
+					vec!["schema.json", "example.json"],
+				);
+			}
+			let package = root.unwrap();
+			let rendered = compile_with_example(package, None);
+
+			let result = json!([{"name": "a"}, {"name": "b"}]);
+
+			assert_eq!(rendered.unwrap(), result);
+		}
+
+		#[test]
+		fn see_libraries_changed_between_compilations() {
+			let (root, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"(import 'kct.libsonnet').include('sub', null)",
+				)],
+				vec![],
+			);
+			let library = dir.path().join("vendor/changing.libsonnet");
+			subpackage(
+				&dir,
+				"sub",
+				vec![("templates/main.jsonnet", "import 'changing.libsonnet'")],
+				vec!["schema.json", "example.json"],
+			);
+			let package = root.unwrap();
+
+			fs::write(&library, "{ before: true }").unwrap();
+			let before = compile_with_example(package.clone(), None);
+			fs::write(&library, "{ after: 'changed' }").unwrap();
+			let after = compile_with_example(package, None);
+
+			assert_eq!(before.unwrap(), json!({"before": true}));
+			assert_eq!(after.unwrap(), json!({"after": "changed"}));
+		}
+	}
+
+	mod progress {
+		use super::*;
+
+		use std::fs;
+		use std::sync::{Arc, Mutex};
+
+		use kct_compiler::Context;
+		use kct_helper::progress::{Event, Reporter};
+
+		#[derive(Default)]
+		struct Recorder(Mutex<Vec<Event>>);
+
+		impl Reporter for Recorder {
+			fn report(&self, event: Event) {
+				self.0.lock().unwrap().push(event);
+			}
+		}
+
+		#[test]
+		fn reports_subpackages() {
+			let (root, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"local _ = import 'kct.libsonnet'; [_.include('sub', _.input), _.include('sub', _.input)]",
+				)],
+				vec![],
+			);
+			let (_sub, source) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
+				vec![],
+			);
+			let path = dir.path().join("vendor").join("sub");
+			testing::dir::mk(&path);
+			testing::dir::mv(&source.into_path(), &path);
+
+			let package = root.unwrap();
+			let recorder = Arc::new(Recorder::default());
+			let context = Context::builder()
+				.root(package.root.clone())
+				.progress(recorder.clone())
+				.build()
+				.unwrap();
+
+			let input = package.example.clone();
+			package.compile_in(&context, input).unwrap();
+
+			let name = String::from("fixture");
+			let events = recorder.0.lock().unwrap().clone();
+			assert_eq!(
+				events,
+				vec![
+					Event::Compiling {
+						package: name.clone()
+					},
+					Event::Including {
+						package: name.clone(),
+						position: 1,
+						total: 1
+					},
+					Event::Compiling {
+						package: name.clone()
+					},
+					Event::Compiled {
+						package: name.clone()
+					},
+					Event::Including {
+						package: name.clone(),
+						position: 1,
+						total: 1
+					},
+					Event::Compiling {
+						package: name.clone()
+					},
+					Event::Compiled {
+						package: name.clone()
+					},
+					Event::Compiled { package: name },
+				]
+			);
+		}
+
+		#[test]
+		fn counts_only_includable_subpackages() {
+			let (root, dir) = package(
+				vec![(
+					"templates/main.jsonnet",
+					"local _ = import 'kct.libsonnet'; _.include('sub', _.input)",
+				)],
+				vec![],
+			);
+			let (_sub, source) = package(
+				vec![("templates/main.jsonnet", "(import 'kct.libsonnet').input")],
+				vec![],
+			);
+			let vendor = dir.path().join("vendor");
+			testing::dir::mk(&vendor.join("sub"));
+			testing::dir::mv(&source.into_path(), &vendor.join("sub"));
+
+			// Vendored by the subpackage, and only shipping libs
+			for (path, spec) in [
+				(
+					"sub/vendor/inner",
+					r#"{"name": "inner", "version": "0.1.0"}"#,
+				),
+				(
+					"lib",
+					r#"{"name": "lib", "version": "0.1.0", "kind": "library"}"#,
+				),
+			] {
+				testing::dir::mk(&vendor.join(path));
+				fs::write(vendor.join(path).join("kcp.json"), spec).unwrap();
+			}
+
+			let package = root.unwrap();
+			let recorder = Arc::new(Recorder::default());
+			let context = Context::builder()
+				.root(package.root.clone())
+				.progress(recorder.clone())
+				.build()
+				.unwrap();
+
+			let input = package.example.clone();
+			package.compile_in(&context, input).unwrap();
+
+			let events = recorder.0.lock().unwrap().clone();
+			let totals: Vec<usize> = events
+				.into_iter()
+				.filter_map(|event| match event {
+					Event::Including { total, .. } => Some(total),
+					_ => None,
+				})
+				.collect();
+			assert_eq!(totals, vec![1]);
+		}
 	}
 }
@@ -0,0 +1,126 @@
+local _ = import 'main.libsonnet';
+
+local without(fields, empty) = { [k]: fields[k] for k in std.objectFields(fields) if !std.member(empty, fields[k]) };
+
+local listOf(items) = if std.isArray(items) then items else [items];
+
+local selector(name) = {
+  'app.kubernetes.io/name': name,
+  'app.kubernetes.io/instance': _.name,
+};
+
+local standard(name, component = null) = selector(name) + {
+  'app.kubernetes.io/version': _.package.version,
+  'app.kubernetes.io/managed-by': 'kct',
+} + (if component == null then {} else { 'app.kubernetes.io/component': component });
+
+local metadata(name, labels = {}, annotations = {}, namespace = null) = without({
+  name: name,
+  namespace: namespace,
+  labels: standard(name) + labels,
+  annotations: annotations,
+}, [null, {}]);
+
+local orName(value, name) = if value == null then name else value;
+
+local subject(serviceAccount, namespace) = without({
+  kind: 'ServiceAccount',
+  name: serviceAccount,
+  namespace: namespace,
+}, [null]);
+
+{
+  labels: standard,
+  selector: selector,
+  metadata: metadata,
+
+  container(name, image, ports = {}, env = {}): without({
+    name: name,
+    image: image,
+    ports: [{ name: k, containerPort: ports[k] } for k in std.objectFields(ports)],
+    env: [{ name: k, value: env[k] } for k in std.objectFields(env)],
+  }, [[]]),
+
+  deployment(name, containers, replicas = 1, labels = {}, serviceAccount = null): {
+    apiVersion: 'apps/v1',
+    kind: 'Deployment',
+    metadata: metadata(name, labels),
+    spec: {
+      replicas: replicas,
+      revisionHistoryLimit: 10,
+      selector: { matchLabels: selector(name) },
+      template: {
+        metadata: { labels: standard(name) + labels },
+        spec: without({
+          serviceAccountName: serviceAccount,
+          containers: listOf(containers),
+        }, [null]),
+      },
+    },
+  },
+
+  service(name, ports, type = 'ClusterIP', labels = {}): {
+    apiVersion: 'v1',
+    kind: 'Service',
+    metadata: metadata(name, labels),
+    spec: {
+      type: type,
+      selector: selector(name),
+      ports: [
+        { name: k, port: ports[k], targetPort: k, protocol: 'TCP' }
+        for k in std.objectFields(ports)
+      ],
+    },
+  },
+
+  configMap(name, data, labels = {}): {
+    apiVersion: 'v1',
+    kind: 'ConfigMap',
+    metadata: metadata(name, labels),
+    data: data,
+  },
+
+  secret(name, stringData, type = 'Opaque', labels = {}): {
+    apiVersion: 'v1',
+    kind: 'Secret',
+    metadata: metadata(name, labels),
+    type: type,
+    stringData: stringData,
+  },
+
+  serviceAccount(name, labels = {}): {
+    apiVersion: 'v1',
+    kind: 'ServiceAccount',
+    metadata: metadata(name, labels),
+  },
+
+  role(name, rules, labels = {}): {
+    apiVersion: 'rbac.authorization.k8s.io/v1',
+    kind: 'Role',
+    metadata: metadata(name, labels),
+    rules: rules,
+  },
+
+  roleBinding(name, role = null, serviceAccount = null, namespace = null, labels = {}): {
+    apiVersion: 'rbac.authorization.k8s.io/v1',
+    kind: 'RoleBinding',
+    metadata: metadata(name, labels),
+    roleRef: { apiGroup: 'rbac.authorization.k8s.io', kind: 'Role', name: orName(role, name) },
+    subjects: [subject(orName(serviceAccount, name), namespace)],
+  },
+
+  clusterRole(name, rules, labels = {}): {
+    apiVersion: 'rbac.authorization.k8s.io/v1',
+    kind: 'ClusterRole',
+    metadata: metadata(name, labels),
+    rules: rules,
+  },
+
+  clusterRoleBinding(name, namespace, role = null, serviceAccount = null, labels = {}): {
+    apiVersion: 'rbac.authorization.k8s.io/v1',
+    kind: 'ClusterRoleBinding',
+    metadata: metadata(name, labels),
+    roleRef: { apiGroup: 'rbac.authorization.k8s.io', kind: 'ClusterRole', name: orName(role, name) },
+    subjects: [subject(orName(serviceAccount, name), namespace)],
+  },
+}
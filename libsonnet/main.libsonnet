@@ -2,14 +2,77 @@ local input = std.extVar("kct.io/input");
 local package = std.extVar("kct.io/package");
 local release = std.extVar("kct.io/release");
 local files = std.extVar("kct.io/files");
+local filesDigest = std.extVar("kct.io/filesDigest");
 local include = std.extVar("kct.io/include");
+local schema = std.extVar("kct.io/schema");
+local schemaVersion = std.extVar("kct.io/schemaVersion");
+local readFile = std.extVar("kct.io/readFile");
+local importBin = std.extVar("kct.io/importBin");
+local semver = std.extVar("kct.io/semver");
+local regex = std.extVar("kct.io/regex");
+local time = std.extVar("kct.io/time");
+local duration = std.extVar("kct.io/duration");
+local uuid = std.extVar("kct.io/uuid");
+local base64 = std.extVar("kct.io/base64");
+local url = std.extVar("kct.io/url");
+local pem = std.extVar("kct.io/pem");
+local tls = std.extVar("kct.io/tls");
+local random = std.extVar("kct.io/random");
+local net = std.extVar("kct.io/net");
+local quantity = std.extVar("kct.io/quantity");
+local names = std.extVar("kct.io/names");
+local patching = std.extVar("kct.io/patch");
+local jsonpath = std.extVar("kct.io/jsonpath");
+local http = std.extVar("kct.io/http");
+local lookup = std.extVar("kct.io/lookup");
+local uuidFrom = std.extVar("kct.io/uuidFrom");
+local kustomize = std.extVar("kct.io/kustomize");
 
 {
 	name: if release != null then '%s-%s' % [release.name, package.name] else package.name,
 	input: input,
 	package: package,
 	release: release,
+	schema: schema,
+	schemaVersion: schemaVersion,
 	files(glob, input = input): files(glob, input),
+	filesDigest(glob, input = input): filesDigest(glob, input),
 	include(dep, input = null): include(dep, input),
+	readFile(path, encoding = 'text'): readFile(path, encoding),
+	importBin(path): importBin(path),
+	merge(target, patch): patching.merge(target, patch),
+	patch(target, operations): patching.json(target, operations),
+	strategicMerge(target, patch, mergeKeys = {}): patching.strategic(target, patch, mergeKeys),
+	jsonpath(target, path): jsonpath(target, path),
+	kustomize(path): kustomize(path),
+	http: http,
+	lookup(apiVersion, kind, namespace = null, name = null): lookup(apiVersion, kind, namespace, name),
+	semver: semver,
+	regex: regex,
+	now(): time.now(),
+	time: time,
+	duration: duration,
+	base64: base64,
+	url: url,
+	pem: pem,
+	tls: {
+		ca(cn, days = 365, seed = null): tls.ca(cn, days, seed),
+		selfSignedCert(cn, sans = [], days = 365, seed = null): tls.selfSignedCert(cn, sans, days, seed),
+		signedCert(cn, sans, ca, days = 365, seed = null): tls.signedCert(cn, sans, ca, days, seed),
+	},
+	random: {
+		password(name, length = 32): random.password(name, length),
+		string(name, length, chars): random.string(name, length, chars),
+	},
+	net: net,
+	quantity: quantity,
+	names: {
+		label(name, maxLength = 63): names.label(name, maxLength),
+		dns1035(name, maxLength = 63): names.dns1035(name, maxLength),
+		subdomain(name, maxLength = 253): names.subdomain(name, maxLength),
+		labelValue(value, maxLength = 63): names.labelValue(value, maxLength),
+	},
+	uuid(): uuid(),
+	uuidFrom(namespace, name): uuidFrom(namespace, name),
 	sdk: import 'sdk.libsonnet',
 }
@@ -1,3 +1,5 @@
 {
-  inOrder: import 'sdk/inOrder.libsonnet'
+  inOrder: import 'sdk/inOrder.libsonnet',
+  chunk: import 'sdk/chunk.libsonnet',
+  dependsOn: import 'sdk/dependsOn.libsonnet',
 }
@@ -0,0 +1,44 @@
+local sizeOf(key, value) = std.length(std.encodeUTF8(key)) + std.length(std.encodeUTF8(value));
+
+local field(kind) = if kind == 'Secret' then 'stringData' else 'data';
+
+local pack(entries, limit, maxKeys) = std.foldl(
+  function(chunks, entry)
+    local size = sizeOf(entry.key, entry.value);
+    local last = chunks[std.length(chunks) - 1];
+    local fits = last.size + size <= limit && std.length(last.keys) < maxKeys;
+
+    if size > limit then
+      error 'key %s has %d bytes, more than the %d allowed per object' % [entry.key, size, limit]
+    else if std.length(last.keys) == 0 || fits then
+      chunks[:std.length(chunks) - 1] + [{ size: last.size + size, keys: last.keys + [entry.key] }]
+    else
+      chunks + [{ size: size, keys: [entry.key] }],
+  entries,
+  [{ size: 0, keys: [] }]
+);
+
+local chunk(name, files, kind = 'ConfigMap', limit = 1000000, maxKeys = null) =
+  local entries = [{ key: k, value: files[k] } for k in std.objectFields(files)];
+  local keys = if maxKeys == null then std.length(entries) else maxKeys;
+  local chunks = pack(entries, limit, std.max(keys, 1));
+  local nameOf(i) = '%s-%d' % [name, i];
+
+  {
+    objects: {
+      [nameOf(i)]: {
+        apiVersion: 'v1',
+        kind: kind,
+        metadata: { name: nameOf(i) },
+        [field(kind)]: { [k]: files[k] for k in chunks[i].keys },
+      }
+      for i in std.range(0, std.length(chunks) - 1)
+    },
+    manifest: {
+      [k]: nameOf(i)
+      for i in std.range(0, std.length(chunks) - 1)
+      for k in chunks[i].keys
+    },
+  };
+
+chunk
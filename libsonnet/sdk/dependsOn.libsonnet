@@ -0,0 +1,19 @@
+local annotation = 'kct.io/depends-on';
+
+local isObject(who) = std.objectHas(who, 'kind') && std.objectHas(who, 'apiVersion');
+
+local annotate(paths, who) = who + {
+  metadata+: {
+    annotations+: {
+      [annotation]: std.join(',', (if annotation in super then [super[annotation]] else []) + paths),
+    },
+  },
+};
+
+local dependsOn(paths, who) =
+  if isObject(who) then
+    annotate(paths, who)
+  else
+    std.mapWithKey(function(k, v) dependsOn(paths, v), who);
+
+dependsOn